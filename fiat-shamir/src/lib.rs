@@ -171,6 +171,7 @@ impl<F: Field, R: RngF<F>, P: SumCheckPolynomial<F>> InteractiveVerifier<F, R>
 }
 
 #[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
     use ark_ff::{field_hashers::DefaultFieldHasher, Field, Fp64, MontBackend, MontConfig};
     use ark_poly::{
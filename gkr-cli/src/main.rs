@@ -0,0 +1,310 @@
+//! A command line interface to build circuits, evaluate them and
+//! prove and verify their evaluations with the GKR protocol.
+//!
+//! Circuits are written with [`bincode`] along with the names of
+//! their witnesses, witnesses and outputs are JSON and proofs are
+//! serialized with [`CanonicalSerialize`]. All the values are
+//! elements of the 64-bit field $\mathbb{F}_{2^{64} - 2^{32} + 1}$,
+//! whose challenges would be too small for the soundness error of a
+//! proof: those are drawn from its quadratic extension instead, of
+//! which the messages of the proofs are elements.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use clap::{Parser, Subcommand};
+use gkr_protocol::{
+    circuit::{Circuit, EvalError},
+    circuit_builder::{BuildError, CircuitBuilder, InputMapping, InputSlot, Wire},
+    expr::ParseError,
+    goldilocks::{GoldilocksExt2, GoldilocksFp},
+    proof::{prove_over_extension, verify_over_extension, GkrProof},
+    witness::{Witness, WitnessError},
+};
+use serde::{Deserialize, Serialize};
+
+/// The field of all the values.
+type Fq = GoldilocksFp;
+
+/// The field of the challenges and of the messages of the proofs.
+type Ext = GoldilocksExt2;
+
+/// An error of a command.
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error("{path}: {error}")]
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+
+    #[error("malformed circuit file: {0}")]
+    Decode(#[from] bincode::Error),
+
+    #[error("malformed JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("malformed proof: {0}")]
+    Proof(#[from] ark_serialize::SerializationError),
+
+    #[error("cannot parse the expression: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("cannot build the circuit: {0}")]
+    Build(#[from] BuildError),
+
+    #[error("cannot evaluate the circuit: {0}")]
+    Eval(#[from] EvalError),
+
+    #[error("bad witness: {0}")]
+    Witness(#[from] WitnessError),
+
+    #[error("the circuit has no witness `{0}`")]
+    UnknownWitness(String),
+
+    #[error("proving failed: {0}")]
+    Protocol(#[from] gkr_protocol::Error),
+}
+
+/// A circuit along with what feeds its inputs, as written by `build`.
+#[derive(Serialize, Deserialize)]
+struct CircuitFile {
+    circuit: Circuit,
+    mapping: InputMapping,
+    witnesses: Vec<(String, Wire)>,
+}
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Build a circuit from an expression or a JSON circuit.
+    ///
+    /// The witnesses of a JSON circuit are its inputs, named `in0`,
+    /// `in1` and so on.
+    Build {
+        /// An arithmetic expression such as `x * y + 3`.
+        #[arg(long, conflicts_with = "json", required_unless_present = "json")]
+        expr: Option<String>,
+
+        /// A JSON circuit.
+        #[arg(long)]
+        json: Option<PathBuf>,
+
+        /// Where to write the circuit.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Print the outputs of a circuit on a witness as JSON.
+    Eval {
+        /// The circuit written by `build`.
+        circuit: PathBuf,
+
+        /// A JSON object of the values of the witnesses by name.
+        witness: PathBuf,
+    },
+
+    /// Prove the evaluation of a circuit on a witness.
+    Prove {
+        /// The circuit written by `build`.
+        circuit: PathBuf,
+
+        /// A JSON object of the values of the witnesses by name.
+        witness: PathBuf,
+
+        /// Where to write the proof.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Verify a proof, exiting with 1 if it is rejected.
+    ///
+    /// The verifier of the GKR protocol reads the input of the
+    /// circuit, so the witness is needed too.
+    Verify {
+        /// The circuit written by `build`.
+        circuit: PathBuf,
+
+        /// A JSON object of the values of the witnesses by name.
+        witness: PathBuf,
+
+        /// A JSON array of the claimed outputs.
+        outputs: PathBuf,
+
+        /// The proof written by `prove`.
+        proof: PathBuf,
+    },
+
+    /// Print a circuit as a Graphviz graph.
+    Dot {
+        /// The circuit written by `build`.
+        circuit: PathBuf,
+    },
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, Error> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|error| Error::Io {
+            path: path.to_owned(),
+            error,
+        })
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>, Error> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|error| Error::Io {
+            path: path.to_owned(),
+            error,
+        })
+}
+
+fn load_circuit(path: &Path) -> Result<CircuitFile, Error> {
+    Ok(bincode::deserialize_from(open(path)?)?)
+}
+
+/// The input of the circuit of `file` on the witness at `path`.
+fn load_input(file: &CircuitFile, path: &Path) -> Result<Vec<Fq>, Error> {
+    let values: BTreeMap<String, u64> = serde_json::from_reader(open(path)?)?;
+
+    let mut witness = Witness::new(file.mapping.clone());
+    for (name, value) in values {
+        let wire = file
+            .witnesses
+            .iter()
+            .find(|(witness, _)| *witness == name)
+            .map(|&(_, wire)| wire)
+            .ok_or(Error::UnknownWitness(name))?;
+        witness.assign(wire, Fq::from(value))?;
+    }
+
+    Ok(witness.to_input()?)
+}
+
+fn to_u64(value: Fq) -> u64 {
+    value.into_bigint().0[0]
+}
+
+fn build(expr: Option<String>, json: Option<PathBuf>, output: &Path) -> Result<(), Error> {
+    let file = match (expr, json) {
+        (Some(expr), _) => {
+            let (builder, layout) = CircuitBuilder::from_expression(&expr)?;
+            CircuitFile {
+                circuit: builder.build_circuit()?,
+                mapping: builder.input_mapping()?,
+                witnesses: layout.witnesses,
+            }
+        }
+        (None, Some(json)) => {
+            let circuit: Circuit = serde_json::from_reader(open(&json)?)?;
+            let inputs = 0..circuit.num_inputs();
+            CircuitFile {
+                mapping: InputMapping {
+                    slots: inputs.clone().map(InputSlot::Witness).collect(),
+                    pruned: vec![],
+                },
+                witnesses: inputs.map(|input| (format!("in{input}"), input)).collect(),
+                circuit,
+            }
+        }
+        (None, None) => unreachable!("clap requires either"),
+    };
+
+    Ok(bincode::serialize_into(create(output)?, &file)?)
+}
+
+fn eval(circuit: &Path, witness: &Path) -> Result<(), Error> {
+    let file = load_circuit(circuit)?;
+    let outputs = file.circuit.outputs(&load_input(&file, witness)?)?;
+
+    let outputs: Vec<_> = outputs.into_iter().map(to_u64).collect();
+    println!("{}", serde_json::to_string(&outputs)?);
+
+    Ok(())
+}
+
+fn prove_to(circuit: &Path, witness: &Path, output: &Path) -> Result<(), Error> {
+    let file = load_circuit(circuit)?;
+    let input = load_input(&file, witness)?;
+    let proof = prove_over_extension::<Fq, Ext>(&file.circuit, &input)?;
+
+    Ok(proof.serialize_compressed(create(output)?)?)
+}
+
+fn verify_from(
+    circuit: &Path,
+    witness: &Path,
+    outputs: &Path,
+    proof: &Path,
+) -> Result<bool, Error> {
+    let file = load_circuit(circuit)?;
+    let input = load_input(&file, witness)?;
+    let outputs: Vec<u64> = serde_json::from_reader(open(outputs)?)?;
+    let proof = GkrProof::<Ext>::deserialize_compressed(open(proof)?)?;
+
+    if proof.outputs != outputs.into_iter().map(Ext::from).collect::<Vec<_>>() {
+        return Ok(false);
+    }
+
+    match verify_over_extension(&file.circuit, &input, &proof) {
+        Ok(()) => Ok(true),
+        Err(error) => {
+            eprintln!("{error}");
+            Ok(false)
+        }
+    }
+}
+
+fn dot(circuit: &Path) -> Result<(), Error> {
+    print!("{}", load_circuit(circuit)?.circuit.to_dot());
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Build { expr, json, output } => build(expr, json, &output),
+        Command::Eval { circuit, witness } => eval(&circuit, &witness),
+        Command::Prove {
+            circuit,
+            witness,
+            output,
+        } => prove_to(&circuit, &witness, &output),
+        Command::Verify {
+            circuit,
+            witness,
+            outputs,
+            proof,
+        } => match verify_from(&circuit, &witness, &outputs, &proof) {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                eprintln!("proof rejected");
+                return ExitCode::FAILURE;
+            }
+            Err(error) => Err(error),
+        },
+        Command::Dot { circuit } => dot(&circuit),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::from(2)
+        }
+    }
+}
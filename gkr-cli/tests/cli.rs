@@ -0,0 +1,206 @@
+use std::{fs, path::PathBuf};
+
+use assert_cmd::Command;
+use gkr_protocol::circuit::{Circuit, CircuitLayer, Gate, GateType};
+
+/// The circuit of figure 4.12 of the book.
+fn circuit_from_book() -> Circuit {
+    Circuit::new(
+        vec![
+            CircuitLayer::new(vec![
+                Gate::new(GateType::Mul, [0, 1]),
+                Gate::new(GateType::Mul, [2, 3]),
+            ]),
+            CircuitLayer::new(vec![
+                Gate::new(GateType::Mul, [0, 0]),
+                Gate::new(GateType::Mul, [1, 1]),
+                Gate::new(GateType::Mul, [1, 2]),
+                Gate::new(GateType::Mul, [3, 3]),
+            ]),
+        ],
+        4,
+    )
+}
+
+/// A fresh directory for the files of test `name`.
+fn workdir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn cli(dir: &PathBuf) -> Command {
+    let mut cmd = Command::cargo_bin("gkr-cli").unwrap();
+    cmd.current_dir(dir);
+    cmd
+}
+
+#[test]
+fn book_circuit() {
+    let dir = workdir("book_circuit");
+    fs::write(
+        dir.join("circuit.json"),
+        serde_json::to_string(&circuit_from_book()).unwrap(),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("witness.json"),
+        r#"{"in0": 3, "in1": 2, "in2": 3, "in3": 1}"#,
+    )
+    .unwrap();
+
+    cli(&dir)
+        .args(["build", "--json", "circuit.json", "-o", "circuit.bin"])
+        .assert()
+        .success();
+    cli(&dir)
+        .args(["eval", "circuit.bin", "witness.json"])
+        .assert()
+        .success()
+        .stdout("[36,6]\n");
+    cli(&dir)
+        .args(["prove", "circuit.bin", "witness.json", "-o", "proof.bin"])
+        .assert()
+        .success();
+
+    fs::write(dir.join("outputs.json"), "[36, 6]").unwrap();
+    cli(&dir)
+        .args([
+            "verify",
+            "circuit.bin",
+            "witness.json",
+            "outputs.json",
+            "proof.bin",
+        ])
+        .assert()
+        .success();
+
+    fs::write(dir.join("wrong.json"), "[36, 7]").unwrap();
+    cli(&dir)
+        .args([
+            "verify",
+            "circuit.bin",
+            "witness.json",
+            "wrong.json",
+            "proof.bin",
+        ])
+        .assert()
+        .code(1);
+
+    fs::write(
+        dir.join("other.json"),
+        r#"{"in0": 4, "in1": 2, "in2": 3, "in3": 1}"#,
+    )
+    .unwrap();
+    cli(&dir)
+        .args([
+            "verify",
+            "circuit.bin",
+            "other.json",
+            "outputs.json",
+            "proof.bin",
+        ])
+        .assert()
+        .code(1);
+
+    let dot = cli(&dir)
+        .args(["dot", "circuit.bin"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(
+        String::from_utf8(dot).unwrap(),
+        circuit_from_book().to_dot()
+    );
+}
+
+#[test]
+fn expression() {
+    let dir = workdir("expression");
+    fs::write(dir.join("witness.json"), r#"{"x": 4, "y": 5}"#).unwrap();
+
+    cli(&dir)
+        .args(["build", "--expr", "x * y + 3", "-o", "circuit.bin"])
+        .assert()
+        .success();
+    cli(&dir)
+        .args(["eval", "circuit.bin", "witness.json"])
+        .assert()
+        .success()
+        .stdout("[23]\n");
+
+    fs::write(dir.join("unknown.json"), r#"{"z": 1}"#).unwrap();
+    cli(&dir)
+        .args(["eval", "circuit.bin", "unknown.json"])
+        .assert()
+        .code(2)
+        .stderr("error: the circuit has no witness `z`\n");
+
+    cli(&dir)
+        .args(["build", "--expr", "x * (y", "-o", "broken.bin"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn error_exits() {
+    let dir = workdir("error_exits");
+    fs::write(
+        dir.join("circuit.json"),
+        serde_json::to_string(&circuit_from_book()).unwrap(),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("witness.json"),
+        r#"{"in0": 3, "in1": 2, "in2": 3, "in3": 1}"#,
+    )
+    .unwrap();
+    fs::write(dir.join("outputs.json"), "[36, 6]").unwrap();
+    cli(&dir)
+        .args(["build", "--json", "circuit.json", "-o", "circuit.bin"])
+        .assert()
+        .success();
+
+    // A malformed circuit.
+    fs::write(dir.join("garbage.bin"), [0xff; 5]).unwrap();
+    cli(&dir)
+        .args(["eval", "garbage.bin", "witness.json"])
+        .assert()
+        .code(2);
+    fs::write(dir.join("garbage.json"), r#"{"layers": 3}"#).unwrap();
+    cli(&dir)
+        .args(["build", "--json", "garbage.json", "-o", "garbage.bin"])
+        .assert()
+        .code(2);
+
+    // Fewer values than inputs.
+    fs::write(dir.join("short.json"), r#"{"in0": 3, "in1": 2, "in2": 3}"#).unwrap();
+    cli(&dir)
+        .args(["prove", "circuit.bin", "short.json", "-o", "proof.bin"])
+        .assert()
+        .code(2)
+        .stderr("error: bad witness: witness 3 is not assigned\n");
+
+    // A tampered proof: the lowest byte of the claimed sum about the
+    // output layer, past the lengths and the two outputs of 16 bytes.
+    cli(&dir)
+        .args(["prove", "circuit.bin", "witness.json", "-o", "proof.bin"])
+        .assert()
+        .success();
+    let mut proof = fs::read(dir.join("proof.bin")).unwrap();
+    proof[8 + 2 * 16 + 8] ^= 1;
+    fs::write(dir.join("tampered.bin"), proof).unwrap();
+    cli(&dir)
+        .args([
+            "verify",
+            "circuit.bin",
+            "witness.json",
+            "outputs.json",
+            "tampered.bin",
+        ])
+        .assert()
+        .code(1);
+}
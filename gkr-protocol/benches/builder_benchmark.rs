@@ -0,0 +1,62 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use gkr_protocol::{circuit::GateType, circuit_builder::CircuitBuilder};
+
+/// The numbers of witnesses of the benchmarked builders.
+const SIZES: [usize; 2] = [1 << 12, 1 << 16];
+
+fn witnesses_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_witnesses");
+
+    for n in SIZES {
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("loop", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut builder = CircuitBuilder::new();
+                (0..n).map(|_| builder.apply_witness()).collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("bulk", n), &n, |b, &n| {
+            b.iter(|| CircuitBuilder::new().apply_witnesses(n))
+        });
+    }
+
+    group.finish();
+}
+
+fn gates_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("append_gates");
+
+    for n in SIZES {
+        let mut builder = CircuitBuilder::new();
+        builder.apply_witnesses(n);
+        let gates: Vec<_> = (0..n / 2)
+            .map(|i| (GateType::Mul, 2 * i, 2 * i + 1))
+            .collect();
+
+        group.throughput(Throughput::Elements(gates.len() as u64));
+        group.bench_with_input(BenchmarkId::new("loop", n), &gates, |b, gates| {
+            b.iter_batched(
+                || builder.clone(),
+                |mut builder| {
+                    gates
+                        .iter()
+                        .map(|&(_, l, r)| builder.append_mul_gate(l, r).unwrap())
+                        .collect::<Vec<_>>()
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("bulk", n), &gates, |b, gates| {
+            b.iter_batched(
+                || builder.clone(),
+                |mut builder| builder.append_gates(gates).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, witnesses_benchmark, gates_benchmark);
+criterion_main!(benches);
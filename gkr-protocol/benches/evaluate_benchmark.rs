@@ -0,0 +1,40 @@
+#![allow(non_local_definitions)]
+
+use ark_ff::{Fp64, MontBackend, MontConfig};
+use ark_std::{test_rng, UniformRand};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use gkr_protocol::circuit_builder::CircuitBuilder;
+
+#[derive(MontConfig)]
+#[modulus = "389"]
+#[generator = "2"]
+struct FrConfig;
+
+type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+/// `(depth, width)` of the benchmarked circuits.
+const SIZES: [(usize, usize); 3] = [(4, 64), (8, 256), (16, 1024)];
+
+fn evaluate_benchmark(c: &mut Criterion) {
+    let rng = &mut test_rng();
+    let mut group = c.benchmark_group("evaluate");
+
+    for (depth, width) in SIZES {
+        let circuit = CircuitBuilder::random(rng, depth, width)
+            .build_circuit()
+            .unwrap();
+        let input: Vec<_> = (0..width).map(|_| Fp389::rand(rng)).collect();
+
+        group.throughput(Throughput::Elements(circuit.num_gates() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{depth}x{width}")),
+            &input,
+            |b, input| b.iter(|| circuit.evaluate(black_box(input))),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, evaluate_benchmark);
+criterion_main!(benches);
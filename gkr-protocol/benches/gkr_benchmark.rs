@@ -0,0 +1,148 @@
+#![allow(non_local_definitions)]
+// `MontConfig` of four limbs checks for the `asm` feature of `ark-ff`.
+#![allow(unexpected_cfgs)]
+
+use ark_ff::{FftField, Fp256, MontBackend, MontConfig};
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use gkr_protocol::{
+    circuit::GateType,
+    goldilocks::{Goldilocks, GoldilocksFp},
+    harness, proof,
+};
+
+#[derive(MontConfig)]
+#[modulus = "21888242871839275222246405745257275088548364400416034343698204186575808495617"]
+#[generator = "7"]
+struct Bn254Config;
+
+/// The scalar field of BN254.
+type Bn254 = Fp256<MontBackend<Bn254Config, 4>>;
+
+/// The depth of the circuits to evaluate and prove.
+const DEPTH: usize = 8;
+
+/// The widths of the circuits to evaluate.
+const WIDTHS: [usize; 3] = [1 << 10, 1 << 14, 1 << 18];
+
+/// The widths of the circuits to prove and verify.
+///
+/// The wiring predicates of a layer of width $2^k$ are tables of
+/// $2^{2k}$ values, out of memory past $2^{10}$.
+const PROVE_WIDTHS: [usize; 3] = [1 << 6, 1 << 8, 1 << 10];
+
+/// `(depth, width)` of the builder of $2^{20}$ cells.
+const BUILDER: (usize, usize) = (15, 1 << 16);
+
+fn builder_benchmark(c: &mut Criterion) {
+    let (depth, width) = BUILDER;
+    let mut group = c.benchmark_group("builder");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(((depth + 1) * width) as u64));
+
+    group.bench_function("random", |b| b.iter(|| harness::builder(depth, width)));
+
+    let builder = harness::builder(depth, width);
+    group.bench_function("build_circuit", |b| {
+        b.iter(|| builder.build_circuit().unwrap())
+    });
+
+    group.finish();
+}
+
+fn evaluate_benchmark<F: FftField>(c: &mut Criterion, field: &str) {
+    let mut group = c.benchmark_group(format!("evaluate/{field}"));
+
+    for width in WIDTHS {
+        let circuit = harness::circuit(DEPTH, width);
+        let input = harness::input::<F>(width);
+
+        group.throughput(Throughput::Elements(circuit.num_gates() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(width), &input, |b, input| {
+            b.iter(|| circuit.evaluate(black_box(input)))
+        });
+    }
+
+    group.finish();
+}
+
+fn prove_benchmark<F: FftField>(c: &mut Criterion, field: &str) {
+    let mut group = c.benchmark_group(format!("prove/{field}"));
+    group.sample_size(10);
+
+    for width in PROVE_WIDTHS {
+        let circuit = harness::circuit(DEPTH, width);
+        let input = harness::input::<F>(width);
+
+        group.throughput(Throughput::Elements(circuit.num_gates() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(width), &input, |b, input| {
+            b.iter(|| proof::prove(&circuit, input).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn verify_benchmark<F: FftField>(c: &mut Criterion, field: &str) {
+    let mut group = c.benchmark_group(format!("verify/{field}"));
+    group.sample_size(10);
+
+    for width in PROVE_WIDTHS {
+        let circuit = harness::circuit(DEPTH, width);
+        let input = harness::input::<F>(width);
+        let gkr_proof = proof::prove(&circuit, &input).unwrap();
+
+        group.throughput(Throughput::Elements(circuit.num_gates() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(width),
+            &gkr_proof,
+            |b, gkr_proof| b.iter(|| proof::verify(&circuit, &input, gkr_proof).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+fn wiring_benchmark<F: FftField>(c: &mut Criterion, field: &str) {
+    let mut group = c.benchmark_group(format!("wiring/{field}"));
+
+    for width in PROVE_WIDTHS {
+        let circuit = harness::circuit(DEPTH, width);
+        let r_i = harness::point::<F>(circuit.num_vars_at(0).unwrap());
+
+        group.throughput(Throughput::Elements(width as u64));
+        group.bench_with_input(BenchmarkId::new("layer_wiring", width), &circuit, |b, c| {
+            b.iter(|| c.layer_wiring(0))
+        });
+
+        let wiring = circuit.layer_wiring(0);
+        group.bench_with_input(BenchmarkId::new("ext", width), &r_i, |b, r_i| {
+            b.iter_batched(
+                || r_i.clone(),
+                |r_i| wiring.ext(GateType::Mul, &r_i),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn field_benchmarks(c: &mut Criterion) {
+    evaluate_benchmark::<Goldilocks>(c, "goldilocks");
+    evaluate_benchmark::<GoldilocksFp>(c, "goldilocks-montgomery");
+    evaluate_benchmark::<Bn254>(c, "bn254");
+    prove_benchmark::<Goldilocks>(c, "goldilocks");
+    prove_benchmark::<GoldilocksFp>(c, "goldilocks-montgomery");
+    prove_benchmark::<Bn254>(c, "bn254");
+    verify_benchmark::<Goldilocks>(c, "goldilocks");
+    verify_benchmark::<GoldilocksFp>(c, "goldilocks-montgomery");
+    verify_benchmark::<Bn254>(c, "bn254");
+    wiring_benchmark::<Goldilocks>(c, "goldilocks");
+    wiring_benchmark::<GoldilocksFp>(c, "goldilocks-montgomery");
+    wiring_benchmark::<Bn254>(c, "bn254");
+}
+
+criterion_group!(benches, builder_benchmark, field_benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+#![allow(non_local_definitions)]
+
+use ark_ff::{Fp64, MontBackend, MontConfig};
+use ark_std::{test_rng, UniformRand};
+use criterion::{criterion_group, criterion_main, Criterion};
+use gkr_protocol::{circuits, proof};
+
+#[derive(MontConfig)]
+#[modulus = "389"]
+#[generator = "2"]
+struct FrConfig;
+
+type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+const ROUNDS: usize = 91;
+
+fn mimc_benchmark(c: &mut Criterion) {
+    let rng = &mut test_rng();
+    let circuit = circuits::mimc(ROUNDS);
+    let input = circuits::mimc_input(ROUNDS, Fp389::rand(rng), Fp389::rand(rng));
+    let gkr_proof = proof::prove(&circuit, &input).unwrap();
+
+    let mut group = c.benchmark_group("mimc");
+    group.sample_size(10);
+
+    group.bench_function("prove", |b| {
+        b.iter(|| proof::prove(&circuit, &input).unwrap())
+    });
+    group.bench_function("verify", |b| {
+        b.iter(|| proof::verify(&circuit, &input, &gkr_proof).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, mimc_benchmark);
+criterion_main!(benches);
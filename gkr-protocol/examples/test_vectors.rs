@@ -0,0 +1,12 @@
+//! Regenerate the test vectors checked into `test-vectors/`, see
+//! [`gkr_protocol::test_vectors`].
+
+use std::path::Path;
+
+fn main() -> std::io::Result<()> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-vectors");
+    gkr_protocol::test_vectors::write_all(&dir)?;
+    println!("wrote the test vectors to {}", dir.display());
+
+    Ok(())
+}
@@ -0,0 +1,15 @@
+//! Feed arbitrary bytes to the Bristol importer, which returns a
+//! circuit or a `ParseError` but never panics.
+//!
+//! ```text
+//! cargo +nightly fuzz run bristol fuzz/corpus/bristol
+//! ```
+
+#![no_main]
+
+use gkr_protocol::circuit::Circuit;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Circuit::from_bristol(&String::from_utf8_lossy(data));
+});
@@ -0,0 +1,676 @@
+//! Importing boolean circuits in the Bristol Fashion format.
+//!
+//! A circuit of `ng` gates over `nw` wires reads
+//!
+//! ```text
+//! ng nw
+//! niv n_1 ... n_niv
+//! nov m_1 ... m_nov
+//!
+//! 2 1 a b c XOR
+//! 2 1 a b c AND
+//! 1 1 a c INV
+//! 1 1 a c EQW
+//! ```
+//!
+//! where the `niv` inputs of `n_i` bits take the first wires, the
+//! `nov` outputs of `m_i` bits the last ones, and every other wire
+//! is set by exactly one gate, after which it may be read. The
+//! importer never panics on malformed text, it returns a
+//! [`ParseError`] instead.
+//!
+//! The gates are arithmetized so that, on inputs in $\{0, 1\}$ and
+//! over any field, every wire keeps its boolean value: `AND` is the
+//! product $ab$, `XOR` the square $(a - b)^2$, `INV` the difference
+//! $1 - a$ and `EQW` a relay.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    circuit::Circuit,
+    circuit_builder::{BuildError, CellGateType, CircuitBuilder, CircuitSource, InputSlot, Wire},
+};
+
+/// An error parsing a Bristol Fashion circuit.
+///
+/// Lines are counted from $1$, blank lines included.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    /// A token that is not a non-negative number where one is expected.
+    #[error("line {line}: expected a count or a wire, found {found:?}")]
+    NotANumber {
+        /// The line of the token.
+        line: usize,
+
+        /// The token itself.
+        found: String,
+    },
+
+    /// A line that ends too early.
+    #[error("line {line}: unexpected end of line")]
+    Truncated {
+        /// The offending line.
+        line: usize,
+    },
+
+    /// A line that goes on after it should have ended.
+    #[error("line {line}: unexpected {found:?} at the end of the line")]
+    Trailing {
+        /// The offending line.
+        line: usize,
+
+        /// The first token too many.
+        found: String,
+    },
+
+    /// The text ends before the header does.
+    #[error("unexpected end of input in the header")]
+    MissingHeader,
+
+    /// The counts of the header do not describe a circuit.
+    #[error("{inputs} input and {outputs} output bits do not fit {gates} gates on {wires} wires")]
+    Dimensions {
+        /// The number of gates.
+        gates: usize,
+
+        /// The number of wires.
+        wires: usize,
+
+        /// The number of input bits, saturated.
+        inputs: usize,
+
+        /// The number of output bits, saturated.
+        outputs: usize,
+    },
+
+    /// The number of gate lines differs from the header.
+    #[error("expected {expected} gates, found {found}")]
+    GateCount {
+        /// The number of gates of the header.
+        expected: usize,
+
+        /// The number of gate lines.
+        found: usize,
+    },
+
+    /// A gate other than `XOR`, `AND`, `INV` and `EQW`.
+    #[error("line {line}: unsupported gate {name:?}")]
+    UnknownGate {
+        /// The line of the gate.
+        line: usize,
+
+        /// The name of the gate.
+        name: String,
+    },
+
+    /// A gate with the wrong number of inputs or outputs.
+    #[error("line {line}: {name} takes {expected} inputs and 1 output")]
+    Arity {
+        /// The line of the gate.
+        line: usize,
+
+        /// The name of the gate.
+        name: String,
+
+        /// The number of inputs the gate takes.
+        expected: usize,
+    },
+
+    /// A gate setting an input or a wire beyond the last one.
+    #[error("line {line}: wire {wire} cannot be set")]
+    OutOfRange {
+        /// The line of the gate.
+        line: usize,
+
+        /// The offending wire.
+        wire: usize,
+    },
+
+    /// A gate reading a wire that no gate before it set.
+    #[error("line {line}: wire {wire} is read before it is set")]
+    Unset {
+        /// The line of the gate.
+        line: usize,
+
+        /// The offending wire.
+        wire: usize,
+    },
+
+    /// A gate setting a wire already set.
+    #[error("line {line}: wire {wire} is set twice")]
+    SetTwice {
+        /// The line of the gate.
+        line: usize,
+
+        /// The offending wire.
+        wire: usize,
+    },
+}
+
+/// Where the wires of a Bristol circuit ended up in the layered
+/// [`Circuit`] of [`Circuit::from_bristol`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BristolLayout {
+    /// What feeds every input of the circuit, a
+    /// [`Witness`](InputSlot::Witness) being the Bristol wire.
+    ///
+    /// Input wires no output depends on are left out.
+    pub inputs: Vec<InputSlot>,
+
+    /// The label in the output layer of every output wire, in order.
+    pub outputs: Vec<usize>,
+}
+
+impl BristolLayout {
+    /// The input of the circuit given the `bits` of the input wires
+    /// of the Bristol circuit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer bits than input wires.
+    pub fn input<F: From<u64>>(&self, bits: &[bool]) -> Vec<F> {
+        self.inputs
+            .iter()
+            .map(|slot| match *slot {
+                InputSlot::Witness(wire) => F::from(u64::from(bits[wire])),
+                InputSlot::Const(value) => F::from(value),
+                InputSlot::Public(_) | InputSlot::Padding => F::from(0),
+            })
+            .collect()
+    }
+
+    /// The values of the output wires of the Bristol circuit given
+    /// the values of the output layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `layer` is not the output layer of the circuit.
+    pub fn outputs<T: Copy>(&self, layer: &[T]) -> Vec<T> {
+        self.outputs.iter().map(|&label| layer[label]).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Xor,
+    And,
+    Inv,
+    Eqw,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BristolGate {
+    op: Op,
+    inputs: [usize; 2],
+    output: usize,
+}
+
+/// The non-blank lines of the text along with their numbers.
+struct Lines<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = (usize, Vec<&'a str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.by_ref().find_map(|(i, line)| {
+            let tokens: Vec<_> = line.split_whitespace().collect();
+            (!tokens.is_empty()).then_some((i + 1, tokens))
+        })
+    }
+}
+
+/// The tokens of `line` as exactly `n` numbers.
+fn numbers(line: usize, tokens: &[&str], n: usize) -> Result<Vec<usize>, ParseError> {
+    if let Some(found) = tokens.get(n) {
+        return Err(ParseError::Trailing {
+            line,
+            found: found.to_string(),
+        });
+    }
+    if tokens.len() < n {
+        return Err(ParseError::Truncated { line });
+    }
+
+    tokens.iter().map(|token| number(line, token)).collect()
+}
+
+fn number(line: usize, token: &str) -> Result<usize, ParseError> {
+    token.parse().map_err(|_| ParseError::NotANumber {
+        line,
+        found: token.to_string(),
+    })
+}
+
+/// A header line of a count followed by that many bit widths,
+/// returning their sum.
+fn widths(lines: &mut Lines) -> Result<Option<usize>, ParseError> {
+    let (line, tokens) = lines.next().ok_or(ParseError::MissingHeader)?;
+    let count = number(line, tokens[0])?;
+    let widths = numbers(line, &tokens[1..], count)?;
+
+    Ok(widths.into_iter().try_fold(0usize, usize::checked_add))
+}
+
+fn gate(line: usize, tokens: &[&str]) -> Result<BristolGate, ParseError> {
+    let (&name, counts) = tokens
+        .split_last()
+        .filter(|(_, counts)| counts.len() >= 2)
+        .ok_or(ParseError::Truncated { line })?;
+    let (op, expected) = match name {
+        "XOR" => (Op::Xor, 2),
+        "AND" => (Op::And, 2),
+        "INV" => (Op::Inv, 1),
+        "EQW" => (Op::Eqw, 1),
+        _ => {
+            return Err(ParseError::UnknownGate {
+                line,
+                name: name.to_string(),
+            })
+        }
+    };
+    if [number(line, counts[0])?, number(line, counts[1])?] != [expected, 1] {
+        return Err(ParseError::Arity {
+            line,
+            name: name.to_string(),
+            expected,
+        });
+    }
+
+    let wires = numbers(line, &counts[2..], expected + 1)?;
+    Ok(BristolGate {
+        op,
+        inputs: [wires[0], wires[expected - 1]],
+        output: wires[expected],
+    })
+}
+
+/// The gate of `ttype` over `l` and `r` lifted to the upper of their
+/// layers, the existing cell if the builder has the gate already.
+fn on_one_layer(
+    builder: &mut CircuitBuilder,
+    ttype: fn(Wire, Wire) -> CellGateType,
+    l: Wire,
+    r: Wire,
+) -> Wire {
+    let layer = builder.layer(l).max(builder.layer(r));
+    let (l, r) = (
+        builder.lift(l, layer).expect("lifted up from below"),
+        builder.lift(r, layer).expect("lifted up from below"),
+    );
+    builder
+        .intern(ttype(l, r), l, r)
+        .expect("operands on one layer")
+}
+
+/// Bristol Fashion text as a [`CircuitSource`], parsed on every
+/// build, see [`Circuit::from_bristol`] for the [`BristolLayout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bristol<'a>(pub &'a str);
+
+impl CircuitSource for Bristol<'_> {
+    fn build(&self) -> Result<Circuit, BuildError> {
+        let (circuit, _) = Circuit::from_bristol(self.0)?;
+        Ok(circuit)
+    }
+}
+
+impl Circuit {
+    /// Import a boolean circuit in the Bristol Fashion format, see the
+    /// [module documentation](crate::bristol) for the format and the
+    /// arithmetization of the gates.
+    ///
+    /// Gates no output depends on are left out, and gates repeated
+    /// over the same wires share a cell.
+    pub fn from_bristol(text: &str) -> Result<(Circuit, BristolLayout), ParseError> {
+        let mut lines = Lines {
+            lines: text.lines().enumerate(),
+        };
+
+        let (line, tokens) = lines.next().ok_or(ParseError::MissingHeader)?;
+        let counts = numbers(line, &tokens, 2)?;
+        let (num_gates, num_wires) = (counts[0], counts[1]);
+        let num_inputs = widths(&mut lines)?;
+        let num_outputs = widths(&mut lines)?;
+
+        // Every wire is an input or set by a gate, and the outputs by
+        // gates: it takes as many gates to fill the wires.
+        let dimensions = || ParseError::Dimensions {
+            gates: num_gates,
+            wires: num_wires,
+            inputs: num_inputs.unwrap_or(usize::MAX),
+            outputs: num_outputs.unwrap_or(usize::MAX),
+        };
+        let (Some(num_inputs), Some(num_outputs)) = (num_inputs, num_outputs) else {
+            return Err(dimensions());
+        };
+        if num_inputs.checked_add(num_gates) != Some(num_wires)
+            || num_outputs == 0
+            || num_outputs > num_gates
+        {
+            return Err(dimensions());
+        }
+
+        let mut gates = vec![];
+        let mut set = HashSet::new();
+        for (line, tokens) in lines {
+            let gate = gate(line, &tokens)?;
+            for wire in gate.inputs {
+                if wire >= num_inputs && !set.contains(&wire) {
+                    return Err(ParseError::Unset { line, wire });
+                }
+            }
+            if !(num_inputs..num_wires).contains(&gate.output) {
+                return Err(ParseError::OutOfRange {
+                    line,
+                    wire: gate.output,
+                });
+            }
+            if !set.insert(gate.output) {
+                return Err(ParseError::SetTwice {
+                    line,
+                    wire: gate.output,
+                });
+            }
+            gates.push(gate);
+        }
+        if gates.len() != num_gates {
+            return Err(ParseError::GateCount {
+                expected: num_gates,
+                found: gates.len(),
+            });
+        }
+
+        // The gates set the distinct wires past the inputs, the
+        // outputs among them.
+        let outputs = num_wires - num_outputs..num_wires;
+        let mut needed: HashSet<usize> = outputs.clone().collect();
+        for gate in gates.iter().rev() {
+            if needed.contains(&gate.output) {
+                needed.extend(gate.inputs);
+            }
+        }
+        let mut inputs: Vec<_> = needed
+            .iter()
+            .copied()
+            .filter(|&wire| wire < num_inputs)
+            .collect();
+        inputs.sort_unstable();
+
+        // The witnesses come first, cell `i` being the input `inputs[i]`.
+        let mut builder = CircuitBuilder::new();
+        let mut cells: HashMap<usize, Wire> = inputs
+            .iter()
+            .map(|&wire| (wire, builder.apply_witness()))
+            .collect();
+
+        for gate in gates.iter().filter(|gate| needed.contains(&gate.output)) {
+            let [a, b] = gate.inputs.map(|wire| cells[&wire]);
+            let cell = match gate.op {
+                Op::Xor => {
+                    let difference = on_one_layer(&mut builder, CellGateType::Sub, a, b);
+                    on_one_layer(&mut builder, CellGateType::Mul, difference, difference)
+                }
+                Op::And => on_one_layer(&mut builder, CellGateType::Mul, a, b),
+                Op::Inv => {
+                    let one = builder.one();
+                    on_one_layer(&mut builder, CellGateType::Sub, one, a)
+                }
+                Op::Eqw => builder.relay(a).expect("relayed from below"),
+            };
+            cells.insert(gate.output, cell);
+        }
+
+        // Every cell is an output or feeds a gate above it, so the
+        // outputs lifted to the topmost of them make up its layer.
+        let top = outputs
+            .clone()
+            .map(|wire| builder.layer(cells[&wire]))
+            .max()
+            .unwrap_or(0);
+        let outputs: Vec<_> = outputs
+            .map(|wire| {
+                builder
+                    .lift(cells[&wire], top)
+                    .expect("lifted up from below")
+            })
+            .collect();
+
+        let circuit = builder
+            .build_circuit()
+            .expect("only witnesses in the input layer");
+        let labels = builder.labels().expect("the circuit builds");
+        let slots = builder
+            .input_mapping()
+            .expect("the circuit builds")
+            .slots
+            .into_iter()
+            .map(|slot| match slot {
+                InputSlot::Witness(cell) => InputSlot::Witness(inputs[cell]),
+                slot => slot,
+            })
+            .collect();
+
+        let layout = BristolLayout {
+            inputs: slots,
+            outputs: outputs.iter().map(|output| labels[output].1).collect(),
+        };
+
+        Ok((circuit, layout))
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use ark_ff::{Fp64, MontBackend, MontConfig};
+    use pretty_assertions::assert_eq;
+
+    use super::{Bristol, BristolLayout, ParseError};
+    use crate::{
+        circuit::Circuit,
+        circuit_builder::BuildError,
+        proof::{self, VerifyError},
+    };
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    /// The circuit of the fuzzing seed corpus: the AND and the XNOR
+    /// of two bits.
+    const TINY: &str = include_str!("../fuzz/corpus/bristol/tiny.txt");
+
+    /// The output wires of `circuit` on the input wires `bits`.
+    fn run(circuit: &Circuit, layout: &BristolLayout, bits: &[bool]) -> Vec<Fp389> {
+        let input: Vec<Fp389> = layout.input(bits);
+        layout.outputs(&circuit.evaluate(&input).layers[0])
+    }
+
+    fn bits(values: &[bool]) -> Vec<Fp389> {
+        values.iter().map(|&bit| Fp389::from(bit)).collect()
+    }
+
+    #[test]
+    fn tiny() {
+        let (circuit, layout) = Circuit::from_bristol(TINY).unwrap();
+
+        for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+            assert_eq!(run(&circuit, &layout, &[a, b]), bits(&[a & b, a == b]));
+        }
+    }
+
+    #[test]
+    fn prove_source() {
+        let (_, layout) = Circuit::from_bristol(TINY).unwrap();
+        let input: Vec<Fp389> = layout.input(&[true, false]);
+
+        let (circuit, gkr_proof) = proof::prove_source(&Bristol(TINY), &input).unwrap();
+        assert_eq!(
+            layout.outputs(&circuit.evaluate(&input).layers[0]),
+            bits(&[false, false])
+        );
+        proof::verify_source(&Bristol(TINY), &input, &gkr_proof).unwrap();
+
+        let truncated = &TINY[..TINY.len() / 2];
+        assert!(matches!(
+            proof::prove_source(&Bristol(truncated), &input),
+            Err(crate::Error::Build(error)) if matches!(*error, BuildError::Bristol(_))
+        ));
+        assert!(matches!(
+            proof::verify_source(&Bristol(truncated), &input, &gkr_proof),
+            Err(VerifyError::Build(error)) if matches!(*error, BuildError::Bristol(_))
+        ));
+    }
+
+    #[test]
+    fn unused_wires() {
+        // Input 1 and the gate setting wire 3 feed no output, wire 5
+        // copies an input.
+        let text = "3 6\n3 1 1 1\n1 2\n\n2 1 0 1 3 AND\n2 1 0 2 4 XOR\n1 1 0 5 EQW\n";
+        let (circuit, layout) = Circuit::from_bristol(text).unwrap();
+        assert_eq!(circuit.num_layers(), 2);
+        assert_eq!(
+            layout
+                .inputs
+                .iter()
+                .filter(|slot| matches!(slot, super::InputSlot::Witness(_)))
+                .count(),
+            2
+        );
+
+        for input in 0..8 {
+            let bits_in = [input & 1 == 1, input & 2 == 2, input & 4 == 4];
+            assert_eq!(
+                run(&circuit, &layout, &bits_in),
+                bits(&[bits_in[0] ^ bits_in[2], bits_in[0]])
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_gates() {
+        // The same XOR twice, and the same INV twice.
+        let xor = include_str!("../fuzz/corpus/bristol/repeated_xor.txt");
+        let inv = include_str!("../fuzz/corpus/bristol/repeated_inv.txt");
+
+        let (circuit, layout) = Circuit::from_bristol(xor).unwrap();
+        assert_eq!(layout.outputs[0], layout.outputs[1]);
+        for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+            assert_eq!(run(&circuit, &layout, &[a, b]), bits(&[a ^ b, a ^ b]));
+        }
+
+        let (circuit, layout) = Circuit::from_bristol(inv).unwrap();
+        for a in [false, true] {
+            assert_eq!(run(&circuit, &layout, &[a]), bits(&[!a, !a]));
+        }
+    }
+    #[test]
+    fn malformed() {
+        let cases = [
+            ("", ParseError::MissingHeader),
+            ("1 3\n2 1 1\n", ParseError::MissingHeader),
+            (
+                "1 -3\n",
+                ParseError::NotANumber {
+                    line: 1,
+                    found: "-3".into(),
+                },
+            ),
+            ("1\n", ParseError::Truncated { line: 1 }),
+            ("1 3\n2 1\n1 1\n", ParseError::Truncated { line: 2 }),
+            (
+                "1 3 7\n",
+                ParseError::Trailing {
+                    line: 1,
+                    found: "7".into(),
+                },
+            ),
+            (
+                "1 9\n2 1 1\n1 1\n2 1 0 1 2 AND\n",
+                ParseError::Dimensions {
+                    gates: 1,
+                    wires: 9,
+                    inputs: 2,
+                    outputs: 1,
+                },
+            ),
+            (
+                "1 3\n2 1 1\n1 18446744073709551615\n",
+                ParseError::Dimensions {
+                    gates: 1,
+                    wires: 3,
+                    inputs: 2,
+                    outputs: usize::MAX,
+                },
+            ),
+            (
+                "1 18446744073709551615\n1 18446744073709551615\n1 1\n",
+                ParseError::Dimensions {
+                    gates: 1,
+                    wires: usize::MAX,
+                    inputs: usize::MAX,
+                    outputs: 1,
+                },
+            ),
+            (
+                "1 3\n2 1 1\n1 1\n2 1 AND\n",
+                ParseError::Truncated { line: 4 },
+            ),
+            (
+                "1 3\n2 1 1\n1 1\n2 1 0 2 AND\n",
+                ParseError::Truncated { line: 4 },
+            ),
+            (
+                "1 3\n2 1 1\n1 1\n2 1 0 1 2 NAND\n",
+                ParseError::UnknownGate {
+                    line: 4,
+                    name: "NAND".into(),
+                },
+            ),
+            (
+                "1 3\n2 1 1\n1 1\n1 1 0 1 2 AND\n",
+                ParseError::Arity {
+                    line: 4,
+                    name: "AND".into(),
+                    expected: 2,
+                },
+            ),
+            (
+                "1 3\n2 1 1\n1 1\n2 1 0 1 3 AND\n",
+                ParseError::OutOfRange { line: 4, wire: 3 },
+            ),
+            (
+                "2 4\n2 1 1\n1 1\n2 1 0 3 2 AND\n1 1 2 3 INV\n",
+                ParseError::Unset { line: 4, wire: 3 },
+            ),
+            (
+                "2 4\n2 1 1\n1 1\n2 1 0 1 2 AND\n1 1 0 2 INV\n",
+                ParseError::SetTwice { line: 5, wire: 2 },
+            ),
+            (
+                "2 4\n2 1 1\n1 1\n2 1 0 1 2 AND\n",
+                ParseError::GateCount {
+                    expected: 2,
+                    found: 1,
+                },
+            ),
+        ];
+
+        for (text, err) in cases {
+            assert_eq!(Circuit::from_bristol(text).unwrap_err(), err, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn truncations() {
+        // Cutting the text anywhere yields a circuit or an error.
+        for end in (0..=TINY.len()).filter(|&end| TINY.is_char_boundary(end)) {
+            let _ = Circuit::from_bristol(&TINY[..end]);
+        }
+    }
+}
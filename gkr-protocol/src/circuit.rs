@@ -1,37 +1,270 @@
-use std::ops::{Add, Mul};
+//! Layered arithmetic circuits.
 
-use ark_ff::Field;
-use ark_poly::{DenseMultilinearExtension, MultilinearExtension};
+use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+use core::{
+    fmt,
+    ops::{Add, Mul, Sub},
+};
+
+use ark_ff::{Field, PrimeField, Zero};
+use ark_poly::DenseMultilinearExtension;
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "std")]
+use crate::{circuit_builder::WireLabels, trace::EvaluationTrace};
+
+/// An error evaluating a circuit.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum EvalError {
+    /// The number of inputs does not match the circuit.
+    #[error("expected {expected} inputs, got {actual}")]
+    InputArityMismatch {
+        /// The number of inputs of the circuit.
+        expected: usize,
+        /// The number of inputs provided.
+        actual: usize,
+    },
+
+    /// A gate overflows the native integer type.
+    #[error("gate {gate_index} at layer {layer} overflows")]
+    Overflow {
+        /// The layer of the gate.
+        layer: usize,
+        /// The label of the gate in its layer.
+        gate_index: usize,
+    },
+
+    /// An input value is not reduced modulo the field, see
+    /// [`Circuit::try_evaluate_raw`].
+    #[error("input {index} is not reduced modulo the field")]
+    NonCanonicalInput {
+        /// The position of the input.
+        index: usize,
+    },
+}
 
 /// A type of a gate in the Circuit.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GateType {
     /// An addition gate.
     Add,
 
     /// A multiplication gate.
     Mul,
+
+    /// A subtraction gate, subtracting the right input from the left one.
+    Sub,
+
+    /// A relay gate of fan-in 1, passing its left input through.
+    ///
+    /// The right input slot is ignored by evaluation, see
+    /// [`Gate::relay`].
+    Relay,
+}
+
+impl GateType {
+    /// The value of a gate of this type given the values of its inputs.
+    pub fn evaluate<F>(self, left: F, right: F) -> F
+    where
+        F: Add<Output = F> + Mul<Output = F> + Sub<Output = F>,
+    {
+        match self {
+            GateType::Add => left + right,
+            GateType::Mul => left * right,
+            GateType::Sub => left - right,
+            GateType::Relay => left,
+        }
+    }
 }
 
 /// A gate in the Circuit.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Gate {
     /// A type of the gate.
     ttype: GateType,
 
     /// Two inputs, indexes into the previous layer gates outputs.
     inputs: [usize; 2],
+
+    /// The constant coefficients the inputs are scaled by.
+    #[cfg_attr(feature = "serde", serde(default = "unit_coeffs"))]
+    coeffs: [u64; 2],
+}
+
+#[cfg(feature = "serde")]
+fn unit_coeffs() -> [u64; 2] {
+    [1, 1]
+}
+
+/// `value` scaled by the constant `coeff`, by doubling and adding so
+/// that integers and field elements alike can be scaled.
+// `value - value` is the zero of any type.
+#[allow(clippy::eq_op)]
+fn scale<F: Add<Output = F> + Sub<Output = F> + Copy>(value: F, coeff: u64) -> F {
+    if coeff == 1 {
+        return value;
+    }
+
+    let mut res = value - value;
+    let mut power = value;
+    let mut rest = coeff;
+    while rest > 0 {
+        if rest & 1 == 1 {
+            res = res + power;
+        }
+        rest >>= 1;
+        if rest > 0 {
+            power = power + power;
+        }
+    }
+
+    res
+}
+
+/// An element of GF(2), adding by XOR and multiplying by AND, so that
+/// [`scale`] reduces the coefficients modulo 2.
+#[derive(Clone, Copy)]
+struct Gf2(bool);
+
+impl Add for Gf2 {
+    type Output = Gf2;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, rhs: Gf2) -> Gf2 {
+        Gf2(self.0 ^ rhs.0)
+    }
+}
+
+impl Sub for Gf2 {
+    type Output = Gf2;
+
+    // Subtraction is addition in characteristic 2.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, rhs: Gf2) -> Gf2 {
+        Gf2(self.0 ^ rhs.0)
+    }
+}
+
+impl Mul for Gf2 {
+    type Output = Gf2;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, rhs: Gf2) -> Gf2 {
+        Gf2(self.0 & rhs.0)
+    }
+}
+
+impl fmt::Display for GateType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GateType::Add => write!(f, "ADD"),
+            GateType::Mul => write!(f, "MUL"),
+            GateType::Sub => write!(f, "SUB"),
+            GateType::Relay => write!(f, "RELAY"),
+        }
+    }
 }
 
 impl Gate {
     /// Create a new `Gate`.
     pub fn new(ttype: GateType, inputs: [usize; 2]) -> Self {
-        Self { ttype, inputs }
+        Self::scaled(ttype, inputs, [1, 1])
+    }
+
+    /// Create a new `Gate` scaling its inputs by the constants
+    /// `coeffs` before combining them, computing
+    /// $c_l \cdot x + c_r \cdot y$ for an addition.
+    pub fn scaled(ttype: GateType, inputs: [usize; 2], coeffs: [u64; 2]) -> Self {
+        Self {
+            ttype,
+            inputs,
+            coeffs,
+        }
+    }
+
+    /// Create a [`GateType::Relay`] gate passing `input` through.
+    ///
+    /// Its second input slot repeats `input`. The slot takes no part
+    /// in evaluation, but the wiring predicate of the gate still
+    /// reads it, so a relay wires $a \rightarrow (b, b)$.
+    pub fn relay(input: usize) -> Self {
+        Self::new(GateType::Relay, [input, input])
+    }
+
+    /// The value of the gate given the values of its inputs.
+    pub fn evaluate<F>(&self, left: F, right: F) -> F
+    where
+        F: Add<Output = F> + Mul<Output = F> + Sub<Output = F> + Copy,
+    {
+        let [cl, cr] = self.coeffs;
+        self.ttype.evaluate(scale(left, cl), scale(right, cr))
+    }
+
+    /// The value of the gate over `u64`, `None` on overflow.
+    pub fn checked_evaluate(&self, left: u64, right: u64) -> Option<u64> {
+        let left = left.checked_mul(self.coeffs[0])?;
+        let right = match self.ttype {
+            GateType::Relay => right,
+            _ => right.checked_mul(self.coeffs[1])?,
+        };
+
+        match self.ttype {
+            GateType::Add => left.checked_add(right),
+            GateType::Mul => left.checked_mul(right),
+            GateType::Sub => left.checked_sub(right),
+            GateType::Relay => Some(left),
+        }
+    }
+
+    /// The type of the gate.
+    pub fn ttype(&self) -> GateType {
+        self.ttype
+    }
+
+    /// The type of the gate, an alias of [`Gate::ttype`].
+    pub fn gate_type(&self) -> GateType {
+        self.ttype
+    }
+
+    /// The labels of the two inputs in the layer below.
+    pub fn inputs(&self) -> [usize; 2] {
+        self.inputs
+    }
+
+    /// The coefficients of the two inputs, `[1, 1]` unless scaled.
+    pub fn coeffs(&self) -> [u64; 2] {
+        self.coeffs
+    }
+
+    /// The labels of the inputs the gate actually reads, only the
+    /// first one for a [`GateType::Relay`].
+    pub fn operands(&self) -> &[usize] {
+        match self.ttype {
+            GateType::Relay => &self.inputs[..1],
+            _ => &self.inputs,
+        }
+    }
+}
+
+impl fmt::Display for Gate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operand = |i: usize| match self.coeffs[i] {
+            1 => format!("{}", self.inputs[i]),
+            coeff => format!("{coeff}*{}", self.inputs[i]),
+        };
+
+        match self.operands() {
+            [_] => write!(f, "{}({})", self.ttype, operand(0)),
+            _ => write!(f, "{}({},{})", self.ttype, operand(0), operand(1)),
+        }
     }
 }
 
 /// A layer of gates in the circuit.
 #[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CircuitLayer {
     layer: Vec<Gate>,
 }
@@ -47,14 +280,249 @@ impl CircuitLayer {
         self.layer.len()
     }
 
+    /// Whether the layer has no gates.
     pub fn is_empty(&self) -> bool {
         self.layer.is_empty()
     }
+
+    /// The gates of the layer.
+    pub fn gates(&self) -> &[Gate] {
+        &self.layer
+    }
+
+    /// The values of the gates of the layer given the values of
+    /// the layer below.
+    pub fn evaluate<F>(&self, input: &[F]) -> Vec<F>
+    where
+        F: Add<Output = F> + Mul<Output = F> + Sub<Output = F> + Copy,
+    {
+        self.layer
+            .iter()
+            .map(|gate| gate.evaluate(input[gate.inputs[0]], input[gate.inputs[1]]))
+            .collect()
+    }
+
+    /// The type shared by all the gates of the layer, `None` if
+    /// the layer is empty or mixes gate types.
+    ///
+    /// The wiring predicates of the other types of such a layer
+    /// vanish, so the prover and the verifier skip them.
+    pub fn gate_type_uniform(&self) -> Option<GateType> {
+        let ttype = self.layer.first()?.ttype;
+        self.layer
+            .iter()
+            .all(|gate| gate.ttype == ttype)
+            .then_some(ttype)
+    }
+
+    /// The layer with the type of every gate replaced by its image
+    /// under `f`, the inputs and coefficients of the gates unchanged.
+    pub fn map_gate_types(&self, f: impl Fn(GateType) -> GateType) -> CircuitLayer {
+        CircuitLayer::new(
+            self.layer
+                .iter()
+                .map(|gate| Gate {
+                    ttype: f(gate.ttype),
+                    ..*gate
+                })
+                .collect(),
+        )
+    }
+
+    /// The wiring predicates of the layer on top of a layer labeled
+    /// by `num_vars_next` variables.
+    fn wiring(&self, num_vars_next: usize) -> Wiring {
+        let mut gates: [Vec<([usize; 3], [u64; 2])>; 4] = Default::default();
+        for (a, gate) in self.layer.iter().enumerate() {
+            let [b, c] = gate.inputs;
+            gates[gate.ttype as usize].push(([a, b, c], gate.coeffs));
+        }
+
+        Wiring {
+            gates,
+            num_vars_next,
+        }
+    }
+
+    /// The value of the wiring predicate of `which` at every point
+    /// $(a, b, c)$ of the boolean hypercube, the layer below being
+    /// labeled by `num_vars_next` variables.
+    ///
+    /// A point lists the bits of $a$, then of $b$ and of $c$, least
+    /// significant first, in the order of the variables of the
+    /// multilinear extensions of [`Wiring::ext`]. The values are those
+    /// the extensions interpolate, zero or one unless the gates are
+    /// scaled.
+    ///
+    /// Meant to check the extensions of small layers: the table has
+    /// $2^{k_i + 2 k_{i + 1}}$ entries.
+    pub fn predicate_truth_table<F: Field>(
+        &self,
+        which: GateType,
+        num_vars_next: usize,
+    ) -> Vec<(Vec<bool>, F)> {
+        let num_vars = self.len().next_power_of_two().trailing_zeros() as usize;
+        let total_vars = num_vars + 2 * num_vars_next;
+
+        let mut values = vec![F::zero(); 1 << total_vars];
+        for ([a, b, c], weight) in self.wiring(num_vars_next).weighted_gates::<F>(which) {
+            values[a | b << num_vars | c << (num_vars + num_vars_next)] += weight;
+        }
+
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(point, value)| {
+                let bits = (0..total_vars).map(|j| point >> j & 1 == 1).collect();
+                (bits, value)
+            })
+            .collect()
+    }
+
+    /// The labels of the gates reading input `input_index` of the
+    /// layer below, in increasing order.
+    pub fn consumers_of(&self, input_index: usize) -> Vec<usize> {
+        self.layer
+            .iter()
+            .enumerate()
+            .filter(|(_, gate)| gate.operands().contains(&input_index))
+            .map(|(label, _)| label)
+            .collect()
+    }
+}
+
+impl fmt::Display for CircuitLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, gate) in self.layer.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{gate}")?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// The cost of the gates of a circuit in some backend, see
+/// [`Circuit::cost`].
+///
+/// Subtractions and relays cost as much as additions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CostModel {
+    /// The cost of an addition.
+    pub add_cost: u64,
+
+    /// The cost of a multiplication.
+    pub mul_cost: u64,
+}
+
+impl CostModel {
+    /// Multiplications cost one and additions nothing, as
+    /// constraints of a rank-1 constraint system do.
+    pub fn r1cs_like() -> Self {
+        Self {
+            add_cost: 0,
+            mul_cost: 1,
+        }
+    }
+
+    /// Every gate costs one.
+    pub fn uniform() -> Self {
+        Self {
+            add_cost: 1,
+            mul_cost: 1,
+        }
+    }
+
+    /// The cost of a gate of type `ttype`.
+    pub fn gate_cost(&self, ttype: GateType) -> u64 {
+        match ttype {
+            GateType::Mul => self.mul_cost,
+            GateType::Add | GateType::Sub | GateType::Relay => self.add_cost,
+        }
+    }
+}
+
+/// A profile of the shape of a circuit, see [`Circuit::stats`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitStats {
+    /// The number of layers of gates.
+    pub num_layers: usize,
+
+    /// The number of inputs.
+    pub num_inputs: usize,
+
+    /// The number of gates.
+    pub num_gates: usize,
+
+    /// The number of gates of every layer, output layer first.
+    pub widths: Vec<usize>,
+
+    /// The largest number of values in a layer, the input layer
+    /// included.
+    pub max_width: usize,
+
+    /// The depth of the circuit, its number of layers of gates.
+    pub depth: usize,
+
+    /// The number of gates of every type present in the circuit.
+    pub gate_types: BTreeMap<GateType, usize>,
+}
+
+impl fmt::Display for CircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "layers: {} (depth {})", self.num_layers, self.depth)?;
+        writeln!(f, "inputs: {}", self.num_inputs)?;
+        writeln!(f, "gates: {}", self.num_gates)?;
+
+        writeln!(f, "widths: {:?} (max {})", self.widths, self.max_width)?;
+
+        let gate_types: Vec<_> = self
+            .gate_types
+            .iter()
+            .map(|(ttype, count)| format!("{ttype} {count}"))
+            .collect();
+        write!(f, "gate types: {}", gate_types.join(", "))
+    }
+}
+
+/// The real widths of a circuit padded by
+/// [`Circuit::pad_to_power_of_two`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PaddingInfo {
+    /// The number of real gates of every layer, output layer first.
+    pub widths: Vec<usize>,
+
+    /// The number of real inputs.
+    pub num_inputs: usize,
+}
+
+impl PaddingInfo {
+    /// Whether gate `label` at `layer` is padding, layer
+    /// `self.widths.len()` being the input layer.
+    pub fn is_padding(&self, layer: usize, label: usize) -> bool {
+        label >= self.widths.get(layer).copied().unwrap_or(self.num_inputs)
+    }
+
+    /// Pad `input` with zeroes to fit the padded circuit.
+    pub fn pad_input<F: Copy + Zero>(&self, input: &[F]) -> Vec<F> {
+        let mut input = input.to_vec();
+        input.resize(self.num_inputs.next_power_of_two(), F::zero());
+        input
+    }
+
+    /// The real outputs among the `outputs` of the padded circuit.
+    pub fn real_outputs<'a, F>(&self, outputs: &'a [F]) -> &'a [F] {
+        &outputs[..self.widths.first().copied().unwrap_or(self.num_inputs)]
+    }
 }
 
 /// An evaluation of a `Circuit` on some input.
 /// Stores every circuit layer interediary evaluations and the
 /// circuit evaluation outputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CircuitEvaluation<F> {
     /// Evaluations on per-layer basis.
     pub layers: Vec<Vec<F>>,
@@ -68,7 +536,12 @@ impl<F: Copy> CircuitEvaluation<F> {
 }
 
 /// The circuit in layered form.
-#[derive(Clone, PartialEq, Eq, Debug)]
+///
+/// The default circuit has no inputs and no gate layers: it is what
+/// an empty [`CircuitBuilder`](crate::circuit_builder::CircuitBuilder)
+/// builds and it has no outputs to prove.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Circuit {
     /// First layer being the output layer, last layer being
     /// the input layer.
@@ -76,13 +549,178 @@ pub struct Circuit {
 
     /// Number of inputs
     num_inputs: usize,
+
+    /// Number of public inputs, a prefix of the inputs.
+    num_public_inputs: usize,
+
+    /// Number of private inputs fixed to constants.
+    num_constants: usize,
+
+    /// Labels of the gates, if any.
+    gate_labels: Option<GateLabels>,
+}
+
+/// The labels of some gates of a [`Circuit`] by layer and position,
+/// layer $0$ being the output layer and the input layer coming last.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        from = "Vec<((usize, usize), String)>",
+        into = "Vec<((usize, usize), String)>"
+    )
+)]
+pub struct GateLabels(BTreeMap<(usize, usize), String>);
+
+// Tuple keys do not make JSON object keys.
+impl From<Vec<((usize, usize), String)>> for GateLabels {
+    fn from(labels: Vec<((usize, usize), String)>) -> Self {
+        Self(labels.into_iter().collect())
+    }
+}
+
+impl From<GateLabels> for Vec<((usize, usize), String)> {
+    fn from(labels: GateLabels) -> Self {
+        labels.0.into_iter().collect()
+    }
+}
+
+impl GateLabels {
+    /// Create the `GateLabels` from the label of every gate by
+    /// layer and position.
+    pub fn new(labels: BTreeMap<(usize, usize), String>) -> Self {
+        Self(labels)
+    }
+
+    /// The label of gate `label` at `layer`, if any.
+    pub fn get(&self, layer: usize, label: usize) -> Option<&str> {
+        self.0.get(&(layer, label)).map(String::as_str)
+    }
+
+    /// The layer and the position of the first gate labeled `name`.
+    pub fn position(&self, name: &str) -> Option<(usize, usize)> {
+        self.0
+            .iter()
+            .find(|(_, label)| *label == name)
+            .map(|(&position, _)| position)
+    }
+
+    /// Whether no gate is labeled.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
+/// The largest degree of the round polynomials of the Sum-Check
+/// protocol about any layer, see [`Circuit::layer_degree`].
+///
+/// $b$ and $c$ being separate variables, every gate type is linear in
+/// each variable of $f^{(i)}_{r_i}(b, c)$, and so is the wiring
+/// predicate it is multiplied by: a round polynomial is the product of
+/// two linear factors. Empty layers, padded with zeroes, are bounded
+/// alike.
+pub const LAYER_DEGREE: usize = 2;
+
 impl Circuit {
+    /// Create a new `Circuit` from its layers, output layer first.
+    ///
+    /// All the inputs are private, see [`Circuit::with_public_inputs`].
     pub fn new(layers: Vec<CircuitLayer>, num_inputs: usize) -> Self {
-        Self { layers, num_inputs }
+        Self {
+            layers,
+            num_inputs,
+            num_public_inputs: 0,
+            num_constants: 0,
+            gate_labels: None,
+        }
+    }
+
+    /// Attach labels to the gates of the circuit.
+    ///
+    /// Labels are meant for debugging, they play no part in the
+    /// [`fingerprint`](Circuit::fingerprint) of the circuit.
+    pub fn with_gate_labels(mut self, gate_labels: GateLabels) -> Self {
+        self.gate_labels = Some(gate_labels);
+        self
+    }
+
+    /// The labels of the gates, if any.
+    pub fn gate_labels(&self) -> Option<&GateLabels> {
+        self.gate_labels.as_ref()
+    }
+
+    /// Make the first `num_public_inputs` inputs public.
+    ///
+    /// The verifier takes the public part of the input as is and the
+    /// rest opened against a commitment, see
+    /// [`Verifier::check_public_input`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer inputs besides the constants.
+    ///
+    /// [`Verifier::check_public_input`]: crate::Verifier::check_public_input
+    pub fn with_public_inputs(mut self, num_public_inputs: usize) -> Self {
+        assert!(num_public_inputs + self.num_constants <= self.num_inputs);
+        self.num_public_inputs = num_public_inputs;
+        self
+    }
+
+    /// The number of public inputs, which come first.
+    pub fn num_public_inputs(&self) -> usize {
+        self.num_public_inputs
+    }
+
+    /// Record that `num_constants` of the private inputs are fixed
+    /// constants rather than witnesses of the prover.
+    ///
+    /// This is bookkeeping only: the values of the constants are part
+    /// of the input all the same and the count plays no part in the
+    /// [`fingerprint`](Circuit::fingerprint) of the circuit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer private inputs.
+    pub fn with_constants(mut self, num_constants: usize) -> Self {
+        assert!(self.num_public_inputs + num_constants <= self.num_inputs);
+        self.num_constants = num_constants;
+        self
+    }
+
+    /// The number of private inputs fixed to constants, the zeroes
+    /// padding the inputs included.
+    pub fn num_constants(&self) -> usize {
+        self.num_constants
+    }
+
+    /// The number of private inputs which are witnesses of the
+    /// prover, neither public nor constant.
+    pub fn num_witnesses(&self) -> usize {
+        self.num_inputs - self.num_public_inputs - self.num_constants
     }
 
+    /// The largest degree of the round polynomials of the Sum-Check
+    /// protocol about layer `layer`, [`LAYER_DEGREE`] whatever the
+    /// gates of the layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `layer` is not a gate layer of the circuit.
+    pub fn layer_degree(&self, layer: usize) -> usize {
+        assert!(
+            layer < self.layers.len(),
+            "layer {layer} is not a gate layer"
+        );
+
+        LAYER_DEGREE
+    }
+
+    /// The number of variables $k_i$ needed to label the gates at
+    /// layer $i$, layer `self.layers().len()` being the input layer.
+    ///
+    /// Layers whose width is not a power of two are thought of as
+    /// padded with zeroes up to the next one.
     pub fn num_vars_at(&self, layer: usize) -> Option<usize> {
         let num_gates = if let Some(layer) = self.layers.get(layer) {
             layer.len()
@@ -92,168 +730,1226 @@ impl Circuit {
             return None;
         };
 
-        Some((num_gates as u64).trailing_zeros() as usize)
+        Some(num_gates.next_power_of_two().trailing_zeros() as usize)
     }
 
     /// Evaluate a `Circuit` on a given input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of inputs does not match the circuit,
+    /// see [`Circuit::try_evaluate`].
     pub fn evaluate<F>(&self, input: &[F]) -> CircuitEvaluation<F>
     where
-        F: Add<Output = F> + Mul<Output = F> + Copy,
+        F: Add<Output = F> + Mul<Output = F> + Sub<Output = F> + Copy,
+    {
+        self.try_evaluate(input)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// The outputs of a `Circuit` on a given input, failing if the
+    /// number of inputs does not match the circuit.
+    pub fn outputs<F>(&self, input: &[F]) -> Result<Vec<F>, EvalError>
+    where
+        F: Add<Output = F> + Mul<Output = F> + Sub<Output = F> + Copy,
+    {
+        Ok(self.try_evaluate(input)?.layers.swap_remove(0))
+    }
+
+    /// Evaluate a `Circuit` on the inputs assigned by `assignments`
+    /// as `(index, value)` pairs, the others being zero.
+    ///
+    /// Handy to probe which outputs an input reaches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an index is not below the number of inputs.
+    pub fn evaluate_partial<F>(&self, assignments: &[(usize, F)]) -> CircuitEvaluation<F>
+    where
+        F: Add<Output = F> + Mul<Output = F> + Sub<Output = F> + Copy + Zero,
+    {
+        let mut input = vec![F::zero(); self.num_inputs];
+        for &(index, value) in assignments {
+            assert!(
+                index < self.num_inputs,
+                "input {index} out of {} inputs",
+                self.num_inputs
+            );
+            input[index] = value;
+        }
+
+        self.evaluate(&input)
+    }
+
+    /// Evaluate a `Circuit` on a given input, failing if the number
+    /// of inputs does not match the circuit.
+    pub fn try_evaluate<F>(&self, input: &[F]) -> Result<CircuitEvaluation<F>, EvalError>
+    where
+        F: Add<Output = F> + Mul<Output = F> + Sub<Output = F> + Copy,
     {
+        if input.len() != self.num_inputs {
+            return Err(EvalError::InputArityMismatch {
+                expected: self.num_inputs,
+                actual: input.len(),
+            });
+        }
+
         let mut layers = vec![];
         let mut current_input = input;
 
         layers.push(input.to_vec());
 
         for layer in self.layers.iter().rev() {
-            let temp_layer: Vec<_> = layer
-                .layer
-                .iter()
-                .map(|e| match e.ttype {
-                    GateType::Add => current_input[e.inputs[0]] + current_input[e.inputs[1]],
-                    GateType::Mul => current_input[e.inputs[0]] * current_input[e.inputs[1]],
-                })
-                .collect();
+            let temp_layer = layer.evaluate(current_input);
 
             layers.push(temp_layer);
             current_input = &layers[layers.len() - 1];
         }
 
         layers.reverse();
-        CircuitEvaluation { layers }
+        Ok(CircuitEvaluation { layers })
     }
 
-    /// The $\text{add}_i(a, b, c)$ polynomial value at layer $i$.
-    pub fn add_i(&self, i: usize, a: usize, b: usize, c: usize) -> bool {
-        let gate = &self.layers[i].layer[a];
+    /// Evaluate a `Circuit` over `F` on an input given by the raw
+    /// values of its field elements, failing on the first value not
+    /// below the modulus, see [`is_canonical`].
+    ///
+    /// `F::from` reduces such values silently, which hides an input
+    /// meant for another field.
+    pub fn try_evaluate_raw<F: PrimeField>(
+        &self,
+        input: &[u64],
+    ) -> Result<CircuitEvaluation<F>, EvalError> {
+        if let Some(index) = input.iter().position(|&value| !is_canonical::<F>(value)) {
+            return Err(EvalError::NonCanonicalInput { index });
+        }
 
-        gate.ttype == GateType::Add && gate.inputs[0] == b && gate.inputs[1] == c
+        self.try_evaluate(
+            &input
+                .iter()
+                .map(|&value| F::from(value))
+                .collect::<Vec<_>>(),
+        )
     }
 
-    /// The $\text{mul}_i(a, b, c)$ polynomial value at layer $i$.
-    pub fn mul_i(&self, i: usize, a: usize, b: usize, c: usize) -> bool {
-        let gate = &self.layers[i].layer[a];
+    /// Evaluate a `Circuit` on a given input over `u64`, failing
+    /// on the first gate that overflows instead of wrapping around.
+    pub fn evaluate_checked(&self, input: &[u64]) -> Result<CircuitEvaluation<u64>, EvalError> {
+        if input.len() != self.num_inputs {
+            return Err(EvalError::InputArityMismatch {
+                expected: self.num_inputs,
+                actual: input.len(),
+            });
+        }
 
-        gate.ttype == GateType::Mul && gate.inputs[0] == b && gate.inputs[1] == c
-    }
+        let mut layers = vec![input.to_vec()];
 
-    pub fn layers(&self) -> &[CircuitLayer] {
-        &self.layers
-    }
+        for (layer, gates) in self.layers.iter().enumerate().rev() {
+            let current_input = layers.last().unwrap();
+            let values = gates
+                .layer
+                .iter()
+                .enumerate()
+                .map(|(gate_index, gate)| {
+                    gate.checked_evaluate(
+                        current_input[gate.inputs[0]],
+                        current_input[gate.inputs[1]],
+                    )
+                    .ok_or(EvalError::Overflow { layer, gate_index })
+                })
+                .collect::<Result<_, _>>()?;
 
-    pub fn num_outputs(&self) -> usize {
-        self.layers[0].layer.len()
+            layers.push(values);
+        }
+
+        layers.reverse();
+        Ok(CircuitEvaluation { layers })
     }
 
-    pub fn num_inputs(&self) -> usize {
-        self.num_inputs
+    /// Evaluate a boolean `Circuit` on `bits`, over GF(2): additions
+    /// and subtractions are XORs, multiplications are ANDs and the
+    /// coefficients of scaled gates count modulo 2.
+    ///
+    /// The circuit is proven by its arithmetization, see
+    /// [`Circuit::lift_gf2`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of inputs does not match the circuit.
+    pub fn evaluate_gf2(&self, bits: &[bool]) -> CircuitEvaluation<bool> {
+        let input: Vec<Gf2> = bits.iter().map(|&bit| Gf2(bit)).collect();
+        let evaluation = self.evaluate(&input);
+
+        CircuitEvaluation {
+            layers: evaluation
+                .layers
+                .into_iter()
+                .map(|layer| layer.into_iter().map(|Gf2(bit)| bit).collect())
+                .collect(),
+        }
     }
 
-    pub fn add_i_ext<F: Field>(&self, r_i: &[F], i: usize) -> DenseMultilinearExtension<F> {
-        let mut add_i = vec![];
-        let num_vars_current = f64::from(self.layers[i].len() as u32).log2() as usize;
-
-        let num_vars_next = f64::from(
-            self.layers
-                .get(i + 1)
-                .map(|c| c.len())
-                .unwrap_or(self.num_inputs) as u32,
-        )
-        .log2() as usize;
-
-        for c in 0..2usize.pow(num_vars_next as u32) {
-            for b in 0..2usize.pow(num_vars_next as u32) {
-                for a in 0..2usize.pow(num_vars_current as u32) {
-                    add_i.push(match self.add_i(i, a, b, c) {
-                        true => F::one(),
-                        false => F::zero(),
+    /// The arithmetization of a boolean `Circuit`: over any field and
+    /// on inputs in $\{0, 1\}$ it computes what the circuit computes
+    /// over GF(2), see [`Circuit::evaluate_gf2`].
+    ///
+    /// Every gate layer becomes two: the lower one computes both the
+    /// sum $a + b$ and the product $ab$ of the inputs of every gate,
+    /// the upper one an XOR as $a + b - 2ab$ and an AND as $ab$. The
+    /// values of every layer stay in $\{0, 1\}$, and layer `i` of the
+    /// circuit is layer `2 * i` of the arithmetization, which keeps
+    /// the positions of the gates and the labels.
+    ///
+    /// The booleanity of the inputs is not checked by the circuit: it
+    /// holds by construction for [`prove_gf2`](crate::proof::prove_gf2),
+    /// as the verifier reads the input itself.
+    pub fn lift_gf2(&self) -> Circuit {
+        let layers = self
+            .layers
+            .iter()
+            .flat_map(|layer| {
+                let mut sums_and_products = Vec::with_capacity(2 * layer.len());
+                let mut combined = Vec::with_capacity(layer.len());
+
+                for (position, gate) in layer.layer.iter().enumerate() {
+                    let [left, right] = gate.inputs;
+                    let coeffs = match gate.ttype {
+                        GateType::Relay => [gate.coeffs[0] % 2, 0],
+                        _ => [gate.coeffs[0] % 2, gate.coeffs[1] % 2],
+                    };
+                    sums_and_products.push(Gate::scaled(GateType::Add, [left, right], coeffs));
+                    sums_and_products.push(Gate::scaled(GateType::Mul, [left, right], coeffs));
+
+                    let (sum, product) = (2 * position, 2 * position + 1);
+                    combined.push(match gate.ttype {
+                        GateType::Add | GateType::Sub => {
+                            Gate::scaled(GateType::Sub, [sum, product], [1, 2])
+                        }
+                        GateType::Mul => Gate::relay(product),
+                        GateType::Relay => Gate::relay(sum),
                     });
                 }
-            }
-        }
 
-        let add_i = DenseMultilinearExtension::from_evaluations_vec(
-            num_vars_current + num_vars_next * 2,
-            add_i,
-        );
+                [
+                    CircuitLayer::new(combined),
+                    CircuitLayer::new(sums_and_products),
+                ]
+            })
+            .collect();
+
+        let mut lifted = Circuit::new(layers, self.num_inputs)
+            .with_public_inputs(self.num_public_inputs)
+            .with_constants(self.num_constants);
+        lifted.gate_labels = self.gate_labels.as_ref().map(|labels| {
+            GateLabels(
+                labels
+                    .0
+                    .iter()
+                    .map(|(&(layer, label), name)| ((2 * layer, label), name.clone()))
+                    .collect(),
+            )
+        });
 
-        add_i.fix_variables(r_i)
+        lifted
     }
 
-    pub fn mul_i_ext<F: Field>(&self, r_i: &[F], i: usize) -> DenseMultilinearExtension<F> {
-        let mut mul_i = vec![];
-        let num_vars_current = f64::from(self.layers[i].len() as u32).log2() as usize;
-
-        let num_vars_next = f64::from(
-            self.layers
-                .get(i + 1)
-                .map(|c| c.len())
-                .unwrap_or(self.num_inputs) as u32,
-        )
-        .log2() as usize;
-
-        for c in 0..2usize.pow(num_vars_next as u32) {
-            for b in 0..2usize.pow(num_vars_next as u32) {
-                for a in 0..2usize.pow(num_vars_current as u32) {
-                    mul_i.push(match self.mul_i(i, a, b, c) {
-                        true => F::one(),
-                        false => F::zero(),
-                    });
-                }
-            }
-        }
+    /// Pad every layer, inputs included, with dummy gates up to the
+    /// next power of two.
+    ///
+    /// The [`Prover`](crate::Prover) and the [`Verifier`](crate::Verifier)
+    /// do not require this: they treat the values of missing gates as
+    /// zeroes. The dummy gates subtract the first input from itself,
+    /// so they evaluate to zero too and the padded circuit proves the
+    /// same claims.
+    pub fn pad_to_power_of_two(&self) -> (Circuit, PaddingInfo) {
+        let dummy = Gate::new(GateType::Sub, [0, 0]);
 
-        let mul_i = DenseMultilinearExtension::from_evaluations_vec(
-            num_vars_current + num_vars_next * 2,
-            mul_i,
-        );
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let mut gates = layer.layer.clone();
+                gates.resize(gates.len().next_power_of_two(), dummy);
+                CircuitLayer::new(gates)
+            })
+            .collect();
+
+        let info = PaddingInfo {
+            widths: self.layers.iter().map(|layer| layer.len()).collect(),
+            num_inputs: self.num_inputs,
+        };
+
+        let num_inputs = self.num_inputs.next_power_of_two();
+        let mut padded = Circuit::new(layers, num_inputs)
+            .with_public_inputs(self.num_public_inputs)
+            .with_constants(self.num_constants + num_inputs - self.num_inputs);
+        padded.gate_labels = self.gate_labels.clone();
 
-        mul_i.fix_variables(r_i)
+        (padded, info)
     }
-}
-#[cfg(test)]
-pub(crate) fn circuit_from_book() -> Circuit {
-    Circuit {
-        layers: vec![
-            CircuitLayer {
-                layer: vec![
-                    Gate {
-                        ttype: GateType::Mul,
-                        inputs: [0, 1],
-                    },
-                    Gate {
-                        ttype: GateType::Mul,
-                        inputs: [2, 3],
-                    },
-                ],
-            },
-            CircuitLayer {
-                layer: vec![
-                    Gate {
-                        ttype: GateType::Mul,
-                        inputs: [0, 0],
-                    },
-                    Gate {
-                        ttype: GateType::Mul,
-                        inputs: [1, 1],
-                    },
-                    Gate {
-                        ttype: GateType::Mul,
-                        inputs: [1, 2],
-                    },
-                    Gate {
-                        ttype: GateType::Mul,
-                        inputs: [3, 3],
-                    },
-                ],
+
+    /// Layer `layer` alone as a circuit of a single gate layer, its
+    /// inputs being the outputs of the layer below.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `layer` is not a gate layer of the circuit.
+    pub fn sub_circuit(&self, layer: usize) -> Circuit {
+        let num_inputs = self
+            .layers
+            .get(layer + 1)
+            .map(|l| l.len())
+            .unwrap_or(self.num_inputs);
+        let (num_public_inputs, num_constants) = match layer + 1 == self.layers.len() {
+            true => (self.num_public_inputs, self.num_constants),
+            false => (0, 0),
+        };
+
+        let mut sub_circuit = Circuit::new(vec![self.layers[layer].clone()], num_inputs)
+            .with_public_inputs(num_public_inputs)
+            .with_constants(num_constants);
+        sub_circuit.gate_labels = self.gate_labels.as_ref().map(|labels| {
+            GateLabels(
+                labels
+                    .0
+                    .range((layer, 0)..(layer + 2, 0))
+                    .map(|(&(l, label), name)| ((l - layer, label), name.clone()))
+                    .collect(),
+            )
+        });
+
+        sub_circuit
+    }
+
+    /// The cone of output `output_pos`: the circuit computing only
+    /// that output from the gates and the inputs it depends on, along
+    /// with the position of every input of the cone among the inputs
+    /// of the circuit.
+    ///
+    /// Gates and inputs keep their order, so the public inputs and the
+    /// constants of the cone still come first and last. The second
+    /// slot of a [`GateType::Relay`] follows the first one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no output `output_pos`.
+    pub fn output_cone(&self, output_pos: usize) -> (Circuit, Vec<usize>) {
+        assert!(output_pos < self.num_outputs(), "no output {output_pos}");
+
+        // The positions of the gates kept at every layer, inputs last.
+        let mut kept = vec![vec![output_pos]];
+        for layer in &self.layers {
+            let mut below: Vec<usize> = kept
+                .last()
+                .unwrap()
+                .iter()
+                .flat_map(|&label| layer.layer[label].operands().to_vec())
+                .collect();
+            below.sort_unstable();
+            below.dedup();
+            kept.push(below);
+        }
+
+        let layers = self
+            .layers
+            .iter()
+            .zip(kept.windows(2))
+            .map(|(layer, kept)| {
+                let position = |label| kept[1].binary_search(&label).unwrap();
+                CircuitLayer::new(
+                    kept[0]
+                        .iter()
+                        .map(|&label| {
+                            let gate = layer.layer[label];
+                            let left = position(gate.inputs[0]);
+                            let right = match gate.ttype {
+                                GateType::Relay => left,
+                                _ => position(gate.inputs[1]),
+                            };
+                            Gate::scaled(gate.ttype, [left, right], gate.coeffs)
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let inputs = kept.last().unwrap();
+        let num_public = inputs.partition_point(|&i| i < self.num_public_inputs);
+        let first_constant = self.num_inputs - self.num_constants;
+        let num_constants = inputs.len() - inputs.partition_point(|&i| i < first_constant);
+
+        let mut cone = Circuit::new(layers, inputs.len())
+            .with_public_inputs(num_public)
+            .with_constants(num_constants);
+        cone.gate_labels = self.gate_labels.as_ref().map(|labels| {
+            GateLabels(
+                kept.iter()
+                    .enumerate()
+                    .flat_map(|(layer, kept)| {
+                        kept.iter()
+                            .enumerate()
+                            .filter_map(move |(position, &label)| {
+                                Some(((layer, position), labels.get(layer, label)?.into()))
+                            })
+                    })
+                    .collect(),
+            )
+        });
+
+        (cone, kept.pop().unwrap())
+    }
+
+    /// The degree of every output as a polynomial in the inputs.
+    ///
+    /// The degrees are propagated from the inputs upwards, a product
+    /// summing the degrees of its inputs and the other gates taking
+    /// the largest one. The public inputs have degree one if
+    /// `inputs_are_degree_one` is set and count as known values of
+    /// degree zero otherwise, the other inputs always have degree one.
+    ///
+    /// These are upper bounds: terms cancelling out, or scaled by zero,
+    /// are not noticed.
+    pub fn output_degrees(&self, inputs_are_degree_one: bool) -> Vec<usize> {
+        let degrees: Vec<usize> = (0..self.num_inputs)
+            .map(|input| usize::from(inputs_are_degree_one || input >= self.num_public_inputs))
+            .collect();
+
+        self.layers.iter().rev().fold(degrees, |below, layer| {
+            layer
+                .layer
+                .iter()
+                .map(|gate| {
+                    let [l, r] = gate.inputs.map(|input| below[input]);
+                    match gate.ttype {
+                        GateType::Mul => l.saturating_add(r),
+                        GateType::Add | GateType::Sub => l.max(r),
+                        GateType::Relay => l,
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// The circuit with its passthrough gates short-circuited and the
+    /// remaining gates laid out anew.
+    ///
+    /// A passthrough is a [`GateType::Relay`] or an addition or a
+    /// subtraction scaling one input by one and the other by zero.
+    /// Consumers read the wire a passthrough passes on instead, then
+    /// every gate is placed right above its highest input, relays
+    /// being added only for inputs further down. Gates feeding no
+    /// output are dropped.
+    ///
+    /// The inputs and the outputs keep their order, and their labels,
+    /// so the circuit computes the same function. Additions of a zero
+    /// constant input are not seen through, the values of the
+    /// constants not being part of the circuit; see
+    /// `CircuitBuilder::fold_constants` for those.
+    pub fn simplify_passthroughs(&self) -> Circuit {
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        enum Wire {
+            Input(usize),
+            Node(usize),
+        }
+
+        if self.layers.is_empty() {
+            return self.clone();
+        }
+
+        // The gates other than passthroughs in topological order,
+        // along with the wires they read.
+        let mut nodes: Vec<(Gate, [Wire; 2])> = vec![];
+        let mut below: Vec<_> = (0..self.num_inputs).map(Wire::Input).collect();
+        for layer in self.layers.iter().rev() {
+            below = layer
+                .layer
+                .iter()
+                .map(|gate| {
+                    let [l, r] = gate.inputs.map(|input| below[input]);
+                    match (gate.ttype, gate.coeffs) {
+                        (GateType::Relay, [1, _]) => l,
+                        (GateType::Add | GateType::Sub, [1, 0]) => l,
+                        (GateType::Add, [0, 1]) => r,
+                        _ => {
+                            nodes.push((*gate, [l, r]));
+                            Wire::Node(nodes.len() - 1)
+                        }
+                    }
+                })
+                .collect();
+        }
+        let outputs = below;
+
+        let mut heights = vec![0; nodes.len()];
+        let height = |heights: &[usize], wire: Wire| match wire {
+            Wire::Input(_) => 0,
+            Wire::Node(node) => heights[node],
+        };
+        for node in 0..nodes.len() {
+            heights[node] = 1 + nodes[node]
+                .1
+                .iter()
+                .map(|&wire| height(&heights, wire))
+                .max()
+                .unwrap();
+        }
+
+        let mut live = vec![false; nodes.len()];
+        for &wire in &outputs {
+            if let Wire::Node(node) = wire {
+                live[node] = true;
+            }
+        }
+        for node in (0..nodes.len()).rev() {
+            if live[node] {
+                for wire in nodes[node].1 {
+                    if let Wire::Node(operand) = wire {
+                        live[operand] = true;
+                    }
+                }
+            }
+        }
+
+        let depth = outputs
+            .iter()
+            .map(|&wire| height(&heights, wire))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        // The gates by height above the inputs, and the position of
+        // every wire at every height it is relayed to.
+        let mut layers = vec![vec![]; depth + 1];
+        let mut positions = BTreeMap::new();
+        let lift = |layers: &mut Vec<Vec<Gate>>,
+                    positions: &mut BTreeMap<(Wire, usize), usize>,
+                    wire: Wire,
+                    to: usize| {
+            let from = height(&heights, wire);
+            let mut label = match wire {
+                Wire::Input(input) => input,
+                Wire::Node(_) => positions[&(wire, from)],
+            };
+            for (k, layer) in layers[..=to].iter_mut().enumerate().skip(from + 1) {
+                label = *positions.entry((wire, k)).or_insert_with(|| {
+                    layer.push(Gate::relay(label));
+                    layer.len() - 1
+                });
+            }
+            label
+        };
+
+        // The gates at the full depth are outputs, laid out in the
+        // order of the outputs.
+        for (node, &(gate, wires)) in nodes.iter().enumerate() {
+            let k = heights[node];
+            if !live[node] || k == depth {
+                continue;
+            }
+            let inputs = wires.map(|wire| lift(&mut layers, &mut positions, wire, k - 1));
+            layers[k].push(Gate::scaled(gate.ttype, inputs, gate.coeffs));
+            positions.insert((Wire::Node(node), k), layers[k].len() - 1);
+        }
+        for &wire in &outputs {
+            let gate = match wire {
+                Wire::Node(node) if heights[node] == depth => {
+                    let (gate, wires) = nodes[node];
+                    let inputs =
+                        wires.map(|wire| lift(&mut layers, &mut positions, wire, depth - 1));
+                    Gate::scaled(gate.ttype, inputs, gate.coeffs)
+                }
+                _ => Gate::relay(lift(&mut layers, &mut positions, wire, depth - 1)),
+            };
+            layers[depth].push(gate);
+        }
+
+        let num_layers = self.layers.len();
+        let gate_labels = self.gate_labels.as_ref().map(|labels| {
+            GateLabels(
+                labels
+                    .0
+                    .iter()
+                    .filter_map(|(&(layer, label), name)| match layer {
+                        0 => Some(((0, label), name.clone())),
+                        _ if layer == num_layers => Some(((depth, label), name.clone())),
+                        _ => None,
+                    })
+                    .collect(),
+            )
+        });
+
+        Circuit {
+            layers: layers
+                .into_iter()
+                .skip(1)
+                .rev()
+                .map(CircuitLayer::new)
+                .collect(),
+            num_inputs: self.num_inputs,
+            num_public_inputs: self.num_public_inputs,
+            num_constants: self.num_constants,
+            gate_labels,
+        }
+    }
+
+    /// The circuit with the type of every gate replaced by its image
+    /// under `f`, see [`CircuitLayer::map_gate_types`].
+    pub fn map_gate_types(&self, f: impl Fn(GateType) -> GateType) -> Circuit {
+        Circuit {
+            layers: self
+                .layers
+                .iter()
+                .map(|layer| layer.map_gate_types(&f))
+                .collect(),
+            num_inputs: self.num_inputs,
+            num_public_inputs: self.num_public_inputs,
+            num_constants: self.num_constants,
+            gate_labels: self.gate_labels.clone(),
+        }
+    }
+
+    /// Evaluate the circuit on `input` recording every value, see
+    /// [`EvaluationTrace`].
+    #[cfg(feature = "std")]
+    pub fn evaluate_trace<F>(&self, input: &[F]) -> Result<EvaluationTrace<F>, EvalError>
+    where
+        F: Add<Output = F> + Mul<Output = F> + Sub<Output = F> + Copy + PartialEq,
+    {
+        EvaluationTrace::new(self, WireLabels::default(), input)
+    }
+
+    /// The total number of gates in the circuit.
+    pub fn num_gates(&self) -> usize {
+        self.layers.iter().map(|layer| layer.len()).sum()
+    }
+
+    /// A profile of the shape of the circuit.
+    pub fn stats(&self) -> CircuitStats {
+        let mut gate_types = BTreeMap::new();
+        for gate in self.layers.iter().flat_map(|layer| &layer.layer) {
+            *gate_types.entry(gate.ttype).or_insert(0) += 1;
+        }
+
+        CircuitStats {
+            num_layers: self.num_layers(),
+            num_inputs: self.num_inputs,
+            num_gates: self.num_gates(),
+            widths: self.layers.iter().map(CircuitLayer::len).collect(),
+            max_width: self.width(),
+            depth: self.depth(),
+            gate_types,
+        }
+    }
+
+    /// The total cost of the gates of the circuit under `model`.
+    pub fn cost(&self, model: &CostModel) -> u64 {
+        self.layers
+            .iter()
+            .flat_map(|layer| &layer.layer)
+            .map(|gate| model.gate_cost(gate.ttype))
+            .sum()
+    }
+
+    /// The gate at position `pos` of layer `layer`, if any.
+    pub fn locate(&self, layer: usize, pos: usize) -> Option<&Gate> {
+        self.layers.get(layer)?.layer.get(pos)
+    }
+
+    /// The index of the gate at position `pos` of layer `layer` when
+    /// numbering all the gates from the output layer down.
+    pub fn gate_index(&self, layer: usize, pos: usize) -> Option<usize> {
+        self.locate(layer, pos)?;
+
+        Some(self.layers[..layer].iter().map(|l| l.len()).sum::<usize>() + pos)
+    }
+
+    /// The layer and the position of the gate numbered `global_index`,
+    /// the reverse of [`gate_index`](Circuit::gate_index).
+    pub fn gate_coord(&self, global_index: usize) -> Option<(usize, usize)> {
+        let mut pos = global_index;
+        for (layer, gates) in self.layers.iter().enumerate() {
+            if pos < gates.len() {
+                return Some((layer, pos));
+            }
+            pos -= gates.len();
+        }
+
+        None
+    }
+
+    /// The multilinear extension $\tilde{W}_d$ of the input layer,
+    /// the one the GKR verifier queries at the very end.
+    ///
+    /// `inputs` are padded with zeroes up to the next power of two.
+    pub fn input_mle<F: Field>(
+        &self,
+        inputs: &[F],
+    ) -> Result<DenseMultilinearExtension<F>, EvalError> {
+        if inputs.len() != self.num_inputs {
+            return Err(EvalError::InputArityMismatch {
+                expected: self.num_inputs,
+                actual: inputs.len(),
+            });
+        }
+
+        let num_vars = self.num_vars_at(self.layers.len()).unwrap();
+        let mut evals = inputs.to_vec();
+        evals.resize(1 << num_vars, F::zero());
+
+        Ok(DenseMultilinearExtension::from_evaluations_vec(
+            num_vars, evals,
+        ))
+    }
+
+    /// The $\text{add}_i(a, b, c)$ polynomial value at layer $i$.
+    pub fn add_i(&self, i: usize, a: usize, b: usize, c: usize) -> bool {
+        self.wiring(GateType::Add, i, a, b, c)
+    }
+
+    /// The $\text{mul}_i(a, b, c)$ polynomial value at layer $i$.
+    pub fn mul_i(&self, i: usize, a: usize, b: usize, c: usize) -> bool {
+        self.wiring(GateType::Mul, i, a, b, c)
+    }
+
+    /// The $\text{sub}_i(a, b, c)$ polynomial value at layer $i$.
+    pub fn sub_i(&self, i: usize, a: usize, b: usize, c: usize) -> bool {
+        self.wiring(GateType::Sub, i, a, b, c)
+    }
+
+    /// The $\text{relay}_i(a, b, c)$ polynomial value at layer $i$.
+    pub fn relay_i(&self, i: usize, a: usize, b: usize, c: usize) -> bool {
+        self.wiring(GateType::Relay, i, a, b, c)
+    }
+
+    fn wiring(&self, ttype: GateType, i: usize, a: usize, b: usize, c: usize) -> bool {
+        let gate = &self.layers[i].layer[a];
+
+        gate.ttype == ttype && gate.inputs[0] == b && gate.inputs[1] == c
+    }
+
+    /// The number of layers of gates, not counting the input layer.
+    pub fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// The depth $d$ of the circuit, its number of layers of gates.
+    pub fn depth(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// The largest number of values in a layer, the input layer
+    /// included.
+    pub fn width(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|layer| layer.len())
+            .fold(self.num_inputs, usize::max)
+    }
+
+    /// The layers of the circuit, output layer first.
+    pub fn layers(&self) -> &[CircuitLayer] {
+        &self.layers
+    }
+
+    /// Layer `i` of the circuit, layer 0 being the output layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not a layer of gates of the circuit.
+    pub fn layer(&self, i: usize) -> &CircuitLayer {
+        &self.layers[i]
+    }
+
+    /// The number of outputs of the circuit.
+    pub fn num_outputs(&self) -> usize {
+        self.layers[0].layer.len()
+    }
+
+    /// The number of inputs of the circuit.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    /// The multilinear extension $\widetilde{add}_i(r_i, b, c)$ with
+    /// the first $k_i$ variables fixed at $r_i$.
+    pub fn add_i_ext<F: Field>(&self, r_i: &[F], i: usize) -> DenseMultilinearExtension<F> {
+        self.wiring_ext(GateType::Add, r_i, i)
+    }
+
+    /// The multilinear extension $\widetilde{mul}_i(r_i, b, c)$ with
+    /// the first $k_i$ variables fixed at $r_i$.
+    pub fn mul_i_ext<F: Field>(&self, r_i: &[F], i: usize) -> DenseMultilinearExtension<F> {
+        self.wiring_ext(GateType::Mul, r_i, i)
+    }
+
+    /// The multilinear extension $\widetilde{sub}_i(r_i, b, c)$ with
+    /// the first $k_i$ variables fixed at $r_i$.
+    pub fn sub_i_ext<F: Field>(&self, r_i: &[F], i: usize) -> DenseMultilinearExtension<F> {
+        self.wiring_ext(GateType::Sub, r_i, i)
+    }
+
+    /// The multilinear extension $\widetilde{relay}_i(r_i, b, c)$ with
+    /// the first $k_i$ variables fixed at $r_i$.
+    pub fn relay_i_ext<F: Field>(&self, r_i: &[F], i: usize) -> DenseMultilinearExtension<F> {
+        self.wiring_ext(GateType::Relay, r_i, i)
+    }
+
+    fn wiring_ext<F: Field>(
+        &self,
+        ttype: GateType,
+        r_i: &[F],
+        i: usize,
+    ) -> DenseMultilinearExtension<F> {
+        self.layer_wiring(i).ext(ttype, r_i)
+    }
+
+    /// The wiring predicates of layer $i$.
+    pub fn layer_wiring(&self, i: usize) -> Wiring {
+        self.layers[i].wiring(self.num_vars_at(i + 1).unwrap())
+    }
+
+    /// Whether every layer has the very same wiring.
+    ///
+    /// Such circuits repeat a single layer on top of inputs of its
+    /// width, see [`CircuitBuilder::repeat_layer`], and their wiring
+    /// predicates only have to be computed once.
+    ///
+    /// [`CircuitBuilder::repeat_layer`]: crate::circuit_builder::CircuitBuilder::repeat_layer
+    pub fn is_uniform(&self) -> bool {
+        self.layers.windows(2).all(|pair| pair[0] == pair[1])
+            && self
+                .layers
+                .last()
+                .is_some_and(|layer| layer.len() == self.num_inputs)
+    }
+
+    /// A SHA-256 hash of the structure of the circuit.
+    ///
+    /// Hashes the numbers of inputs and of public inputs followed by
+    /// the width of every layer and the type and inputs of every gate,
+    /// output layer first, all integers as little-endian `u64`s. Equal circuits
+    /// always have equal fingerprints, however they were built.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new_with_prefix(b"gkr-protocol circuit");
+
+        hasher.update((self.num_inputs as u64).to_le_bytes());
+        hasher.update((self.num_public_inputs as u64).to_le_bytes());
+        hasher.update((self.layers.len() as u64).to_le_bytes());
+        for layer in &self.layers {
+            hasher.update((layer.len() as u64).to_le_bytes());
+            for gate in &layer.layer {
+                hasher.update([gate.ttype as u8]);
+                for input in gate.inputs {
+                    hasher.update((input as u64).to_le_bytes());
+                }
+                if gate.coeffs != [1, 1] {
+                    hasher.update(b"scaled");
+                    for coeff in gate.coeffs {
+                        hasher.update(coeff.to_le_bytes());
+                    }
+                }
+            }
+        }
+
+        hasher.finalize().into()
+    }
+
+    /// The circuit as a Graphviz graph, inputs at the bottom.
+    ///
+    /// Gates are named `g<layer>_<label>`, inputs being the gates of
+    /// layer $d$, and show their labels if any.
+    pub fn to_dot(&self) -> String {
+        let d = self.layers.len();
+        let name = |layer: usize, label: usize| {
+            self.gate_labels
+                .as_ref()
+                .and_then(|labels| labels.get(layer, label))
+                .map(|name| format!(" {name}"))
+                .unwrap_or_default()
+        };
+
+        let mut dot = String::from("digraph circuit {\n    rankdir=BT;\n");
+        for input in 0..self.num_inputs {
+            dot += &format!(
+                "    g{d}_{input} [label=\"in {input}{}\", shape=box];\n",
+                name(d, input)
+            );
+        }
+        for (layer, gates) in self.layers.iter().enumerate().rev() {
+            for (label, gate) in gates.layer.iter().enumerate() {
+                dot += &format!(
+                    "    g{layer}_{label} [label=\"{}{}\"];\n",
+                    gate.ttype,
+                    name(layer, label)
+                );
+                for input in gate.operands() {
+                    dot += &format!("    g{}_{input} -> g{layer}_{label};\n", layer + 1);
+                }
+            }
+        }
+        dot += "}\n";
+
+        dot
+    }
+
+    /// The circuit listed layer by layer, output layer first, every
+    /// gate as `L0[1] = MUL(L1[2], L1[3])` followed by its label if
+    /// any, then the number of inputs and the labeled inputs.
+    ///
+    /// Only the first and last gates of a layer with more than
+    /// `max_gates` of them are listed.
+    pub fn pretty(&self, max_gates: usize) -> String {
+        let d = self.layers.len();
+        let name = |layer: usize, label: usize| {
+            self.gate_labels
+                .as_ref()
+                .and_then(|labels| labels.get(layer, label))
+                .map(|name| format!("  # {name}"))
+                .unwrap_or_default()
+        };
+
+        let mut out = String::new();
+        for (layer, gates) in self.layers.iter().enumerate() {
+            out += &format!("layer {layer}:\n");
+            push_elided(&mut out, gates.len(), max_gates, |label| {
+                let gate = &gates.layer[label];
+                let operand = |i: usize| match gate.coeffs[i] {
+                    1 => format!("L{}[{}]", layer + 1, gate.inputs[i]),
+                    coeff => format!("{coeff}*L{}[{}]", layer + 1, gate.inputs[i]),
+                };
+                let operands = match gate.operands() {
+                    [_] => operand(0),
+                    _ => format!("{}, {}", operand(0), operand(1)),
+                };
+                format!(
+                    "L{layer}[{label}] = {}({operands}){}",
+                    gate.ttype,
+                    name(layer, label)
+                )
+            });
+        }
+
+        let kinds: Vec<_> = [
+            (self.num_public_inputs, "public"),
+            (self.num_constants, "constant"),
+        ]
+        .into_iter()
+        .filter(|&(count, _)| count > 0)
+        .map(|(count, kind)| format!("{count} {kind}"))
+        .collect();
+        out += &format!("inputs: {}", self.num_inputs);
+        if !kinds.is_empty() {
+            out += &format!(" ({})", kinds.join(", "));
+        }
+        out += "\n";
+        let labeled: Vec<_> = (0..self.num_inputs)
+            .filter_map(|input| {
+                let name = name(d, input);
+                (!name.is_empty()).then(|| format!("L{d}[{input}]{name}"))
+            })
+            .collect();
+        push_elided(&mut out, labeled.len(), max_gates, |i| labeled[i].clone());
+
+        out
+    }
+}
+
+/// The number of gates per layer [`Circuit`]'s `Display` lists, see
+/// [`Circuit::pretty`].
+const DISPLAY_MAX_GATES: usize = 16;
+
+impl fmt::Display for Circuit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pretty(DISPLAY_MAX_GATES))
+    }
+}
+
+/// Push the indented lines `line(0..len)` to `out`, only the first
+/// and last ones if there are more than `max`.
+fn push_elided(out: &mut String, len: usize, max: usize, line: impl Fn(usize) -> String) {
+    if len <= max {
+        for i in 0..len {
+            *out += &format!("  {}\n", line(i));
+        }
+        return;
+    }
+
+    let (head, tail) = (max.div_ceil(2), max / 2);
+    for i in 0..head {
+        *out += &format!("  {}\n", line(i));
+    }
+    *out += &format!("  \u{2026} ({} more)\n", len - max);
+    for i in len - tail..len {
+        *out += &format!("  {}\n", line(i));
+    }
+}
+
+/// The wiring predicates of a single layer in sparse form,
+/// the $(a, b, c)$ labels of the gates of every type along with
+/// their coefficients.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Wiring {
+    gates: [Vec<([usize; 3], [u64; 2])>; 4],
+    num_vars_next: usize,
+}
+
+impl Wiring {
+    /// The multilinear extension $\widetilde{wiring}_i(r_i, b, c)$ of
+    /// the gates of type `ttype` with the first $k_i$ variables fixed
+    /// at $r_i$.
+    ///
+    /// Builds it going over the gates rather than over the whole
+    /// $(a, b, c)$ cube: fixing $a$ at $r_i$ turns the indicator of
+    /// every gate $a \rightarrow (b, c)$ into $\widetilde{eq}(r_i, a)$
+    /// at $(b, c)$.
+    ///
+    /// The coefficients of scaled gates are folded into the predicates,
+    /// which then take the value of the coefficient rather than one. A
+    /// gate $c_l \cdot x \pm c_r \cdot y$ is split into
+    /// $c_r \cdot (x \pm y)$ and a relay $(c_l - c_r) \cdot x$, so the
+    /// relay predicate covers the additions and subtractions whose
+    /// coefficients differ. A multiplication weighs $c_l \cdot c_r$.
+    pub fn ext<F: Field>(&self, ttype: GateType, r_i: &[F]) -> DenseMultilinearExtension<F> {
+        self.ext_weighted(ttype, &eq_evals(r_i))
+    }
+
+    /// [`Wiring::ext`] with $\widetilde{eq}(r_i, a)$ replaced by the
+    /// weight `weights[a]` of every gate $a$.
+    ///
+    /// A combination of claims about single gates weighs each of them
+    /// by its coefficient.
+    pub fn ext_weighted<F: Field>(
+        &self,
+        ttype: GateType,
+        weights: &[F],
+    ) -> DenseMultilinearExtension<F> {
+        let eq = weights;
+
+        let mut evals = vec![F::zero(); 1 << (2 * self.num_vars_next)];
+        for ([a, b, c], weight) in self.weighted_gates::<F>(ttype) {
+            evals[b | (c << self.num_vars_next)] += eq[a] * weight;
+        }
+
+        DenseMultilinearExtension::from_evaluations_vec(2 * self.num_vars_next, evals)
+    }
+
+    /// The $(a, b, c)$ labels making up the predicate of `ttype`
+    /// along with their weights, see [`Wiring::ext`].
+    fn weighted_gates<F: Field>(&self, ttype: GateType) -> Vec<([usize; 3], F)> {
+        let mut weighted: Vec<_> = self.gates[ttype as usize]
+            .iter()
+            .map(|&(abc, [cl, cr])| {
+                let weight = match ttype {
+                    GateType::Add | GateType::Sub => F::from(cr),
+                    GateType::Mul => F::from(cl) * F::from(cr),
+                    GateType::Relay => F::from(cl),
+                };
+                (abc, weight)
+            })
+            .collect();
+        if ttype == GateType::Relay {
+            for other in [GateType::Add, GateType::Sub] {
+                for &(abc, [cl, cr]) in &self.gates[other as usize] {
+                    if cl != cr {
+                        weighted.push((abc, F::from(cl) - F::from(cr)));
+                    }
+                }
+            }
+        }
+
+        weighted
+    }
+
+    /// Whether the predicate of `ttype` does not vanish, which
+    /// [`Wiring::ext`] would build as zero.
+    pub fn has(&self, ttype: GateType) -> bool {
+        !self.gates[ttype as usize].is_empty()
+            || ttype == GateType::Relay
+                && [GateType::Add, GateType::Sub].iter().any(|other| {
+                    self.gates[*other as usize]
+                        .iter()
+                        .any(|(_, [cl, cr])| cl != cr)
+                })
+    }
+
+    /// A SHA-256 hash of the sparse predicates, committing to the
+    /// $(a, b, c)$ labels and the coefficients of the gates of every
+    /// type, all integers as little-endian `u64`s.
+    pub fn commitment(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new_with_prefix(b"gkr-protocol wiring");
+
+        hasher.update((self.num_vars_next as u64).to_le_bytes());
+        for gates in &self.gates {
+            hasher.update((gates.len() as u64).to_le_bytes());
+            for (abc, coeffs) in gates {
+                for label in abc {
+                    hasher.update((*label as u64).to_le_bytes());
+                }
+                for coeff in coeffs {
+                    hasher.update(coeff.to_le_bytes());
+                }
+            }
+        }
+
+        hasher.finalize().into()
+    }
+
+    /// The layer of gates the predicates are made of, `None` unless
+    /// every label from zero up is the output of exactly one gate.
+    #[cfg(feature = "serde")]
+    pub(crate) fn to_layer(&self) -> Option<CircuitLayer> {
+        let width = self.gates.iter().map(Vec::len).sum();
+        let mut layer = vec![None; width];
+
+        for (ttype, gates) in [GateType::Add, GateType::Mul, GateType::Sub, GateType::Relay]
+            .into_iter()
+            .zip(&self.gates)
+        {
+            for &([a, b, c], coeffs) in gates {
+                let slot = layer.get_mut(a)?;
+                if slot.is_some() {
+                    return None;
+                }
+                *slot = Some(Gate::scaled(ttype, [b, c], coeffs));
+            }
+        }
+
+        Some(CircuitLayer::new(layer.into_iter().collect::<Option<_>>()?))
+    }
+
+    /// The number of variables labeling the layer below.
+    pub fn num_vars_next(&self) -> usize {
+        self.num_vars_next
+    }
+}
+
+/// Whether `value` is below the modulus of `F`, the canonical
+/// representative of an element of `F`.
+pub fn is_canonical<F: PrimeField>(value: u64) -> bool {
+    F::BigInt::from(value) < F::MODULUS
+}
+
+/// Evaluations of $\widetilde{eq}(r, x)$ over $x \in \lbrace 0, 1 \rbrace^{|r|}$,
+/// the $j$-th bit of the index being $x_j$.
+pub(crate) fn eq_evals<F: Field>(r: &[F]) -> Vec<F> {
+    let mut evals = vec![F::one()];
+
+    for r_j in r {
+        let hi: Vec<_> = evals.iter().map(|e| *e * r_j).collect();
+        for e in evals.iter_mut() {
+            *e *= F::one() - r_j;
+        }
+        evals.extend(hi);
+    }
+
+    evals
+}
+
+#[cfg(test)]
+pub(crate) fn circuit_from_book() -> Circuit {
+    Circuit {
+        layers: vec![
+            CircuitLayer {
+                layer: vec![
+                    Gate::new(GateType::Mul, [0, 1]),
+                    Gate::new(GateType::Mul, [2, 3]),
+                ],
+            },
+            CircuitLayer {
+                layer: vec![
+                    Gate::new(GateType::Mul, [0, 0]),
+                    Gate::new(GateType::Mul, [1, 1]),
+                    Gate::new(GateType::Mul, [1, 2]),
+                    Gate::new(GateType::Mul, [3, 3]),
+                ],
             },
         ],
         num_inputs: 4,
+        num_public_inputs: 0,
+        num_constants: 0,
+        gate_labels: None,
     }
 }
+/// A boolean circuit adding two `n`-bit numbers and a carry by
+/// ripple carry, three layers a bit.
+///
+/// The inputs are the bits of the first number, those of the second
+/// one, least significant first, and the carry. The outputs are the
+/// `n + 1` bits of the sum.
 #[cfg(test)]
+pub(crate) fn ripple_carry_adder(n: usize) -> Circuit {
+    fn push(layer: &mut Vec<Gate>, gate: Gate) -> usize {
+        layer.push(gate);
+        layer.len() - 1
+    }
+
+    fn relay_all(layer: &mut Vec<Gate>, labels: &[usize]) -> Vec<usize> {
+        labels
+            .iter()
+            .map(|&label| push(layer, Gate::relay(label)))
+            .collect()
+    }
+
+    let (mut a, mut b): (Vec<_>, Vec<_>) = ((0..n).collect(), (n..2 * n).collect());
+    let (mut sums, mut carry) = (vec![], 2 * n);
+    let mut layers = vec![];
+
+    for _ in 0..n {
+        let mut first = vec![];
+        sums = relay_all(&mut first, &sums);
+        carry = push(&mut first, Gate::relay(carry));
+        let half_sum = push(&mut first, Gate::new(GateType::Add, [a[0], b[0]]));
+        let generate = push(&mut first, Gate::new(GateType::Mul, [a[0], b[0]]));
+        a = relay_all(&mut first, &a[1..]);
+        b = relay_all(&mut first, &b[1..]);
+
+        let mut second = vec![];
+        sums = relay_all(&mut second, &sums);
+        sums.push(push(
+            &mut second,
+            Gate::new(GateType::Add, [half_sum, carry]),
+        ));
+        let propagate = push(&mut second, Gate::new(GateType::Mul, [half_sum, carry]));
+        let generate = push(&mut second, Gate::relay(generate));
+        a = relay_all(&mut second, &a);
+        b = relay_all(&mut second, &b);
+
+        let mut third = vec![];
+        sums = relay_all(&mut third, &sums);
+        carry = push(&mut third, Gate::new(GateType::Add, [generate, propagate]));
+        a = relay_all(&mut third, &a);
+        b = relay_all(&mut third, &b);
+
+        layers.extend([first, second, third].map(CircuitLayer::new));
+    }
+
+    layers.reverse();
+    Circuit::new(layers, 2 * n + 1)
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
-    use super::circuit_from_book;
+    use alloc::{string::ToString, vec, vec::Vec};
+
+    use ark_ff::{Fp64, MontBackend, MontConfig, Zero};
+    use ark_poly::MultilinearExtension;
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        circuit_from_book, is_canonical, ripple_carry_adder, Circuit, CircuitLayer, CircuitStats,
+        CostModel, EvalError, Gate, GateLabels, GateType, LAYER_DEGREE,
+    };
+    #[cfg(feature = "std")]
+    use crate::circuit_builder::CircuitBuilder;
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
 
     /// A test of the circuit from figure 4.12
     #[test]
@@ -282,4 +1978,781 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn input_mle() {
+        let circuit = circuit_from_book();
+        let inputs: Vec<Fp389> = [3u64, 2, 3, 1].into_iter().map(Fp389::from).collect();
+
+        let mle = circuit.input_mle(&inputs).unwrap();
+        for (label, input) in inputs.iter().enumerate() {
+            let point: Vec<_> = (0..2)
+                .map(|bit| Fp389::from(((label >> bit) & 1) as u64))
+                .collect();
+            assert_eq!(mle.evaluate(&point).unwrap(), *input);
+        }
+
+        assert_eq!(
+            circuit.input_mle(&inputs[1..]).unwrap_err(),
+            EvalError::InputArityMismatch {
+                expected: 4,
+                actual: 3
+            }
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn input_mle_padding() {
+        let circuit = crate::circuits::inner_product(3);
+        let inputs: Vec<Fp389> = (1..=7).map(Fp389::from).collect();
+
+        let mle = circuit.input_mle(&inputs).unwrap();
+        let point = |label: u64| -> Vec<Fp389> {
+            (0..3).map(|bit| Fp389::from((label >> bit) & 1)).collect()
+        };
+        assert_eq!(mle.evaluate(&point(6)).unwrap(), Fp389::from(7));
+        assert_eq!(mle.evaluate(&point(7)).unwrap(), Fp389::zero());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Gate::new(GateType::Add, [0, 1]).to_string(), "ADD(0,1)");
+        assert_eq!(Gate::new(GateType::Mul, [2, 3]).to_string(), "MUL(2,3)");
+
+        let layer = CircuitLayer::new(vec![
+            Gate::new(GateType::Mul, [0, 1]),
+            Gate::new(GateType::Sub, [2, 3]),
+        ]);
+        assert_eq!(layer.to_string(), "[MUL(0,1), SUB(2,3)]");
+        assert_eq!(CircuitLayer::new(vec![]).to_string(), "[]");
+    }
+
+    #[test]
+    fn pretty() {
+        assert_eq!(
+            circuit_from_book().to_string(),
+            "\
+layer 0:
+  L0[0] = MUL(L1[0], L1[1])
+  L0[1] = MUL(L1[2], L1[3])
+layer 1:
+  L1[0] = MUL(L2[0], L2[0])
+  L1[1] = MUL(L2[1], L2[1])
+  L1[2] = MUL(L2[1], L2[2])
+  L1[3] = MUL(L2[3], L2[3])
+inputs: 4
+"
+        );
+
+        let labeled = Circuit::new(
+            vec![CircuitLayer::new(vec![
+                Gate::scaled(GateType::Add, [0, 1], [2, 1]),
+                Gate::relay(2),
+            ])],
+            3,
+        )
+        .with_public_inputs(1)
+        .with_gate_labels(GateLabels::new(
+            [((0, 0), "sum".into()), ((1, 2), "z".into())].into(),
+        ));
+        assert_eq!(
+            labeled.pretty(16),
+            "\
+layer 0:
+  L0[0] = ADD(2*L1[0], L1[1])  # sum
+  L0[1] = RELAY(L1[2])
+inputs: 3 (1 public)
+  L1[2]  # z
+"
+        );
+
+        let wide = Circuit::new(
+            vec![CircuitLayer::new((0..1024).map(Gate::relay).collect())],
+            1024,
+        );
+        assert_eq!(
+            wide.pretty(3),
+            "\
+layer 0:
+  L0[0] = RELAY(L1[0])
+  L0[1] = RELAY(L1[1])
+  \u{2026} (1021 more)
+  L0[1023] = RELAY(L1[1023])
+inputs: 1024
+"
+        );
+        assert_eq!(wide.to_string().lines().count(), 19);
+    }
+
+    #[test]
+    fn predicate_truth_table() {
+        let layer = CircuitLayer::new(vec![
+            Gate::new(GateType::Add, [0, 1]),
+            Gate::new(GateType::Mul, [1, 1]),
+        ]);
+        let circuit = Circuit::new(vec![layer.clone()], 2);
+        let wiring = circuit.layer_wiring(0);
+
+        for which in [GateType::Add, GateType::Mul, GateType::Sub, GateType::Relay] {
+            let table = layer.predicate_truth_table::<Fp389>(which, 1);
+            assert_eq!(table.len(), 8);
+
+            for (point, value) in &table {
+                let point: Vec<_> = point.iter().map(|&bit| Fp389::from(bit)).collect();
+                let (a, bc) = point.split_at(1);
+                assert_eq!(wiring.ext(which, a).evaluate(bc), Some(*value));
+            }
+
+            let ones: Vec<_> = table
+                .into_iter()
+                .filter(|(_, value)| !value.is_zero())
+                .collect();
+            let expected = match which {
+                GateType::Add => vec![(vec![false, false, true], Fp389::from(1))],
+                GateType::Mul => vec![(vec![true, true, true], Fp389::from(1))],
+                _ => vec![],
+            };
+            assert_eq!(ones, expected);
+        }
+    }
+
+    #[test]
+    fn output_degrees() {
+        let circuit = circuit_from_book();
+        assert_eq!(circuit.output_degrees(true), vec![4, 4]);
+
+        // x_0 * x_1 + x_2 and x_0 - x_0 * x_0 with x_0 public.
+        let circuit = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Add, [0, 1]),
+                    Gate::new(GateType::Sub, [2, 3]),
+                ]),
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 1]),
+                    Gate::relay(2),
+                    Gate::relay(0),
+                    Gate::new(GateType::Mul, [0, 0]),
+                ]),
+            ],
+            3,
+        )
+        .with_public_inputs(1);
+        assert_eq!(circuit.output_degrees(true), vec![2, 2]);
+        assert_eq!(circuit.output_degrees(false), vec![1, 0]);
+    }
+
+    #[test]
+    fn simplify_passthroughs() {
+        let circuit = circuit_from_book();
+        assert_eq!(circuit.simplify_passthroughs(), circuit);
+
+        // x_0 * (x_1 + x_2) and x_3, lifted by relays and additions
+        // of nothing, with x_0 needing a single relay.
+        let circuit = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![Gate::new(GateType::Mul, [0, 1]), Gate::relay(2)]),
+                CircuitLayer::new(vec![
+                    Gate::relay(0),
+                    Gate::scaled(GateType::Add, [2, 1], [0, 1]),
+                    Gate::relay(2),
+                ]),
+                CircuitLayer::new(vec![
+                    Gate::scaled(GateType::Add, [0, 3], [1, 0]),
+                    Gate::new(GateType::Add, [1, 2]),
+                    Gate::relay(3),
+                ]),
+            ],
+            4,
+        )
+        .with_public_inputs(1);
+        let simplified = circuit.simplify_passthroughs();
+
+        assert_eq!(
+            simplified,
+            Circuit::new(
+                vec![
+                    CircuitLayer::new(vec![Gate::new(GateType::Mul, [1, 0]), Gate::relay(2)]),
+                    CircuitLayer::new(vec![
+                        Gate::new(GateType::Add, [1, 2]),
+                        Gate::relay(0),
+                        Gate::relay(3),
+                    ]),
+                ],
+                4,
+            )
+            .with_public_inputs(1)
+        );
+
+        for input in [[3u64, 2, 3, 1], [0, 5, 7, 9], [8, 1, 1, 2]] {
+            assert_eq!(
+                simplified.outputs(&input).unwrap(),
+                circuit.outputs(&input).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn layer_degree() {
+        let circuit = circuit_from_book();
+        for i in 0..circuit.num_layers() {
+            assert_eq!(circuit.layer_degree(i), LAYER_DEGREE);
+        }
+
+        let empty = Circuit::new(vec![CircuitLayer::new(vec![])], 2);
+        assert_eq!(empty.layer_degree(0), LAYER_DEGREE);
+    }
+
+    #[test]
+    fn stats() {
+        let stats = circuit_from_book().stats();
+        assert_eq!(
+            stats,
+            CircuitStats {
+                num_layers: 2,
+                num_inputs: 4,
+                num_gates: 6,
+                widths: vec![2, 4],
+                max_width: 4,
+                depth: 2,
+                gate_types: [(GateType::Mul, 6)].into(),
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "\
+layers: 2 (depth 2)
+inputs: 4
+gates: 6
+widths: [2, 4] (max 4)
+gate types: MUL 6"
+        );
+
+        #[cfg(feature = "std")]
+        {
+            let stats = crate::circuits::inner_product(3).stats();
+            assert_eq!(stats.widths, [1, 2, 4]);
+            assert_eq!(stats.max_width, 7);
+            assert_eq!(
+                stats.gate_types,
+                [(GateType::Add, 4), (GateType::Mul, 3)].into()
+            );
+        }
+    }
+
+    #[test]
+    fn cost() {
+        let model = CostModel {
+            add_cost: 1,
+            mul_cost: 2,
+        };
+
+        // Six multiplications.
+        let circuit = circuit_from_book();
+        assert_eq!(circuit.cost(&model), 12);
+        assert_eq!(circuit.cost(&CostModel::r1cs_like()), 6);
+        assert_eq!(circuit.cost(&CostModel::uniform()), 6);
+
+        // Two multiplications, an addition, a subtraction and two relays.
+        let circuit = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Add, [0, 1]),
+                    Gate::new(GateType::Sub, [2, 3]),
+                ]),
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 1]),
+                    Gate::relay(2),
+                    Gate::relay(0),
+                    Gate::new(GateType::Mul, [0, 0]),
+                ]),
+            ],
+            3,
+        );
+        assert_eq!(circuit.cost(&model), 8);
+        assert_eq!(circuit.cost(&CostModel::r1cs_like()), 2);
+        assert_eq!(
+            circuit.cost(&CostModel::uniform()),
+            circuit.num_gates() as u64
+        );
+    }
+
+    #[test]
+    fn sub_circuit() {
+        let circuit = circuit_from_book();
+
+        let top = circuit.sub_circuit(0);
+        assert_eq!(top.num_layers(), 1);
+        assert_eq!(top.num_inputs(), 4);
+        assert_eq!(top.layers()[0], circuit.layers()[0]);
+        assert_eq!(top.evaluate(&[1, 2, 3, 4]).layers[0], vec![2, 12]);
+
+        let bottom = circuit.sub_circuit(1);
+        assert_eq!(bottom.num_inputs(), circuit.num_inputs());
+        assert_eq!(
+            bottom.evaluate(&[3, 2, 3, 1]).layers[0],
+            circuit.evaluate(&[3, 2, 3, 1]).layers[1]
+        );
+    }
+
+    #[test]
+    fn output_cone() {
+        let circuit = circuit_from_book();
+        let num_gates = |c: &Circuit| c.layers().iter().map(CircuitLayer::len).sum::<usize>();
+
+        let (cone, inputs) = circuit.output_cone(0);
+        assert_eq!(inputs, vec![0, 1]);
+        assert_eq!(cone.num_outputs(), 1);
+        assert!(num_gates(&cone) < num_gates(&circuit));
+        assert_eq!(cone.evaluate(&[3, 2]).layers[0], vec![36]);
+
+        let (cone, inputs) = circuit.output_cone(1);
+        assert_eq!(inputs, vec![1, 2, 3]);
+        assert_eq!(
+            cone.layers()[1].gates(),
+            [
+                Gate::new(GateType::Mul, [0, 1]),
+                Gate::new(GateType::Mul, [2, 2])
+            ]
+        );
+        let input: Vec<_> = inputs.iter().map(|&i| [3, 2, 3, 1][i]).collect();
+        assert_eq!(cone.evaluate(&input).layers[0], vec![6]);
+
+        let labeled = Circuit::new(
+            vec![CircuitLayer::new(vec![
+                Gate::new(GateType::Add, [0, 0]),
+                Gate::relay(2),
+            ])],
+            3,
+        )
+        .with_public_inputs(1)
+        .with_constants(1)
+        .with_gate_labels(GateLabels::new(
+            [((0, 1), "out".into()), ((1, 2), "one".into())].into(),
+        ));
+        let (cone, inputs) = labeled.output_cone(1);
+        assert_eq!(inputs, vec![2]);
+        assert_eq!(cone.layers()[0].gates(), [Gate::relay(0)]);
+        assert_eq!((cone.num_public_inputs(), cone.num_constants()), (0, 1));
+        assert_eq!(cone.gate_labels().unwrap().get(0, 0), Some("out"));
+        assert_eq!(cone.gate_labels().unwrap().get(1, 0), Some("one"));
+    }
+
+    #[test]
+    fn gate_evaluate() {
+        assert_eq!(Gate::new(GateType::Add, [0, 1]).evaluate(7, 3), 10);
+        assert_eq!(Gate::new(GateType::Mul, [0, 1]).evaluate(7, 3), 21);
+        assert_eq!(Gate::new(GateType::Sub, [0, 1]).evaluate(7, 3), 4);
+        assert_eq!(
+            Gate::new(GateType::Sub, [0, 1]).evaluate(Fp389::from(3), Fp389::from(7)),
+            -Fp389::from(4)
+        );
+    }
+
+    #[test]
+    fn scaled_gate_evaluate() {
+        let add = Gate::scaled(GateType::Add, [0, 1], [3, 5]);
+        assert_eq!(add.evaluate(7, 2), 31);
+        assert_eq!(add.checked_evaluate(7, 2), Some(31));
+        assert_eq!(add.checked_evaluate(u64::MAX, 2), None);
+        assert_eq!(add.to_string(), "ADD(3*0,5*1)");
+
+        let mul = Gate::scaled(GateType::Mul, [0, 1], [2, 1]);
+        assert_eq!(
+            mul.evaluate(Fp389::from(7), Fp389::from(3)),
+            Fp389::from(42)
+        );
+        assert_eq!(mul.to_string(), "MUL(2*0,1)");
+
+        let sub = Gate::scaled(GateType::Sub, [0, 1], [1, 4]);
+        assert_eq!(
+            sub.evaluate(Fp389::from(3), Fp389::from(1)),
+            -Fp389::from(1)
+        );
+        assert_eq!(
+            Gate::scaled(GateType::Relay, [0, 0], [0, 1]).evaluate(9, 9),
+            0
+        );
+
+        let circuit = Circuit::new(vec![CircuitLayer::new(vec![add, sub])], 2);
+        assert_eq!(circuit.evaluate(&[7, 1]).layers[0], vec![26, 3]);
+        assert_ne!(
+            circuit.fingerprint(),
+            Circuit::new(
+                vec![CircuitLayer::new(vec![
+                    Gate::new(GateType::Add, [0, 1]),
+                    Gate::new(GateType::Sub, [0, 1]),
+                ])],
+                2
+            )
+            .fingerprint()
+        );
+    }
+
+    #[test]
+    fn relay_gate() {
+        let relay = Gate::relay(2);
+        assert_eq!(relay.evaluate(7, 3), 7);
+        assert_eq!(relay.checked_evaluate(7, 3), Some(7));
+        assert_eq!(relay.operands(), &[2]);
+        assert_eq!(relay.to_string(), "RELAY(2)");
+
+        // The ignored slot does not make the gate a consumer.
+        let layer = CircuitLayer::new(vec![Gate::new(GateType::Relay, [0, 1])]);
+        assert_eq!(layer.consumers_of(0), vec![0]);
+        assert!(layer.consumers_of(1).is_empty());
+    }
+
+    #[test]
+    fn dot() {
+        let circuit = circuit_from_book();
+        let dot = circuit.to_dot();
+
+        assert!(dot.starts_with("digraph circuit {"));
+        assert!(dot.contains("g2_3 [label=\"in 3\", shape=box];"));
+        assert!(dot.contains("g0_1 [label=\"MUL\"];"));
+        assert!(dot.contains("g1_2 -> g0_1;"));
+        assert_eq!(dot.matches("->").count(), 2 * circuit.num_gates());
+    }
+
+    #[test]
+    fn shape() {
+        let circuit = circuit_from_book();
+
+        assert_eq!(circuit.depth(), 2);
+        assert_eq!(circuit.width(), 4);
+        assert_eq!(circuit.num_outputs(), circuit.layer(0).len());
+        assert_eq!(
+            circuit.num_gates(),
+            circuit
+                .layers()
+                .iter()
+                .map(CircuitLayer::len)
+                .sum::<usize>()
+        );
+
+        let wiring: Vec<Vec<_>> = (0..circuit.depth())
+            .map(|i| {
+                circuit
+                    .layer(i)
+                    .gates()
+                    .iter()
+                    .map(|gate| (gate.gate_type(), gate.inputs()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            wiring,
+            vec![
+                vec![(GateType::Mul, [0, 1]), (GateType::Mul, [2, 3])],
+                vec![
+                    (GateType::Mul, [0, 0]),
+                    (GateType::Mul, [1, 1]),
+                    (GateType::Mul, [1, 2]),
+                    (GateType::Mul, [3, 3]),
+                ],
+            ]
+        );
+
+        let wide = Circuit::new(vec![CircuitLayer::new(vec![Gate::relay(0)])], 5);
+        assert_eq!(wide.width(), 5);
+    }
+
+    #[test]
+    fn gate_coordinates() {
+        let circuit = circuit_from_book();
+
+        assert_eq!(
+            circuit.locate(0, 1),
+            Some(&Gate::new(GateType::Mul, [2, 3]))
+        );
+        assert_eq!(circuit.locate(0, 2), None);
+        assert_eq!(circuit.locate(2, 0), None);
+
+        assert_eq!(circuit.gate_index(0, 1), Some(1));
+        assert_eq!(circuit.gate_index(1, 3), Some(5));
+        assert_eq!(circuit.gate_index(1, 4), None);
+        for index in 0..circuit.num_gates() {
+            let (layer, pos) = circuit.gate_coord(index).unwrap();
+            assert_eq!(circuit.gate_index(layer, pos), Some(index));
+        }
+        assert_eq!(circuit.gate_coord(circuit.num_gates()), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn fingerprint() {
+        let circuit = circuit_from_book();
+        assert_eq!(circuit.fingerprint(), circuit.clone().fingerprint());
+
+        // The same circuit, witnesses and gates interleaved differently.
+        let build = |interleaved: bool| {
+            let mut builder = CircuitBuilder::new();
+            let (v0, v1) = if interleaved {
+                let (w0, w1) = (builder.apply_witness(), builder.apply_witness());
+                let v0 = builder.append_mul_gate(w0, w1).unwrap();
+                let (w2, w3) = (builder.apply_witness(), builder.apply_witness());
+                (v0, builder.append_mul_gate(w2, w3).unwrap())
+            } else {
+                let w: Vec<_> = (0..4).map(|_| builder.apply_witness()).collect();
+                (
+                    builder.append_mul_gate(w[0], w[1]).unwrap(),
+                    builder.append_mul_gate(w[2], w[3]).unwrap(),
+                )
+            };
+            builder.append_add_gate(v0, v1).unwrap();
+            builder.build_circuit().unwrap()
+        };
+        assert_eq!(build(true), build(false));
+        assert_eq!(build(true).fingerprint(), build(false).fingerprint());
+
+        let mut gates = circuit.layers()[1].gates().to_vec();
+        gates[2] = Gate::new(GateType::Add, gates[2].inputs);
+        let mut layers = circuit.layers().to_vec();
+        layers[1] = CircuitLayer::new(gates);
+        let changed = Circuit::new(layers, circuit.num_inputs());
+        assert_ne!(changed.fingerprint(), circuit.fingerprint());
+    }
+
+    #[test]
+    fn uniform() {
+        let square = CircuitLayer::new(vec![
+            Gate::new(GateType::Mul, [0, 0]),
+            Gate::new(GateType::Mul, [1, 1]),
+        ]);
+
+        let circuit = Circuit::new(vec![square.clone(), square.clone()], 2);
+        assert!(circuit.is_uniform());
+        assert_eq!(circuit.layer_wiring(0), circuit.layer_wiring(1));
+
+        assert!(!Circuit::new(vec![square.clone(), square], 4).is_uniform());
+        assert!(!circuit_from_book().is_uniform());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn pad_to_power_of_two() {
+        let circuit = crate::circuits::inner_product(5);
+        assert_eq!(circuit.num_inputs(), 11);
+
+        let (padded, info) = circuit.pad_to_power_of_two();
+        assert_eq!(padded.num_inputs(), 16);
+        assert!(info.is_padding(padded.num_layers(), 11));
+
+        for (i, layer) in padded.layers().iter().enumerate() {
+            assert!(layer.len().is_power_of_two());
+            for label in 0..layer.len() {
+                assert_eq!(
+                    info.is_padding(i, label),
+                    label >= circuit.layers()[i].len()
+                );
+            }
+        }
+        assert!(circuit.layers().iter().any(|l| !l.len().is_power_of_two()));
+
+        let input: Vec<_> = (1..=11).map(Fp389::from).collect();
+        let padded_input = info.pad_input(&input);
+
+        let evaluation = circuit.evaluate(&input);
+        let padded_evaluation = padded.evaluate(&padded_input);
+        assert_eq!(
+            info.real_outputs(&padded_evaluation.layers[0]),
+            &evaluation.layers[0][..]
+        );
+
+        let proof = crate::proof::prove(&padded, &padded_input).unwrap();
+        crate::proof::verify(&padded, &padded_input, &proof).unwrap();
+    }
+
+    #[test]
+    fn non_canonical_input() {
+        let circuit = circuit_from_book();
+        assert_eq!(
+            circuit.try_evaluate_raw::<Fp389>(&[3, 2, 3, 1]),
+            Ok(circuit.evaluate(&[3u64, 2, 3, 1].map(Fp389::from)))
+        );
+
+        // 392 would be taken for 3.
+        assert!(is_canonical::<Fp389>(388));
+        assert!(!is_canonical::<Fp389>(389));
+        assert_eq!(
+            circuit.try_evaluate_raw::<Fp389>(&[3, 2, 392, 1]),
+            Err(EvalError::NonCanonicalInput { index: 2 })
+        );
+        assert_eq!(
+            circuit.try_evaluate_raw::<Fp389>(&[3, 2]),
+            Err(EvalError::InputArityMismatch {
+                expected: 4,
+                actual: 2
+            })
+        );
+    }
+
+    #[test]
+    fn evaluate_gf2() {
+        let adder = ripple_carry_adder(8);
+        let bits = |value: u16, n: usize| (0..n).map(move |i| value >> i & 1 == 1);
+
+        for x in (0..=255).step_by(7) {
+            for y in (0..=255).step_by(11) {
+                for carry in [false, true] {
+                    let input: Vec<bool> = bits(x, 8).chain(bits(y, 8)).chain([carry]).collect();
+                    let sum = x + y + u16::from(carry);
+                    assert_eq!(
+                        adder.evaluate_gf2(&input).layers[0],
+                        bits(sum, 9).collect::<Vec<_>>()
+                    );
+                }
+            }
+        }
+
+        // Coefficients count modulo 2, subtraction is addition.
+        let circuit = Circuit::new(
+            vec![CircuitLayer::new(vec![
+                Gate::scaled(GateType::Add, [0, 1], [2, 3]),
+                Gate::new(GateType::Sub, [0, 1]),
+            ])],
+            2,
+        );
+        assert_eq!(
+            circuit.evaluate_gf2(&[true, false]).layers[0],
+            [false, true]
+        );
+        assert_eq!(circuit.evaluate_gf2(&[true, true]).layers[0], [true, false]);
+    }
+
+    #[test]
+    fn lift_gf2() {
+        let adder = ripple_carry_adder(4);
+        let lifted = adder.lift_gf2();
+        assert_eq!(lifted.num_layers(), 2 * adder.num_layers());
+
+        for input in 0..1u16 << 9 {
+            let bits: Vec<bool> = (0..9).map(|i| input >> i & 1 == 1).collect();
+            let field: Vec<Fp389> = bits.iter().map(|&bit| Fp389::from(bit)).collect();
+
+            let boolean = adder.evaluate_gf2(&bits);
+            let arithmetic = lifted.evaluate(&field);
+            for (layer, values) in boolean.layers.iter().enumerate() {
+                let values: Vec<Fp389> = values.iter().map(|&bit| Fp389::from(bit)).collect();
+                assert_eq!(arithmetic.layers[2 * layer], values);
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate_checked() {
+        let circuit = circuit_from_book();
+        assert_eq!(
+            circuit.evaluate_checked(&[3, 2, 3, 1]).unwrap().layers,
+            circuit.evaluate(&[3u64, 2, 3, 1]).layers
+        );
+
+        // (2^16)^2 * (2^16)^2 is 2^64.
+        let big = 1 << 16;
+        assert_eq!(
+            circuit.evaluate_checked(&[big, big, 1, 1]).unwrap_err(),
+            EvalError::Overflow {
+                layer: 0,
+                gate_index: 0
+            }
+        );
+        assert_eq!(
+            circuit.evaluate_checked(&[1, 1, 1, u64::MAX]).unwrap_err(),
+            EvalError::Overflow {
+                layer: 1,
+                gate_index: 3
+            }
+        );
+        assert_eq!(
+            circuit.evaluate_checked(&[1, 1]).unwrap_err(),
+            EvalError::InputArityMismatch {
+                expected: 4,
+                actual: 2
+            }
+        );
+    }
+
+    #[test]
+    fn consumers_of() {
+        let circuit = circuit_from_book();
+
+        assert_eq!(circuit.layers()[0].consumers_of(1), vec![0]);
+        assert_eq!(circuit.layers()[0].consumers_of(3), vec![1]);
+        assert_eq!(circuit.layers()[1].consumers_of(1), vec![1, 2]);
+        assert!(circuit.layers()[0].consumers_of(4).is_empty());
+    }
+
+    #[test]
+    fn gate_type_uniform() {
+        let circuit = circuit_from_book();
+        assert_eq!(circuit.layers()[0].gate_type_uniform(), Some(GateType::Mul));
+        assert_eq!(circuit.layers()[1].gate_type_uniform(), Some(GateType::Mul));
+
+        let mixed = CircuitLayer::new(vec![
+            Gate::new(GateType::Mul, [0, 1]),
+            Gate::new(GateType::Add, [0, 1]),
+        ]);
+        assert_eq!(mixed.gate_type_uniform(), None);
+        assert_eq!(CircuitLayer::new(vec![]).gate_type_uniform(), None);
+    }
+
+    #[test]
+    fn input_arity_mismatch() {
+        let circuit = circuit_from_book();
+
+        assert_eq!(
+            circuit.try_evaluate(&[3u64, 2, 3]).unwrap_err(),
+            EvalError::InputArityMismatch {
+                expected: 4,
+                actual: 3
+            }
+        );
+        assert_eq!(
+            circuit.try_evaluate(&[3u64, 2, 3, 1]).unwrap().layers,
+            circuit.evaluate(&[3u64, 2, 3, 1]).layers
+        );
+    }
+
+    #[test]
+    fn evaluate_partial() {
+        let circuit = Circuit::new(
+            vec![CircuitLayer::new(vec![
+                Gate::new(GateType::Mul, [0, 0]),
+                Gate::new(GateType::Mul, [1, 2]),
+                Gate::new(GateType::Add, [0, 3]),
+                Gate::new(GateType::Sub, [3, 1]),
+            ])],
+            4,
+        );
+
+        // Only the outputs reading input 0 see it.
+        let evaluation = circuit.evaluate_partial(&[(0, 3u64)]);
+        assert_eq!(evaluation.layers[0], vec![9, 0, 3, 0]);
+        assert_eq!(evaluation, circuit.evaluate(&[3, 0, 0, 0]));
+
+        let evaluation = circuit.evaluate_partial(&[(2, 5i64), (1, 2)]);
+        assert_eq!(evaluation.layers[0], vec![0, 10, 0, -2]);
+    }
+
+    #[test]
+    fn outputs() {
+        let square = Circuit::new(
+            vec![CircuitLayer::new(vec![
+                Gate::new(GateType::Mul, [0, 0]),
+                Gate::new(GateType::Mul, [1, 1]),
+            ])],
+            2,
+        );
+
+        assert_eq!(square.outputs(&[3u64, 5]).unwrap(), vec![9, 25]);
+        assert_eq!(
+            square.outputs(&[3u64]).unwrap_err(),
+            EvalError::InputArityMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+    }
 }
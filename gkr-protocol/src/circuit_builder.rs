@@ -1,44 +1,715 @@
-use crate::circuit::{Circuit, Gate, GateType, CircuitLayer};
-use std::collections::{HashSet, HashMap};
+//! A builder assembling a layered [`Circuit`] gate by gate.
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub enum CellGateType{
+use crate::{
+    bristol::ParseError,
+    circuit::{Circuit, CircuitLayer, Gate, GateLabels, GateType},
+};
+use ark_ff::Field;
+use ark_std::rand::{rngs::StdRng, Rng, SeedableRng};
+#[cfg(feature = "serde")]
+use std::io;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, iter,
+    ops::Range,
+};
+
+/// The index of a cell in the [`CircuitBuilder`].
+pub type Wire = usize;
+
+/// The kind of a cell in the [`CircuitBuilder`].
+///
+/// Gates reference their inputs by the global cell index
+/// returned from the builder.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CellGateType {
+    /// An addition of two cells.
     Add(usize, usize),
+
+    /// A multiplication of two cells.
     Mul(usize, usize),
+
+    /// A subtraction of the right cell from the left one.
+    Sub(usize, usize),
+
+    /// A private input of the circuit.
     Witness,
+
+    /// A public input of the circuit.
+    PublicInput,
+
+    /// A constant input of the circuit.
+    Const(u64),
+
+    /// An addition of two cells scaled by constant coefficients,
+    /// $c_l \cdot l + c_r \cdot r$ for `ScaledAdd(l, c_l, r, c_r)`.
+    ScaledAdd(usize, u64, usize, u64),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl CellGateType {
+    /// The left and right operands of a gate, `None` for an input.
+    pub fn operands(&self) -> Option<(usize, usize)> {
+        match *self {
+            CellGateType::Add(l, r)
+            | CellGateType::Mul(l, r)
+            | CellGateType::Sub(l, r)
+            | CellGateType::ScaledAdd(l, _, r, _) => Some((l, r)),
+            CellGateType::Witness | CellGateType::PublicInput | CellGateType::Const(_) => None,
+        }
+    }
+
+    /// The gate of the circuit: its type, its operands and their
+    /// coefficients, `None` for an input.
+    fn gate(&self) -> Option<(GateType, usize, usize, [u64; 2])> {
+        match *self {
+            CellGateType::Add(l, r) => Some((GateType::Add, l, r, [1, 1])),
+            CellGateType::Mul(l, r) => Some((GateType::Mul, l, r, [1, 1])),
+            CellGateType::Sub(l, r) => Some((GateType::Sub, l, r, [1, 1])),
+            CellGateType::ScaledAdd(l, cl, r, cr) => Some((GateType::Add, l, r, [cl, cr])),
+            CellGateType::Witness | CellGateType::PublicInput | CellGateType::Const(_) => None,
+        }
+    }
+
+    /// The same gate on the operands `left` and `right`, inputs
+    /// being returned as they are.
+    fn rewired(&self, left: usize, right: usize) -> Self {
+        match *self {
+            CellGateType::Add(..) => CellGateType::Add(left, right),
+            CellGateType::Mul(..) => CellGateType::Mul(left, right),
+            CellGateType::Sub(..) => CellGateType::Sub(left, right),
+            CellGateType::ScaledAdd(_, cl, _, cr) => CellGateType::ScaledAdd(left, cl, right, cr),
+            CellGateType::Witness | CellGateType::PublicInput | CellGateType::Const(_) => {
+                self.clone()
+            }
+        }
+    }
+}
+
+impl fmt::Display for CellGateType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellGateType::Add(l, r) => write!(f, "{l} + {r}"),
+            CellGateType::ScaledAdd(l, cl, r, cr) => write!(f, "{cl} * {l} + {cr} * {r}"),
+            CellGateType::Mul(l, r) => write!(f, "{l} * {r}"),
+            CellGateType::Sub(l, r) => write!(f, "{l} - {r}"),
+            CellGateType::Witness => write!(f, "witness"),
+            CellGateType::PublicInput => write!(f, "public input"),
+            CellGateType::Const(c) => write!(f, "constant {c}"),
+        }
+    }
+}
+
+/// A cell of a [`CircuitBuilder`] named in a [`BuildError`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CellRef {
+    /// The index of the cell.
+    pub index: usize,
+
+    /// The label of the cell, if any.
+    pub label: Option<String>,
+}
+
+impl fmt::Display for CellRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "cell {} `{label}`", self.index),
+            None => write!(f, "cell {}", self.index),
+        }
+    }
+}
+
+/// An error building a circuit.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
 pub enum BuildError {
-    DuplicateGate,
-    IllegalGate,
+    /// The exact same gate has already been added.
+    #[error("gate {gate} already exists as cell {existing}")]
+    DuplicateGate {
+        /// The gate added again.
+        gate: CellGateType,
+        /// The index of the existing cell.
+        existing: usize,
+    },
+
+    /// The inputs of the gate do not belong to the same layer.
+    #[error("cannot combine {left} at layer {left_layer} with {right} at layer {right_layer}")]
+    IllegalGate {
+        /// The left input of the gate.
+        left: CellRef,
+        /// The right input of the gate.
+        right: CellRef,
+        /// The layer of the left input.
+        left_layer: usize,
+        /// The layer of the right input.
+        right_layer: usize,
+    },
+
+    /// The wire does not belong to the builder.
+    #[error("wire {0} does not exist")]
+    UnknownWire(Wire),
+
+    /// A gadget is applied to cells it does not accept.
+    #[error("the operands do not fit the gadget")]
+    IllegalOperands,
+
+    /// The operands of a gadget differ in length.
+    #[error("the operands differ in length")]
+    LengthMismatch,
+
+    /// An input cell is reachable above the input layer.
+    #[error("input {0} is reachable above the input layer")]
+    WitnessNotInInputLayer(CellRef),
+
+    /// The cell does not contribute to any output while
+    /// [`BuildOptions::deny_dead_code`] is set.
+    #[error("{0} does not contribute to any output")]
+    DeadCell(CellRef),
+
+    /// The gate recorded for deduplication is not held by exactly
+    /// the cell it maps to.
+    #[error("gate {gate} is recorded as cell {recorded} but held by {holders} cells")]
+    InconsistentGate {
+        /// The recorded gate.
+        gate: CellGateType,
+        /// The cell the gate is recorded as.
+        recorded: usize,
+        /// The number of cells holding the gate.
+        holders: usize,
+    },
+
+    /// The input count differs from the number of witnesses.
+    #[error("{n_input} inputs are counted but {witnesses} witnesses exist")]
+    InputCountMismatch {
+        /// The counted inputs.
+        n_input: usize,
+        /// The witnesses and public inputs among the cells.
+        witnesses: usize,
+    },
+
+    /// A gate of a [`Circuit`] has no counterpart among the cells,
+    /// see [`CircuitBuilder::from_circuit`].
+    #[error("gate {label} at layer {layer} has no builder counterpart")]
+    UnsupportedGate {
+        /// The layer of the gate in the circuit.
+        layer: usize,
+        /// The label of the gate in its layer.
+        label: usize,
+    },
+
+    /// A gate reads a label past the width of the layer below it, see
+    /// [`Circuit::try_from`].
+    #[error("gate {label} at layer {layer} reads {input} of a layer of width {width}")]
+    GateInputOutOfRange {
+        /// The layer of the gate.
+        layer: usize,
+        /// The label of the gate in its layer.
+        label: usize,
+        /// The label read past the width.
+        input: usize,
+        /// The width of the layer below, the number of inputs below
+        /// the last layer.
+        width: usize,
+    },
+
+    /// The layer count differs from the layers of the cells.
+    #[error("{n_layer} layers are counted but the cells span {layers}")]
+    LayerCountMismatch {
+        /// The counted layers.
+        n_layer: usize,
+        /// One more than the highest layer of a cell.
+        layers: usize,
+    },
+
+    /// A Bristol Fashion circuit does not parse.
+    #[error("malformed Bristol circuit: {0}")]
+    Bristol(#[from] ParseError),
+}
+
+/// Anything producing a [`Circuit`], such as a [`CircuitBuilder`],
+/// a [`RandomCircuit`] generator or a [`Bristol`] circuit, to prove
+/// with [`prove_source`] and verify with [`verify_source`].
+///
+/// [`Bristol`]: crate::bristol::Bristol
+/// [`prove_source`]: crate::proof::prove_source
+/// [`verify_source`]: crate::proof::verify_source
+pub trait CircuitSource {
+    /// Build the circuit.
+    fn build(&self) -> Result<Circuit, BuildError>;
+}
+
+impl CircuitSource for Circuit {
+    fn build(&self) -> Result<Circuit, BuildError> {
+        Ok(self.clone())
+    }
+}
+
+/// A generator of the random circuits of [`CircuitBuilder::random`],
+/// the same seed always giving the same circuit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RandomCircuit {
+    /// The number of gate layers.
+    pub depth: usize,
+
+    /// The number of inputs and of gates of every layer.
+    pub width: usize,
+
+    /// The seed of the random gates.
+    pub seed: u64,
+}
+
+impl CircuitSource for RandomCircuit {
+    fn build(&self) -> Result<Circuit, BuildError> {
+        let rng = &mut StdRng::seed_from_u64(self.seed);
+        CircuitBuilder::random(rng, self.depth, self.width).build_circuit()
+    }
+}
+
+/// Options of [`CircuitBuilder::build_circuit`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuildOptions {
+    /// Pad the layers of the built circuit to power-of-two widths,
+    /// see [`Circuit::pad_to_power_of_two`].
+    ///
+    /// [`CircuitBuilder::input_values`] then pads the inputs with zeroes.
+    pub pad_to_power_of_two: bool,
+
+    /// Fail with [`BuildError::DeadCell`] instead of dropping the
+    /// cells that do not contribute to any output.
+    pub deny_dead_code: bool,
+
+    /// Lay the gates of every layer out by their type, coefficients
+    /// and inputs rather than by the order the cells were added in,
+    /// see [`CircuitBuilder::with_canonical_layout`].
+    pub canonical_layout: bool,
+}
+
+/// What feeds an input of the circuit built by a [`CircuitBuilder`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputSlot {
+    /// A witness of the builder.
+    Witness(Wire),
+
+    /// A public input of the builder.
+    Public(Wire),
+
+    /// A constant of the builder.
+    Const(u64),
+
+    /// A zero padding the inputs to a power of two, see
+    /// [`BuildOptions::pad_to_power_of_two`].
+    Padding,
+}
+
+/// The inputs of the circuit built by a [`CircuitBuilder`], see
+/// [`CircuitBuilder::input_mapping`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputMapping {
+    /// What feeds every input of the circuit, in order.
+    pub slots: Vec<InputSlot>,
+
+    /// The witnesses dropped for not contributing to any output.
+    pub pruned: Vec<Wire>,
+}
+
+/// The layer and the label in the built [`Circuit`] of every cell
+/// contributing to an output, see
+/// [`CircuitBuilder::build_circuit_with_labels`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WireLabels(HashMap<Wire, (usize, usize)>);
+
+impl WireLabels {
+    /// The layer and the label of `wire`, layer $0$ being the output
+    /// layer, `None` if the cell was dropped.
+    pub fn get(&self, wire: Wire) -> Option<(usize, usize)> {
+        self.0.get(&wire).copied()
+    }
+}
+
+/// The outcome of [`CircuitBuilder::fold_constants`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FoldSummary {
+    /// The number of gates found to evaluate to a constant and
+    /// replaced by the lifted constant.
+    pub folded: usize,
+
+    /// The number of trivial gates, such as $x \cdot 1$ or $x + 0$,
+    /// replaced by relays.
+    pub simplified: usize,
+
+    /// The number of gates fewer in the built circuit, lifting the
+    /// constants may take more gates in degenerate cases.
+    pub eliminated: usize,
+}
+
+/// The outcome of [`CircuitBuilder::rebalance`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RebalanceReport {
+    /// The number of gate layers of the built circuit before.
+    pub old_depth: usize,
+
+    /// The number of gate layers of the built circuit after.
+    pub new_depth: usize,
+
+    /// The wire of the rebalanced builder of every cell before, `None`
+    /// for the gates absorbed into a chain or contributing to no output.
+    pub wires: Vec<Option<Wire>>,
+}
+
+/// An error loading a [`CircuitBuilder`] saved by
+/// [`CircuitBuilder::save`].
+#[cfg(feature = "serde")]
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    /// The bytes do not decode.
+    #[error("malformed checkpoint: {0}")]
+    Decode(#[from] bincode::Error),
+
+    /// The decoded cells do not make up a layered builder.
+    #[error("inconsistent checkpoint")]
+    Inconsistent,
+}
+
+/// The state of a [`CircuitBuilder`] as saved by
+/// [`CircuitBuilder::save`], the gates being ordered by cell.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Checkpoint {
+    cells: Vec<Cell>,
+    gates: Vec<(usize, CellGateType)>,
+    n_layer: usize,
+    n_input: usize,
+    outputs: Vec<usize>,
+    options: BuildOptions,
+}
+
+#[cfg(feature = "serde")]
+impl Checkpoint {
+    fn new(builder: &CircuitBuilder) -> Self {
+        let mut gates: Vec<_> = builder
+            .gatehashset
+            .iter()
+            .map(|(gt, &idx)| (idx, gt.clone()))
+            .collect();
+        gates.sort();
+
+        Self {
+            cells: builder.cells.clone(),
+            gates,
+            n_layer: builder.n_layer,
+            n_input: builder.n_input,
+            outputs: builder.outputs.clone(),
+            options: builder.options,
+        }
+    }
+}
+
+impl TryFrom<(Vec<CircuitLayer>, usize)> for Circuit {
+    type Error = BuildError;
+
+    /// [`Circuit::new`] from the layers, output layer first, and the
+    /// number of inputs, failing if a gate reads past the width of
+    /// the layer below.
+    fn try_from((layers, num_inputs): (Vec<CircuitLayer>, usize)) -> Result<Self, BuildError> {
+        let widths = layers.iter().skip(1).map(CircuitLayer::len);
+        for (layer, (gates, width)) in layers.iter().zip(widths.chain([num_inputs])).enumerate() {
+            for (label, gate) in gates.gates().iter().enumerate() {
+                if let Some(&input) = gate.operands().iter().find(|&&input| input >= width) {
+                    return Err(BuildError::GateInputOutOfRange {
+                        layer,
+                        label,
+                        input,
+                        width,
+                    });
+                }
+            }
+        }
+
+        Ok(Circuit::new(layers, num_inputs))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Checkpoint> for CircuitBuilder {
+    type Error = LoadError;
+
+    fn try_from(checkpoint: Checkpoint) -> Result<Self, LoadError> {
+        let n_cells = checkpoint.cells.len();
+
+        let builder = Self {
+            cells: checkpoint.cells,
+            gatehashset: checkpoint
+                .gates
+                .into_iter()
+                .map(|(idx, gt)| (gt, idx))
+                .collect(),
+            n_layer: checkpoint.n_layer,
+            n_input: checkpoint.n_input,
+            outputs: checkpoint.outputs,
+            options: checkpoint.options,
+        };
+
+        let in_range = builder.cells.iter().enumerate().all(|(i, cell)| {
+            cell.index == i
+                && cell.layer_id < builder.n_layer
+                && match cell.gate_type.operands() {
+                    Some((l, r)) => l < n_cells && r < n_cells,
+                    None => true,
+                }
+        }) && builder.gatehashset.values().all(|&idx| idx < n_cells)
+            && builder.outputs.iter().all(|&idx| idx < n_cells);
+
+        if !in_range || !builder.is_strictly_layered() {
+            return Err(LoadError::Inconsistent);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// A builder serializes as the checkpoint of [`CircuitBuilder::save`].
+#[cfg(feature = "serde")]
+impl serde::Serialize for CircuitBuilder {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Checkpoint::new(self).serialize(serializer)
+    }
+}
+
+/// Deserializing checks the builder like [`CircuitBuilder::load`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CircuitBuilder {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Checkpoint::deserialize(deserializer)?
+            .try_into()
+            .map_err(serde::de::Error::custom)
+    }
 }
 
+/// A single cell of the [`CircuitBuilder`].
 #[derive(Clone, Debug)]
-pub struct Cell{
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cell {
     index: usize,
     layer_id: usize,
     gate_type: CellGateType,
+    label: Option<String>,
 }
 
-pub struct CircuitBuilder{
+/// A builder of layered circuits.
+///
+/// Witnesses live in layer $0$ and every gate is placed one layer
+/// above its inputs, which therefore have to share a layer.
+/// The cells of the topmost layer become the outputs of the
+/// built [`Circuit`], along with the cells marked by
+/// [`CircuitBuilder::mark_output`] which are relayed up there.
+///
+/// Constants are inputs too, their values are filled in by
+/// [`CircuitBuilder::input_values`].
+#[derive(Clone, Default)]
+pub struct CircuitBuilder {
     cells: Vec<Cell>,
-    gatehashset: HashSet<CellGateType>,
+    gatehashset: HashMap<CellGateType, usize>,
     n_layer: usize,
     n_input: usize,
+    outputs: Vec<usize>,
+    options: BuildOptions,
 }
 
 impl CircuitBuilder {
+    /// Create an empty `CircuitBuilder`.
     pub fn new() -> Self {
-        Self{cells: vec![], gatehashset: HashSet::new(), n_layer: 0, n_input: 0}
+        Self {
+            cells: vec![],
+            gatehashset: HashMap::new(),
+            n_layer: 0,
+            n_input: 0,
+            outputs: vec![],
+            options: BuildOptions::default(),
+        }
+    }
+
+    /// Set the options of [`CircuitBuilder::build_circuit`].
+    pub fn set_options(&mut self, options: BuildOptions) {
+        self.options = options;
+    }
+
+    /// The builder laying the gates of every layer out by their type,
+    /// coefficients and inputs if `canonical_layout` is set, from the
+    /// input layer up, so that builders adding the same gates in any
+    /// order build the same circuit.
+    ///
+    /// The outputs are laid out the same, the inputs keep their order.
+    pub fn with_canonical_layout(mut self, canonical_layout: bool) -> Self {
+        self.options.canonical_layout = canonical_layout;
+        self
+    }
+
+    /// Serialize the state of the builder.
+    ///
+    /// The encoding is deterministic: saving equal builders gives
+    /// equal bytes.
+    #[cfg(feature = "serde")]
+    pub fn save(&self) -> Vec<u8> {
+        bincode::serialize(&Checkpoint::new(self)).expect("builders always serialize")
+    }
+
+    /// Deserialize a builder saved by [`CircuitBuilder::save`].
+    ///
+    /// The loaded builder hands out the same indices as the saved one
+    /// would have.
+    #[cfg(feature = "serde")]
+    pub fn load(bytes: &[u8]) -> Result<Self, LoadError> {
+        bincode::deserialize::<Checkpoint>(bytes)?.try_into()
+    }
+
+    /// Write the state of the builder to `writer`, the bytes of
+    /// [`CircuitBuilder::save`].
+    #[cfg(feature = "serde")]
+    pub fn save_to(&self, mut writer: impl io::Write) -> io::Result<()> {
+        writer.write_all(&self.save())
+    }
+
+    /// Read a builder written by [`CircuitBuilder::save_to`] from
+    /// `reader`, which is left right after it.
+    #[cfg(feature = "serde")]
+    pub fn load_from(reader: impl io::Read) -> Result<Self, LoadError> {
+        bincode::deserialize_from::<_, Checkpoint>(reader)?.try_into()
     }
 
+    /// A builder holding the gates of `circuit`, to be extended.
+    ///
+    /// The inputs become public inputs and witnesses, constants being
+    /// indistinguishable from witnesses in a circuit, and the gate
+    /// labels become the labels of the cells. Also returns the cell of
+    /// every gate by layer and label, layer $0$ being the output layer
+    /// and the input layer coming last.
+    ///
+    /// Building the builder as is gives `circuit` back, provided every
+    /// gate below the output layer and every input feeds some gate:
+    /// [`CircuitBuilder::build_circuit`] drops the other ones.
+    ///
+    /// Fails with [`BuildError::UnsupportedGate`] on relays, on scaled
+    /// multiplications and subtractions and on inputs out of range, and with
+    /// [`BuildError::DuplicateGate`] if a layer repeats a gate.
+    pub fn from_circuit(circuit: &Circuit) -> Result<(Self, Vec<Vec<Wire>>), BuildError> {
+        let mut builder = Self::new();
+        let d = circuit.num_layers();
+        let labels = circuit.gate_labels();
+
+        let mut cells = vec![vec![]; d + 1];
+        cells[d] = (0..circuit.num_inputs())
+            .map(|input| match input < circuit.num_public_inputs() {
+                true => builder.apply_public_input(),
+                false => builder.apply_witness(),
+            })
+            .collect();
+
+        for layer in (0..d).rev() {
+            let below = &cells[layer + 1];
+            cells[layer] = circuit
+                .layer(layer)
+                .gates()
+                .iter()
+                .enumerate()
+                .map(|(label, gate)| {
+                    let [l, r] = gate.inputs().map(|input| below.get(input).copied());
+                    let (Some(l), Some(r)) = (l, r) else {
+                        return Err(BuildError::UnsupportedGate { layer, label });
+                    };
+                    let gt = match (gate.ttype(), gate.coeffs()) {
+                        (GateType::Add, [1, 1]) => CellGateType::Add(l, r),
+                        (GateType::Mul, [1, 1]) => CellGateType::Mul(l, r),
+                        (GateType::Sub, [1, 1]) => CellGateType::Sub(l, r),
+                        (GateType::Add, [cl, cr]) => CellGateType::ScaledAdd(l, cl, r, cr),
+                        _ => return Err(BuildError::UnsupportedGate { layer, label }),
+                    };
+                    builder.append(gt, l, r)
+                })
+                .collect::<Result<_, _>>()?;
+        }
+
+        for (layer, layer_cells) in cells.iter().enumerate() {
+            for (label, &cell) in layer_cells.iter().enumerate() {
+                if let Some(name) = labels.and_then(|labels| labels.get(layer, label)) {
+                    builder.set_label(cell, name);
+                }
+            }
+        }
+
+        Ok((builder, cells))
+    }
+
+    /// Generate a random builder of `depth` gate layers on top of
+    /// `width` witnesses, every layer being `width` cells wide.
+    ///
+    /// The $j$-th gate of a layer takes the $j$-th cell of the layer
+    /// below as its left input, so no cell gets dropped by
+    /// [`CircuitBuilder::build_circuit`].
+    pub fn random<R: Rng>(rng: &mut R, depth: usize, width: usize) -> Self {
+        let mut builder = Self::new();
+        let mut layer: Vec<_> = (0..width).map(|_| builder.apply_witness()).collect();
+
+        for _ in 0..depth {
+            layer = layer
+                .iter()
+                .map(|&left| {
+                    let right = layer[rng.gen_range(0..width)];
+                    let gt = match rng.gen_range(0..3) {
+                        0 => CellGateType::Add(left, right),
+                        1 => CellGateType::Mul(left, right),
+                        _ => CellGateType::Sub(left, right),
+                    };
+                    builder
+                        .append(gt, left, right)
+                        .expect("distinct gates within a single layer")
+                })
+                .collect();
+        }
+
+        builder
+    }
+
+    /// Add a new witness to the input layer returning its index.
     pub fn apply_witness(&mut self) -> usize {
+        self.apply_input(CellGateType::Witness)
+    }
+
+    /// Add `n` new witnesses returning their indices.
+    pub fn apply_witnesses(&mut self, n: usize) -> Range<Wire> {
+        let start = self.cells.len();
+        self.cells.reserve(n);
+        for _ in 0..n {
+            self.apply_witness();
+        }
+
+        start..self.cells.len()
+    }
+
+    /// Add a new witness labeled `label` returning its index.
+    pub fn apply_witness_named(&mut self, label: &str) -> usize {
+        let cell = self.apply_witness();
+        self.set_label(cell, label);
+        cell
+    }
+
+    /// Add a new public input to the input layer returning its index.
+    ///
+    /// Public inputs come first in the input layer of the built
+    /// circuit, see [`Circuit::num_public_inputs`].
+    pub fn apply_public_input(&mut self) -> usize {
+        self.apply_input(CellGateType::PublicInput)
+    }
+
+    fn apply_input(&mut self, gate_type: CellGateType) -> usize {
         let idx = self.cells.len();
         let cell = Cell {
             index: idx,
             layer_id: 0,
-            gate_type: CellGateType::Witness,
+            gate_type,
+            label: None,
         };
         self.cells.push(cell);
         if self.n_layer == 0 {
@@ -48,35 +719,1003 @@ impl CircuitBuilder {
         idx
     }
 
+    /// Add a constant to the input layer returning its index.
+    ///
+    /// Constants are interned: asking for the same value twice
+    /// returns the same cell.
+    pub fn constant(&mut self, value: u64) -> usize {
+        let gt = CellGateType::Const(value);
+        if let Some(idx) = self.gatehashset.get(&gt) {
+            return *idx;
+        }
+
+        let idx = self.cells.len();
+        self.cells.push(Cell {
+            index: idx,
+            layer_id: 0,
+            gate_type: gt.clone(),
+            label: None,
+        });
+        if self.n_layer == 0 {
+            self.n_layer = 1
+        };
+        self.gatehashset.insert(gt, idx);
+        idx
+    }
+
+    /// The constant zero, created on first use.
+    pub fn zero(&mut self) -> usize {
+        self.constant(0)
+    }
+
+    /// The constant one, created on first use.
+    pub fn one(&mut self) -> usize {
+        self.constant(1)
+    }
+
+    /// Add a gate of type `gate` on top of `left` and `right`
+    /// returning its index.
+    ///
+    /// Relays go through [`CircuitBuilder::relay`], ignoring `right`.
+    pub fn append_gate(
+        &mut self,
+        gate: GateType,
+        left: usize,
+        right: usize,
+    ) -> Result<usize, BuildError> {
+        match gate {
+            GateType::Add => self.append(CellGateType::Add(left, right), left, right),
+            GateType::Mul => self.append(CellGateType::Mul(left, right), left, right),
+            GateType::Sub => self.append(CellGateType::Sub(left, right), left, right),
+            GateType::Relay => self.relay(left),
+        }
+    }
+
+    /// Add an addition gate on top of `left` and `right` returning its index.
     pub fn append_add_gate(&mut self, left: usize, right: usize) -> Result<usize, BuildError> {
-        let gt = CellGateType::Add(left, right);
-        if self.gatehashset.contains(&gt) {
-            Err(BuildError::DuplicateGate)
-        } else if self.cells[left].layer_id != self.cells[right].layer_id {
-            Err(BuildError::IllegalGate)
-        } else {
-            let idx = self.cells.len();
-            let layer = self.cells[left].layer_id + 1;
-            if layer == self.n_layer {
-                self.n_layer += 1
+        self.append_gate(GateType::Add, left, right)
+    }
+
+    /// Add a multiplication gate on top of `left` and `right` returning its index.
+    pub fn append_mul_gate(&mut self, left: usize, right: usize) -> Result<usize, BuildError> {
+        self.append_gate(GateType::Mul, left, right)
+    }
+
+    /// Add a gate computing `left - right` returning its index.
+    pub fn append_sub_gate(&mut self, left: usize, right: usize) -> Result<usize, BuildError> {
+        self.append_gate(GateType::Sub, left, right)
+    }
+
+    /// Add the gates `(ttype, left, right)` in order returning their
+    /// indices, so that a gate may take the previous ones as inputs.
+    ///
+    /// See [`CircuitBuilder::append_gate`]. The gates are added all
+    /// or nothing: if one of them fails, the
+    /// ones added before it are taken back and the builder is left
+    /// as it was.
+    pub fn append_gates(
+        &mut self,
+        gates: &[(GateType, usize, usize)],
+    ) -> Result<Vec<usize>, BuildError> {
+        self.cells.reserve(gates.len());
+        self.gatehashset.reserve(gates.len());
+
+        self.atomically(|builder| {
+            gates
+                .iter()
+                .map(|&(ttype, left, right)| builder.append_gate(ttype, left, right))
+                .collect()
+        })
+    }
+
+    /// Add a gate computing `left_coeff * left + right_coeff * right`
+    /// returning its index.
+    ///
+    /// The coefficients are part of the gate, so this takes a single
+    /// layer where multiplying by constants would take two.
+    pub fn append_scaled_add_gate(
+        &mut self,
+        left: usize,
+        left_coeff: u64,
+        right: usize,
+        right_coeff: u64,
+    ) -> Result<usize, BuildError> {
+        self.append(
+            CellGateType::ScaledAdd(left, left_coeff, right, right_coeff),
+            left,
+            right,
+        )
+    }
+
+    /// Add a gate computing `left + right` labeled `label`.
+    pub fn append_add_gate_named(
+        &mut self,
+        left: usize,
+        right: usize,
+        label: &str,
+    ) -> Result<usize, BuildError> {
+        let cell = self.append_add_gate(left, right)?;
+        self.set_label(cell, label);
+        Ok(cell)
+    }
+
+    /// Add a gate computing `left * right` labeled `label`.
+    pub fn append_mul_gate_named(
+        &mut self,
+        left: usize,
+        right: usize,
+        label: &str,
+    ) -> Result<usize, BuildError> {
+        let cell = self.append_mul_gate(left, right)?;
+        self.set_label(cell, label);
+        Ok(cell)
+    }
+
+    /// Add a gate computing `left - right` labeled `label`.
+    pub fn append_sub_gate_named(
+        &mut self,
+        left: usize,
+        right: usize,
+        label: &str,
+    ) -> Result<usize, BuildError> {
+        let cell = self.append_sub_gate(left, right)?;
+        self.set_label(cell, label);
+        Ok(cell)
+    }
+
+    /// Label `cell` for debugging, replacing any earlier label.
+    ///
+    /// Labels show up in the errors of the builder and are passed on
+    /// to the built circuit, see [`Circuit::gate_labels`].
+    pub fn set_label(&mut self, cell: Wire, label: &str) {
+        self.cells[cell].label = Some(label.to_owned());
+    }
+
+    /// The label of `cell`, if any.
+    pub fn label(&self, cell: Wire) -> Option<&str> {
+        self.cells[cell].label.as_deref()
+    }
+
+    /// Pass `cell` one layer up unchanged returning the index of the relay.
+    ///
+    /// A relay adds the constant zero, lifted to the layer of `cell`,
+    /// to the `cell`. Relays and zeroes are interned, so relaying the
+    /// same cell twice returns the same relay.
+    pub fn relay(&mut self, cell: usize) -> Result<usize, BuildError> {
+        self.check_wire(cell)?;
+        let layer = self.layer(cell);
+        let mut zero = self.zero();
+        for _ in 0..layer {
+            zero = self.intern(CellGateType::Add(zero, zero), zero, zero)?;
+        }
+
+        self.intern(CellGateType::Add(cell, zero), cell, zero)
+    }
+
+    /// Relay `cell` up to `layer` returning the index of the last relay,
+    /// so that gates may take it along with the cells of `layer`.
+    ///
+    /// Every relay passes its cell on unchanged and is interned, so
+    /// lifting a cell twice adds no gates. Returns `cell` itself if it
+    /// already belongs to `layer`, and [`BuildError::IllegalGate`] if
+    /// it sits above `layer`.
+    pub fn lift(&mut self, mut cell: Wire, layer: usize) -> Result<Wire, BuildError> {
+        self.check_wire(cell)?;
+        if self.layer(cell) > layer {
+            return Err(BuildError::IllegalOperands);
+        }
+        while self.layer(cell) < layer {
+            cell = self.relay(cell)?;
+        }
+
+        Ok(cell)
+    }
+
+    /// Add a balanced tree of addition gates summing up `wires`.
+    ///
+    /// The wires are first lifted to the topmost layer among them, so
+    /// the sum ends up $\lceil \log_2 n \rceil$ layers above it. A wire
+    /// left over on a layer with an odd number of cells is relayed.
+    /// The sum of no wires is the constant zero.
+    pub fn sum(&mut self, wires: &[Wire]) -> Result<Wire, BuildError> {
+        self.atomically(|builder| match wires.is_empty() {
+            true => Ok(builder.zero()),
+            false => builder.reduce(wires, CellGateType::Add),
+        })
+    }
+
+    /// Add a balanced tree of multiplication gates over `wires`.
+    ///
+    /// The depth of the tree is that of [`CircuitBuilder::sum`].
+    /// The product of no wires is the constant one.
+    pub fn product(&mut self, wires: &[Wire]) -> Result<Wire, BuildError> {
+        self.atomically(|builder| match wires.is_empty() {
+            true => Ok(builder.one()),
+            false => builder.reduce(wires, CellGateType::Mul),
+        })
+    }
+
+    /// Add the inner product $\langle a, b \rangle$: a layer of
+    /// pairwise multiplications followed by [`CircuitBuilder::sum`].
+    pub fn inner_product(&mut self, a: &[Wire], b: &[Wire]) -> Result<Wire, BuildError> {
+        self.atomically(|builder| {
+            if a.len() != b.len() {
+                return Err(BuildError::LengthMismatch);
             }
-            let cell = Cell {
-                index: idx,
-                layer_id: layer,
-                gate_type: gt.clone(),
+
+            let products = iter::zip(a, b)
+                .map(|(&a, &b)| {
+                    let (a, b) = builder.align(a, b)?;
+                    builder.intern(CellGateType::Mul(a, b), a, b)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            builder.sum(&products)
+        })
+    }
+
+    /// Add the power $x^k$ computed by square-and-multiply.
+    ///
+    /// Takes $\lfloor \log_2 k \rfloor$ squarings and one multiplication
+    /// per set bit of $k$ but the lowest one, so the power ends up at
+    /// most $\lfloor \log_2 k \rfloor + 1$ layers above `x`. Partial
+    /// products are relayed up to the squares they get multiplied by.
+    ///
+    /// $x^0$ is the constant one and $x^1$ is `x` itself.
+    pub fn pow(&mut self, x: Wire, k: u64) -> Result<Wire, BuildError> {
+        self.atomically(|builder| {
+            if k == 0 {
+                return Ok(builder.one());
+            }
+
+            let mut acc = None;
+            let mut square = x;
+            let mut k = k;
+
+            loop {
+                if k & 1 == 1 {
+                    acc = Some(match acc {
+                        None => square,
+                        Some(acc) => builder.commutative(CellGateType::Mul, acc, square)?,
+                    });
+                }
+
+                k >>= 1;
+                if k == 0 {
+                    return Ok(acc.unwrap());
+                }
+
+                square = builder.intern(CellGateType::Mul(square, square), square, square)?;
+            }
+        })
+    }
+
+    /// Add a commutative gate lifting the lower of the operands and
+    /// ordering them, so that the gate is shared with the swapped one.
+    fn commutative(
+        &mut self,
+        gate: fn(usize, usize) -> CellGateType,
+        l: Wire,
+        r: Wire,
+    ) -> Result<Wire, BuildError> {
+        let (l, r) = self.align(l, r)?;
+        let (l, r) = (l.min(r), l.max(r));
+
+        self.intern(gate(l, r), l, r)
+    }
+
+    /// Lift the lower of the two cells up to the layer of the other one.
+    fn align(&mut self, l: Wire, r: Wire) -> Result<(Wire, Wire), BuildError> {
+        self.check_wire(l)?;
+        self.check_wire(r)?;
+        let layer = self.layer(l).max(self.layer(r));
+        Ok((self.lift(l, layer)?, self.lift(r, layer)?))
+    }
+
+    /// Stamp the gates of `template` on top of the topmost layer
+    /// `times` over, returning the cells of the last layer.
+    ///
+    /// The operands of the template gates are positions in the layer
+    /// below, the cells of a layer being ordered by index. Repeating
+    /// a template as wide as the inputs over the input layer gives a
+    /// uniform [`Circuit`], see [`Circuit::is_uniform`].
+    pub fn repeat_layer(
+        &mut self,
+        template: &[CellGateType],
+        times: usize,
+    ) -> Result<Vec<Wire>, BuildError> {
+        self.atomically(|builder| {
+            let top = builder.n_layer.saturating_sub(1);
+            let mut frontier: Vec<_> = builder
+                .cells
+                .iter()
+                .filter(|cell| cell.layer_id == top)
+                .map(|cell| cell.index)
+                .collect();
+
+            for _ in 0..times {
+                frontier = template
+                    .iter()
+                    .map(|gt| {
+                        let (l, r) = gt.operands().ok_or(BuildError::IllegalOperands)?;
+                        let (&l, &r) = frontier
+                            .get(l)
+                            .zip(frontier.get(r))
+                            .ok_or(BuildError::IllegalOperands)?;
+
+                        builder.append(gt.rewired(l, r), l, r)
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+
+            Ok(frontier)
+        })
+    }
+
+    /// Add a multiplexer computing $cond \cdot a + (1 - cond) \cdot b$,
+    /// that is `a` if `cond` is one and `b` if it is zero.
+    ///
+    /// It is lowered to $b + cond \cdot (a - b)$, the operands being
+    /// relayed as needed, so it takes three layers above the topmost
+    /// operand. `cond` is not constrained to be boolean, combine with
+    /// [`CircuitBuilder::assert_boolean`] for that.
+    pub fn select(&mut self, cond: Wire, a: Wire, b: Wire) -> Result<Wire, BuildError> {
+        self.atomically(|builder| {
+            let (a, b_lifted) = builder.align(a, b)?;
+            let diff = builder.intern(CellGateType::Sub(a, b_lifted), a, b_lifted)?;
+            let scaled = builder.commutative(CellGateType::Mul, cond, diff)?;
+
+            builder.commutative(CellGateType::Add, b, scaled)
+        })
+    }
+
+    /// Make `cell` an output of the built circuit.
+    ///
+    /// Cells below the topmost layer get relayed up to it when
+    /// the circuit is built.
+    pub fn mark_output(&mut self, cell: Wire) {
+        self.outputs.push(cell);
+    }
+
+    /// Constrain `x` to $\lbrace 0, 1 \rbrace$ by the output
+    /// $x \cdot x - x$ which must evaluate to zero.
+    ///
+    /// Returns the constraint output.
+    pub fn assert_boolean(&mut self, x: Wire) -> Result<Wire, BuildError> {
+        self.atomically(|builder| {
+            let square = builder.intern(CellGateType::Mul(x, x), x, x)?;
+            let x = builder.relay(x)?;
+            let constraint = builder.intern(CellGateType::Sub(square, x), square, x)?;
+
+            builder.mark_output(constraint);
+            Ok(constraint)
+        })
+    }
+
+    /// Decompose `x` into `n_bits` bits, lowest first.
+    ///
+    /// The bits are fresh witnesses whose values the caller supplies
+    /// in [`CircuitBuilder::input_values`]. Every bit is constrained
+    /// by [`CircuitBuilder::assert_boolean`] and one more output
+    /// $\sum_i 2^i b_i - x$ checks the recomposition, so a valid
+    /// decomposition makes all of them zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_bits` exceeds $64$.
+    pub fn decompose_bits(&mut self, x: Wire, n_bits: usize) -> Result<Vec<Wire>, BuildError> {
+        assert!(n_bits <= 64, "at most 64 bits fit the constants");
+
+        self.atomically(|builder| {
+            let bits: Vec<_> = (0..n_bits).map(|_| builder.apply_witness()).collect();
+            for bit in &bits {
+                builder.assert_boolean(*bit)?;
+            }
+
+            let terms = bits
+                .iter()
+                .enumerate()
+                .map(|(i, &bit)| {
+                    let power = builder.constant(1 << i);
+                    builder.commutative(CellGateType::Mul, power, bit)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let sum = builder.sum(&terms)?;
+
+            let (sum, x) = builder.align(sum, x)?;
+            let constraint = builder.intern(CellGateType::Sub(sum, x), sum, x)?;
+            builder.mark_output(constraint);
+
+            Ok(bits)
+        })
+    }
+
+    /// Propagate constants from the input layer upwards and simplify
+    /// trivial gates.
+    ///
+    /// Gates over constants are replaced by the lifted constant they
+    /// evaluate to, as long as it fits a `u64`. Multiplications by zero
+    /// and differences of a cell with itself are replaced by zero,
+    /// multiplications by one, additions of zero and subtractions of
+    /// zero by a relay of the other operand. Consumers get rewired to
+    /// the replacements.
+    ///
+    /// Only the cells contributing to an output are considered. Cells
+    /// are never removed so that wires held by the caller stay valid;
+    /// the replaced ones are dropped by
+    /// [`CircuitBuilder::build_circuit`]. The cells of the topmost
+    /// layer and the marked outputs keep their place among the outputs,
+    /// only their operands get rewired.
+    pub fn fold_constants(&mut self) -> Result<FoldSummary, BuildError> {
+        let live_before = self.num_live_gates()?;
+        let mut summary = FoldSummary::default();
+
+        let top = self.n_layer.saturating_sub(1);
+        let protected: HashSet<_> = self.outputs.iter().copied().collect();
+
+        // Only the cells contributing to an output are visited, input
+        // layer first. Rewired operands may come after their consumers
+        // so the indices alone do not order them.
+        let n_cells = self.cells.len();
+        let live = self.with_outputs()?.layered_cells()?;
+        let order: Vec<_> = live
+            .iter()
+            .rev()
+            .flatten()
+            .filter(|&&cell| cell < n_cells)
+            .copied()
+            .collect();
+
+        let mut values = vec![None; n_cells];
+        let mut replacements: Vec<_> = (0..n_cells).collect();
+
+        for cell in order {
+            let gate_type = self.cells[cell].gate_type.clone();
+            let Some((l, r)) = gate_type.operands() else {
+                values[cell] = Self::fold(&gate_type, &values);
+                continue;
             };
-            self.gatehashset.insert(gt);
-            self.cells.push(cell);
-            Ok(idx)
+            let (l, r) = (replacements[l], replacements[r]);
+            let rewired = gate_type.rewired(l, r);
+
+            let value = Self::fold(&rewired, &values);
+            values[cell] = value;
+
+            if self.cells[cell].layer_id == top || protected.contains(&cell) {
+                self.gatehashset.entry(rewired.clone()).or_insert(cell);
+                self.cells[cell].gate_type = rewired;
+                continue;
+            }
+
+            let (left, right) = (values[l], values[r]);
+            let zero = match rewired {
+                CellGateType::Mul(..) => left == Some(0) || right == Some(0),
+                CellGateType::Sub(..) => l == r,
+                _ => false,
+            };
+            let identity = match rewired {
+                CellGateType::Mul(..) if left == Some(1) => Some(r),
+                CellGateType::Mul(..) if right == Some(1) => Some(l),
+                CellGateType::Add(..) if left == Some(0) => Some(r),
+                CellGateType::Add(..) | CellGateType::Sub(..) if right == Some(0) => Some(l),
+                _ => None,
+            };
+
+            let layer = self.cells[cell].layer_id;
+            let replacement = if let Some(value) = value.or(zero.then_some(0)) {
+                let constant = self.constant(value);
+                let lifted = self.lift(constant, layer)?;
+                if lifted != cell {
+                    summary.folded += 1;
+                }
+                lifted
+            } else if let Some(operand) = identity {
+                // Relays are additions of zero themselves.
+                let relay = self.relay(operand)?;
+                if relay != cell {
+                    summary.simplified += 1;
+                }
+                relay
+            } else if rewired != gate_type {
+                self.intern(rewired, l, r)?
+            } else {
+                cell
+            };
+
+            self.track_new_cells(&mut values, &mut replacements);
+            replacements[cell] = replacement;
         }
+
+        summary.eliminated = live_before.saturating_sub(self.num_live_gates()?);
+        Ok(summary)
     }
 
-    pub fn append_mul_gate(&mut self, left: usize, right: usize) -> Result<usize, BuildError> {
-        let gt = CellGateType::Mul(left, right);
-        if self.gatehashset.contains(&gt) {
-            Err(BuildError::DuplicateGate)
+    /// Rewrite chains of additions or of multiplications as balanced
+    /// trees and lay the cells out anew, reducing the depth.
+    ///
+    /// A chain is a maximal tree of gates of the same operation whose
+    /// inner gates feed nothing else. Relays and constants are seen
+    /// through, zeroes dropped from sums. The order of the operands
+    /// is kept, so only associativity is relied upon.
+    ///
+    /// The builder is rebuilt from scratch: the inputs come first and
+    /// in the same order, then the gates contributing to an output,
+    /// whose new wires are given by [`RebalanceReport::wires`]. Wires
+    /// held from before must be translated through it. The cells of
+    /// the topmost layer stay there in the same order, the marked
+    /// outputs are relayed up as usual. The other gates, inner gates
+    /// of chains included, are dropped.
+    pub fn rebalance(&mut self) -> Result<RebalanceReport, BuildError> {
+        let old_depth = self.n_layer.saturating_sub(1);
+        let n_cells = self.cells.len();
+        let original: Vec<_> = self.cells.iter().map(|c| c.gate_type.clone()).collect();
+        let operands = |cell: usize| original[cell].operands();
+
+        let mut by_layer: Vec<_> = (0..n_cells).collect();
+        by_layer.sort_by_key(|&cell| self.cells[cell].layer_id);
+        let mut values = vec![None; n_cells];
+        for &cell in &by_layer {
+            values[cell] = Self::fold(&original[cell], &values);
+        }
+        let is_relay = |cell: usize| match original[cell] {
+            CellGateType::Add(l, r) => {
+                values[cell].is_none() && (values[l] == Some(0) || values[r] == Some(0))
+            }
+            _ => false,
+        };
+
+        let top = self.n_layer.saturating_sub(1);
+        let outputs: HashSet<_> = (0..n_cells)
+            .filter(|&cell| self.cells[cell].layer_id == top)
+            .chain(self.outputs.iter().copied())
+            .collect();
+        let live = self.live_cells()?;
+
+        // A gate is absorbed into the chain of its only consumer.
+        let mut consumers = vec![0; n_cells];
+        let mut consumer = vec![0; n_cells];
+        for &cell in &live {
+            for operand in operands(cell).into_iter().flat_map(|(l, r)| [l, r]) {
+                consumers[operand] += 1;
+                consumer[operand] = cell;
+            }
+        }
+        let absorbed = |cell: usize| {
+            consumers[cell] == 1
+                && !outputs.contains(&cell)
+                && !is_relay(consumer[cell])
+                && matches!(
+                    (&original[cell], &original[consumer[cell]]),
+                    (CellGateType::Add(..), CellGateType::Add(..))
+                        | (CellGateType::Mul(..), CellGateType::Mul(..))
+                )
+        };
+
+        // The inputs first, so that they keep their order.
+        let mut new = CircuitBuilder::new();
+        new.options = self.options;
+        let mut wires = vec![None; n_cells];
+        for cell in 0..n_cells {
+            wires[cell] = match original[cell] {
+                CellGateType::Witness => Some(new.apply_witness()),
+                CellGateType::PublicInput => Some(new.apply_public_input()),
+                CellGateType::Const(value) => Some(new.constant(value)),
+                _ => None,
+            };
+        }
+
+        let mut new_values = vec![];
+        let mut top_operands = vec![];
+        for cell in by_layer {
+            let Some((l, r)) = operands(cell) else {
+                continue;
+            };
+            if !live.contains(&cell) || absorbed(cell) {
+                continue;
+            }
+
+            let wire = |cell: usize| wires[cell].expect("operands placed below");
+            let (l, r) = match original[cell] {
+                CellGateType::Add(..) | CellGateType::Mul(..) => {
+                    let leaves =
+                        Self::chain_leaves(&mut new, cell, &original, &values, &wires, &absorbed);
+                    let gate = match original[cell] {
+                        CellGateType::Add(..) => CellGateType::Add,
+                        _ => CellGateType::Mul,
+                    };
+                    let (left, right) = leaves.split_at(leaves.len() / 2);
+                    (new.reduce(left, gate)?, new.reduce(right, gate)?)
+                }
+                _ => (wire(l), wire(r)),
+            };
+
+            new.extend_values(&mut new_values);
+            let (l, r) = new.align_operands(l, r, &new_values)?;
+            if self.cells[cell].layer_id == top {
+                top_operands.push((cell, l, r));
+            } else {
+                let gate_type = original[cell].rewired(l, r);
+                wires[cell] = Some(new.intern(gate_type, l, r)?);
+            }
+        }
+
+        // The outputs of the top layer go above every other cell.
+        let new_top = new.n_layer;
+        for (cell, l, r) in top_operands {
+            new.extend_values(&mut new_values);
+            let (l, r) = (
+                new.materialize(l, new_top - 1, &new_values)?,
+                new.materialize(r, new_top - 1, &new_values)?,
+            );
+            let gate = new.intern(original[cell].rewired(l, r), l, r)?;
+            wires[cell] = Some(new.lift(gate, new_top)?);
+        }
+
+        for (cell, wire) in wires.iter().enumerate() {
+            if let (Some(wire), Some(label)) = (wire, &self.cells[cell].label) {
+                new.set_label(*wire, label);
+            }
+        }
+        new.outputs = self
+            .outputs
+            .iter()
+            .map(|&cell| wires[cell].expect("outputs are live"))
+            .collect();
+        *self = new;
+
+        Ok(RebalanceReport {
+            old_depth,
+            new_depth: self.n_layer.saturating_sub(1),
+            wires,
+        })
+    }
+
+    /// The operands of the chain rooted at `root` in the `original`
+    /// gates, left to right, as `wires` of the rebuilt builder `new`.
+    fn chain_leaves(
+        new: &mut CircuitBuilder,
+        root: usize,
+        original: &[CellGateType],
+        values: &[Option<u64>],
+        wires: &[Option<Wire>],
+        absorbed: &impl Fn(usize) -> bool,
+    ) -> Vec<Wire> {
+        let is_sum = matches!(original[root], CellGateType::Add(..));
+        let expand = |cell: usize| match original[cell] {
+            CellGateType::Add(l, r) if is_sum => Some((l, r)),
+            CellGateType::Mul(l, r) if !is_sum => Some((l, r)),
+            _ => None,
+        };
+
+        let mut leaves = vec![];
+        let (l, r) = expand(root).expect("the root of a chain");
+        let mut stack = vec![r, l];
+
+        while let Some(mut cell) = stack.pop() {
+            // See through relays.
+            while let CellGateType::Add(a, b) = original[cell] {
+                match (values[a], values[b]) {
+                    (_, Some(0)) if values[cell].is_none() => cell = a,
+                    (Some(0), _) if values[cell].is_none() => cell = b,
+                    _ => break,
+                }
+            }
+
+            match (values[cell], expand(cell)) {
+                (Some(0), _) if is_sum => {}
+                (Some(value), _) => leaves.push(new.constant(value)),
+                (None, Some((l, r))) if absorbed(cell) => stack.extend([r, l]),
+                (None, _) => leaves.push(wires[cell].expect("leaves placed below")),
+            }
+        }
+
+        while leaves.len() < 2 {
+            leaves.push(new.zero());
+        }
+        leaves
+    }
+
+    /// Bring two operands to a common layer, constants being brought
+    /// down rather than the other operand up.
+    fn align_operands(
+        &mut self,
+        l: Wire,
+        r: Wire,
+        values: &[Option<u64>],
+    ) -> Result<(Wire, Wire), BuildError> {
+        let value = |cell: usize| values.get(cell).copied().flatten();
+        let layer = match (value(l), value(r)) {
+            (None, Some(_)) => self.layer(l),
+            (Some(_), None) => self.layer(r),
+            (Some(_), Some(_)) => self.layer(l).min(self.layer(r)),
+            (None, None) => self.layer(l).max(self.layer(r)),
+        };
+
+        Ok((
+            self.materialize(l, layer, values)?,
+            self.materialize(r, layer, values)?,
+        ))
+    }
+
+    /// `cell` at `layer`, relayed up or rebuilt from its constant value.
+    fn materialize(
+        &mut self,
+        cell: Wire,
+        layer: usize,
+        values: &[Option<u64>],
+    ) -> Result<Wire, BuildError> {
+        match values.get(cell).copied().flatten() {
+            Some(value) if self.layer(cell) > layer => {
+                let constant = self.constant(value);
+                self.lift(constant, layer)
+            }
+            _ => self.lift(cell, layer),
+        }
+    }
+
+    /// Extend the constant `values` of the cells to the cells added
+    /// since, whose operands are already known.
+    fn extend_values(&self, values: &mut Vec<Option<u64>>) {
+        for cell in values.len()..self.cells.len() {
+            let value = Self::fold(&self.cells[cell].gate_type, values);
+            values.push(value);
+        }
+    }
+
+    /// Extend the constant values and the replacements of
+    /// [`CircuitBuilder::fold_constants`] to the cells added since,
+    /// whose operands are already known.
+    fn track_new_cells(&self, values: &mut Vec<Option<u64>>, replacements: &mut Vec<Wire>) {
+        for cell in values.len()..self.cells.len() {
+            let value = Self::fold(&self.cells[cell].gate_type, values);
+            values.push(value);
+            replacements.push(cell);
+        }
+    }
+
+    /// The value of a gate over constants, if it fits a `u64`.
+    fn fold(gate_type: &CellGateType, values: &[Option<u64>]) -> Option<u64> {
+        match *gate_type {
+            CellGateType::Const(value) => Some(value),
+            CellGateType::Witness | CellGateType::PublicInput => None,
+            CellGateType::Add(l, r) => values[l]?.checked_add(values[r]?),
+            CellGateType::Mul(l, r) => values[l]?.checked_mul(values[r]?),
+            CellGateType::Sub(l, r) => values[l]?.checked_sub(values[r]?),
+            CellGateType::ScaledAdd(l, cl, r, cr) => values[l]?
+                .checked_mul(cl)?
+                .checked_add(values[r]?.checked_mul(cr)?),
+        }
+    }
+
+    /// The values of all the cells given the values of the inputs by
+    /// cell, constants filled in.
+    fn cell_values<F: Field>(&self, mut input: impl FnMut(usize) -> F) -> Vec<F> {
+        let mut by_layer: Vec<_> = (0..self.cells.len()).collect();
+        by_layer.sort_by_key(|&cell| self.cells[cell].layer_id);
+
+        let mut values = vec![F::zero(); self.cells.len()];
+        for cell in by_layer {
+            values[cell] = match self.cells[cell].gate_type {
+                CellGateType::Add(l, r) => values[l] + values[r],
+                CellGateType::Mul(l, r) => values[l] * values[r],
+                CellGateType::Sub(l, r) => values[l] - values[r],
+                CellGateType::ScaledAdd(l, cl, r, cr) => {
+                    F::from(cl) * values[l] + F::from(cr) * values[r]
+                }
+                CellGateType::Witness | CellGateType::PublicInput => input(cell),
+                CellGateType::Const(value) => F::from(value),
+            };
+        }
+
+        values
+    }
+
+    /// The number of gates of the built circuit.
+    fn num_live_gates(&self) -> Result<usize, BuildError> {
+        let cells = self.with_outputs()?.layered_cells()?;
+
+        Ok(cells.iter().rev().skip(1).map(Vec::len).sum())
+    }
+
+    /// A copy of the builder with the marked outputs relayed up to
+    /// the topmost layer.
+    fn with_outputs(&self) -> Result<Self, BuildError> {
+        let mut builder = self.clone();
+        let top = builder.n_layer.saturating_sub(1);
+
+        for &cell in &self.outputs {
+            builder.lift(cell, top)?;
+        }
+
+        Ok(builder)
+    }
+
+    fn reduce(
+        &mut self,
+        wires: &[Wire],
+        gate: fn(usize, usize) -> CellGateType,
+    ) -> Result<Wire, BuildError> {
+        for &w in wires {
+            self.check_wire(w)?;
+        }
+        let top = wires.iter().map(|&w| self.layer(w)).max().unwrap_or(0);
+        let mut level = wires
+            .iter()
+            .map(|&w| self.lift(w, top))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [l, r] => self.intern(gate(l, r), l, r),
+                    [leftover] => self.relay(leftover),
+                    _ => unreachable!(),
+                })
+                .collect::<Result<_, _>>()?;
+        }
+
+        Ok(level[0])
+    }
+
+    /// The layer a `cell` belongs to.
+    pub fn layer(&self, cell: usize) -> usize {
+        self.cells[cell].layer_id
+    }
+
+    /// Whether the inputs of every gate are exactly one layer below it
+    /// and only witnesses and constants sit in the input layer.
+    ///
+    /// Gates appended through the builder always are, relays
+    /// included, so this only fails on a corrupted builder.
+    pub fn is_strictly_layered(&self) -> bool {
+        self.cells
+            .iter()
+            .all(|cell| match cell.gate_type.operands() {
+                Some((l, r)) => {
+                    cell.layer_id > 0
+                        && self.cells[l].layer_id + 1 == cell.layer_id
+                        && self.cells[r].layer_id + 1 == cell.layer_id
+                }
+                None => cell.layer_id == 0,
+            })
+    }
+
+    /// Check the bookkeeping of the builder against its cells: every
+    /// recorded gate is held by exactly the cell it maps to, the input
+    /// count matches the witnesses and the layer count matches the
+    /// highest layer.
+    ///
+    /// The builder maintains these itself, so this only fails on a
+    /// corrupted builder.
+    pub fn check_invariants(&self) -> Result<(), BuildError> {
+        for (gate, &recorded) in &self.gatehashset {
+            let holders = self
+                .cells
+                .iter()
+                .filter(|cell| cell.gate_type == *gate)
+                .count();
+            let held = self
+                .cells
+                .get(recorded)
+                .is_some_and(|cell| cell.gate_type == *gate);
+            if holders != 1 || !held {
+                return Err(BuildError::InconsistentGate {
+                    gate: gate.clone(),
+                    recorded,
+                    holders,
+                });
+            }
+        }
+
+        let witnesses = self
+            .cells
+            .iter()
+            .filter(|cell| {
+                matches!(
+                    cell.gate_type,
+                    CellGateType::Witness | CellGateType::PublicInput
+                )
+            })
+            .count();
+        if self.n_input != witnesses {
+            return Err(BuildError::InputCountMismatch {
+                n_input: self.n_input,
+                witnesses,
+            });
+        }
+
+        let layers = self.cells.iter().map(|c| c.layer_id + 1).max().unwrap_or(0);
+        if self.n_layer != layers {
+            return Err(BuildError::LayerCountMismatch {
+                n_layer: self.n_layer,
+                layers,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Add a gate unless the very same one already exists, in
+    /// which case the index of the existing gate is returned.
+    pub(crate) fn intern(
+        &mut self,
+        gt: CellGateType,
+        left: usize,
+        right: usize,
+    ) -> Result<usize, BuildError> {
+        match self.gatehashset.get(&gt) {
+            Some(idx) => Ok(*idx),
+            None => self.append(gt, left, right),
+        }
+    }
+
+    /// Run the operation `op`, taking back everything it added if it
+    /// fails.
+    ///
+    /// Every operation adding cells goes through here or checks its
+    /// operands before adding any, so that a failing operation leaves
+    /// the builder as it was. The cells `op` adds are staged after the
+    /// existing ones and only kept once it succeeds.
+    fn atomically<T>(
+        &mut self,
+        op: impl FnOnce(&mut Self) -> Result<T, BuildError>,
+    ) -> Result<T, BuildError> {
+        let n_cells = self.cells.len();
+        let (n_layer, n_input) = (self.n_layer, self.n_input);
+        let (n_gates, n_outputs) = (self.gatehashset.len(), self.outputs.len());
+
+        let result = op(self);
+        if result.is_err() {
+            for cell in self.cells.drain(n_cells..) {
+                if self.gatehashset.get(&cell.gate_type) == Some(&cell.index) {
+                    self.gatehashset.remove(&cell.gate_type);
+                }
+            }
+            self.n_layer = n_layer;
+            self.n_input = n_input;
+            self.outputs.truncate(n_outputs);
+            debug_assert_eq!(self.gatehashset.len(), n_gates);
+        }
+        debug_assert!(self.cells[n_cells..].iter().all(|cell| {
+            matches!(
+                cell.gate_type,
+                CellGateType::Witness | CellGateType::PublicInput
+            ) || self.gatehashset.get(&cell.gate_type) == Some(&cell.index)
+        }));
+
+        result
+    }
+
+    /// `cell` along with its label for error reporting.
+    fn cell_ref(&self, cell: usize) -> CellRef {
+        CellRef {
+            index: cell,
+            label: self.cells[cell].label.clone(),
+        }
+    }
+
+    fn check_wire(&self, cell: Wire) -> Result<(), BuildError> {
+        if cell < self.cells.len() {
+            Ok(())
+        } else {
+            Err(BuildError::UnknownWire(cell))
+        }
+    }
+
+    fn append(&mut self, gt: CellGateType, left: usize, right: usize) -> Result<usize, BuildError> {
+        self.check_wire(left)?;
+        self.check_wire(right)?;
+
+        if let Some(&existing) = self.gatehashset.get(&gt) {
+            Err(BuildError::DuplicateGate { gate: gt, existing })
         } else if self.cells[left].layer_id != self.cells[right].layer_id {
-            Err(BuildError::IllegalGate)
+            Err(BuildError::IllegalGate {
+                left: self.cell_ref(left),
+                right: self.cell_ref(right),
+                left_layer: self.cells[left].layer_id,
+                right_layer: self.cells[right].layer_id,
+            })
         } else {
             let idx = self.cells.len();
             let layer = self.cells[left].layer_id + 1;
@@ -87,99 +1726,353 @@ impl CircuitBuilder {
                 index: idx,
                 layer_id: layer,
                 gate_type: gt.clone(),
+                label: None,
             };
-            self.gatehashset.insert(gt);
+            self.gatehashset.insert(gt, idx);
             self.cells.push(cell);
             Ok(idx)
         }
     }
 
-    pub fn build_circuit(&self) -> Circuit {
-        let mut queue = vec![];
-        for cell in self.cells.clone() {
-            if cell.layer_id == self.n_layer - 1 {
-                queue.push(cell.clone())
+    /// The values of the input layer of the circuit built by
+    /// [`CircuitBuilder::build_circuit`].
+    ///
+    /// `witness` provides the value of a witness by its index,
+    /// constants are filled in with their values.
+    pub fn input_values<F: From<u64>>(
+        &self,
+        witness: impl Fn(usize) -> F,
+    ) -> Result<Vec<F>, BuildError> {
+        Ok(self
+            .input_mapping()?
+            .slots
+            .into_iter()
+            .map(|slot| match slot {
+                InputSlot::Witness(cell) | InputSlot::Public(cell) => witness(cell),
+                InputSlot::Const(value) => F::from(value),
+                InputSlot::Padding => F::from(0),
+            })
+            .collect())
+    }
+
+    /// What feeds every input of the circuit built by
+    /// [`CircuitBuilder::build_circuit`].
+    pub fn input_mapping(&self) -> Result<InputMapping, BuildError> {
+        let builder = self.with_outputs()?;
+        let inputs = builder.layered_cells()?.pop().unwrap_or_default();
+
+        let mut slots: Vec<_> = inputs
+            .iter()
+            .map(|&cell| match builder.cells[cell].gate_type {
+                CellGateType::Const(value) => InputSlot::Const(value),
+                CellGateType::PublicInput => InputSlot::Public(cell),
+                _ => InputSlot::Witness(cell),
+            })
+            .collect();
+
+        if self.options.pad_to_power_of_two {
+            slots.resize(slots.len().next_power_of_two(), InputSlot::Padding);
+        }
+
+        let inputs: HashSet<_> = inputs.into_iter().collect();
+        let pruned = (0..self.cells.len())
+            .filter(|cell| {
+                matches!(
+                    self.cells[*cell].gate_type,
+                    CellGateType::Witness | CellGateType::PublicInput
+                ) && !inputs.contains(cell)
+            })
+            .collect();
+
+        Ok(InputMapping { slots, pruned })
+    }
+
+    /// The cells dropped by [`CircuitBuilder::build_circuit`] for not
+    /// contributing to any output, in increasing order.
+    pub fn unreachable_cells(&self) -> Result<Vec<usize>, BuildError> {
+        let live = self.live_cells()?;
+
+        Ok((0..self.cells.len())
+            .filter(|cell| !live.contains(cell))
+            .collect())
+    }
+
+    /// The cells contributing to an output.
+    fn live_cells(&self) -> Result<HashSet<usize>, BuildError> {
+        Ok(self
+            .with_outputs()?
+            .layered_cells()?
+            .into_iter()
+            .flatten()
+            .filter(|&cell| cell < self.cells.len())
+            .collect())
+    }
+
+    /// The layer and the label in the built [`Circuit`] of every cell
+    /// contributing to an output, layer $0$ being the output layer.
+    pub(crate) fn labels(&self) -> Result<HashMap<Wire, (usize, usize)>, BuildError> {
+        Ok(self
+            .with_outputs()?
+            .layered_cells()?
+            .into_iter()
+            .enumerate()
+            .flat_map(|(layer, cells)| {
+                cells
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(label, cell)| (cell, (layer, label)))
+            })
+            .collect())
+    }
+
+    /// Build a layered [`Circuit`] out of the cells.
+    ///
+    /// The circuit is traversed from the output layer downwards, so
+    /// cells that do not contribute to any output are dropped.
+    pub fn build_circuit(&self) -> Result<Circuit, BuildError> {
+        if self.options.deny_dead_code {
+            if let Some(&cell) = self.unreachable_cells()?.first() {
+                return Err(BuildError::DeadCell(self.cell_ref(cell)));
             }
         }
 
+        let builder = self.with_outputs()?;
+        let cells = builder.layered_cells()?;
+
         let mut layers = vec![];
+        for pair in cells.windows(2) {
+            let layer_index: HashMap<_, _> = pair[1]
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| (*cell, i))
+                .collect();
+
+            let mut layer = vec![];
+            for cell in pair[0].iter() {
+                let Some((ttype, l, r, coeffs)) = builder.cells[*cell].gate_type.gate() else {
+                    return Err(BuildError::WitnessNotInInputLayer(self.cell_ref(*cell)));
+                };
+                layer.push(Gate::scaled(
+                    ttype,
+                    [layer_index[&l], layer_index[&r]],
+                    coeffs,
+                ));
+            }
+            layers.push(CircuitLayer::new(layer));
+        }
+
+        let inputs = cells.last().map(Vec::as_slice).unwrap_or_default();
+        let num_public_inputs = inputs
+            .iter()
+            .filter(|&&cell| builder.cells[cell].gate_type == CellGateType::PublicInput)
+            .count();
+        let num_constants = inputs
+            .iter()
+            .filter(|&&cell| matches!(builder.cells[cell].gate_type, CellGateType::Const(_)))
+            .count();
+        let mut circuit = Circuit::new(layers, inputs.len())
+            .with_public_inputs(num_public_inputs)
+            .with_constants(num_constants);
+
+        let gate_labels: BTreeMap<_, _> = self
+            .labels()?
+            .into_iter()
+            .filter(|&(cell, _)| cell < self.cells.len())
+            .filter_map(|(cell, position)| Some((position, self.cells[cell].label.clone()?)))
+            .collect();
+        if !gate_labels.is_empty() {
+            circuit = circuit.with_gate_labels(GateLabels::new(gate_labels));
+        }
+
+        Ok(if self.options.pad_to_power_of_two {
+            circuit.pad_to_power_of_two().0
+        } else {
+            circuit
+        })
+    }
+
+    /// Build the circuit along with where every cell ended up in it.
+    pub fn build_circuit_with_labels(&self) -> Result<(Circuit, WireLabels), BuildError> {
+        let mut labels = self.labels()?;
+        // Drop the relays of the outputs, which are not cells of `self`.
+        labels.retain(|&cell, _| cell < self.cells.len());
+
+        Ok((self.build_circuit()?, WireLabels(labels)))
+    }
+
+    /// Whether the circuit built by [`CircuitBuilder::build_circuit`]
+    /// computes the same outputs as the cells of the builder on
+    /// random inputs drawn from `seed`.
+    ///
+    /// The cells are evaluated one by one, independently of the
+    /// layers of the built circuit, so a disagreement points at a
+    /// wiring bug of the builder. A builder failing to build fails
+    /// the check too.
+    pub fn self_check<F: Field>(&self, seed: u64) -> bool {
+        let (Ok(circuit), Ok(builder)) = (self.build_circuit(), self.with_outputs()) else {
+            return false;
+        };
+        let Ok(cells) = builder.layered_cells() else {
+            return false;
+        };
+
+        let rng = &mut StdRng::seed_from_u64(seed);
+        let values = builder.cell_values(|_| F::rand(rng));
+        let Ok(input) = self.input_values(|cell| values[cell]) else {
+            return false;
+        };
+        let Ok(outputs) = circuit.outputs(&input) else {
+            return false;
+        };
+
+        let top = cells.first().map(Vec::as_slice).unwrap_or_default();
+        outputs.len() >= top.len()
+            && top
+                .iter()
+                .zip(&outputs)
+                .all(|(&cell, output)| values[cell] == *output)
+    }
+
+    /// Indices of the cells contributing to the outputs on per-layer
+    /// basis, output layer first. Every layer is sorted by index,
+    /// except for the public inputs coming first in the input layer
+    /// and the gates of a canonical layout.
+    fn layered_cells(&self) -> Result<Vec<Vec<usize>>, BuildError> {
+        let mut layers: Vec<Vec<usize>> = vec![self
+            .cells
+            .iter()
+            .filter(|cell| cell.layer_id == self.n_layer - 1)
+            .map(|cell| cell.index)
+            .collect()];
+
         let mut hs = HashSet::new();
-        let mut layer_index = HashMap::new();
         for _ in 1usize..self.n_layer {
-            let mut cells = vec![];
-            let n = queue.len();
-            for i in 0usize..n {
-                let cell = queue[i].clone();
-                cells.push(cell.clone());
-                let (l, r) = match cell.gate_type {
-                    CellGateType::Add(x, y) => (x, y),
-                    CellGateType::Mul(x, y) => (x, y),
-                    CellGateType::Witness => panic!("witness only in layer_0")
+            let mut queue = vec![];
+            for cell in layers.last().unwrap() {
+                let Some((l, r)) = self.cells[*cell].gate_type.operands() else {
+                    return Err(BuildError::WitnessNotInInputLayer(self.cell_ref(*cell)));
                 };
-                if !hs.contains(&l) {
-                    hs.insert(l);
-                    queue.push(self.cells[l].clone())
+                if hs.insert(l) {
+                    queue.push(l)
                 }
-                if !hs.contains(&r) {
-                    hs.insert(r);
-                    queue.push(self.cells[r].clone())
+                if hs.insert(r) {
+                    queue.push(r)
                 }
             }
 
-            let newlen = queue.len();
-            queue = queue[n..newlen].to_vec();
-            queue.sort_by(|a, b| a.index.cmp(&b.index));
-            for i in 0usize..queue.len() {
-                layer_index.insert(queue[i].index, i);
-            }
+            queue.sort();
+            layers.push(queue);
+        }
 
-            let mut layer = vec![];
-            for cell in cells {
-                match cell.gate_type {
-                    CellGateType::Add(l, r) => {
-                        layer.push(Gate::new(GateType::Add, [layer_index[&l], layer_index[&r]]));
-                    },
-                    CellGateType::Mul(l, r) => {
-                        layer.push(Gate::new(GateType::Mul, [layer_index[&l], layer_index[&r]]));
-                    },
-                    CellGateType::Witness => panic!("witness only in layer_0")
-                }
+        // Public inputs first and constants last, see `Circuit::num_witnesses`.
+        if let Some(inputs) = layers.last_mut() {
+            inputs.sort_by_key(|&cell| {
+                let rank = match self.cells[cell].gate_type {
+                    CellGateType::PublicInput => 0,
+                    CellGateType::Const(_) => 2,
+                    _ => 1,
+                };
+                (rank, cell)
+            });
+        }
+
+        if self.options.canonical_layout {
+            for i in (1..layers.len()).rev() {
+                let position: HashMap<_, _> = layers[i]
+                    .iter()
+                    .enumerate()
+                    .map(|(position, &cell)| (cell, position))
+                    .collect();
+                layers[i - 1].sort_by_cached_key(|&cell| {
+                    let (ttype, l, r, coeffs) = self.cells[cell].gate_type.gate()?;
+                    Some((ttype, coeffs, position[&l], position[&r]))
+                });
             }
-            layers.push(CircuitLayer::new(layer));
         }
 
-        let num_inputs = queue.len();
-        Circuit::new(layers, num_inputs)
+        Ok(layers)
+    }
+}
+
+impl CircuitSource for CircuitBuilder {
+    fn build(&self) -> Result<Circuit, BuildError> {
+        self.build_circuit()
     }
 }
 
 #[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
-    use super::{CircuitBuilder, BuildError};
-    use crate::circuit::{Circuit, Gate, GateType, CircuitLayer};
+    use ark_ff::{Field, Fp64, MontBackend, MontConfig, Zero};
+    use ark_std::{test_rng, UniformRand};
+
+    use super::{
+        BuildError, BuildOptions, Cell, CellGateType, CircuitBuilder, CircuitSource, FoldSummary,
+        RandomCircuit, Wire,
+    };
+    use crate::{bristol::Bristol, circuit};
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+    use crate::circuit::{Circuit, CircuitLayer, Gate, GateLabels, GateType};
 
     //normal circuit check
     #[test]
-    fn test_circuit_build() {
-        let mut builder = CircuitBuilder::new();
-        let w0 = builder.apply_witness();
-        let w1 = builder.apply_witness();
-        let w2 = builder.apply_witness();
-        let w3 = builder.apply_witness();
-        let v0 = builder.append_mul_gate(w0, w0).unwrap();
-        let v1 = builder.append_mul_gate(w1, w1).unwrap();
-        let v2 = builder.append_mul_gate(w1, w2).unwrap();
-        let v3 = builder.append_mul_gate(w3, w3).unwrap();
-        let _ = builder.append_mul_gate(v0, v1);
-        let _ = builder.append_mul_gate(v2, v3);
+    fn test_circuit_build() {
+        let (c, _) = circuit! {
+            inputs: [w0, w1, w2, w3];
+            v0 = w0 * w0;
+            v1 = w1 * w1;
+            v2 = w1 * w2;
+            v3 = w3 * w3;
+            o0 = v0 * v1;
+            o1 = v2 * v3;
+        }
+        .unwrap();
 
-        let c = builder.build_circuit();
-        let c0 = Circuit::new(vec![CircuitLayer::new(vec![Gate::new(GateType::Mul, [0, 1]), Gate::new(GateType::Mul, [2, 3])]),
-        CircuitLayer::new(vec![Gate::new(GateType::Mul, [0, 0]), Gate::new(GateType::Mul, [1, 1]), Gate::new(GateType::Mul, [1, 2]), Gate::new(GateType::Mul, [3, 3])]),]
-        ,4);
+        let c0 = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 1]),
+                    Gate::new(GateType::Mul, [2, 3]),
+                ]),
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 0]),
+                    Gate::new(GateType::Mul, [1, 1]),
+                    Gate::new(GateType::Mul, [1, 2]),
+                    Gate::new(GateType::Mul, [3, 3]),
+                ]),
+            ],
+            4,
+        );
         assert_eq!(c, c0);
     }
 
+    #[test]
+    fn default_builder() {
+        let build = |mut builder: CircuitBuilder| {
+            let w0 = builder.apply_witness();
+            let w1 = builder.apply_witness();
+            let v0 = builder.append_mul_gate(w0, w1).unwrap();
+            let v1 = builder.append_add_gate(w0, w1).unwrap();
+            builder.append_sub_gate(v0, v1).unwrap();
+            builder.build_circuit().unwrap()
+        };
+        assert_eq!(
+            build(CircuitBuilder::default()),
+            build(CircuitBuilder::new())
+        );
+
+        assert_eq!(
+            CircuitBuilder::default().build_circuit(),
+            Ok(Circuit::default())
+        );
+    }
+
     //change cell order and gate type
     #[test]
     fn test_circuit_build2() {
@@ -195,10 +2088,22 @@ mod tests {
         let v3 = builder.append_add_gate(w3, w3).unwrap();
         let _ = builder.append_add_gate(v2, v3);
 
-        let c = builder.build_circuit();
-        let c0 = Circuit::new(vec![CircuitLayer::new(vec![Gate::new(GateType::Mul, [0, 1]), Gate::new(GateType::Add, [2, 3])]),
-        CircuitLayer::new(vec![Gate::new(GateType::Add, [0, 0]), Gate::new(GateType::Add, [1, 1]), Gate::new(GateType::Mul, [1, 2]), Gate::new(GateType::Add, [3, 3])]),]
-        ,4);
+        let c = builder.build_circuit().unwrap();
+        let c0 = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 1]),
+                    Gate::new(GateType::Add, [2, 3]),
+                ]),
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Add, [0, 0]),
+                    Gate::new(GateType::Add, [1, 1]),
+                    Gate::new(GateType::Mul, [1, 2]),
+                    Gate::new(GateType::Add, [3, 3]),
+                ]),
+            ],
+            4,
+        );
         assert_eq!(c, c0);
     }
 
@@ -220,11 +2125,294 @@ mod tests {
         let v5 = builder.append_mul_gate(w1, w4).unwrap(); //ignored
         let _ = builder.append_mul_gate(v2, v3);
 
-        let c = builder.build_circuit();
-        let c0 = Circuit::new(vec![CircuitLayer::new(vec![Gate::new(GateType::Mul, [0, 1]), Gate::new(GateType::Mul, [2, 3])]),
-        CircuitLayer::new(vec![Gate::new(GateType::Mul, [0, 0]), Gate::new(GateType::Mul, [1, 1]), Gate::new(GateType::Mul, [1, 2]), Gate::new(GateType::Mul, [3, 3])]),]
-        ,4);
+        let c = builder.build_circuit().unwrap();
+        let c0 = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 1]),
+                    Gate::new(GateType::Mul, [2, 3]),
+                ]),
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 0]),
+                    Gate::new(GateType::Mul, [1, 1]),
+                    Gate::new(GateType::Mul, [1, 2]),
+                    Gate::new(GateType::Mul, [3, 3]),
+                ]),
+            ],
+            4,
+        );
         assert_eq!(c, c0);
+
+        assert_eq!(builder.unreachable_cells().unwrap(), vec![w4, v1_1, v5]);
+
+        builder.set_options(BuildOptions {
+            deny_dead_code: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            builder.build_circuit(),
+            Err(BuildError::DeadCell(builder.cell_ref(w4)))
+        );
+    }
+
+    #[test]
+    fn self_check() {
+        type Append = fn(&mut CircuitBuilder, usize, usize) -> Result<usize, BuildError>;
+
+        // The constructions of the three tests above.
+        let sample = |square: Append, top: Append, dead: bool| {
+            let mut builder = CircuitBuilder::new();
+            let w: Vec<_> = (0..5).map(|_| builder.apply_witness()).collect();
+            let v0 = square(&mut builder, w[0], w[0]).unwrap();
+            let v1 = square(&mut builder, w[1], w[1]).unwrap();
+            let v2 = builder.append_mul_gate(w[1], w[2]).unwrap();
+            let v3 = square(&mut builder, w[3], w[3]).unwrap();
+            if dead {
+                builder.append_mul_gate(w[0], w[2]).unwrap();
+                builder.append_mul_gate(w[1], w[4]).unwrap();
+            }
+            builder.append_mul_gate(v0, v1).unwrap();
+            top(&mut builder, v2, v3).unwrap();
+            builder
+        };
+        let mul = CircuitBuilder::append_mul_gate;
+        let add = CircuitBuilder::append_add_gate;
+
+        for builder in [
+            sample(mul, mul, false),
+            sample(add, add, false),
+            sample(mul, mul, true),
+        ] {
+            assert!(builder.self_check::<Fp389>(0));
+            assert!(builder.self_check::<Fp389>(1));
+        }
+
+        let mut builder = CircuitBuilder::new();
+        let x = builder.apply_public_input();
+        let y = builder.apply_witness();
+        let three = builder.constant(3);
+        let v = builder.append_scaled_add_gate(x, 2, y, 5).unwrap();
+        let w = builder.append_mul_gate(y, three).unwrap();
+        builder.mark_output(v);
+        builder.append_sub_gate(v, w).unwrap();
+        builder.set_options(BuildOptions {
+            pad_to_power_of_two: true,
+            ..Default::default()
+        });
+        assert!(builder.self_check::<Fp389>(7));
+
+        assert!(CircuitBuilder::new().self_check::<Fp389>(0));
+    }
+
+    #[test]
+    fn from_circuit() {
+        let book = circuit::circuit_from_book();
+        let (mut builder, cells) = CircuitBuilder::from_circuit(&book).unwrap();
+        assert_eq!(cells, vec![vec![8, 9], vec![4, 5, 6, 7], vec![0, 1, 2, 3]]);
+        assert_eq!(builder.build_circuit(), Ok(book.clone()));
+
+        let labeled = Circuit::new(
+            vec![CircuitLayer::new(vec![
+                Gate::scaled(GateType::Add, [1, 0], [3, 2]),
+                Gate::new(GateType::Sub, [1, 2]),
+            ])],
+            3,
+        )
+        .with_public_inputs(1)
+        .with_gate_labels(GateLabels::new([((0, 1), "diff".into())].into()));
+        let (rebuilt, _) = CircuitBuilder::from_circuit(&labeled).unwrap();
+        assert_eq!(rebuilt.build_circuit(), Ok(labeled));
+
+        // One more layer multiplying the outputs, which stay outputs.
+        let product = builder.append_mul_gate(cells[0][0], cells[0][1]).unwrap();
+        builder.mark_output(cells[0][0]);
+        builder.mark_output(cells[0][1]);
+        let extended = builder.build_circuit().unwrap();
+        assert_eq!(extended.num_layers(), 3);
+        assert_eq!(builder.layer(product), 3);
+        // The relays of the outputs add the constant zero to the inputs.
+        let input = builder.input_values(|cell| [3u64, 2, 3, 1][cell]).unwrap();
+        assert_eq!(input, vec![3, 2, 3, 1, 0]);
+        assert_eq!(extended.outputs(&input), Ok(vec![36 * 6, 36, 6]));
+
+        for (circuit, layer, label) in [
+            (
+                Circuit::new(vec![CircuitLayer::new(vec![Gate::relay(0)])], 1),
+                0,
+                0,
+            ),
+            (
+                Circuit::new(
+                    vec![CircuitLayer::new(vec![
+                        Gate::new(GateType::Add, [0, 0]),
+                        Gate::scaled(GateType::Mul, [0, 0], [2, 1]),
+                    ])],
+                    1,
+                ),
+                0,
+                1,
+            ),
+            (
+                Circuit::new(
+                    vec![CircuitLayer::new(vec![Gate::new(GateType::Add, [0, 1])])],
+                    1,
+                ),
+                0,
+                0,
+            ),
+        ] {
+            assert_eq!(
+                CircuitBuilder::from_circuit(&circuit).err(),
+                Some(BuildError::UnsupportedGate { layer, label })
+            );
+        }
+        let repeated = Circuit::new(
+            vec![CircuitLayer::new(vec![
+                Gate::new(GateType::Sub, [0, 0]),
+                Gate::new(GateType::Sub, [0, 0]),
+            ])],
+            1,
+        );
+        assert!(matches!(
+            CircuitBuilder::from_circuit(&repeated),
+            Err(BuildError::DuplicateGate { .. })
+        ));
+    }
+
+    #[test]
+    fn append_gate() {
+        let mut generic = CircuitBuilder::new();
+        let mut specific = CircuitBuilder::new();
+        for builder in [&mut generic, &mut specific] {
+            builder.apply_witnesses(2);
+        }
+
+        assert_eq!(generic.append_gate(GateType::Mul, 0, 1), Ok(2));
+        assert_eq!(specific.append_mul_gate(0, 1), Ok(2));
+        assert_eq!(generic.append_gate(GateType::Sub, 0, 1), Ok(3));
+        assert_eq!(specific.append_sub_gate(0, 1), Ok(3));
+        assert_eq!(generic.build_circuit(), specific.build_circuit());
+
+        assert!(matches!(
+            generic.append_gate(GateType::Mul, 0, 1),
+            Err(BuildError::DuplicateGate { existing: 2, .. })
+        ));
+        assert_eq!(generic.append_gate(GateType::Relay, 2, 3), generic.relay(2));
+    }
+
+    #[test]
+    fn bulk_append() {
+        let mut builder = CircuitBuilder::new();
+        let w = builder.apply_witnesses(4);
+        assert_eq!(w, 0..4);
+        assert_eq!(builder.n_input, 4);
+
+        let gates = [
+            (GateType::Mul, 0, 0),
+            (GateType::Mul, 1, 1),
+            (GateType::Mul, 1, 2),
+            (GateType::Mul, 3, 3),
+            (GateType::Mul, 4, 5),
+            (GateType::Mul, 6, 7),
+        ];
+        assert_eq!(builder.append_gates(&gates), Ok((4..10).collect()));
+        assert_eq!(builder.build_circuit(), Ok(circuit::circuit_from_book()));
+
+        // Failures anywhere in the batch leave the builder untouched.
+        let before = builder.clone();
+        for (gates, error) in [
+            (
+                vec![
+                    (GateType::Add, 4, 5),
+                    (GateType::Relay, 8, 8),
+                    (GateType::Mul, 0, 0),
+                ],
+                BuildError::DuplicateGate {
+                    gate: CellGateType::Mul(0, 0),
+                    existing: 4,
+                },
+            ),
+            (
+                vec![
+                    (GateType::Add, 8, 9),
+                    (GateType::Sub, 10, 10),
+                    (GateType::Add, 11, 12),
+                ],
+                BuildError::UnknownWire(12),
+            ),
+            (
+                vec![
+                    (GateType::Add, 0, 1),
+                    (GateType::Add, 2, 3),
+                    (GateType::Add, 0, 1),
+                ],
+                BuildError::DuplicateGate {
+                    gate: CellGateType::Add(0, 1),
+                    existing: 10,
+                },
+            ),
+        ] {
+            assert_eq!(builder.append_gates(&gates), Err(error));
+            assert_eq!(builder.cells.len(), before.cells.len());
+            assert_eq!(builder.gatehashset, before.gatehashset);
+            assert_eq!(builder.n_layer, before.n_layer);
+        }
+        assert!(matches!(
+            builder.append_gates(&[(GateType::Mul, 0, 4)]),
+            Err(BuildError::IllegalGate { .. })
+        ));
+        assert_eq!(builder.n_layer, before.n_layer);
+
+        assert_eq!(builder.append_gates(&[]), Ok(vec![]));
+        assert_eq!(builder.append_gates(&[(GateType::Add, 0, 1)]), Ok(vec![10]));
+    }
+
+    #[test]
+    fn failed_operations_roll_back() {
+        use ark_std::rand::Rng;
+
+        let apply = |builder: &mut CircuitBuilder, op: usize, w: &[Wire]| match op {
+            0 => Ok(vec![builder.apply_witness()]),
+            1 => builder.append_add_gate(w[0], w[1]).map(|c| vec![c]),
+            2 => builder.append_mul_gate(w[0], w[1]).map(|c| vec![c]),
+            3 => builder.append_sub_gate(w[0], w[1]).map(|c| vec![c]),
+            4 => builder.sum(w).map(|c| vec![c]),
+            5 => builder.select(w[0], w[1], w[2]).map(|c| vec![c]),
+            6 => builder.inner_product(&w[..2], &w[2..]).map(|c| vec![c]),
+            7 => builder.assert_boolean(w[0]).map(|c| vec![c]),
+            _ => builder.append_gates(&[
+                (GateType::Add, w[0], w[1]),
+                (GateType::Relay, w[2], w[2]),
+                (GateType::Mul, w[1], w[3]),
+            ]),
+        };
+
+        let rng = &mut test_rng();
+        let mut builder = CircuitBuilder::new();
+        let mut valid = CircuitBuilder::new();
+        builder.apply_witnesses(4);
+        valid.apply_witnesses(4);
+
+        let mut failures = 0;
+        for _ in 0..1000 {
+            let op = rng.gen_range(0..9);
+            // Some wires out of range, many across layers or duplicates.
+            let n = builder.cells.len();
+            let w: Vec<_> = (0..4).map(|_| rng.gen_range(0..n + 2)).collect();
+
+            match apply(&mut builder, op, &w) {
+                Ok(cells) => assert_eq!(apply(&mut valid, op, &w), Ok(cells)),
+                Err(_) => failures += 1,
+            }
+            assert_eq!(builder.cells.len(), valid.cells.len());
+            assert_eq!(builder.gatehashset, valid.gatehashset);
+            assert_eq!(builder.n_layer, valid.n_layer);
+            assert_eq!(builder.n_input, valid.n_input);
+            assert_eq!(builder.outputs, valid.outputs);
+        }
+        assert!(failures > 50);
+        assert_eq!(builder.check_invariants(), Ok(()));
+        assert_eq!(builder.build_circuit(), valid.build_circuit());
     }
 
     //error check
@@ -242,7 +2430,14 @@ mod tests {
         let _ = builder.append_mul_gate(v0, v1);
         let _ = builder.append_mul_gate(v2, v3);
         let err = builder.append_mul_gate(w0, w0).unwrap_err();
-        assert_eq!(err, BuildError::DuplicateGate);
+        assert_eq!(
+            err,
+            BuildError::DuplicateGate {
+                gate: CellGateType::Mul(w0, w0),
+                existing: v0
+            }
+        );
+        assert_eq!(err.to_string(), "gate 0 * 0 already exists as cell 4");
     }
 
     //error check
@@ -260,7 +2455,814 @@ mod tests {
         let _ = builder.append_mul_gate(v0, v1);
         let _ = builder.append_mul_gate(v2, v3);
         let err = builder.append_mul_gate(w2, v3).unwrap_err();
-        assert_eq!(err, BuildError::IllegalGate);
+        assert_eq!(
+            err,
+            BuildError::IllegalGate {
+                left: builder.cell_ref(w2),
+                right: builder.cell_ref(v3),
+                left_layer: 0,
+                right_layer: 1,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "cannot combine cell 2 at layer 0 with cell 7 at layer 1"
+        );
+
+        let err = builder.append_add_gate(w0, 42).unwrap_err();
+        assert_eq!(err, BuildError::UnknownWire(42));
+        assert_eq!(err.to_string(), "wire 42 does not exist");
+        assert_eq!(builder.lift(42, 1), Err(BuildError::UnknownWire(42)));
+    }
+
+    #[test]
+    fn labels() {
+        let mut builder = CircuitBuilder::new();
+        let x = builder.apply_witness_named("x");
+        let y = builder.apply_witness_named("y");
+        let z = builder.apply_witness_named("z");
+        let xy = builder.append_mul_gate_named(x, y, "xy").unwrap();
+        let yz = builder.append_add_gate_named(y, z, "yz").unwrap();
+        builder.append_sub_gate_named(x, z, "dead").unwrap();
+        builder.append_sub_gate_named(xy, yz, "out").unwrap();
+        assert_eq!(builder.label(xy), Some("xy"));
+
+        let c = builder.build_circuit().unwrap();
+        let labels = c.gate_labels().unwrap();
+        assert_eq!(labels.position("out"), Some((0, 0)));
+        assert_eq!(labels.position("yz"), Some((1, 1)));
+        assert_eq!(labels.position("z"), Some((2, 2)));
+        assert_eq!(labels.position("dead"), None);
+        assert_eq!(labels.get(1, 0), Some("xy"));
+
+        // Labels play no part in the structure of the circuit.
+        builder.set_label(xy, "renamed");
+        assert_ne!(builder.build_circuit().unwrap(), c);
+        assert_eq!(
+            builder.build_circuit().unwrap().fingerprint(),
+            c.fingerprint()
+        );
+
+        let sub = c.sub_circuit(1);
+        assert_eq!(sub.gate_labels().unwrap().position("yz"), Some((0, 1)));
+        assert_eq!(sub.gate_labels().unwrap().position("out"), None);
+
+        builder.set_options(BuildOptions {
+            deny_dead_code: true,
+            ..Default::default()
+        });
+        let err = builder.build_circuit().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cell 5 `dead` does not contribute to any output"
+        );
+
+        let err = builder.append_mul_gate(x, yz).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot combine cell 0 `x` at layer 0 with cell 4 `yz` at layer 1"
+        );
+    }
+
+    #[test]
+    fn circuit_sources() {
+        fn widths(source: &impl CircuitSource) -> Vec<usize> {
+            let circuit = source.build().unwrap();
+            circuit.layers().iter().map(|layer| layer.len()).collect()
+        }
+
+        let mut builder = CircuitBuilder::new();
+        let w: Vec<_> = (0..3).map(|_| builder.apply_witness()).collect();
+        let v = builder.append_mul_gate(w[0], w[1]).unwrap();
+        let w2 = builder.relay(w[2]).unwrap();
+        builder.append_add_gate(v, w2).unwrap();
+        assert_eq!(widths(&builder), vec![1, 2]);
+
+        let random = RandomCircuit {
+            depth: 3,
+            width: 4,
+            seed: 7,
+        };
+        assert_eq!(widths(&random), vec![4; 3]);
+        assert_eq!(random.build(), random.build());
+        assert_eq!(widths(&random.build().unwrap()), vec![4; 3]);
+
+        let bristol = Bristol("1 3\n2 1 1\n1 1\n\n2 1 0 1 2 AND\n");
+        assert_eq!(widths(&bristol), vec![1]);
+        assert!(matches!(
+            Bristol("1 3\n").build(),
+            Err(BuildError::Bristol(_))
+        ));
+    }
+
+    #[test]
+    fn random_circuit() {
+        let rng = &mut test_rng();
+        let c = CircuitBuilder::random(rng, 3, 8).build_circuit().unwrap();
+
+        assert_eq!(c.num_layers(), 3);
+        assert_eq!(c.num_inputs(), 8);
+        assert_eq!(c.num_gates(), 24);
+        assert!(c.layers().iter().all(|layer| layer.len() == 8));
+    }
+
+    #[test]
+    fn sum_and_product_trees() {
+        for n in [1usize, 2, 5, 8, 1000] {
+            let mut builder = CircuitBuilder::new();
+            let wires: Vec<Wire> = (0..n).map(|_| builder.apply_witness()).collect();
+            let sum = builder.sum(&wires).unwrap();
+
+            assert_eq!(
+                builder.layer(sum),
+                n.next_power_of_two().trailing_zeros() as usize
+            );
+
+            let c = builder.build_circuit().unwrap();
+            let input = builder.input_values(|w| w as i128 + 1).unwrap();
+            let n = n as i128;
+            assert_eq!(c.evaluate(&input).layers[0], vec![n * (n + 1) / 2]);
+
+            let mut builder = CircuitBuilder::new();
+            let wires: Vec<Wire> = (0..n).map(|_| builder.apply_witness()).collect();
+            builder.product(&wires).unwrap();
+
+            let c = builder.build_circuit().unwrap();
+            let input = builder
+                .input_values(|w| if w % 2 == 0 { 1 } else { -1i128 })
+                .unwrap();
+            let sign = if (n / 2) % 2 == 0 { 1 } else { -1 };
+            assert_eq!(c.evaluate(&input).layers[0], vec![sign]);
+        }
+    }
+
+    #[test]
+    fn sum_over_layers() {
+        let mut builder = CircuitBuilder::new();
+        let w0 = builder.apply_witness();
+        let w1 = builder.apply_witness();
+        let w2 = builder.apply_witness();
+        let v0 = builder.append_mul_gate(w0, w1).unwrap();
+        let v1 = builder.append_mul_gate(v0, v0).unwrap();
+
+        // `w2` and `w0` are relayed up to `v1`, then two layers of additions.
+        let sum = builder.sum(&[w2, v1, w0]).unwrap();
+        assert_eq!(builder.layer(sum), 4);
+
+        let c = builder.build_circuit().unwrap();
+        let input = builder.input_values(|w| [2i128, 3, 5][w]).unwrap();
+        assert_eq!(c.evaluate(&input).layers[0], vec![5 + 36 + 2]);
+
+        assert_eq!(builder.sum(&[]), Ok(builder.constant(0)));
+        assert_eq!(builder.product(&[]), Ok(builder.constant(1)));
+    }
+
+    #[test]
+    fn zero_and_one() {
+        let mut builder = CircuitBuilder::new();
+        let zero = builder.zero();
+        assert_eq!(builder.zero(), zero);
+        assert_eq!(builder.constant(0), zero);
+
+        let one = builder.one();
+        assert_ne!(one, zero);
+        assert_eq!(builder.product(&[]), Ok(one));
+        assert_eq!(builder.cells.len(), 2);
+    }
+
+    #[test]
+    fn scaled_add() {
+        let mut builder = CircuitBuilder::new();
+        let x = builder.apply_witness();
+        let y = builder.apply_witness();
+        let scaled = builder.append_scaled_add_gate(x, 3, y, 5).unwrap();
+        assert_ne!(builder.append_add_gate(x, y), Ok(scaled));
+        assert!(matches!(
+            builder.append_scaled_add_gate(x, 3, y, 5),
+            Err(BuildError::DuplicateGate { existing, .. }) if existing == scaled
+        ));
+        builder.mark_output(scaled);
+
+        let circuit = builder.build_circuit().unwrap();
+        assert_eq!(
+            circuit.layers()[0].gates()[0],
+            Gate::scaled(GateType::Add, [0, 1], [3, 5])
+        );
+        assert_eq!(circuit.evaluate(&[4, 2]).layers[0], vec![22, 6]);
+    }
+
+    #[test]
+    fn input_kinds() {
+        let mut builder = CircuitBuilder::new();
+        let x = builder.apply_witness();
+        let y = builder.apply_witness();
+        let three = builder.constant(3);
+        let xy = builder.append_mul_gate(x, y).unwrap();
+        let y3 = builder.append_mul_gate(y, three).unwrap();
+        builder.append_add_gate(xy, y3).unwrap();
+
+        let circuit = builder.build_circuit().unwrap();
+        assert_eq!(circuit.num_inputs(), 3);
+        assert_eq!(circuit.num_witnesses(), 2);
+        assert_eq!(circuit.num_constants(), 1);
+        assert_eq!(circuit.num_public_inputs(), 0);
+
+        builder.set_options(BuildOptions {
+            pad_to_power_of_two: true,
+            ..Default::default()
+        });
+        let padded = builder.build_circuit().unwrap();
+        assert_eq!(padded.num_inputs(), 4);
+        assert_eq!(padded.num_witnesses(), 2);
+        assert_eq!(padded.num_constants(), 2);
+    }
+
+    #[test]
+    fn invariants() {
+        let mut builder = CircuitBuilder::new();
+        assert_eq!(builder.check_invariants(), Ok(()));
+
+        let x = builder.apply_witness();
+        let y = builder.apply_public_input();
+        let three = builder.constant(3);
+        let xy = builder.append_mul_gate(x, y).unwrap();
+        builder.append_add_gate(three, three).unwrap();
+        builder.append_sub_gate(xy, xy).unwrap();
+        assert_eq!(builder.check_invariants(), Ok(()));
+
+        let mut corrupted = builder.clone();
+        corrupted.gatehashset.insert(CellGateType::Mul(x, y), three);
+        assert_eq!(
+            corrupted.check_invariants(),
+            Err(BuildError::InconsistentGate {
+                gate: CellGateType::Mul(x, y),
+                recorded: three,
+                holders: 1,
+            })
+        );
+
+        let mut corrupted = builder.clone();
+        corrupted.cells[x].gate_type = CellGateType::Const(7);
+        assert_eq!(
+            corrupted.check_invariants(),
+            Err(BuildError::InputCountMismatch {
+                n_input: 2,
+                witnesses: 1,
+            })
+        );
+
+        let mut corrupted = builder;
+        corrupted.n_layer += 1;
+        assert_eq!(
+            corrupted.check_invariants(),
+            Err(BuildError::LayerCountMismatch {
+                n_layer: 4,
+                layers: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn lift() {
+        let mut builder = CircuitBuilder::new();
+        let w0 = builder.apply_witness();
+        let w1 = builder.apply_witness();
+        let w2 = builder.apply_witness();
+        let v0 = builder.append_mul_gate(w0, w1).unwrap();
+        let v1 = builder.append_add_gate(v0, v0).unwrap();
+
+        // `w2` feeds a gate two layers up.
+        let lifted = builder.lift(w2, 2).unwrap();
+        assert_eq!(builder.layer(lifted), 2);
+        assert_eq!(builder.lift(w2, 2), Ok(lifted));
+        assert_eq!(builder.lift(lifted, 2), Ok(lifted));
+        assert_eq!(builder.lift(v1, 1), Err(BuildError::IllegalOperands));
+
+        builder.append_mul_gate(v1, lifted).unwrap();
+        let c = builder.build_circuit().unwrap();
+        let input = builder.input_values(|w| [2i128, 3, 5][w]).unwrap();
+        assert_eq!(c.evaluate(&input).layers[0], vec![(6 + 6) * 5]);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn witness_above_input_layer() {
+        let mut builder = CircuitBuilder::new();
+        let w0 = builder.apply_witness();
+        let w1 = builder.apply_witness();
+        let v0 = builder.append_add_gate(w0, w1).unwrap();
+
+        // A witness smuggled into layer 1 next to `v0`.
+        let stray = builder.cells.len();
+        builder.cells.push(Cell {
+            index: stray,
+            layer_id: 1,
+            gate_type: CellGateType::Witness,
+            label: None,
+        });
+        builder.append_mul_gate(v0, stray).unwrap();
+
+        assert_eq!(
+            builder.build_circuit(),
+            Err(BuildError::WitnessNotInInputLayer(builder.cell_ref(stray)))
+        );
+        assert_eq!(
+            builder.input_values(|_| 0u64),
+            Err(BuildError::WitnessNotInInputLayer(builder.cell_ref(stray)))
+        );
+    }
+
+    #[test]
+    fn pow() {
+        let rng = &mut test_rng();
+
+        for k in [0u64, 1, 2, 3, 5, 8, 13, 255] {
+            let mut builder = CircuitBuilder::new();
+            let x = builder.apply_witness();
+            let power = builder.pow(x, k).unwrap();
+
+            let bits = 64 - k.leading_zeros() as usize;
+            let squarings = bits.saturating_sub(1);
+            let multiplies = (k.count_ones() as usize).saturating_sub(1);
+            assert!(builder.layer(power) <= squarings + 1);
+
+            let c = builder.build_circuit().unwrap();
+            let muls = c
+                .layers()
+                .iter()
+                .flat_map(|layer| layer.gates())
+                .filter(|gate| gate.ttype() == GateType::Mul)
+                .count();
+            assert_eq!(muls, squarings + multiplies, "{k}");
+
+            for _ in 0..4 {
+                let base = Fp389::rand(rng);
+                let input = builder.input_values(|_| base).unwrap();
+                // With `k = 0` the witness itself is an output too.
+                let outputs = &c.evaluate(&input).layers[0];
+                assert_eq!(outputs.last(), Some(&base.pow([k])));
+            }
+        }
+
+        let mut builder = CircuitBuilder::new();
+        let x = builder.apply_witness();
+        assert_eq!(builder.pow(x, 1), Ok(x));
+        assert_eq!(builder.cells.len(), 1);
+    }
+
+    #[test]
+    fn boolean_constraints() {
+        let mut builder = CircuitBuilder::new();
+        let x = builder.apply_witness();
+        let y = builder.apply_witness();
+        let v = builder.append_mul_gate(x, y).unwrap();
+        builder.append_mul_gate(v, v).unwrap();
+        let constraint = builder.assert_boolean(x).unwrap();
+        assert_eq!(builder.layer(constraint), 2);
+
+        let c = builder.build_circuit().unwrap();
+        // The constraint gets relayed up next to `v * v`.
+        assert_eq!(c.num_outputs(), 2);
+
+        for (value, zero) in [(0, true), (1, true), (2, false)] {
+            let input = builder
+                .input_values(|w| Fp389::from(if w == x { value } else { 5 }))
+                .unwrap();
+            let outputs = &c.evaluate(&input).layers[0];
+            assert_eq!(outputs[1].is_zero(), zero);
+        }
+    }
+
+    #[test]
+    fn decompose_bits() {
+        let mut builder = CircuitBuilder::new();
+        let x = builder.apply_witness();
+        let bits = builder.decompose_bits(x, 4).unwrap();
+
+        let c = builder.build_circuit().unwrap();
+        assert_eq!(c.num_outputs(), 5);
+
+        let value = 11u64;
+        let witness = |flipped: Option<usize>| {
+            builder
+                .input_values(|w| {
+                    if w == x {
+                        return Fp389::from(value);
+                    }
+                    let i = bits.iter().position(|b| *b == w).unwrap();
+                    Fp389::from(((value >> i) & 1) ^ (flipped == Some(i)) as u64)
+                })
+                .unwrap()
+        };
+
+        let outputs = &c.evaluate(&witness(None)).layers[0];
+        assert!(outputs.iter().all(Fp389::is_zero));
+
+        for i in 0..bits.len() {
+            let outputs = &c.evaluate(&witness(Some(i))).layers[0];
+            assert!(outputs.iter().any(|o| !o.is_zero()));
+        }
+    }
+
+    #[test]
+    fn repeat_layer() {
+        let mut builder = CircuitBuilder::new();
+        for _ in 0..4 {
+            builder.apply_witness();
+        }
+
+        let square: Vec<_> = (0..4).map(|i| CellGateType::Mul(i, i)).collect();
+        let outputs = builder.repeat_layer(&square, 3).unwrap();
+        assert_eq!(outputs, vec![12, 13, 14, 15]);
+
+        let c = builder.build_circuit().unwrap();
+        assert_eq!(c.num_layers(), 3);
+        assert!(c.is_uniform());
+        assert_eq!(
+            c.evaluate(&[1, 2, 3, 4]).layers[0],
+            vec![1, 256, 6561, 65536]
+        );
+
+        assert_eq!(
+            builder.repeat_layer(&[CellGateType::Add(0, 4)], 1),
+            Err(BuildError::IllegalOperands)
+        );
+        assert_eq!(
+            builder.repeat_layer(&[CellGateType::Witness], 1),
+            Err(BuildError::IllegalOperands)
+        );
+    }
+
+    #[test]
+    fn select() {
+        let rng = &mut test_rng();
+
+        let mut builder = CircuitBuilder::new();
+        let cond = builder.apply_witness();
+        let a = builder.apply_witness();
+        let x = builder.apply_witness();
+        // `b` one layer above `a`.
+        let b = builder.append_mul_gate(x, x).unwrap();
+        builder.select(cond, a, b).unwrap();
+
+        let c = builder.build_circuit().unwrap();
+
+        for _ in 0..8 {
+            let (a, x) = (Fp389::rand(rng), Fp389::rand(rng));
+            let b = x * x;
+
+            for (cond, expected) in [(0, b), (1, a), (2, a + a - b)] {
+                let input = builder
+                    .input_values(|w| [Fp389::from(cond), a, x][w])
+                    .unwrap();
+                assert_eq!(c.evaluate(&input).layers[0], vec![expected]);
+            }
+        }
+    }
+
+    #[test]
+    fn strictly_layered() {
+        let (builder, _) = CircuitBuilder::from_expression("(a + b) * c - a * a").unwrap();
+        assert!(builder.is_strictly_layered());
+
+        let mut builder = CircuitBuilder::new();
+        let wires: Vec<_> = (0..5).map(|_| builder.apply_witness()).collect();
+        builder.sum(&wires).unwrap();
+        builder.decompose_bits(wires[0], 3).unwrap();
+        assert!(builder.is_strictly_layered());
+
+        // A gate skipping a layer.
+        let v = builder.append_mul_gate(wires[1], wires[2]).unwrap();
+        builder.cells[v].layer_id = 2;
+        assert!(!builder.is_strictly_layered());
+    }
+
+    #[test]
+    fn padding() {
+        let mut builder = CircuitBuilder::new();
+        let wires: Vec<_> = (0..5).map(|_| builder.apply_witness()).collect();
+        builder.sum(&wires).unwrap();
+        let unpadded = builder.build_circuit().unwrap();
+
+        builder.set_options(BuildOptions {
+            pad_to_power_of_two: true,
+            ..Default::default()
+        });
+        let c = builder.build_circuit().unwrap();
+        assert_eq!(c.num_inputs(), 8);
+        assert!(c.layers().iter().all(|l| l.len().is_power_of_two()));
+
+        let input = builder.input_values(|w| Fp389::from(w as u64 + 1)).unwrap();
+        assert_eq!(input.len(), 8);
+        assert_eq!(c.evaluate(&input).layers[0][0], Fp389::from(15));
+        assert_eq!(
+            c.evaluate(&input).layers[0][..unpadded.num_outputs()],
+            unpadded.evaluate(&input[..unpadded.num_inputs()]).layers[0][..]
+        );
+    }
+
+    #[test]
+    fn fold_constants() {
+        let rng = &mut test_rng();
+
+        let exprs = [
+            "(x * 1 + 0) * (2 * 3) + y * (4 - 4) - (x - x)",
+            "(x + 2 * 3) * (y - 0) + (1 + 1) * (1 * y)",
+            "x * y + 5 - 7",
+        ];
+
+        for expr in exprs {
+            let (mut builder, layout) = CircuitBuilder::from_expression(expr).unwrap();
+            let x = layout.witness("x").unwrap();
+            // An output below the top layer that folds away.
+            let one = builder.constant(1);
+            let marked = builder.intern(CellGateType::Mul(x, one), x, one).unwrap();
+            builder.mark_output(marked);
+
+            let original = builder.clone();
+            let before = original.build_circuit().unwrap();
+
+            let summary = builder.fold_constants().unwrap();
+            let after = builder.build_circuit().unwrap();
+            assert!(builder.is_strictly_layered());
+            assert_eq!(after.num_outputs(), before.num_outputs(), "{expr}");
+            assert_eq!(after.num_gates() + summary.eliminated, before.num_gates());
+
+            for _ in 0..8 {
+                let (vx, vy) = (Fp389::rand(rng), Fp389::rand(rng));
+                // Every witness but `x` is `y`.
+                let witness = |w| if w == x { vx } else { vy };
+
+                assert_eq!(
+                    after
+                        .evaluate(&builder.input_values(witness).unwrap())
+                        .layers[0],
+                    before
+                        .evaluate(&original.input_values(witness).unwrap())
+                        .layers[0],
+                    "{expr}"
+                );
+            }
+        }
+
+        let (mut builder, _) = CircuitBuilder::from_expression(exprs[0]).unwrap();
+        let summary = builder.fold_constants().unwrap();
+        assert!(summary.folded > 0 && summary.simplified > 0 && summary.eliminated > 0);
+        // Folding again finds nothing left to do.
+        assert_eq!(builder.fold_constants().unwrap(), FoldSummary::default());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn save_and_load() {
+        let rng = &mut test_rng();
+
+        let mut builder = CircuitBuilder::random(rng, 3, 6);
+        builder.constant(7);
+        let saved = builder.save();
+        assert_eq!(saved, builder.save());
+
+        let mut loaded = CircuitBuilder::load(&saved).unwrap();
+        assert_eq!(loaded.save(), saved);
+
+        // Continue building both the same way.
+        for builder in [&mut builder, &mut loaded] {
+            let top: Vec<_> = (0..builder.cells.len())
+                .filter(|&cell| builder.layer(cell) == 3)
+                .collect();
+            let seven = builder.constant(7);
+            let seven = builder.lift(seven, 3).unwrap();
+            let product = builder.append_mul_gate(top[0], seven).unwrap();
+            builder.mark_output(product);
+            let sum = builder.sum(&top).unwrap();
+            assert!(matches!(
+                builder.append_add_gate(top[0], top[1]),
+                Err(BuildError::DuplicateGate { .. })
+            ));
+            builder.relay(sum).unwrap();
+        }
+        assert_eq!(loaded.save(), builder.save());
+        assert_eq!(loaded.build_circuit(), builder.build_circuit());
+
+        assert!(matches!(
+            CircuitBuilder::load(&saved[..saved.len() / 2]),
+            Err(super::LoadError::Decode(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn resume() {
+        // A builder program, cell `i` being created by step `i` up
+        // to the marked output and the duplicate.
+        let step = |builder: &mut CircuitBuilder, i: usize| match i {
+            0..=3 => assert_eq!(builder.apply_witness(), i),
+            4 => assert_eq!(builder.constant(3), 4),
+            5 => assert_eq!(builder.append_mul_gate(0, 1), Ok(5)),
+            6 => assert_eq!(builder.append_scaled_add_gate(2, 2, 3, 7), Ok(6)),
+            7 => assert_eq!(builder.append_mul_gate(3, 4), Ok(7)),
+            8 => assert_eq!(builder.append_sub_gate(0, 4), Ok(8)),
+            9 => assert_eq!(builder.append_mul_gate(5, 6), Ok(9)),
+            10 => assert_eq!(builder.append_add_gate(7, 8), Ok(10)),
+            11 => builder.mark_output(5),
+            _ => assert!(matches!(
+                builder.append_mul_gate(0, 1),
+                Err(BuildError::DuplicateGate { existing: 5, .. })
+            )),
+        };
+        let n_steps = 13;
+
+        let mut uninterrupted = CircuitBuilder::new();
+        (0..n_steps).for_each(|i| step(&mut uninterrupted, i));
+        let circuit = uninterrupted.build_circuit().unwrap();
+
+        for split in 0..=n_steps {
+            let mut builder = CircuitBuilder::new();
+            (0..split).for_each(|i| step(&mut builder, i));
+
+            let mut bytes = vec![];
+            builder.save_to(&mut bytes).unwrap();
+            let mut builder = CircuitBuilder::load_from(bytes.as_slice()).unwrap();
+
+            (split..n_steps).for_each(|i| step(&mut builder, i));
+            assert_eq!(builder.build_circuit().unwrap(), circuit);
+            assert_eq!(builder.save(), uninterrupted.save());
+        }
+
+        let json = serde_json::to_string(&uninterrupted).unwrap();
+        let builder: CircuitBuilder = serde_json::from_str(&json).unwrap();
+        assert_eq!(builder.save(), uninterrupted.save());
+    }
+
+    #[test]
+    fn rebalance() {
+        let rng = &mut test_rng();
+
+        // ((w_0 + w_1) + w_2) + ... + w_63
+        let mut builder = CircuitBuilder::new();
+        let wires: Vec<_> = (0..64).map(|_| builder.apply_witness()).collect();
+        let mut acc = wires[0];
+        let mut inner = vec![];
+        for &w in &wires[1..] {
+            let w = builder.lift(w, builder.layer(acc)).unwrap();
+            inner.push(acc);
+            acc = builder.append_add_gate(acc, w).unwrap();
+        }
+
+        let original = builder.clone();
+        let report = builder.rebalance().unwrap();
+        assert_eq!(report.old_depth, 63);
+        assert_eq!(report.new_depth, 6);
+        assert!(builder.is_strictly_layered());
+        assert!(builder.check_invariants().is_ok());
+        let kept: Vec<_> = wires.iter().map(|&w| Some(w)).collect();
+        assert_eq!(report.wires[..64], kept[..]);
+        assert_eq!(report.wires[acc], Some(builder.cells.len() - 1));
+        // The inner gates of the chain are gone.
+        assert!(inner[1..].iter().all(|&cell| report.wires[cell].is_none()));
+
+        let c = builder.build_circuit().unwrap();
+        assert_eq!(c.num_layers(), 6);
+        let input = builder.input_values(|w| Fp389::from(w as u64)).unwrap();
+        assert_eq!(c.evaluate(&input).layers[0], vec![Fp389::from(63 * 64 / 2)]);
+        // The relays of the chain needed the constant zero.
+        assert_eq!(
+            original
+                .input_values(|w| Fp389::from(w as u64))
+                .unwrap()
+                .len(),
+            65
+        );
+        assert_eq!(input.len(), 64);
+
+        // Products, sums and differences mixed up with marked outputs.
+        for _ in 0..16 {
+            let mut builder = CircuitBuilder::random(rng, 2, 4);
+            let mut chains = vec![];
+            for gate in [CellGateType::Add, CellGateType::Mul] {
+                let mut acc = builder.relay(0).unwrap();
+                for w in 0..4 {
+                    let w = builder.lift(w, builder.layer(acc)).unwrap();
+                    acc = builder.intern(gate(acc, w), acc, w).unwrap();
+                }
+                chains.push(acc);
+            }
+            builder.mark_output(chains[0]);
+            let seven = builder.constant(7);
+            let scaled = builder.product(&[chains[1], seven, chains[1]]).unwrap();
+            let diff = builder.select(chains[0], scaled, 3).unwrap();
+            builder.mark_output(diff);
+            let relay = builder.relay(3).unwrap();
+            builder.mark_output(relay);
+
+            let original = builder.clone();
+            let before = original.build_circuit().unwrap();
+            let report = builder.rebalance().unwrap();
+            let after = builder.build_circuit().unwrap();
+
+            assert!(builder.is_strictly_layered());
+            assert!(builder.check_invariants().is_ok());
+            assert!(report.new_depth <= report.old_depth);
+            assert_eq!(after.num_layers(), report.new_depth);
+            assert_eq!(after.num_outputs(), before.num_outputs());
+
+            let witness: Vec<_> = (0..4).map(|_| Fp389::rand(rng)).collect();
+            assert_eq!(
+                after
+                    .evaluate(&builder.input_values(|w| witness[w]).unwrap())
+                    .layers[0],
+                before
+                    .evaluate(&original.input_values(|w| witness[w]).unwrap())
+                    .layers[0]
+            );
+        }
+    }
+
+    #[test]
+    fn circuit_try_from_layers() {
+        let layers = circuit::circuit_from_book().layers().to_vec();
+        assert_eq!(
+            Circuit::try_from((layers.clone(), 4)),
+            Ok(circuit::circuit_from_book())
+        );
+        assert_eq!(
+            Circuit::try_from((layers, 3)),
+            Err(BuildError::GateInputOutOfRange {
+                layer: 1,
+                label: 3,
+                input: 3,
+                width: 3,
+            })
+        );
+
+        // The second gate reads past the two gates of the layer below.
+        let layers = vec![
+            CircuitLayer::new(vec![Gate::relay(1), Gate::new(GateType::Add, [0, 2])]),
+            CircuitLayer::new(vec![
+                Gate::new(GateType::Mul, [0, 1]),
+                Gate::new(GateType::Add, [0, 1]),
+            ]),
+        ];
+        assert_eq!(
+            Circuit::try_from((layers, 2)),
+            Err(BuildError::GateInputOutOfRange {
+                layer: 0,
+                label: 1,
+                input: 2,
+                width: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn canonical_layout() {
+        // The same gates added in opposite orders.
+        let build = |reversed: bool, canonical: bool| {
+            let mut builder = CircuitBuilder::new().with_canonical_layout(canonical);
+            let w0 = builder.apply_witness();
+            let w1 = builder.apply_witness();
+
+            let mut order = [0, 1, 2];
+            if reversed {
+                order.reverse();
+            }
+            let mut below = [w0; 3];
+            for i in order {
+                below[i] = match i {
+                    0 => builder.append_add_gate(w1, w0),
+                    1 => builder.append_mul_gate(w1, w0),
+                    _ => builder.append_sub_gate(w1, w0),
+                }
+                .unwrap();
+            }
+            let [x, y, z] = below;
+            for i in order.into_iter().filter(|&i| i < 2) {
+                match i {
+                    0 => builder.append_mul_gate(x, y),
+                    _ => builder.append_add_gate(y, z),
+                }
+                .unwrap();
+            }
+
+            builder.build_circuit().unwrap()
+        };
+
+        assert_ne!(build(false, false), build(true, false));
+        let canonical = build(false, true);
+        assert_eq!(canonical, build(true, true));
+        assert_eq!(
+            canonical.layers()[1].gates(),
+            [
+                Gate::new(GateType::Add, [1, 0]),
+                Gate::new(GateType::Mul, [1, 0]),
+                Gate::new(GateType::Sub, [1, 0]),
+            ]
+        );
+        assert_eq!(
+            canonical.layers()[0].gates(),
+            [
+                Gate::new(GateType::Add, [1, 2]),
+                Gate::new(GateType::Mul, [0, 1]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Generators of commonly used circuits.
+
+use ark_ff::Zero;
+
+use crate::{
+    circuit::Circuit,
+    circuit_builder::{BuildError, CircuitBuilder, Wire},
+    gadgets,
+};
+
+/// A circuit computing the inner product $\langle a, b \rangle$
+/// of two vectors of length `n`.
+///
+/// The input is the concatenation of $a$ and $b$. Unless `n` is a
+/// power of two it is followed by a single zero feeding the relays
+/// of the addition tree, see [`CircuitBuilder::sum`].
+pub fn inner_product(n: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let a: Vec<_> = (0..n).map(|_| builder.apply_witness()).collect();
+    let b: Vec<_> = (0..n).map(|_| builder.apply_witness()).collect();
+
+    builder
+        .inner_product(&a, &b)
+        .expect("witnesses share the input layer");
+
+    builder
+        .build_circuit()
+        .expect("witnesses only live in the input layer")
+}
+
+/// The order of the inputs of the [`matmul`] circuit.
+///
+/// The input holds $A$ followed by $B$, both in row-major order,
+/// and unless `n` is a power of two a single zero feeding the relays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputLayout {
+    n: usize,
+    num_inputs: usize,
+}
+
+impl InputLayout {
+    /// The input label of $A_{ij}$.
+    pub fn a(&self, i: usize, j: usize) -> usize {
+        i * self.n + j
+    }
+
+    /// The input label of $B_{ij}$.
+    pub fn b(&self, i: usize, j: usize) -> usize {
+        self.n * self.n + i * self.n + j
+    }
+
+    /// Lay out the matrices `a` and `b` as the input of the circuit.
+    pub fn input<F: Copy + Zero>(&self, a: &[Vec<F>], b: &[Vec<F>]) -> Vec<F> {
+        let mut input: Vec<F> = a.iter().chain(b).flatten().copied().collect();
+        input.resize(self.num_inputs, F::zero());
+        input
+    }
+}
+
+/// A circuit computing the product $C = A \cdot B$ of two
+/// $n \times n$ matrices.
+///
+/// Every entry of $C$ is an inner product of a row of $A$ and a
+/// column of $B$: a layer of $n^3$ multiplications is followed by
+/// balanced addition trees. The output layer holds $C$ in row-major
+/// order. All layer widths are powers of two if `n` is one.
+pub fn matmul(n: usize) -> (Circuit, InputLayout) {
+    let mut builder = CircuitBuilder::new();
+    let a: Vec<_> = (0..n * n).map(|_| builder.apply_witness()).collect();
+    let b: Vec<_> = (0..n * n).map(|_| builder.apply_witness()).collect();
+
+    matmul_gadget(&mut builder, n, &a, &b).expect("witnesses share the input layer");
+
+    let circuit = builder
+        .build_circuit()
+        .expect("witnesses only live in the input layer");
+    let layout = InputLayout {
+        n,
+        num_inputs: circuit.num_inputs(),
+    };
+
+    (circuit, layout)
+}
+
+/// A circuit computing $\sum_{i,j} (A^2)_{ij} A_{ij}$ for the
+/// adjacency matrix $A$ of an undirected graph on `n` vertices,
+/// which is six times the number of triangles in the graph.
+///
+/// The input holds $A$ in row-major order followed by a single zero
+/// feeding the relays that lift $A$ up to $A^2$.
+pub fn count_triangles(n: usize) -> Circuit {
+    let mut builder = CircuitBuilder::new();
+    let a: Vec<_> = (0..n * n).map(|_| builder.apply_witness()).collect();
+
+    matmul_gadget(&mut builder, n, &a, &a)
+        .and_then(|squared| builder.inner_product(&squared, &a))
+        .expect("gadgets lift their operands");
+
+    builder
+        .build_circuit()
+        .expect("witnesses only live in the input layer")
+}
+
+/// A circuit of `rounds` rounds of [`gadgets::mimc`] with the
+/// constants of [`gadgets::mimc_constants`].
+///
+/// The input is laid out by [`mimc_input`].
+pub fn mimc(rounds: usize) -> Circuit {
+    mimc_builder(rounds)
+        .build_circuit()
+        .expect("witnesses only live in the input layer")
+}
+
+/// The input of the [`mimc`] circuit encrypting `x` with the key `k`:
+/// `x` and `k` followed by the round constants.
+pub fn mimc_input<F: From<u64> + Copy>(rounds: usize, x: F, k: F) -> Vec<F> {
+    mimc_builder(rounds)
+        .input_values(|w| if w == 0 { x } else { k })
+        .expect("witnesses only live in the input layer")
+}
+
+fn mimc_builder(rounds: usize) -> CircuitBuilder {
+    let mut builder = CircuitBuilder::new();
+    let x = builder.apply_witness();
+    let k = builder.apply_witness();
+
+    gadgets::mimc(&mut builder, x, k, &gadgets::mimc_constants(rounds), rounds)
+        .expect("gadgets lift their operands");
+
+    builder
+}
+
+/// The entries of the product of two row-major $n \times n$ matrices
+/// of wires in row-major order.
+fn matmul_gadget(
+    builder: &mut CircuitBuilder,
+    n: usize,
+    a: &[Wire],
+    b: &[Wire],
+) -> Result<Vec<Wire>, BuildError> {
+    let mut c = Vec::with_capacity(n * n);
+
+    for i in 0..n {
+        for j in 0..n {
+            let row = &a[i * n..(i + 1) * n];
+            let column: Vec<_> = (0..n).map(|k| b[k * n + j]).collect();
+
+            c.push(builder.inner_product(row, &column)?);
+        }
+    }
+
+    Ok(c)
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use ark_ff::{Field, Fp64, MontBackend, MontConfig, Zero};
+    use ark_std::{rand::Rng, test_rng, UniformRand};
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        circuit_builder::{BuildError, CircuitBuilder},
+        gadgets,
+        proof::{prove, verify},
+        tests::run_protocol,
+    };
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    fn random_matrix<R: Rng>(rng: &mut R, n: usize) -> Vec<Vec<Fp389>> {
+        (0..n).map(|_| random_vec(rng, n)).collect()
+    }
+
+    fn random_vec<R: Rng>(rng: &mut R, n: usize) -> Vec<Fp389> {
+        (0..n).map(|_| Fp389::rand(rng)).collect()
+    }
+
+    #[test]
+    fn inner_product() {
+        let rng = &mut test_rng();
+        for n in [1, 2, 7, 64, 100] {
+            let circuit = super::inner_product(n);
+            let (a, b) = (random_vec(rng, n), random_vec(rng, n));
+
+            let mut input = [a.clone(), b.clone()].concat();
+            if !n.is_power_of_two() {
+                input.push(Fp389::zero());
+            }
+            assert_eq!(circuit.num_inputs(), input.len());
+
+            let dot = a.iter().zip(&b).map(|(a, b)| *a * b).sum::<Fp389>();
+            assert_eq!(circuit.evaluate(&input).layers[0], vec![dot]);
+        }
+    }
+
+    #[test]
+    fn inner_product_length_mismatch() {
+        let mut builder = CircuitBuilder::new();
+        let a: Vec<_> = (0..3).map(|_| builder.apply_witness()).collect();
+
+        assert_eq!(
+            builder.inner_product(&a, &a[1..]),
+            Err(BuildError::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn inner_product_protocol() {
+        let rng = &mut test_rng();
+        let n = 1024;
+        let circuit = super::inner_product(n);
+        let input = [random_vec(rng, n), random_vec(rng, n)].concat();
+
+        assert!(run_protocol(&circuit, &input));
+
+        // Layers of widths other than powers of two get padded.
+        let n = 100;
+        let circuit = super::inner_product(n);
+        let input = [random_vec(rng, n), random_vec(rng, n), vec![Fp389::zero()]].concat();
+
+        assert!(run_protocol(&circuit, &input));
+    }
+
+    #[test]
+    fn matmul() {
+        let rng = &mut test_rng();
+        for n in [2, 3, 4] {
+            let (circuit, layout) = super::matmul(n);
+            let (a, b) = (random_matrix(rng, n), random_matrix(rng, n));
+            let input = layout.input(&a, &b);
+
+            assert_eq!(input[layout.a(1, 0)], a[1][0]);
+            assert_eq!(input[layout.b(0, 1)], b[0][1]);
+
+            let c: Vec<_> = (0..n)
+                .flat_map(|i| {
+                    let (a, b) = (&a, &b);
+                    (0..n).map(move |j| (0..n).map(|k| a[i][k] * b[k][j]).sum::<Fp389>())
+                })
+                .collect();
+            assert_eq!(circuit.evaluate(&input).layers[0], c);
+
+            if n.is_power_of_two() {
+                assert!(circuit.layers().iter().all(|l| l.len().is_power_of_two()));
+            }
+
+            assert!(run_protocol(&circuit, &input));
+        }
+    }
+
+    /// A random undirected graph without loops on `n` vertices.
+    fn random_graph<R: Rng>(rng: &mut R, n: usize) -> Vec<Vec<bool>> {
+        let mut a = vec![vec![false; n]; n];
+        for (i, j) in (0..n).flat_map(|i| (0..i).map(move |j| (i, j))) {
+            a[i][j] = rng.gen_bool(0.5);
+            a[j][i] = a[i][j];
+        }
+        a
+    }
+
+    fn triangles_input(a: &[Vec<bool>]) -> Vec<Fp389> {
+        let mut input: Vec<_> = a.iter().flatten().map(|e| Fp389::from(*e)).collect();
+        input.push(Fp389::zero());
+        input
+    }
+
+    #[test]
+    fn count_triangles() {
+        let rng = &mut test_rng();
+
+        for n in 2..=6 {
+            let circuit = super::count_triangles(n);
+            let a = random_graph(rng, n);
+
+            let mut triangles = 0u64;
+            for i in 0..n {
+                for j in i + 1..n {
+                    for k in j + 1..n {
+                        triangles += (a[i][j] && a[j][k] && a[k][i]) as u64;
+                    }
+                }
+            }
+
+            assert_eq!(
+                circuit.evaluate(&triangles_input(&a)).layers[0],
+                vec![Fp389::from(6 * triangles)]
+            );
+        }
+    }
+
+    #[test]
+    fn count_triangles_protocol() {
+        let rng = &mut test_rng();
+        let circuit = super::count_triangles(8);
+        let input = triangles_input(&random_graph(rng, 8));
+
+        assert!(run_protocol(&circuit, &input));
+    }
+
+    /// MiMC over `Fp389` computed directly.
+    fn mimc_reference(rounds: usize, x: Fp389, k: Fp389) -> Fp389 {
+        gadgets::mimc_constants(rounds)
+            .into_iter()
+            .fold(x, |x, c| (x + k + Fp389::from(c)).pow([3]))
+    }
+
+    #[test]
+    fn mimc() {
+        let rng = &mut test_rng();
+
+        for rounds in [1, 2, 5, 20] {
+            let circuit = super::mimc(rounds);
+            assert_eq!(circuit.num_layers(), 3 * rounds + 1);
+
+            let (x, k) = (Fp389::rand(rng), Fp389::rand(rng));
+            let input = super::mimc_input(rounds, x, k);
+
+            assert_eq!(
+                circuit.evaluate(&input).layers[0],
+                vec![mimc_reference(rounds, x, k)]
+            );
+        }
+    }
+
+    #[test]
+    fn mimc_protocol() {
+        let rng = &mut test_rng();
+        let rounds = 20;
+        let circuit = super::mimc(rounds);
+        let input = super::mimc_input(rounds, Fp389::rand(rng), Fp389::rand(rng));
+
+        let proof = prove(&circuit, &input).unwrap();
+        verify(&circuit, &input, &proof).unwrap();
+    }
+}
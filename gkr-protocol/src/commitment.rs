@@ -0,0 +1,154 @@
+//! Commitments to the values of the layers of a circuit.
+//!
+//! A hook for zero-knowledge extensions: the prover commits to the
+//! multilinear extension of every layer by its evaluations, see
+//! [`prove_committed`](crate::proof::prove_committed), and the
+//! commitments bind the challenges of the transcript.
+//!
+//! [`WitnessCommitment`] commits to the private inputs alone, so that
+//! the statement proven by
+//! [`prove_committed_witness`](crate::proof::prove_committed_witness)
+//! is about a witness fixed beforehand.
+
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+
+use ark_ff::Field;
+use ark_serialize::CanonicalSerialize;
+use sha2::{Digest, Sha256};
+
+/// A commitment scheme to vectors of field elements.
+pub trait Commitment<F: Field> {
+    /// The commitment, kept in proofs by its bytes.
+    type Output: AsRef<[u8]>;
+
+    /// Commit to `values`.
+    fn commit(&self, values: &[F]) -> Self::Output;
+}
+
+/// The SHA-256 hash of the values, binding but not hiding: opening it
+/// takes all the values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HashCommitment;
+
+impl<F: Field> Commitment<F> for HashCommitment {
+    type Output = [u8; 32];
+
+    fn commit(&self, values: &[F]) -> [u8; 32] {
+        let mut bytes = Vec::new();
+        values
+            .serialize_compressed(&mut bytes)
+            .expect("serializing into a vector does not fail");
+
+        Sha256::new_with_prefix(b"gkr-protocol commitment")
+            .chain_update(&bytes)
+            .finalize()
+            .into()
+    }
+}
+
+/// A Merkle commitment, with the hash `D`, to a witness by its values
+/// over the hypercube, padded with zeroes to a power of two.
+///
+/// Leaves and inner nodes are hashed with distinct prefixes, so that
+/// no node passes for a leaf.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WitnessCommitment<D = Sha256>(PhantomData<D>);
+
+impl<D: Digest> WitnessCommitment<D> {
+    /// Commit to `witness`, returning the root and the tree the
+    /// values are opened from.
+    pub fn commit<F: Field>(witness: &[F]) -> (Vec<u8>, MerkleTree<D>) {
+        let mut leaves: Vec<Vec<u8>> = witness.iter().map(Self::leaf).collect();
+        leaves.resize(witness.len().next_power_of_two(), Self::leaf(&F::zero()));
+
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| Self::node(&pair[0], &pair[1]))
+                .collect();
+            levels.push(level);
+        }
+
+        let tree = MerkleTree {
+            levels,
+            digest: PhantomData,
+        };
+        (tree.root().to_vec(), tree)
+    }
+
+    /// Whether `path` authenticates `value` as the value at `index`
+    /// of the witness committed to by `root`.
+    pub fn verify_path<F: Field>(root: &[u8], index: usize, value: &F, path: &[Vec<u8>]) -> bool {
+        let mut node = Self::leaf(value);
+        let mut index = index;
+        for sibling in path {
+            node = match index & 1 {
+                0 => Self::node(&node, sibling),
+                _ => Self::node(sibling, &node),
+            };
+            index >>= 1;
+        }
+
+        index == 0 && node == root
+    }
+
+    fn leaf<F: Field>(value: &F) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        value
+            .serialize_compressed(&mut bytes)
+            .expect("serializing into a vector does not fail");
+
+        D::new_with_prefix([0])
+            .chain_update(&bytes)
+            .finalize()
+            .to_vec()
+    }
+
+    fn node(left: &[u8], right: &[u8]) -> Vec<u8> {
+        D::new_with_prefix([1])
+            .chain_update(left)
+            .chain_update(right)
+            .finalize()
+            .to_vec()
+    }
+}
+
+/// The Merkle tree of a [`WitnessCommitment`], keeping the hashes of
+/// every level from the leaves up to the root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree<D> {
+    levels: Vec<Vec<Vec<u8>>>,
+    digest: PhantomData<D>,
+}
+
+impl<D> MerkleTree<D> {
+    /// The root of the tree.
+    pub fn root(&self) -> &[u8] {
+        &self.levels.last().unwrap()[0]
+    }
+
+    /// The number of leaves, a power of two.
+    pub fn num_leaves(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// The authentication path of leaf `index`: its sibling at every
+    /// level, from the leaves up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below the number of leaves.
+    pub fn path(&self, index: usize) -> Vec<Vec<u8>> {
+        assert!(index < self.num_leaves(), "no leaf {index}");
+
+        self.levels[..self.levels.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(height, level)| level[(index >> height) ^ 1].clone())
+            .collect()
+    }
+}
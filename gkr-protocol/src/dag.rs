@@ -0,0 +1,320 @@
+//! Layering of arbitrary gate DAGs into circuits.
+//!
+//! Every node of the DAG is placed one layer above its highest
+//! predecessor, lower predecessors being relayed up by a
+//! [`CircuitBuilder`]. The sinks of the DAG become the outputs.
+
+use crate::{
+    circuit::{Circuit, GateType},
+    circuit_builder::{CellGateType, CircuitBuilder},
+};
+
+/// A node of a gate DAG, referring to its predecessors by id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DagNode {
+    /// An input of the DAG.
+    Input,
+
+    /// A gate over two predecessors.
+    Gate {
+        /// The type of the gate.
+        ttype: GateType,
+        /// The ids of the left and the right predecessor.
+        inputs: [usize; 2],
+    },
+}
+
+/// An error layering a gate DAG.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum LayeringError {
+    /// A node refers to a predecessor that does not exist.
+    #[error("node {node} refers to unknown node {predecessor}")]
+    UnknownNode {
+        /// The offending node.
+        node: usize,
+        /// The missing predecessor.
+        predecessor: usize,
+    },
+
+    /// The node of the given id lies on a cycle.
+    #[error("node {0} lies on a cycle")]
+    Cycle(usize),
+}
+
+/// Where the nodes of a DAG ended up in the layered [`Circuit`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WireMap {
+    /// The layer and the label of every node, layer $0$ being the
+    /// output layer and the input layer coming last.
+    pub positions: Vec<(usize, usize)>,
+
+    /// The labels in the output layer of the sinks, by node id.
+    pub outputs: Vec<(usize, usize)>,
+
+    num_inputs: usize,
+}
+
+impl WireMap {
+    /// The input of the circuit given the `values` of the input
+    /// nodes, in the order of their ids.
+    ///
+    /// Relays add a constant zero, which is an input of the circuit too.
+    pub fn circuit_input<F: Copy + From<u64>>(&self, dag: &[DagNode], values: &[F]) -> Vec<F> {
+        let mut input = vec![F::from(0); self.num_inputs];
+        let input_nodes = (0..dag.len()).filter(|&node| dag[node] == DagNode::Input);
+
+        for (node, &value) in input_nodes.zip(values) {
+            input[self.positions[node].1] = value;
+        }
+        input
+    }
+}
+
+impl Circuit {
+    /// Layer a gate DAG, the sinks of which become the outputs.
+    pub fn from_dag(nodes: &[DagNode]) -> Result<(Circuit, WireMap), LayeringError> {
+        let order = topological_order(nodes)?;
+        let mut builder = CircuitBuilder::new();
+        let mut cells = vec![0; nodes.len()];
+
+        // Inputs first, so that they keep their order in the circuit.
+        for node in 0..nodes.len() {
+            if nodes[node] == DagNode::Input {
+                cells[node] = builder.apply_witness();
+            }
+        }
+
+        for node in order {
+            let DagNode::Gate { ttype, inputs } = nodes[node] else {
+                continue;
+            };
+            let [l, r] = inputs.map(|input| cells[input]);
+            let layer = builder.layer(l).max(builder.layer(r));
+            let (l, r) = (
+                builder.lift(l, layer).expect("lifted up from below"),
+                builder.lift(r, layer).expect("lifted up from below"),
+            );
+            let gt = match ttype {
+                GateType::Add => CellGateType::Add(l, r),
+                GateType::Mul => CellGateType::Mul(l, r),
+                GateType::Sub => CellGateType::Sub(l, r),
+                GateType::Relay => {
+                    cells[node] = builder.relay(l).expect("relayed from below");
+                    continue;
+                }
+            };
+            cells[node] = builder.intern(gt, l, r).expect("operands on one layer");
+        }
+
+        let mut has_successor = vec![false; nodes.len()];
+        for node in nodes {
+            if let DagNode::Gate { inputs, .. } = node {
+                for &input in inputs {
+                    has_successor[input] = true;
+                }
+            }
+        }
+        let top = (0..nodes.len())
+            .map(|node| builder.layer(cells[node]))
+            .max()
+            .unwrap_or(0);
+        let sinks: Vec<_> = (0..nodes.len())
+            .filter(|&node| !has_successor[node])
+            .map(|node| {
+                let output = builder
+                    .lift(cells[node], top)
+                    .expect("lifted up from below");
+                (node, output)
+            })
+            .collect();
+
+        let circuit = builder
+            .build_circuit()
+            .expect("only witnesses in the input layer");
+        let labels = builder.labels().expect("the circuit builds");
+
+        let wire_map = WireMap {
+            positions: cells.iter().map(|cell| labels[cell]).collect(),
+            outputs: sinks
+                .into_iter()
+                .map(|(node, output)| (node, labels[&output].1))
+                .collect(),
+            num_inputs: circuit.num_inputs(),
+        };
+
+        Ok((circuit, wire_map))
+    }
+}
+
+/// The ids of the `nodes` ordered so that predecessors come first.
+fn topological_order(nodes: &[DagNode]) -> Result<Vec<usize>, LayeringError> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Mark {
+        Unvisited,
+        InProgress,
+        Done,
+    }
+
+    let predecessors = |node: usize| match nodes[node] {
+        DagNode::Input => vec![],
+        DagNode::Gate { inputs, .. } => inputs.to_vec(),
+    };
+
+    let mut marks = vec![Mark::Unvisited; nodes.len()];
+    let mut order = Vec::with_capacity(nodes.len());
+
+    for root in 0..nodes.len() {
+        if marks[root] != Mark::Unvisited {
+            continue;
+        }
+
+        // Depth-first, every node on the stack with the predecessors
+        // left to visit.
+        marks[root] = Mark::InProgress;
+        let mut stack = vec![(root, predecessors(root))];
+
+        while let Some((node, pending)) = stack.last_mut() {
+            let node = *node;
+            let Some(predecessor) = pending.pop() else {
+                marks[node] = Mark::Done;
+                order.push(node);
+                stack.pop();
+                continue;
+            };
+
+            match marks.get(predecessor) {
+                None => return Err(LayeringError::UnknownNode { node, predecessor }),
+                Some(Mark::InProgress) => return Err(LayeringError::Cycle(predecessor)),
+                Some(Mark::Done) => {}
+                Some(Mark::Unvisited) => {
+                    marks[predecessor] = Mark::InProgress;
+                    stack.push((predecessor, predecessors(predecessor)));
+                }
+            }
+        }
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use ark_ff::{Fp64, MontBackend, MontConfig};
+    use ark_std::{rand::Rng, test_rng, UniformRand};
+    use pretty_assertions::assert_eq;
+
+    use super::{DagNode, LayeringError};
+    use crate::circuit::{Circuit, GateType};
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    fn gate(ttype: GateType, l: usize, r: usize) -> DagNode {
+        DagNode::Gate {
+            ttype,
+            inputs: [l, r],
+        }
+    }
+
+    /// Evaluate the nodes of a DAG whose predecessors come first.
+    fn interpret(nodes: &[DagNode], inputs: &[Fp389]) -> Vec<Fp389> {
+        let mut inputs = inputs.iter();
+        let mut values: Vec<Fp389> = vec![];
+        for node in nodes {
+            let value = match *node {
+                DagNode::Input => *inputs.next().unwrap(),
+                DagNode::Gate { ttype, inputs } => {
+                    let (l, r) = (values[inputs[0]], values[inputs[1]]);
+                    match ttype {
+                        GateType::Add => l + r,
+                        GateType::Mul => l * r,
+                        GateType::Sub => l - r,
+                        GateType::Relay => l,
+                    }
+                }
+            };
+            values.push(value);
+        }
+        values
+    }
+
+    fn check(nodes: &[DagNode], inputs: &[Fp389]) {
+        let (circuit, wire_map) = Circuit::from_dag(nodes).unwrap();
+        let expected = interpret(nodes, inputs);
+
+        let evaluation = circuit.evaluate(&wire_map.circuit_input(nodes, inputs));
+        for (node, &(layer, label)) in wire_map.positions.iter().enumerate() {
+            assert_eq!(evaluation.w(layer, label), expected[node], "node {node}");
+        }
+        for &(node, label) in &wire_map.outputs {
+            assert_eq!(evaluation.w(0, label), expected[node], "output {node}");
+        }
+    }
+
+    #[test]
+    fn diamond() {
+        // s feeds l and r which meet at t, s also skips to u.
+        let nodes = [
+            DagNode::Input,
+            gate(GateType::Mul, 0, 0),
+            gate(GateType::Add, 0, 0),
+            gate(GateType::Mul, 1, 2),
+            gate(GateType::Sub, 3, 0),
+        ];
+
+        let (circuit, wire_map) = Circuit::from_dag(&nodes).unwrap();
+        assert_eq!(circuit.num_layers(), 3);
+        assert_eq!(wire_map.positions[0], (3, 0));
+        assert_eq!(wire_map.positions[4], (0, 0));
+        assert_eq!(wire_map.outputs, vec![(4, 0)]);
+
+        check(&nodes, &[Fp389::from(5)]);
+    }
+
+    #[test]
+    fn cycles() {
+        let nodes = [
+            DagNode::Input,
+            gate(GateType::Add, 0, 2),
+            gate(GateType::Mul, 1, 0),
+        ];
+        let err = Circuit::from_dag(&nodes).unwrap_err();
+        assert!(matches!(err, LayeringError::Cycle(1 | 2)), "{err:?}");
+
+        let nodes = [DagNode::Input, gate(GateType::Add, 1, 0)];
+        assert_eq!(
+            Circuit::from_dag(&nodes).unwrap_err(),
+            LayeringError::Cycle(1)
+        );
+
+        let nodes = [DagNode::Input, gate(GateType::Add, 0, 7)];
+        assert_eq!(
+            Circuit::from_dag(&nodes).unwrap_err(),
+            LayeringError::UnknownNode {
+                node: 1,
+                predecessor: 7
+            }
+        );
+    }
+
+    #[test]
+    fn random_dags() {
+        let rng = &mut test_rng();
+
+        for _ in 0..16 {
+            let mut nodes = vec![DagNode::Input; 4];
+            for node in 4..40 {
+                let ttype = [GateType::Add, GateType::Mul, GateType::Sub][rng.gen_range(0..3)];
+                nodes.push(gate(ttype, rng.gen_range(0..node), rng.gen_range(0..node)));
+            }
+
+            let inputs: Vec<_> = (0..4).map(|_| Fp389::rand(rng)).collect();
+            check(&nodes, &inputs);
+        }
+    }
+}
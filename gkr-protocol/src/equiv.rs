@@ -0,0 +1,311 @@
+//! Checking that two circuits compute the same function by
+//! evaluating them on random inputs.
+//!
+//! Meant to sanity check circuit rewrites: agreeing on a random input
+//! is a strong hint of equivalence by the Schwartz-Zippel lemma, while
+//! a disagreement is a definite counterexample.
+
+use std::fmt;
+
+use ark_ff::Field;
+use ark_std::rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{circuit::Circuit, goldilocks::GoldilocksFp};
+
+/// The field of [`Circuit::probably_equivalent`].
+type Fq = GoldilocksFp;
+
+/// The outcome of [`circuit_equiv`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EquivResult<F> {
+    /// The circuits agree on all the random inputs tried.
+    Equivalent,
+
+    /// The circuits take different numbers of inputs.
+    InputArityMismatch {
+        /// The number of inputs of the first circuit.
+        a: usize,
+        /// The number of inputs of the second circuit.
+        b: usize,
+    },
+
+    /// The output mapping does not match the outputs of the circuits.
+    OutputMismatch {
+        /// The number of outputs of the first circuit.
+        a: usize,
+        /// The number of outputs of the second circuit.
+        b: usize,
+    },
+
+    /// The circuits disagree on an input.
+    Counterexample {
+        /// The distinguishing input.
+        input: Vec<F>,
+        /// The first output of the first circuit that disagrees.
+        output: usize,
+        /// The outputs of the first circuit.
+        a: Vec<F>,
+        /// The outputs of the second circuit.
+        b: Vec<F>,
+    },
+}
+
+impl<F> EquivResult<F> {
+    /// Whether no difference was found between the circuits.
+    pub fn is_equivalent(&self) -> bool {
+        matches!(self, EquivResult::Equivalent)
+    }
+}
+
+impl<F: fmt::Display> fmt::Display for EquivResult<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EquivResult::Equivalent => write!(f, "no difference found"),
+            EquivResult::InputArityMismatch { a, b } => {
+                write!(f, "circuits take {a} and {b} inputs")
+            }
+            EquivResult::OutputMismatch { a, b } => {
+                write!(f, "cannot map {a} outputs onto {b} outputs")
+            }
+            EquivResult::Counterexample {
+                input,
+                output,
+                a,
+                b,
+            } => {
+                write!(f, "on input [")?;
+                for (i, value) in input.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(
+                    f,
+                    "] output {output} is {} rather than {}",
+                    b[*output], a[*output]
+                )
+            }
+        }
+    }
+}
+
+/// Compare circuits `a` and `b` on `trials` random inputs.
+///
+/// Inputs are matched position by position, constant inputs of
+/// built circuits included.
+pub fn circuit_equiv<F: Field, R: Rng>(
+    a: &Circuit,
+    b: &Circuit,
+    trials: usize,
+    rng: &mut R,
+) -> EquivResult<F> {
+    let num_outputs = a
+        .layers()
+        .first()
+        .map_or(a.num_inputs(), |layer| layer.len());
+    let output_map: Vec<_> = (0..num_outputs).collect();
+
+    circuit_equiv_mapped(a, b, &output_map, trials, rng)
+}
+
+/// Compare circuits `a` and `b` on `trials` random inputs, output
+/// `i` of `a` corresponding to output `output_map[i]` of `b`.
+///
+/// The outputs reported by a counterexample are in the order of `a`.
+pub fn circuit_equiv_mapped<F: Field, R: Rng>(
+    a: &Circuit,
+    b: &Circuit,
+    output_map: &[usize],
+    trials: usize,
+    rng: &mut R,
+) -> EquivResult<F> {
+    if a.num_inputs() != b.num_inputs() {
+        return EquivResult::InputArityMismatch {
+            a: a.num_inputs(),
+            b: b.num_inputs(),
+        };
+    }
+
+    for _ in 0..trials {
+        let input: Vec<F> = (0..a.num_inputs()).map(|_| F::rand(rng)).collect();
+        let outputs_a = a.outputs(&input).expect("input arities match");
+        let outputs_b = b.outputs(&input).expect("input arities match");
+
+        if outputs_a.len() != output_map.len() || output_map.iter().any(|&j| j >= outputs_b.len()) {
+            return EquivResult::OutputMismatch {
+                a: outputs_a.len(),
+                b: outputs_b.len(),
+            };
+        }
+
+        let outputs_b: Vec<_> = output_map.iter().map(|&j| outputs_b[j]).collect();
+        if let Some(output) = (0..outputs_a.len()).find(|&i| outputs_a[i] != outputs_b[i]) {
+            return EquivResult::Counterexample {
+                input,
+                output,
+                a: outputs_a,
+                b: outputs_b,
+            };
+        }
+    }
+
+    EquivResult::Equivalent
+}
+
+impl Circuit {
+    /// Whether `self` and `other` take and give as many values and
+    /// agree on `trials` random inputs drawn from `seed`, see
+    /// [`circuit_equiv`].
+    ///
+    /// The inputs are taken in the field of $2^{64} - 2^{32} + 1$
+    /// elements.
+    pub fn probably_equivalent(&self, other: &Circuit, trials: usize, seed: u64) -> bool {
+        let rng = &mut StdRng::seed_from_u64(seed);
+
+        self.num_outputs() == other.num_outputs()
+            && circuit_equiv::<Fq, _>(self, other, trials, rng).is_equivalent()
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use ark_ff::{Fp64, MontBackend, MontConfig};
+    use ark_std::test_rng;
+    use pretty_assertions::assert_eq;
+
+    use super::{circuit_equiv, circuit_equiv_mapped, EquivResult};
+    use crate::circuit::{circuit_from_book, Circuit, CircuitLayer, Gate, GateType};
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    /// Swap the operands of every gate of the bottom layer, a valid
+    /// rewrite of additions and multiplications only.
+    fn commute(circuit: &Circuit) -> Circuit {
+        let mut layers = circuit.layers().to_vec();
+        let bottom = layers.last_mut().unwrap();
+        *bottom = CircuitLayer::new(
+            bottom
+                .gates()
+                .iter()
+                .map(|gate| {
+                    let [left, right] = gate.inputs();
+                    Gate::new(gate.ttype(), [right, left])
+                })
+                .collect(),
+        );
+        Circuit::new(layers, circuit.num_inputs())
+    }
+
+    fn with_sub() -> Circuit {
+        Circuit::new(
+            vec![
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 1]),
+                    Gate::new(GateType::Add, [1, 2]),
+                ]),
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Add, [0, 1]),
+                    Gate::new(GateType::Mul, [1, 2]),
+                    Gate::new(GateType::Sub, [2, 0]),
+                ]),
+            ],
+            3,
+        )
+    }
+
+    #[test]
+    fn equivalent_rewrites() {
+        let rng = &mut test_rng();
+        let circuit = circuit_from_book();
+
+        let result = circuit_equiv::<Fp389, _>(&circuit, &commute(&circuit), 10, rng);
+        assert_eq!(result, EquivResult::Equivalent);
+        assert!(result.is_equivalent());
+    }
+
+    #[test]
+    fn broken_rewrite() {
+        let rng = &mut test_rng();
+        let circuit = with_sub();
+        let broken = commute(&circuit);
+
+        let result = circuit_equiv::<Fp389, _>(&circuit, &broken, 10, rng);
+        let EquivResult::Counterexample {
+            input,
+            output,
+            a,
+            b,
+        } = &result
+        else {
+            panic!("{result:?}");
+        };
+        assert_eq!(&circuit.evaluate(input).layers[0], a);
+        assert_eq!(&broken.evaluate(input).layers[0], b);
+        assert_ne!(a[*output], b[*output]);
+        assert!(result.to_string().contains(&format!("output {output}")));
+    }
+
+    #[test]
+    fn permuted_outputs() {
+        let rng = &mut test_rng();
+        let circuit = with_sub();
+
+        let mut layers = circuit.layers().to_vec();
+        let mut top = layers[0].gates().to_vec();
+        top.reverse();
+        layers[0] = CircuitLayer::new(top);
+        let reversed = Circuit::new(layers, circuit.num_inputs());
+
+        assert!(!circuit_equiv::<Fp389, _>(&circuit, &reversed, 10, rng).is_equivalent());
+        assert_eq!(
+            circuit_equiv_mapped::<Fp389, _>(&circuit, &reversed, &[1, 0], 10, rng),
+            EquivResult::Equivalent
+        );
+        assert_eq!(
+            circuit_equiv_mapped::<Fp389, _>(&circuit, &reversed, &[1, 2], 10, rng),
+            EquivResult::OutputMismatch { a: 2, b: 2 }
+        );
+    }
+
+    #[test]
+    fn probably_equivalent() {
+        // x_0 * (x_1 + x_2) and x_3, lifted by relays.
+        let circuit = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![Gate::new(GateType::Mul, [0, 1]), Gate::relay(2)]),
+                CircuitLayer::new(vec![
+                    Gate::relay(0),
+                    Gate::new(GateType::Add, [1, 2]),
+                    Gate::relay(3),
+                ]),
+            ],
+            4,
+        );
+        let simplified = circuit.simplify_passthroughs();
+        assert!(circuit.probably_equivalent(&simplified, 10, 0));
+
+        let mut layers = simplified.layers().to_vec();
+        layers[0] = CircuitLayer::new(vec![Gate::new(GateType::Add, [0, 1]), Gate::relay(2)]);
+        let tampered = Circuit::new(layers, 4);
+        assert!(!circuit.probably_equivalent(&tampered, 10, 0));
+
+        assert!(!circuit_from_book().probably_equivalent(&with_sub(), 10, 0));
+    }
+
+    #[test]
+    fn arity_mismatch() {
+        let rng = &mut test_rng();
+
+        assert_eq!(
+            circuit_equiv::<Fp389, _>(&circuit_from_book(), &with_sub(), 10, rng),
+            EquivResult::InputArityMismatch { a: 4, b: 3 }
+        );
+    }
+}
@@ -0,0 +1,640 @@
+//! A front-end turning arithmetic expressions into circuits.
+//!
+//! Expressions are made of variables, integer constants, parentheses,
+//! `+`, `-`, `*` and exponentiation by an integer constant `^`.
+//! From the lowest precedence to the highest the grammar is
+//!
+//! ```text
+//! expr  := term (('+' | '-') term)*
+//! term  := unary ('*' unary)*
+//! unary := '-' unary | power
+//! power := atom ('^' integer)?
+//! atom  := variable | integer | '(' expr ')'
+//! ```
+//!
+//! A unary minus `-x` stands for `0 - x`, so `-x ^ 2` is `-(x ^ 2)`.
+//!
+//! Every variable becomes a witness of the [`CircuitBuilder`] and every
+//! integer a constant. Operands sitting on different layers are lined
+//! up with relays and identical subexpressions share their gates.
+
+use std::collections::HashMap;
+
+use crate::circuit_builder::{CellGateType, CircuitBuilder};
+
+/// A parsed arithmetic expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    /// A variable.
+    Var(String),
+
+    /// An integer constant.
+    Const(u64),
+
+    /// A sum of two expressions.
+    Add(Box<Expr>, Box<Expr>),
+
+    /// A difference of two expressions.
+    Sub(Box<Expr>, Box<Expr>),
+
+    /// A product of two expressions.
+    Mul(Box<Expr>, Box<Expr>),
+
+    /// An expression raised to a constant power.
+    Pow(Box<Expr>, u64),
+}
+
+/// An error parsing an expression.
+///
+/// Every variant carries the byte offset into the source it
+/// was encountered at.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    /// A character not belonging to the grammar.
+    #[error("unexpected character {found:?} at offset {offset}")]
+    UnexpectedChar {
+        /// Byte offset of the character.
+        offset: usize,
+
+        /// The character itself.
+        found: char,
+    },
+
+    /// A token that may not appear at this position.
+    #[error("unexpected token at offset {offset}")]
+    UnexpectedToken {
+        /// Byte offset of the token.
+        offset: usize,
+    },
+
+    /// The source ended in the middle of an expression.
+    #[error("unexpected end of input at offset {offset}")]
+    UnexpectedEnd {
+        /// Length of the source.
+        offset: usize,
+    },
+
+    /// An integer does not fit into a `u64`.
+    #[error("integer at offset {offset} is too large")]
+    IntegerTooLarge {
+        /// Byte offset of the integer.
+        offset: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(u64),
+    Plus,
+    Minus,
+    Star,
+    Caret,
+    LParen,
+    RParen,
+}
+
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = src.char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '^' => Token::Caret,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            c if c.is_ascii_digit() => {
+                let mut end = offset + 1;
+                while let Some((i, c)) = chars.peek() {
+                    if !c.is_ascii_digit() {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                src[offset..end]
+                    .parse()
+                    .map(Token::Int)
+                    .map_err(|_| ParseError::IntegerTooLarge { offset })?
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = offset + 1;
+                while let Some((i, c)) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() && *c != '_' {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                Token::Ident(src[offset..end].to_string())
+            }
+            found => return Err(ParseError::UnexpectedChar { offset, found }),
+        };
+
+        tokens.push((offset, token));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Result<(usize, Token), ParseError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or(ParseError::UnexpectedEnd { offset: self.len })?;
+        self.pos += 1;
+
+        Ok(token)
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.term()?;
+
+        loop {
+            match self.peek() {
+                Some(Token::Plus) => {
+                    self.pos += 1;
+                    lhs = Expr::Add(Box::new(lhs), Box::new(self.term()?));
+                }
+                Some(Token::Minus) => {
+                    self.pos += 1;
+                    lhs = Expr::Sub(Box::new(lhs), Box::new(self.term()?));
+                }
+                _ => return Ok(lhs),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+
+        while let Some(Token::Star) = self.peek() {
+            self.pos += 1;
+            lhs = Expr::Mul(Box::new(lhs), Box::new(self.unary()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if let Some(Token::Minus) = self.peek() {
+            self.pos += 1;
+            Ok(Expr::Sub(Box::new(Expr::Const(0)), Box::new(self.unary()?)))
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let base = self.atom()?;
+
+        if let Some(Token::Caret) = self.peek() {
+            self.pos += 1;
+            match self.next()? {
+                (_, Token::Int(exponent)) => Ok(Expr::Pow(Box::new(base), exponent)),
+                (offset, _) => Err(ParseError::UnexpectedToken { offset }),
+            }
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, ParseError> {
+        match self.next()? {
+            (_, Token::Ident(name)) => Ok(Expr::Var(name)),
+            (_, Token::Int(value)) => Ok(Expr::Const(value)),
+            (_, Token::LParen) => {
+                let expr = self.expr()?;
+                match self.next()? {
+                    (_, Token::RParen) => Ok(expr),
+                    (offset, _) => Err(ParseError::UnexpectedToken { offset }),
+                }
+            }
+            (offset, _) => Err(ParseError::UnexpectedToken { offset }),
+        }
+    }
+}
+
+/// Parse an arithmetic expression.
+pub fn parse(src: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        len: src.len(),
+    };
+
+    let expr = parser.expr()?;
+
+    match parser.tokens.get(parser.pos) {
+        Some((offset, _)) => Err(ParseError::UnexpectedToken { offset: *offset }),
+        None => Ok(expr),
+    }
+}
+
+impl Expr {
+    /// Emit the gates computing `self` into a `builder`.
+    ///
+    /// Returns the cell holding the value of the expression and the
+    /// witnesses created for the variables by their names.
+    pub fn lower(&self, builder: &mut CircuitBuilder) -> (usize, HashMap<String, usize>) {
+        let mut lowering = Lowering {
+            builder,
+            witnesses: HashMap::new(),
+        };
+
+        let output = lowering.lower(self);
+
+        (output, lowering.witnesses)
+    }
+}
+
+struct Lowering<'a> {
+    builder: &'a mut CircuitBuilder,
+    witnesses: HashMap<String, usize>,
+}
+
+impl Lowering<'_> {
+    fn lower(&mut self, expr: &Expr) -> usize {
+        match expr {
+            Expr::Var(name) => *self
+                .witnesses
+                .entry(name.clone())
+                .or_insert_with(|| self.builder.apply_witness()),
+            Expr::Const(value) => self.builder.constant(*value),
+            Expr::Add(l, r) => {
+                let (l, r) = (self.lower(l), self.lower(r));
+                self.add(l, r)
+            }
+            Expr::Sub(l, r) => {
+                let (l, r) = (self.lower(l), self.lower(r));
+                let (l, r) = self.align(l, r);
+                self.gate(CellGateType::Sub(l, r), l, r)
+            }
+            Expr::Mul(l, r) => {
+                let (l, r) = (self.lower(l), self.lower(r));
+                self.mul(l, r)
+            }
+            Expr::Pow(base, exponent) => {
+                let base = self.lower(base);
+                self.pow(base, *exponent)
+            }
+        }
+    }
+
+    /// Addition is commutative, ordering the operands lets `b + a`
+    /// share the gate with `a + b`.
+    fn add(&mut self, l: usize, r: usize) -> usize {
+        let (l, r) = self.align(l, r);
+        let (l, r) = (l.min(r), l.max(r));
+        self.gate(CellGateType::Add(l, r), l, r)
+    }
+
+    fn mul(&mut self, l: usize, r: usize) -> usize {
+        let (l, r) = self.align(l, r);
+        let (l, r) = (l.min(r), l.max(r));
+        self.gate(CellGateType::Mul(l, r), l, r)
+    }
+
+    fn pow(&mut self, base: usize, exponent: u64) -> usize {
+        self.builder
+            .pow(base, exponent)
+            .expect("powers lift their operands")
+    }
+
+    /// Relay the lower of the two cells up to the layer of the other one.
+    fn align(&mut self, mut l: usize, mut r: usize) -> (usize, usize) {
+        while self.builder.layer(l) < self.builder.layer(r) {
+            l = self.builder.relay(l).expect("relays stay within a layer");
+        }
+        while self.builder.layer(r) < self.builder.layer(l) {
+            r = self.builder.relay(r).expect("relays stay within a layer");
+        }
+
+        (l, r)
+    }
+
+    fn gate(&mut self, gt: CellGateType, l: usize, r: usize) -> usize {
+        self.builder
+            .intern(gt, l, r)
+            .expect("operands are aligned to the same layer")
+    }
+}
+
+/// Where the parts of an expression ended up in a [`CircuitBuilder`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessLayout {
+    /// The cell holding the value of the expression.
+    pub output: usize,
+
+    /// Witnesses by variable name in the order of their first
+    /// appearance in the expression.
+    pub witnesses: Vec<(String, usize)>,
+}
+
+impl WitnessLayout {
+    /// The witness of a variable.
+    pub fn witness(&self, name: &str) -> Option<usize> {
+        self.witnesses
+            .iter()
+            .find(|(var, _)| var == name)
+            .map(|(_, cell)| *cell)
+    }
+}
+
+impl CircuitBuilder {
+    /// Parse an arithmetic expression into a new `CircuitBuilder`.
+    ///
+    /// Same as [`CircuitBuilder::from_expr`] with the witnesses
+    /// ordered by their appearance in `expr`.
+    pub fn from_expression(expr: &str) -> Result<(Self, WitnessLayout), ParseError> {
+        let (builder, output, witnesses) = Self::from_expr(expr)?;
+
+        let mut witnesses: Vec<_> = witnesses.into_iter().collect();
+        witnesses.sort_by_key(|(_, cell)| *cell);
+
+        Ok((builder, WitnessLayout { output, witnesses }))
+    }
+
+    /// Parse an arithmetic expression into a new `CircuitBuilder`.
+    ///
+    /// Returns the builder, the cell holding the value of the
+    /// expression and the witnesses created for the variables
+    /// by their names.
+    pub fn from_expr(src: &str) -> Result<(Self, usize, HashMap<String, usize>), ParseError> {
+        let expr = parse(src)?;
+        let mut builder = Self::new();
+        let (output, witnesses) = expr.lower(&mut builder);
+
+        Ok((builder, output, witnesses))
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ark_ff::{Field, Fp64, MontBackend, MontConfig};
+    use ark_std::{test_rng, UniformRand};
+    use pretty_assertions::assert_eq;
+
+    use super::{parse, Expr, ParseError, WitnessLayout};
+    use crate::{
+        circuit::{Circuit, CircuitLayer, Gate, GateType},
+        circuit_builder::CircuitBuilder,
+    };
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    fn var(name: &str) -> Box<Expr> {
+        Box::new(Expr::Var(name.to_string()))
+    }
+
+    fn interpret<F: Field>(expr: &Expr, values: &HashMap<String, F>) -> F {
+        match expr {
+            Expr::Var(name) => values[name],
+            Expr::Const(value) => F::from(*value),
+            Expr::Add(l, r) => interpret(l, values) + interpret(r, values),
+            Expr::Sub(l, r) => interpret(l, values) - interpret(r, values),
+            Expr::Mul(l, r) => interpret(l, values) * interpret(r, values),
+            Expr::Pow(base, exponent) => interpret(base, values).pow([*exponent]),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            parse("a + b * c").unwrap(),
+            Expr::Add(var("a"), Box::new(Expr::Mul(var("b"), var("c"))))
+        );
+        assert_eq!(
+            parse("a - b - c").unwrap(),
+            Expr::Sub(Box::new(Expr::Sub(var("a"), var("b"))), var("c"))
+        );
+        assert_eq!(
+            parse("2 * a ^ 3").unwrap(),
+            Expr::Mul(Box::new(Expr::Const(2)), Box::new(Expr::Pow(var("a"), 3)))
+        );
+        assert_eq!(
+            parse("(a + b) * c").unwrap(),
+            Expr::Mul(Box::new(Expr::Add(var("a"), var("b"))), var("c"))
+        );
+
+        let neg = |expr| Box::new(Expr::Sub(Box::new(Expr::Const(0)), expr));
+        assert_eq!(parse("-x + y").unwrap(), Expr::Add(neg(var("x")), var("y")));
+        assert_eq!(
+            parse("x - y * z").unwrap(),
+            Expr::Sub(var("x"), Box::new(Expr::Mul(var("y"), var("z"))))
+        );
+        assert_eq!(
+            parse("x - 2 * y").unwrap(),
+            Expr::Sub(
+                var("x"),
+                Box::new(Expr::Mul(Box::new(Expr::Const(2)), var("y")))
+            )
+        );
+        assert_eq!(
+            parse("-x ^ 2").unwrap(),
+            *neg(Box::new(Expr::Pow(var("x"), 2)))
+        );
+        assert_eq!(
+            parse("x * --y").unwrap(),
+            Expr::Mul(var("x"), neg(neg(var("y"))))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse("x + $").unwrap_err(),
+            ParseError::UnexpectedChar {
+                offset: 4,
+                found: '$'
+            }
+        );
+        assert_eq!(
+            parse("x + * y").unwrap_err(),
+            ParseError::UnexpectedToken { offset: 4 }
+        );
+        assert_eq!(
+            parse("(x + y").unwrap_err(),
+            ParseError::UnexpectedEnd { offset: 6 }
+        );
+        assert_eq!(
+            parse("x y").unwrap_err(),
+            ParseError::UnexpectedToken { offset: 2 }
+        );
+        assert_eq!(
+            parse("x ^ y").unwrap_err(),
+            ParseError::UnexpectedToken { offset: 4 }
+        );
+        assert_eq!(
+            parse("1 + 99999999999999999999").unwrap_err(),
+            ParseError::IntegerTooLarge { offset: 4 }
+        );
+    }
+
+    #[test]
+    fn repeated_subexpressions() {
+        let (builder, _, witnesses) = CircuitBuilder::from_expr("x0 * x1 + x1 * x0").unwrap();
+
+        assert_eq!(witnesses.len(), 2);
+        assert_eq!(
+            builder.build_circuit().unwrap(),
+            Circuit::new(
+                vec![
+                    CircuitLayer::new(vec![Gate::new(GateType::Add, [0, 0])]),
+                    CircuitLayer::new(vec![Gate::new(GateType::Mul, [0, 1])]),
+                ],
+                2
+            )
+        );
+    }
+
+    #[test]
+    fn relays() {
+        let (builder, _, _) = CircuitBuilder::from_expr("x * y + z").unwrap();
+
+        // Inputs are `x`, `y`, `z` and the zero `z` is relayed with.
+        assert_eq!(
+            builder.build_circuit().unwrap(),
+            Circuit::new(
+                vec![
+                    CircuitLayer::new(vec![Gate::new(GateType::Add, [0, 1])]),
+                    CircuitLayer::new(vec![
+                        Gate::new(GateType::Mul, [0, 1]),
+                        Gate::new(GateType::Add, [2, 3]),
+                    ]),
+                ],
+                4
+            )
+            .with_constants(1)
+        );
+    }
+
+    #[test]
+    fn evaluation_matches_interpreter() {
+        let rng = &mut test_rng();
+
+        let sources = [
+            "(x0 + x1) * (x2 * x2) + x3",
+            "x0 - x1 * 3 + 7",
+            "(a + b) ^ 5 - a * b ^ 2",
+            "x ^ 0 + x ^ 1 + x ^ 13",
+            "((a * b) * (a * b) - (a * b)) * c",
+            "-x + y",
+            "x - y * z",
+            "x - 2 * y",
+            "-(a + b) * -c - -3",
+        ];
+
+        for src in sources {
+            let expr = parse(src).unwrap();
+            let (builder, output, witnesses) = CircuitBuilder::from_expr(src).unwrap();
+            let circuit = builder.build_circuit().unwrap();
+
+            for _ in 0..10 {
+                let values: HashMap<String, Fp389> = witnesses
+                    .keys()
+                    .map(|name| (name.clone(), Fp389::rand(rng)))
+                    .collect();
+                let by_cell: HashMap<usize, Fp389> = witnesses
+                    .iter()
+                    .map(|(name, cell)| (*cell, values[name]))
+                    .collect();
+
+                let evaluation =
+                    circuit.evaluate(&builder.input_values(|cell| by_cell[&cell]).unwrap());
+
+                assert_eq!(builder.layer(output), circuit.num_layers());
+                assert_eq!(
+                    evaluation.layers[0],
+                    vec![interpret(&expr, &values)],
+                    "{src}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn from_expression() {
+        let (builder, layout) = CircuitBuilder::from_expression("(x + y) * z").unwrap();
+
+        assert_eq!(
+            layout,
+            WitnessLayout {
+                output: 6,
+                witnesses: vec![
+                    ("x".to_string(), 0),
+                    ("y".to_string(), 1),
+                    ("z".to_string(), 3)
+                ],
+            }
+        );
+
+        // The two operator gates and the relay lifting `z`
+        // up to the layer of `x + y`.
+        let circuit = builder.build_circuit().unwrap();
+        assert_eq!(
+            circuit
+                .layers()
+                .iter()
+                .map(|layer| layer.len())
+                .sum::<usize>(),
+            3
+        );
+
+        type Direct = fn(&[i128]) -> i128;
+
+        let cases: [(&str, Direct); 4] = [
+            ("(x + y) * z", |v| (v[0] + v[1]) * v[2]),
+            ("a * b - c", |v| v[0] * v[1] - v[2]),
+            ("(a - b) * (a + b)", |v| (v[0] - v[1]) * (v[0] + v[1])),
+            ("u * (v + 2) * w", |v| v[0] * (v[1] + 2) * v[2]),
+        ];
+
+        for (src, direct) in cases {
+            let (builder, layout) = CircuitBuilder::from_expression(src).unwrap();
+            let circuit = builder.build_circuit().unwrap();
+            let values = [3i128, -4, 11];
+
+            let input = builder
+                .input_values(|cell| {
+                    let position = layout
+                        .witnesses
+                        .iter()
+                        .position(|(_, witness)| *witness == cell)
+                        .unwrap();
+                    values[position]
+                })
+                .unwrap();
+
+            assert_eq!(
+                circuit.evaluate(&input).layers[0],
+                vec![direct(&values)],
+                "{src}"
+            );
+        }
+    }
+}
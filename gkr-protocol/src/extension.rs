@@ -0,0 +1,29 @@
+//! Challenges from an extension of a small base field.
+//!
+//! Over a field of 64 bits the Sum-Check protocol is not sound enough:
+//! a cheating prover survives a round with probability about
+//! $d / |\mathbb{F}|$. The circuit is then evaluated over the base
+//! field, and the protocol is run over an extension of it, the values
+//! of the layers embedded, so that the challenges of the verifier are
+//! drawn from the much larger extension.
+//!
+//! See [`prove_over_extension`](crate::proof::prove_over_extension)
+//! and [`verify_over_extension`](crate::proof::verify_over_extension).
+
+use ark_ff::Field;
+
+/// A field containing `B`, the field the protocol is run over when
+/// the circuit is evaluated over `B`.
+///
+/// Every field extends its base prime field, a prime field extending
+/// itself.
+pub trait ExtensionOf<B: Field>: Field {
+    /// The element `value` of the base field.
+    fn from_base(value: B) -> Self;
+}
+
+impl<E: Field> ExtensionOf<E::BasePrimeField> for E {
+    fn from_base(value: E::BasePrimeField) -> Self {
+        E::from_base_prime_field(value)
+    }
+}
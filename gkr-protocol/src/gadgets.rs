@@ -0,0 +1,53 @@
+//! Gadgets composing the primitives of the [`CircuitBuilder`].
+
+use sha2::{Digest, Sha256};
+
+use crate::circuit_builder::{BuildError, CircuitBuilder, Wire};
+
+/// Add `rounds` rounds of the MiMC permutation
+/// $x \leftarrow (x + k + c_i)^3$ on top of `x` with the key `k`.
+///
+/// Every round takes three layers: an addition of the round key
+/// $k + c_i$, prepared right above the inputs and relayed up, and
+/// the two multiplications of the cube. The constants are inputs of
+/// the circuit, see [`CircuitBuilder::constant`].
+///
+/// # Panics
+///
+/// Panics if there are fewer `constants` than `rounds`.
+pub fn mimc(
+    builder: &mut CircuitBuilder,
+    x: Wire,
+    k: Wire,
+    constants: &[u64],
+    rounds: usize,
+) -> Result<Wire, BuildError> {
+    let mut x = x;
+
+    for &c in &constants[..rounds] {
+        let c = builder.constant(c);
+        let round_key = builder.sum(&[k, c])?;
+        let t = builder.sum(&[x, round_key])?;
+
+        x = builder.pow(t, 3)?;
+    }
+
+    Ok(x)
+}
+
+/// The round constants of [`mimc`]: $c_0 = 0$ followed by
+/// SHA-256 derived values.
+pub fn mimc_constants(rounds: usize) -> Vec<u64> {
+    (0..rounds)
+        .map(|i| match i {
+            0 => 0,
+            i => {
+                let digest = Sha256::new()
+                    .chain_update(b"mimc")
+                    .chain_update((i as u64).to_le_bytes())
+                    .finalize();
+                u64::from_le_bytes(digest[..8].try_into().unwrap())
+            }
+        })
+        .collect()
+}
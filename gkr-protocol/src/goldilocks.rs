@@ -0,0 +1,731 @@
+//! Arithmetic in the Goldilocks field of $p = 2^{64} - 2^{32} + 1$.
+//!
+//! [`Goldilocks`] keeps its elements as canonical `u64`s and reduces
+//! products by the shape of the modulus alone: $2^{64} \equiv 2^{32} - 1$
+//! and $2^{96} \equiv -1$, so a 128-bit product folds back into 64
+//! bits with a few additions and no division. The reductions select
+//! rather than branch on carries and borrows.
+//!
+//! [`Goldilocks`] implements [`Field`] and [`FftField`], so circuits
+//! evaluate over it, see [`Circuit::evaluate`], and the [`Prover`]
+//! and the [`Verifier`] run over it as over any arkworks field. Its
+//! base prime field is [`GoldilocksFp`], the Montgomery form of the
+//! same modulus: the two convert into each other and serialize to
+//! the same bytes, and [`GoldilocksExt2`], the quadratic extension
+//! of [`GoldilocksFp`], is where the challenges are drawn from when
+//! 64 bits are too few, see [`extension`](crate::extension).
+//!
+//! [`Circuit::evaluate`]: crate::circuit::Circuit::evaluate
+//! [`Prover`]: crate::Prover
+//! [`Verifier`]: crate::Verifier
+
+use alloc::vec::Vec;
+use core::{
+    fmt, iter,
+    iter::{Product, Sum},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use ark_ff::{FftField, Field, LegendreSymbol, PrimeField, SqrtPrecomputation};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalDeserializeWithFlags, CanonicalSerialize,
+    CanonicalSerializeWithFlags, Compress, Flags, Read, SerializationError, Valid, Validate, Write,
+};
+use ark_std::{
+    rand::{
+        distributions::{Distribution, Standard},
+        Rng,
+    },
+    One, Zero,
+};
+use zeroize::Zeroize;
+
+pub use field::{GoldilocksExt2, GoldilocksFp};
+
+#[allow(non_local_definitions)]
+mod field {
+    use ark_ff::{Fp2, Fp2Config, Fp64, MontBackend, MontConfig, MontFp};
+
+    #[derive(MontConfig)]
+    #[modulus = "18446744069414584321"]
+    #[generator = "7"]
+    pub struct GoldilocksConfig;
+
+    /// The arkworks field of the modulus of [`Goldilocks`].
+    ///
+    /// [`Goldilocks`]: super::Goldilocks
+    pub type GoldilocksFp = Fp64<MontBackend<GoldilocksConfig, 1>>;
+
+    pub struct GoldilocksExt2Config;
+
+    impl Fp2Config for GoldilocksExt2Config {
+        type Fp = GoldilocksFp;
+
+        /// The generator $7$ is not a square.
+        const NONRESIDUE: GoldilocksFp = MontFp!("7");
+
+        /// $7^{(p^i - 1) / 2}$, that is $1$ and $-1$.
+        const FROBENIUS_COEFF_FP2_C1: &'static [GoldilocksFp] =
+            &[MontFp!("1"), MontFp!("18446744069414584320")];
+    }
+
+    /// The quadratic extension $\mathbb{F}_p[X] / (X^2 - 7)$ of
+    /// [`GoldilocksFp`], to draw challenges from, see
+    /// [`extension`](crate::extension).
+    pub type GoldilocksExt2 = Fp2<GoldilocksExt2Config>;
+}
+
+/// The modulus $p = 2^{64} - 2^{32} + 1$.
+pub const MODULUS: u64 = 0xffff_ffff_0000_0001;
+
+/// $2^{64} \bmod p = 2^{32} - 1$.
+const EPSILON: u64 = 0xffff_ffff;
+
+/// An element of the Goldilocks field, to evaluate circuits and
+/// prove over, see the [module documentation](self).
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Goldilocks(u64);
+
+impl Goldilocks {
+    /// The element of `value` reduced modulo $p$.
+    pub const fn new(value: u64) -> Self {
+        Self(canonicalize(value))
+    }
+
+    /// The value in $[0, p)$.
+    pub const fn to_canonical_u64(self) -> u64 {
+        self.0
+    }
+
+    /// The element squared.
+    pub fn square(self) -> Self {
+        let x = self.0 as u128;
+        Self(reduce128(x * x))
+    }
+
+    /// The element to the power `exp`.
+    pub fn pow(self, mut exp: u64) -> Self {
+        let mut base = self;
+        let mut res = Self::one();
+        while exp != 0 {
+            if exp & 1 == 1 {
+                res *= base;
+            }
+            base = base.square();
+            exp >>= 1;
+        }
+
+        res
+    }
+
+    /// The inverse, `None` for zero.
+    pub fn inverse(self) -> Option<Self> {
+        match self.is_zero() {
+            true => None,
+            // Fermat's little theorem.
+            false => Some(self.pow(MODULUS - 2)),
+        }
+    }
+}
+
+/// Replace every non-zero element of `values` by its inverse with a
+/// single inversion, leaving the zeroes as they are.
+///
+/// Montgomery's trick: the prefix products are inverted all at once
+/// and unwound from the back, three multiplications an element.
+pub fn batch_inverse(values: &mut [Goldilocks]) {
+    let mut prefix = Vec::with_capacity(values.len());
+    let mut acc = Goldilocks::one();
+    for v in values.iter().filter(|v| !v.is_zero()) {
+        prefix.push(acc);
+        acc *= *v;
+    }
+
+    let mut inv = acc.inverse().expect("the product of non-zero elements");
+    for (v, before) in values
+        .iter_mut()
+        .rev()
+        .filter(|v| !v.is_zero())
+        .zip(prefix.into_iter().rev())
+    {
+        let next = inv * *v;
+        *v = inv * before;
+        inv = next;
+    }
+}
+
+/// `x` in $[0, p)$, given $x < 2^{64} < 2p$.
+#[inline(always)]
+const fn canonicalize(x: u64) -> u64 {
+    let (reduced, borrow) = x.overflowing_sub(MODULUS);
+    match borrow {
+        true => x,
+        false => reduced,
+    }
+}
+
+/// [`EPSILON`] if `flag` is set, zero otherwise, without branching.
+#[inline(always)]
+const fn epsilon_if(flag: bool) -> u64 {
+    EPSILON * flag as u64
+}
+
+/// `x` modulo $p$.
+///
+/// With $x = x_0 + 2^{64} x_1 + 2^{96} x_2$ for $x_0 < 2^{64}$ and
+/// $x_1, x_2 < 2^{32}$, $x \equiv x_0 - x_2 + (2^{32} - 1) x_1$.
+#[inline(always)]
+fn reduce128(x: u128) -> u64 {
+    let x_lo = x as u64;
+    let x_hi = (x >> 64) as u64;
+    let x_hi_hi = x_hi >> 32;
+    let x_hi_lo = x_hi & EPSILON;
+
+    // A borrow takes 2^64 \equiv EPSILON back off, which cannot
+    // underflow as x_hi_hi < 2^32.
+    let (t0, borrow) = x_lo.overflowing_sub(x_hi_hi);
+    let t0 = t0.wrapping_sub(epsilon_if(borrow));
+
+    // A carry leaves less than EPSILON * EPSILON, so adding EPSILON
+    // back cannot overflow.
+    let t1 = x_hi_lo * EPSILON;
+    let (res, carry) = t0.overflowing_add(t1);
+    let res = res.wrapping_add(epsilon_if(carry));
+
+    canonicalize(res)
+}
+
+impl Add for Goldilocks {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        // Below 2p - 2, so a carry leaves room to add EPSILON.
+        let (sum, carry) = self.0.overflowing_add(other.0);
+        Self(canonicalize(sum.wrapping_add(epsilon_if(carry))))
+    }
+}
+
+impl Sub for Goldilocks {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, other: Self) -> Self {
+        // A borrow adds 2^64, p = 2^64 - EPSILON is what should be.
+        let (diff, borrow) = self.0.overflowing_sub(other.0);
+        Self(diff.wrapping_sub(epsilon_if(borrow)))
+    }
+}
+
+impl Mul for Goldilocks {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, other: Self) -> Self {
+        Self(reduce128(self.0 as u128 * other.0 as u128))
+    }
+}
+
+impl Neg for Goldilocks {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::zero() - self
+    }
+}
+
+impl AddAssign for Goldilocks {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Goldilocks {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for Goldilocks {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl Div for Goldilocks {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: Self) -> Self {
+        self * other.inverse().expect("division by zero")
+    }
+}
+
+impl DivAssign for Goldilocks {
+    fn div_assign(&mut self, other: Self) {
+        *self = *self / other;
+    }
+}
+
+/// The operations with the right-hand side by reference, which
+/// [`Field`] asks for.
+macro_rules! by_reference {
+    ($($op:ident::$fn:ident, $op_assign:ident::$fn_assign:ident;)*) => {$(
+        impl<'a> $op<&'a Goldilocks> for Goldilocks {
+            type Output = Self;
+
+            fn $fn(self, other: &'a Self) -> Self {
+                self.$fn(*other)
+            }
+        }
+
+        impl<'a> $op<&'a mut Goldilocks> for Goldilocks {
+            type Output = Self;
+
+            fn $fn(self, other: &'a mut Self) -> Self {
+                self.$fn(*other)
+            }
+        }
+
+        impl<'a> $op_assign<&'a Goldilocks> for Goldilocks {
+            fn $fn_assign(&mut self, other: &'a Self) {
+                self.$fn_assign(*other)
+            }
+        }
+
+        impl<'a> $op_assign<&'a mut Goldilocks> for Goldilocks {
+            fn $fn_assign(&mut self, other: &'a mut Self) {
+                self.$fn_assign(*other)
+            }
+        }
+    )*};
+}
+
+by_reference! {
+    Add::add, AddAssign::add_assign;
+    Sub::sub, SubAssign::sub_assign;
+    Mul::mul, MulAssign::mul_assign;
+    Div::div, DivAssign::div_assign;
+}
+
+impl Sum for Goldilocks {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), Add::add)
+    }
+}
+
+impl Product for Goldilocks {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::one(), Mul::mul)
+    }
+}
+
+impl<'a> Sum<&'a Goldilocks> for Goldilocks {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+impl<'a> Product<&'a Goldilocks> for Goldilocks {
+    fn product<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().product()
+    }
+}
+
+impl Zero for Goldilocks {
+    fn zero() -> Self {
+        Self(0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl One for Goldilocks {
+    fn one() -> Self {
+        Self(1)
+    }
+}
+
+impl From<u64> for Goldilocks {
+    fn from(value: u64) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<u128> for Goldilocks {
+    fn from(value: u128) -> Self {
+        Self(reduce128(value))
+    }
+}
+
+impl From<u32> for Goldilocks {
+    fn from(value: u32) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<u16> for Goldilocks {
+    fn from(value: u16) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<u8> for Goldilocks {
+    fn from(value: u8) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<bool> for Goldilocks {
+    fn from(value: bool) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<Goldilocks> for GoldilocksFp {
+    fn from(value: Goldilocks) -> Self {
+        GoldilocksFp::from(value.0)
+    }
+}
+
+impl From<GoldilocksFp> for Goldilocks {
+    fn from(value: GoldilocksFp) -> Self {
+        Self(value.into_bigint().as_ref()[0])
+    }
+}
+
+impl Distribution<Goldilocks> for Standard {
+    /// Uniform by rejection of the values past $p$.
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Goldilocks {
+        loop {
+            let value: u64 = rng.gen();
+            if value < MODULUS {
+                return Goldilocks(value);
+            }
+        }
+    }
+}
+
+impl Zeroize for Goldilocks {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Field for Goldilocks {
+    type BasePrimeField = GoldilocksFp;
+    type BasePrimeFieldIter = iter::Once<GoldilocksFp>;
+
+    /// The square roots are taken in [`GoldilocksFp`].
+    const SQRT_PRECOMP: Option<SqrtPrecomputation<Self>> = None;
+
+    const ZERO: Self = Self(0);
+    const ONE: Self = Self(1);
+
+    fn extension_degree() -> u64 {
+        1
+    }
+
+    fn to_base_prime_field_elements(&self) -> Self::BasePrimeFieldIter {
+        iter::once((*self).into())
+    }
+
+    fn from_base_prime_field_elems(elems: &[GoldilocksFp]) -> Option<Self> {
+        match elems {
+            &[elem] => Some(elem.into()),
+            _ => None,
+        }
+    }
+
+    fn from_base_prime_field(elem: GoldilocksFp) -> Self {
+        elem.into()
+    }
+
+    fn double(&self) -> Self {
+        *self + *self
+    }
+
+    fn double_in_place(&mut self) -> &mut Self {
+        *self = self.double();
+        self
+    }
+
+    fn neg_in_place(&mut self) -> &mut Self {
+        *self = -*self;
+        self
+    }
+
+    fn from_random_bytes_with_flags<F: Flags>(bytes: &[u8]) -> Option<(Self, F)> {
+        GoldilocksFp::from_random_bytes_with_flags(bytes).map(|(elem, flags)| (elem.into(), flags))
+    }
+
+    fn legendre(&self) -> LegendreSymbol {
+        GoldilocksFp::from(*self).legendre()
+    }
+
+    fn sqrt(&self) -> Option<Self> {
+        GoldilocksFp::from(*self).sqrt().map(Self::from)
+    }
+
+    fn square(&self) -> Self {
+        Goldilocks::square(*self)
+    }
+
+    fn square_in_place(&mut self) -> &mut Self {
+        *self = Goldilocks::square(*self);
+        self
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        Goldilocks::inverse(*self)
+    }
+
+    fn inverse_in_place(&mut self) -> Option<&mut Self> {
+        *self = Goldilocks::inverse(*self)?;
+        Some(self)
+    }
+
+    /// The identity, as over any prime field.
+    fn frobenius_map_in_place(&mut self, _power: usize) {}
+}
+
+impl FftField for Goldilocks {
+    const GENERATOR: Self = Self(7);
+
+    /// $p - 1 = 2^{32} (2^{32} - 1)$.
+    const TWO_ADICITY: u32 = 32;
+
+    /// $7^{2^{32} - 1}$.
+    const TWO_ADIC_ROOT_OF_UNITY: Self = Self(1_753_635_133_440_165_772);
+}
+
+impl CanonicalSerialize for Goldilocks {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        GoldilocksFp::from(*self).serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        GoldilocksFp::from(*self).serialized_size(compress)
+    }
+}
+
+impl CanonicalSerializeWithFlags for Goldilocks {
+    fn serialize_with_flags<W: Write, F: Flags>(
+        &self,
+        writer: W,
+        flags: F,
+    ) -> Result<(), SerializationError> {
+        GoldilocksFp::from(*self).serialize_with_flags(writer, flags)
+    }
+
+    fn serialized_size_with_flags<F: Flags>(&self) -> usize {
+        GoldilocksFp::from(*self).serialized_size_with_flags::<F>()
+    }
+}
+
+impl Valid for Goldilocks {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for Goldilocks {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        GoldilocksFp::deserialize_with_mode(reader, compress, validate).map(Self::from)
+    }
+}
+
+impl CanonicalDeserializeWithFlags for Goldilocks {
+    fn deserialize_with_flags<R: Read, F: Flags>(
+        reader: R,
+    ) -> Result<(Self, F), SerializationError> {
+        GoldilocksFp::deserialize_with_flags(reader).map(|(elem, flags)| (elem.into(), flags))
+    }
+}
+
+impl fmt::Debug for Goldilocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Goldilocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use ark_ff::{FftField, Field};
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::{test_rng, One, UniformRand, Zero};
+    use num_bigint::BigUint;
+    use pretty_assertions::assert_eq;
+
+    use super::{batch_inverse, Goldilocks, GoldilocksFp, MODULUS};
+    use crate::{circuit::circuit_from_book, proof, test_utils::FaultSpec};
+
+    fn model(x: Goldilocks) -> BigUint {
+        BigUint::from(x.to_canonical_u64())
+    }
+
+    /// Values near the edges: 0, 1, around $2^{32}$, $2^{63}$ and $p$.
+    fn edges() -> Vec<Goldilocks> {
+        let mut values = vec![];
+        for base in [0, 1 << 32, 1 << 63, MODULUS - 1] {
+            for offset in [0u64, 1, 2, 3] {
+                values.push(Goldilocks::new(base.wrapping_add(offset)));
+                values.push(Goldilocks::new(base.wrapping_sub(offset)));
+            }
+        }
+        values.push(Goldilocks::new(MODULUS - (1 << 32)));
+        values.push(Goldilocks::new(u64::MAX));
+        values
+    }
+
+    #[test]
+    fn reduction() {
+        assert_eq!(Goldilocks::new(MODULUS), Goldilocks::zero());
+        assert_eq!(Goldilocks::new(MODULUS + 1), Goldilocks::one());
+        assert_eq!(
+            Goldilocks::new(u64::MAX).to_canonical_u64(),
+            u64::MAX - MODULUS
+        );
+        assert_eq!(-Goldilocks::one(), Goldilocks::new(MODULUS - 1));
+        assert_eq!(-Goldilocks::zero(), Goldilocks::zero());
+        assert_eq!(Goldilocks::new(MODULUS - 1).square(), Goldilocks::one());
+        assert_eq!(Goldilocks::zero().inverse(), None);
+        assert_eq!(Goldilocks::one().inverse(), Some(Goldilocks::one()));
+    }
+
+    #[test]
+    fn against_biguint() {
+        let rng = &mut test_rng();
+        let p = BigUint::from(MODULUS);
+
+        let mut values = edges();
+        values.extend((0..64).map(|_| Goldilocks::rand(rng)));
+
+        for &a in &values {
+            assert!(a.to_canonical_u64() < MODULUS);
+            assert_eq!(model(a.square()), model(a) * model(a) % &p);
+            if let Some(inv) = a.inverse() {
+                assert_eq!(model(a) * model(inv) % &p, BigUint::one());
+            }
+
+            for &b in &values {
+                assert_eq!(model(a + b), (model(a) + model(b)) % &p);
+                assert_eq!(model(a - b), (&p + model(a) - model(b)) % &p);
+                assert_eq!(model(a * b), model(a) * model(b) % &p);
+            }
+        }
+    }
+
+    #[test]
+    fn against_arkworks() {
+        let rng = &mut test_rng();
+
+        for _ in 0..256 {
+            let (a, b) = (Goldilocks::rand(rng), Goldilocks::rand(rng));
+            let (x, y) = (GoldilocksFp::from(a), GoldilocksFp::from(b));
+            assert_eq!(Goldilocks::from(x), a);
+            assert_eq!(GoldilocksFp::from(a * b - a.square()), x * y - x.square());
+            assert_eq!(a.inverse().map(GoldilocksFp::from), x.inverse(),);
+        }
+
+        let input = [3u64, 2, 3, 1].map(Goldilocks::from);
+        let outputs = &circuit_from_book().evaluate(&input).layers[0];
+        assert_eq!(outputs, &[Goldilocks::from(36u64), Goldilocks::from(6u64)]);
+    }
+
+    #[test]
+    fn batch_inversion() {
+        let rng = &mut test_rng();
+
+        let mut values = edges();
+        values.extend((0..32).map(|_| Goldilocks::rand(rng)));
+        let expected: Vec<_> = values
+            .iter()
+            .map(|v| v.inverse().unwrap_or(Goldilocks::zero()))
+            .collect();
+
+        batch_inverse(&mut values);
+        assert_eq!(values, expected);
+
+        batch_inverse(&mut []);
+        let mut zeroes = [Goldilocks::zero(); 3];
+        batch_inverse(&mut zeroes);
+        assert_eq!(zeroes, [Goldilocks::zero(); 3]);
+    }
+
+    #[test]
+    fn field() {
+        let rng = &mut test_rng();
+
+        for a in edges()
+            .into_iter()
+            .chain((0..64).map(|_| Goldilocks::rand(rng)))
+        {
+            let x = GoldilocksFp::from(a);
+            assert_eq!(GoldilocksFp::from(Field::square(&a)), x.square());
+            assert_eq!(Field::inverse(&a).map(GoldilocksFp::from), x.inverse());
+            assert_eq!(a.legendre(), x.legendre());
+            assert_eq!(a.sqrt().map(GoldilocksFp::from), x.sqrt());
+            assert_eq!(
+                Field::pow(&a, [MODULUS - 1]),
+                Goldilocks::from(!a.is_zero())
+            );
+
+            let mut bytes = vec![];
+            a.serialize_compressed(&mut bytes).unwrap();
+            let mut expected = vec![];
+            x.serialize_compressed(&mut expected).unwrap();
+            assert_eq!(bytes, expected);
+            assert_eq!(Goldilocks::deserialize_compressed(&*bytes).unwrap(), a);
+        }
+
+        let root = Goldilocks::TWO_ADIC_ROOT_OF_UNITY;
+        assert_eq!(Field::pow(&root, [1 << 31]), -Goldilocks::one());
+        assert_eq!(Field::pow(&root, [1 << 32]), Goldilocks::one());
+        assert_eq!(
+            Goldilocks::from(u128::MAX),
+            Goldilocks::from(u64::MAX).square() + Goldilocks::from(u64::MAX).double()
+        );
+    }
+
+    #[test]
+    fn prove_and_verify() {
+        let circuit = circuit_from_book();
+        let input = [3u64, 2, 3, 1].map(Goldilocks::from);
+
+        let mut gkr_proof = proof::prove(&circuit, &input).unwrap();
+        proof::verify(&circuit, &input, &gkr_proof).unwrap();
+
+        let wrong = [3u64, 2, 3, 2].map(Goldilocks::from);
+        assert!(proof::verify(&circuit, &wrong, &gkr_proof).is_err());
+
+        let fault = FaultSpec {
+            layer: 0,
+            round: 0,
+            coefficient: 0,
+            offset: Goldilocks::one(),
+        };
+        let (_, p) = gkr_proof.round_polys_mut().next().unwrap();
+        fault.apply(p);
+        assert!(proof::verify(&circuit, &input, &gkr_proof).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! Deterministic workloads for the benchmarks and the tests.
+//!
+//! Every generator draws from a [`StdRng`] seeded with [`SEED`], so
+//! that the same parameters give the same circuit, input and point on
+//! every machine and at every commit, and timings stay comparable.
+
+use ark_ff::Field;
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    circuit::Circuit,
+    circuit_builder::{CircuitBuilder, CircuitSource, RandomCircuit},
+};
+
+/// The seed of all the generators.
+pub const SEED: u64 = 0x676b_7200;
+
+/// The builder of [`CircuitBuilder::random`] with `depth` layers of
+/// `width` cells over `width` inputs, `(depth + 1) * width` cells in
+/// all.
+pub fn builder(depth: usize, width: usize) -> CircuitBuilder {
+    CircuitBuilder::random(&mut StdRng::seed_from_u64(SEED), depth, width)
+}
+
+/// The circuit built by [`builder`].
+pub fn circuit(depth: usize, width: usize) -> Circuit {
+    RandomCircuit {
+        depth,
+        width,
+        seed: SEED,
+    }
+    .build()
+    .expect("random circuits build")
+}
+
+/// `len` field elements, an input of a circuit of `len` inputs.
+pub fn input<F: Field>(len: usize) -> Vec<F> {
+    let rng = &mut StdRng::seed_from_u64(SEED ^ len as u64);
+    (0..len).map(|_| F::rand(rng)).collect()
+}
+
+/// A point of `num_vars` field elements, such as $r_i$.
+pub fn point<F: Field>(num_vars: usize) -> Vec<F> {
+    let rng = &mut StdRng::seed_from_u64(!SEED ^ num_vars as u64);
+    (0..num_vars).map(|_| F::rand(rng)).collect()
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use ark_ff::{Fp64, MontBackend, MontConfig};
+    use pretty_assertions::assert_eq;
+
+    use super::{builder, circuit, input, point};
+    use crate::tests::run_protocol;
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    #[test]
+    fn deterministic() {
+        let c = circuit(3, 16);
+        assert_eq!(c, circuit(3, 16));
+        assert_eq!(c, builder(3, 16).build_circuit().unwrap());
+        assert_eq!(c.num_layers(), 3);
+        assert_eq!(c.num_inputs(), 16);
+
+        assert_eq!(input::<Fp389>(16), input::<Fp389>(16));
+        assert_eq!(point::<Fp389>(4).len(), 4);
+        assert!(run_protocol(&c, &input::<Fp389>(16)));
+    }
+}
@@ -1,28 +1,86 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(unused_crate_dependencies)]
 #![deny(missing_docs)]
 
 //! The implementation of the GKR protocol.
+//!
+//! Without the default `std` feature only the circuits, the
+//! [`Prover`], the [`Verifier`] and non-interactive [`proof`]s are
+//! available, on top of `alloc`.
 
-use std::iter;
+extern crate alloc;
 
-use ark_ff::{FftField, Field, Zero};
-use ark_poly::{
-    univariate, DenseMultilinearExtension, DenseUVPolynomial, MultilinearExtension, Polynomial,
-};
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::iter;
+
+use ark_ff::Field;
+use ark_poly::{univariate, DenseMultilinearExtension, MultilinearExtension, Polynomial};
 use ark_std::rand::Rng;
+use sha2::Digest;
+// Only used by the benchmarks.
+#[cfg(test)]
+use criterion as _;
+// Only used by the tests of the `serde` feature.
+#[cfg(all(test, not(feature = "serde")))]
+use serde_json as _;
 
 use sum_check_protocol::{
     Prover as SumCheckProver, Verifier as SumCheckVerifier,
     VerifierRoundResult as SumCheckVerifierRoundResult,
 };
 
-mod circuit_builder;
-mod circuit;
+#[cfg(feature = "std")]
+pub mod bristol;
+pub mod circuit;
+#[cfg(feature = "std")]
+pub mod circuit_builder;
+#[cfg(feature = "std")]
+pub mod circuits;
+pub mod commitment;
+#[cfg(feature = "std")]
+pub mod dag;
+#[cfg(feature = "std")]
+pub mod equiv;
+#[cfg(feature = "std")]
+pub mod expr;
+pub mod extension;
+#[cfg(feature = "std")]
+pub mod gadgets;
+pub mod goldilocks;
+#[cfg(feature = "std")]
+pub mod harness;
+#[cfg(feature = "std")]
+pub mod macros;
+#[cfg(feature = "memory-stats")]
+pub mod memory_stats;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod preprocessed;
+pub mod proof;
 mod round_polynomial;
-
-use round_polynomial::W;
-
-use circuit::{Circuit, CircuitEvaluation};
+pub mod simulation;
+#[cfg(test)]
+mod test_utils;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+#[cfg(feature = "std")]
+pub mod trace;
+pub mod transcript;
+pub mod uni_poly;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod witness;
+
+use round_polynomial::{LayerMleCache, W};
+
+use circuit::{Circuit, CircuitEvaluation, GateType, Wiring};
+use commitment::{Commitment, MerkleTree, WitnessCommitment};
+use extension::ExtensionOf;
+#[cfg(feature = "metrics")]
+use metrics::ProverStats;
+use proof::WitnessOpening;
+use uni_poly::UniPoly;
 
 /// GKR protocol error type.
 #[derive(Debug, thiserror::Error)]
@@ -30,28 +88,105 @@ pub enum Error {
     /// Wrong state.
     #[error("Verifier is in the wrong state.")]
     WrongVerifierState,
+
+    /// A round of the Sum-Check protocol is rejected.
+    #[error("sum-check round rejected: {0}")]
+    SumCheck(#[from] sum_check_protocol::Error),
+
+    /// A round polynomial is of a degree higher than
+    /// [`Circuit::layer_degree`].
+    #[error("a round polynomial of layer {layer} has degree {degree} over {max}")]
+    DegreeTooHigh {
+        /// The layer of the Sum-Check protocol.
+        layer: usize,
+        /// The degree of the round polynomial.
+        degree: usize,
+        /// The largest degree allowed.
+        max: usize,
+    },
+
+    /// The claimed sum $c_1$ about a layer is not the value the claim
+    /// about it reduces to.
+    #[error("the sum about layer {layer} is not the claim about it")]
+    ClaimMismatch {
+        /// The layer of the Sum-Check protocol.
+        layer: usize,
+    },
+
+    /// A claim is about an output the circuit does not have.
+    #[error("the circuit has no output {index}")]
+    NoSuchOutput {
+        /// The index of the output.
+        index: usize,
+    },
+
+    /// $g_j(0) + g_j(1)$ is not the value of the previous round.
+    #[error("layer {layer}, round {round}: the sum is not the value of the previous round")]
+    SumMismatch {
+        /// The layer of the Sum-Check protocol.
+        layer: usize,
+        /// The round of the Sum-Check protocol.
+        round: usize,
+    },
+
+    /// The restriction $q$ of $\tilde{W}_{i+1}$ to a line is of a
+    /// degree higher than the number of variables of $\tilde{W}_{i+1}$.
+    #[error("layer {layer}: the restriction to a line has degree {degree} over {max}")]
+    LineDegreeTooHigh {
+        /// The layer of the Sum-Check protocol.
+        layer: usize,
+        /// The degree of $q$.
+        degree: usize,
+        /// The largest degree allowed.
+        max: usize,
+    },
+
+    /// The final Sum-Check claim about a layer does not hold.
+    #[error("the claim about layer {layer} does not hold")]
+    LayerCheckFailed {
+        /// The layer whose claim fails.
+        layer: usize,
+    },
+
+    /// The circuit to prove does not build, see
+    /// [`proof::prove_source`].
+    #[cfg(feature = "std")]
+    #[error("the circuit does not build: {0}")]
+    Build(#[from] Box<circuit_builder::BuildError>),
 }
 
 /// GKR protocol result type.
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 /// The state of the Verifier.
-pub struct Verifier<F: FftField> {
+pub struct Verifier<F: Field> {
     /// $r_0, r_1,..., r_n$.
     r: Vec<Vec<F>>,
 
     /// $m$.
     m: Vec<F>,
 
+    /// The weights of the outputs in $m_0$ when it combines claims
+    /// about single outputs, $r_0$ is then left empty.
+    output_weights: Option<Vec<F>>,
+
     /// Circuit
     circuit: Circuit,
 
+    /// Wiring predicates of the layers, a single one for
+    /// uniform circuits.
+    wirings: Vec<Wiring>,
+
     /// State of the verifier.
     state: VerifierState<F>,
+
+    /// The root of the commitment to the witness, see
+    /// [`Verifier::receive_witness_root`].
+    witness_root: Option<Vec<u8>>,
 }
 
 /// The inner state of the [`Verifier`].
-enum VerifierState<F: FftField> {
+enum VerifierState<F: Field> {
     Empty,
     RunningSumCheck {
         /// $b$ and $c$.
@@ -60,15 +195,18 @@ enum VerifierState<F: FftField> {
         /// Verifier.
         verifier: Box<SumCheckVerifier<F, W<F>>>,
 
-        /// $add_i$
-        add_i: DenseMultilinearExtension<F>,
+        /// The wiring predicates of the gate types present in the layer.
+        predicates: Vec<(GateType, DenseMultilinearExtension<F>)>,
+
+        /// The largest degree of the round polynomials.
+        max_degree: usize,
 
-        /// $mul_i$
-        mul_i: DenseMultilinearExtension<F>,
+        /// $c_1$ before the first round, $g_{j-1}(r_{j-1})$ after.
+        expected: F,
     },
 }
 
-impl<F: FftField> Verifier<F> {
+impl<F: Field> Verifier<F> {
     /// Create a new `Verifier` with the claim of the `Prover`.
     ///
     /// At the start of the protocol picks a random
@@ -79,17 +217,74 @@ impl<F: FftField> Verifier<F> {
     /// that
     /// $m_0 = \tilde{W}_0(r_0)$
     pub fn new(circuit: Circuit) -> Self {
+        let wirings = match circuit.is_uniform() {
+            true => vec![circuit.layer_wiring(0)],
+            false => (0..circuit.num_layers())
+                .map(|i| circuit.layer_wiring(i))
+                .collect(),
+        };
+
         Self {
             r: vec![],
             m: vec![],
+            output_weights: None,
             circuit,
+            wirings,
             state: VerifierState::Empty,
+            witness_root: None,
+        }
+    }
+
+    /// Receive the root of the [`WitnessCommitment`] of the [`Prover`]
+    /// to the private inputs, which [`Verifier::check_public_input`]
+    /// opens the witness against.
+    ///
+    /// The root has to come before the protocol begins, so that the
+    /// witness is fixed before any challenge.
+    pub fn receive_witness_root(&mut self, root: Vec<u8>) -> Result<()> {
+        if !self.r.is_empty() || !matches!(self.state, VerifierState::Empty) {
+            return Err(Error::WrongVerifierState);
         }
+
+        self.witness_root = Some(root);
+        Ok(())
+    }
+
+    /// Verify a non-interactive `proof` of the evaluations of several
+    /// circuits in one `transcript`, see [`proof::verify_many`].
+    pub fn verify_many(
+        instances: &[(Circuit, Vec<F>)],
+        proof: &proof::MultiProof<F>,
+        transcript: &mut transcript::Transcript,
+    ) -> core::result::Result<(), proof::MultiVerifyError<F>> {
+        proof::verify_many(instances, proof, transcript)
+    }
+
+    /// Verify a non-interactive `proof` of the evaluation of a circuit
+    /// on `input`, reading only its `preprocessed` form rather than
+    /// the circuit itself, see [`proof::verify_preprocessed`].
+    pub fn verify_preprocessed(
+        preprocessed: &preprocessed::PreprocessedCircuit,
+        input: &[F],
+        proof: &proof::GkrProof<F>,
+    ) -> core::result::Result<(), proof::VerifyError<F>> {
+        proof::verify_preprocessed(preprocessed, input, proof)
     }
 
     fn start_round(&mut self, c_1: F, round: usize, num_vars: usize) -> Result<VerifierMessage<F>> {
-        let add_i = self.circuit.add_i_ext(self.r.last().unwrap(), round);
-        let mul_i = self.circuit.mul_i_ext(self.r.last().unwrap(), round);
+        if self.m.get(round) != Some(&c_1) {
+            return Err(Error::ClaimMismatch { layer: round });
+        }
+
+        let wiring = &self.wirings[round.min(self.wirings.len() - 1)];
+        let r_i = self.r.last().unwrap();
+        let predicates = gate_types(wiring)
+            .into_iter()
+            .map(|ttype| match (round, &self.output_weights) {
+                (0, Some(weights)) => (ttype, wiring.ext_weighted(ttype, weights)),
+                _ => (ttype, wiring.ext(ttype, r_i)),
+            })
+            .collect();
         let mut verifier = SumCheckVerifier::new(num_vars, None);
         verifier.set_c_1(c_1);
 
@@ -98,8 +293,9 @@ impl<F: FftField> Verifier<F> {
         self.state = VerifierState::RunningSumCheck {
             bc,
             verifier: Box::new(verifier),
-            add_i,
-            mul_i,
+            predicates,
+            max_degree: self.circuit.layer_degree(round),
+            expected: c_1,
         };
 
         Ok(VerifierMessage::RoundStarted(round))
@@ -124,10 +320,19 @@ impl<F: FftField> Verifier<F> {
         message: univariate::SparsePolynomial<F>,
         rng: &mut R,
     ) -> Result<VerifierMessage<F>> {
-        if let VerifierState::RunningSumCheck { bc, verifier, .. } = &mut self.state {
-            let res = verifier.round(message, rng).unwrap();
+        if let VerifierState::RunningSumCheck {
+            bc,
+            verifier,
+            max_degree,
+            expected,
+            ..
+        } = &mut self.state
+        {
+            check_degree(self.r.len() - 1, &message, *max_degree)?;
+            let res = verifier.round(message.clone(), rng)?;
 
             if let SumCheckVerifierRoundResult::JthRound(point) = res {
+                *expected = message.evaluate(&point);
                 bc.push(point);
             }
 
@@ -144,19 +349,44 @@ impl<F: FftField> Verifier<F> {
         rng: &mut R,
     ) -> Result<VerifierMessage<F>> {
         if let VerifierState::RunningSumCheck {
-            bc, add_i, mul_i, ..
+            bc,
+            predicates,
+            max_degree,
+            expected,
+            ..
         } = &self.state
         {
-            /*
-             * TODO: check q degree
-             */
+            let layer = self.r.len() - 1;
+            check_degree(layer, &p, *max_degree)?;
+            if p.evaluate(&F::zero()) + p.evaluate(&F::one()) != *expected {
+                return Err(Error::SumMismatch {
+                    layer,
+                    round: bc.len() - 1,
+                });
+            }
+
+            let max = self.circuit.num_vars_at(layer + 1).unwrap();
+            if q.degree() > max {
+                return Err(Error::LineDegreeTooHigh {
+                    layer,
+                    degree: q.degree(),
+                    max,
+                });
+            }
+
             let q_0 = q.evaluate(&F::zero());
             let q_1 = q.evaluate(&F::one());
 
-            let eval =
-                add_i.evaluate(bc).unwrap() * (q_0 + q_1) + mul_i.evaluate(bc).unwrap() * q_0 * q_1;
+            let eval: F = predicates
+                .iter()
+                .map(|(ttype, predicate)| {
+                    predicate.evaluate(bc).unwrap() * ttype.evaluate(q_0, q_1)
+                })
+                .sum();
 
-            assert_eq!(eval, p.evaluate(bc.last().unwrap()));
+            if eval != p.evaluate(bc.last().unwrap()) {
+                return Err(Error::LayerCheckFailed { layer });
+            }
 
             let r = F::rand(rng);
             let (b, c) = bc.split_at(bc.len() / 2);
@@ -191,10 +421,7 @@ impl<F: FftField> Verifier<F> {
             ProverMessage::FinalRoundMessage { p, q } => self.final_round_message(p, q, rng),
             ProverMessage::Begin { circuit_outputs } => {
                 let num_output_vars = self.circuit.num_vars_at(0).unwrap();
-                let d = DenseMultilinearExtension::from_evaluations_slice(
-                    num_output_vars,
-                    &circuit_outputs,
-                );
+                let d = padded_mle(num_output_vars, &circuit_outputs);
 
                 let r_zero: Vec<_> = (0..num_output_vars).map(|_| F::rand(rng)).collect();
 
@@ -202,20 +429,75 @@ impl<F: FftField> Verifier<F> {
 
                 self.r = vec![r_zero.clone()];
                 self.m = vec![m_zero];
+                self.output_weights = None;
 
                 Ok(VerifierMessage::R { r: r_zero })
             }
+            ProverMessage::BeginClaims { claims } => {
+                let num_outputs = self.circuit.num_outputs();
+                if let Some((index, _)) = claims.iter().find(|(index, _)| *index >= num_outputs) {
+                    return Err(Error::NoSuchOutput { index: *index });
+                }
+
+                let coefficients: Vec<_> = claims.iter().map(|_| F::rand(rng)).collect();
+                let m_zero = iter::zip(&claims, &coefficients)
+                    .map(|((_, value), coefficient)| *coefficient * value)
+                    .sum();
+
+                self.r = vec![vec![]];
+                self.m = vec![m_zero];
+                self.output_weights = Some(output_weights(
+                    self.circuit.num_vars_at(0).unwrap(),
+                    &claims,
+                    &coefficients,
+                ));
+
+                Ok(VerifierMessage::Coefficients { coefficients })
+            }
         }
     }
 
     /// Perform the final check of the input.
+    ///
+    /// Inputs of the wrong arity never pass.
     pub fn check_input(&self, input: &[F]) -> bool {
-        let w = DenseMultilinearExtension::from_evaluations_slice(
-            (f64::from(input.len() as u32)).log2() as usize,
-            input,
-        );
+        match self.circuit.input_mle(input) {
+            Ok(w) => &w.evaluate(self.r.last().unwrap()).unwrap() == self.m.last().unwrap(),
+            Err(_) => false,
+        }
+    }
+
+    /// Perform the final check of the input given only its public
+    /// part and the constants, the witness being opened against the
+    /// root of [`Verifier::receive_witness_root`], see
+    /// [`Prover::witness_opening`].
+    ///
+    /// Never passes without the root, for inputs of the wrong arity or
+    /// if a value of the witness is not the one committed to.
+    pub fn check_public_input<D: Digest>(
+        &self,
+        public: &[F],
+        constants: &[F],
+        opening: &WitnessOpening<F>,
+    ) -> bool {
+        let Some(root) = &self.witness_root else {
+            return false;
+        };
+        if public.len() != self.circuit.num_public_inputs()
+            || opening.values.len() != self.circuit.num_witnesses()
+            || constants.len() != self.circuit.num_constants()
+        {
+            return false;
+        }
 
-        &w.evaluate(self.r.last().unwrap()).unwrap() == self.m.last().unwrap()
+        let authentic = opening.values.iter().enumerate().all(|(index, value)| {
+            opening
+                .paths
+                .get(index)
+                .is_some_and(|path| WitnessCommitment::<D>::verify_path(root, index, value, path))
+        });
+
+        authentic && self.check_input(&[public, &opening.values, constants].concat())
     }
 }
 
@@ -239,6 +521,13 @@ pub enum VerifierMessage<F: Field> {
         /// $r_i$
         r: Vec<F>,
     },
+
+    /// Sends out the coefficients of the claims about single outputs
+    /// in $m_0$, in the order of the claims.
+    Coefficients {
+        /// The coefficients.
+        coefficients: Vec<F>,
+    },
 }
 
 /// Messages emitted by the [`Prover`].
@@ -249,6 +538,12 @@ pub enum ProverMessage<F: Field> {
         /// Claimed outputs
         circuit_outputs: Vec<F>,
     },
+    /// [`Prover`] begins the protocol by claims about single outputs,
+    /// see [`Prover::with_output_claims`].
+    BeginClaims {
+        /// The indices of the outputs along with their claimed values.
+        claims: Vec<(usize, F)>,
+    },
     /// A step of the current sum-check protocol.
     SumCheckProverMessage {
         /// A polynomial sent at each step of Sum-Check.
@@ -276,12 +571,52 @@ pub enum ProverMessage<F: Field> {
     },
 }
 
+/// Fail if the round polynomial `p` of `layer` is of a degree
+/// higher than `max`.
+fn check_degree<F: Field>(
+    layer: usize,
+    p: &univariate::SparsePolynomial<F>,
+    max: usize,
+) -> Result<()> {
+    match p.degree() {
+        degree if degree > max => Err(Error::DegreeTooHigh { layer, degree, max }),
+        _ => Ok(()),
+    }
+}
+
+/// The gate types whose wiring predicates do not vanish on the layer
+/// of `wiring`.
+fn gate_types(wiring: &Wiring) -> Vec<GateType> {
+    [GateType::Add, GateType::Mul, GateType::Sub, GateType::Relay]
+        .into_iter()
+        .filter(|ttype| wiring.has(*ttype))
+        .collect()
+}
+
+/// The weights of the outputs, over `num_vars` variables, in the
+/// combination of `claims` by `coefficients`.
+fn output_weights<F: Field>(num_vars: usize, claims: &[(usize, F)], coefficients: &[F]) -> Vec<F> {
+    let mut weights = vec![F::zero(); 1 << num_vars];
+    for ((index, _), coefficient) in iter::zip(claims, coefficients) {
+        weights[*index] += coefficient;
+    }
+
+    weights
+}
+
+/// The multilinear extension of `evals` padded with zeroes
+/// up to `1 << num_vars` evaluations.
+fn padded_mle<F: Field>(num_vars: usize, evals: &[F]) -> DenseMultilinearExtension<F> {
+    let mut evals = evals.to_vec();
+    evals.resize(1 << num_vars, F::zero());
+
+    DenseMultilinearExtension::from_evaluations_vec(num_vars, evals)
+}
+
 /// Compute a line
-pub fn line<F: Field>(b: &[F], c: &[F]) -> Vec<univariate::SparsePolynomial<F>> {
+pub fn line<F: Field>(b: &[F], c: &[F]) -> Vec<UniPoly<F>> {
     iter::zip(b, c)
-        .map(|(b, c)| {
-            univariate::SparsePolynomial::from_coefficients_slice(&[(0, *b), (1, *c - b)])
-        })
+        .map(|(b, c)| UniPoly::from_coefficients(vec![*b, *c - b]))
         .collect()
 }
 
@@ -295,35 +630,47 @@ pub fn restrict_poly<F: Field, M: MultilinearExtension<F>>(
     c: &[F],
     mle: &M,
 ) -> univariate::SparsePolynomial<F> {
-    let k: Vec<_> = iter::zip(b, c).map(|(b, c)| *c - b).collect();
+    let line = line(b, c);
+    let one = UniPoly::from_coefficients(vec![F::one()]);
+    let complements: Vec<_> = line.iter().map(|l| &one + &l.scale(-F::one())).collect();
 
     let evaluations = mle.to_evaluations();
     let num_vars = mle.num_vars();
 
-    let mut res = univariate::SparsePolynomial::zero();
+    let mut res = UniPoly::zero();
 
     for (i, evaluation) in evaluations.iter().enumerate() {
-        let mut p = univariate::SparsePolynomial::from_coefficients_vec(vec![(0, *evaluation)]);
+        let mut p = UniPoly::from_coefficients(vec![*evaluation]);
         for bit in 0..num_vars {
-            let mut b =
-                univariate::SparsePolynomial::from_coefficients_vec(vec![(0, b[bit]), (1, k[bit])]);
-
-            if i & (1 << bit) == 0 {
-                b = (&univariate::DensePolynomial::from_coefficients_vec(vec![F::one()]) - &b)
-                    .into();
-            }
+            let l = match i & (1 << bit) {
+                0 => &complements[bit],
+                _ => &line[bit],
+            };
 
-            p = p.mul(&b);
+            p = &p * l;
         }
 
         res += &p;
     }
 
-    res
+    res.into()
+}
+
+/// The share of the hypercube of `num_vars` variables summed over in
+/// the first `rounds` rounds of the Sum-Check protocol, in percent.
+///
+/// Round $j$ sums over $2^{n - j - 1}$ points, so the first rounds
+/// take most of the time.
+#[cfg(feature = "tracing")]
+fn hypercube_percent(num_vars: usize, rounds: usize) -> u64 {
+    let total = (1u128 << num_vars) - 1;
+    let done = total + 1 - (1u128 << (num_vars - rounds.min(num_vars)));
+
+    (done * 100 / total) as u64
 }
 
 /// The state of the Prover.
-pub struct Prover<F: FftField> {
+pub struct Prover<F: Field> {
     /// Current round of the protocol.
     i: usize,
 
@@ -341,13 +688,40 @@ pub struct Prover<F: FftField> {
 
     /// Random points collected through a single Sum-Check protocol run.
     r: Vec<F>,
+
+    /// The claims about single outputs to begin with, if any.
+    output_claims: Option<Vec<(usize, F)>>,
+
+    /// Timings collected so far.
+    #[cfg(feature = "metrics")]
+    stats: ProverStats,
 }
 
-impl<F: FftField> Prover<F> {
+impl<F: Field> Prover<F> {
     /// Create a new `Prover` state from a circuit and an evaluation.
     pub fn new(circuit: Circuit, input: &[F]) -> Self {
         let evaluation = circuit.evaluate(input);
+        Self::with_evaluation(circuit, evaluation)
+    }
+
+    /// Create a new `Prover` state evaluating the circuit over the
+    /// base field `B` and running the protocol over its extension `F`,
+    /// see [`extension`].
+    pub fn from_base_input<B: Field>(circuit: Circuit, input: &[B]) -> Self
+    where
+        F: ExtensionOf<B>,
+    {
+        let layers = circuit
+            .evaluate(input)
+            .layers
+            .into_iter()
+            .map(|layer| layer.into_iter().map(F::from_base).collect())
+            .collect();
+
+        Self::with_evaluation(circuit, CircuitEvaluation { layers })
+    }
 
+    fn with_evaluation(circuit: Circuit, evaluation: CircuitEvaluation<F>) -> Self {
         Self {
             i: 0,
             circuit,
@@ -355,16 +729,81 @@ impl<F: FftField> Prover<F> {
             prover: None,
             w: Default::default(),
             r: vec![],
+            output_claims: None,
+            #[cfg(feature = "metrics")]
+            stats: Default::default(),
+        }
+    }
+
+    /// Create a new `Prover` state from a circuit and the public and
+    /// the private part of its input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of public inputs does not match the
+    /// circuit, or the number of inputs overall.
+    pub fn with_public_input(circuit: Circuit, public: &[F], private: &[F]) -> Self {
+        assert_eq!(public.len(), circuit.num_public_inputs());
+        Self::new(circuit, &[public, private].concat())
+    }
+
+    /// Create a new `Prover` state claiming the values of single
+    /// outputs of the circuit rather than all of them.
+    ///
+    /// The first Sum-Check protocol is about a random combination of
+    /// the claims, see [`Prover::start_claims_round`].
+    pub fn with_output_claims(circuit: Circuit, input: &[F], claims: Vec<(usize, F)>) -> Self {
+        Self {
+            output_claims: Some(claims),
+            ..Self::new(circuit, input)
+        }
+    }
+
+    /// Prove the evaluations of several circuits on their inputs in a
+    /// single `transcript`, see [`proof::prove_many`].
+    pub fn prove_many(
+        instances: &[(Circuit, Vec<F>)],
+        transcript: &mut transcript::Transcript,
+    ) -> Result<proof::MultiProof<F>> {
+        proof::prove_many(instances, transcript)
+    }
+
+    /// Open the witness, the private inputs besides the constants,
+    /// committed to by `tree`, for [`Verifier::check_public_input`].
+    pub fn witness_opening<D: Digest>(&self, tree: &MerkleTree<D>) -> WitnessOpening<F> {
+        let input = self.evaluation.layers.last().unwrap();
+        let witness = &input[self.circuit.num_public_inputs()..][..self.circuit.num_witnesses()];
+
+        WitnessOpening {
+            values: witness.to_vec(),
+            paths: (0..witness.len()).map(|index| tree.path(index)).collect(),
         }
     }
 
+    /// Commitments to the values of every layer below the outputs, the
+    /// input layer last, in `scheme`.
+    pub fn commit_layers<C: Commitment<F>>(&self, scheme: &C) -> Vec<C::Output> {
+        self.evaluation.layers[1..]
+            .iter()
+            .map(|layer| scheme.commit(layer))
+            .collect()
+    }
+
     /// At the start of the protocol $P$ sends a function
     /// $D: \lbrace 0, 1 \rbrace ^{k_0} \rightarrow \mathbb{F}$
     /// claimed to equal $W_0$ (the function mapping output gate
     /// labels to output values).
+    ///
+    /// A prover created by [`Prover::with_output_claims`] sends its
+    /// claims instead.
     pub fn start_protocol(&self) -> ProverMessage<F> {
-        ProverMessage::Begin {
-            circuit_outputs: self.evaluation.layers.first().unwrap().clone(),
+        match &self.output_claims {
+            Some(claims) => ProverMessage::BeginClaims {
+                claims: claims.clone(),
+            },
+            None => ProverMessage::Begin {
+                circuit_outputs: self.evaluation.layers.first().unwrap().clone(),
+            },
         }
     }
 
@@ -373,54 +812,64 @@ impl<F: FftField> Prover<F> {
     /// At round $i$ a Sum-Check prover for polynomial
     /// $f^{(i)}_{r_i}(b, c)$.
     pub fn start_round(&mut self, i: usize, r_i: &[F]) -> ProverMessage<F> {
-        let num_vars_current = self.circuit.num_vars_at(i).unwrap();
+        self.start_round_with(i, |wiring, ttype| wiring.ext(ttype, r_i))
+    }
 
-        let num_vars_next = self.circuit.num_vars_at(i + 1).unwrap();
+    /// Create a Sum-Check prover for round $0$ about the combination
+    /// of the claims about single outputs by `coefficients`, see
+    /// [`VerifierMessage::Coefficients`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the prover was not created with claims.
+    pub fn start_claims_round(&mut self, coefficients: &[F]) -> ProverMessage<F> {
+        let claims = self
+            .output_claims
+            .as_ref()
+            .expect("no claims about outputs");
+        let num_vars = self.circuit.num_vars_at(0).unwrap();
+        let weights = output_weights(num_vars, claims, coefficients);
+
+        self.start_round_with(0, |wiring, ttype| wiring.ext_weighted(ttype, &weights))
+    }
 
-        let w_b = DenseMultilinearExtension::from_evaluations_slice(
-            num_vars_next,
-            &self.evaluation.layers[i + 1],
-        );
+    /// Create a Sum-Check prover for round $i$ with the wiring
+    /// predicates given by `predicate`.
+    fn start_round_with(
+        &mut self,
+        i: usize,
+        predicate: impl Fn(&Wiring, GateType) -> DenseMultilinearExtension<F>,
+    ) -> ProverMessage<F> {
+        #[cfg(feature = "metrics")]
+        let started = std::time::Instant::now();
+
+        let num_vars_next = self.circuit.num_vars_at(i + 1).unwrap();
 
-        self.w = w_b.clone();
+        // The tables of $\tilde{W}_{i+1}(b)$ and $\tilde{W}_{i+1}(c)$
+        // are folded in place through the rounds, `self.w` keeps the
+        // whole of it for the final restriction to a line.
+        let w_b = LayerMleCache::new(num_vars_next, &self.evaluation.layers[i + 1]);
+        self.w = w_b.to_mle();
         let w_c = w_b.clone();
-        let mut add_i = vec![];
-        let mut mult_i = vec![];
-
-        for c in 0..2usize.pow(num_vars_next as u32) {
-            for b in 0..2usize.pow(num_vars_next as u32) {
-                for a in 0..2usize.pow(num_vars_current as u32) {
-                    add_i.push(match self.circuit.add_i(i, a, b, c) {
-                        true => F::one(),
-                        false => F::zero(),
-                    });
-
-                    mult_i.push(match self.circuit.mul_i(i, a, b, c) {
-                        true => F::one(),
-                        false => F::zero(),
-                    });
-                }
-            }
-        }
 
-        let add_i = DenseMultilinearExtension::from_evaluations_vec(
-            num_vars_current + num_vars_next * 2,
-            add_i,
-        );
-        let mult_i = DenseMultilinearExtension::from_evaluations_vec(
-            num_vars_current + num_vars_next * 2,
-            mult_i,
-        );
+        #[cfg(feature = "tracing")]
+        let wiring_span = tracing::debug_span!("gkr.wiring", layer = i).entered();
+
+        let wiring = self.circuit.layer_wiring(i);
+        let predicates: Vec<_> = gate_types(&wiring)
+            .into_iter()
+            .map(|ttype| (ttype, predicate(&wiring, ttype)))
+            .collect();
 
-        let add_i = add_i.fix_variables(r_i);
-        let mult_i = mult_i.fix_variables(r_i);
+        #[cfg(feature = "tracing")]
+        drop(wiring_span);
 
-        let num_vars = add_i.num_vars();
+        #[cfg(feature = "metrics")]
+        let predicates_built = std::time::Instant::now();
 
-        assert_eq!(add_i.num_vars(), mult_i.num_vars());
-        assert_eq!(add_i.num_vars(), 2 * w_b.num_vars());
+        let num_vars = 2 * w_b.num_vars();
 
-        let w = W::new(add_i, mult_i, w_b, w_c);
+        let w = W::new(predicates, w_b, w_c, self.circuit.layer_degree(i));
         self.i = i;
 
         let prover = SumCheckProver::new(w);
@@ -428,6 +877,13 @@ impl<F: FftField> Prover<F> {
         self.prover = Some(prover);
         self.r = vec![];
 
+        #[cfg(feature = "metrics")]
+        {
+            let layer = self.stats.layer_mut(i);
+            layer.predicates = predicates_built - started;
+            layer.sumcheck = predicates_built.elapsed();
+        }
+
         ProverMessage::StartSumCheck {
             c_1,
             round: i,
@@ -437,7 +893,10 @@ impl<F: FftField> Prover<F> {
 
     /// Perform a step of the Sum-Check protocol and provide a message for the [`Verifier`].
     pub fn round_msg(&mut self, j: usize) -> ProverMessage<F> {
-        if j == 2 * self.circuit.num_vars_at(self.i + 1).unwrap() - 1 {
+        #[cfg(feature = "metrics")]
+        let started = std::time::Instant::now();
+
+        let msg = if j == 2 * self.circuit.num_vars_at(self.i + 1).unwrap() - 1 {
             // The last round; do the polynomial restriction.
             let (b, c) = self.r.split_at(self.r.len() / 2);
 
@@ -452,7 +911,22 @@ impl<F: FftField> Prover<F> {
             ProverMessage::SumCheckProverMessage {
                 p: self.prover.as_mut().unwrap().round(point, j),
             }
+        };
+
+        #[cfg(feature = "metrics")]
+        {
+            self.stats.layer_mut(self.i).sumcheck += started.elapsed();
         }
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            target: "gkr.progress",
+            layer = self.i,
+            round = j,
+            percent = hypercube_percent(2 * self.circuit.num_vars_at(self.i + 1).unwrap(), j + 1),
+        );
+
+        msg
     }
 
     /// Receive a message from the [`Verifier`].
@@ -471,17 +945,30 @@ impl<F: FftField> Prover<F> {
     pub fn c_1(&self) -> F {
         self.prover.as_ref().unwrap().c_1()
     }
+
+    /// Timings collected so far through the run of the protocol.
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> &ProverStats {
+        &self.stats
+    }
 }
 
 #[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
-    use ark_ff::{Fp64, MontBackend, MontConfig, PrimeField};
-    use ark_poly::univariate::DensePolynomial;
+    use alloc::string::ToString;
+
+    use ark_ff::{Fp64, MontBackend, MontConfig, PrimeField, Zero};
+    use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
     use ark_std::test_rng;
     use circuit::circuit_from_book;
     use pretty_assertions::assert_eq;
+    #[cfg(feature = "std")]
+    use sha2::Sha256;
 
-    use crate::circuit::{CircuitLayer, Gate, GateType};
+    use crate::circuit::{CircuitLayer, Gate};
+    #[cfg(feature = "std")]
+    use crate::circuit_builder::{CircuitBuilder, InputSlot};
 
     use super::*;
 
@@ -503,6 +990,212 @@ mod tests {
         )
     }
 
+    #[test]
+    fn map_gate_types() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let circuit = three_layer_circuit();
+        let swap = |ttype| match ttype {
+            GateType::Add => GateType::Mul,
+            GateType::Mul => GateType::Add,
+            ttype => ttype,
+        };
+
+        let swapped = circuit.map_gate_types(swap);
+        assert_eq!(swapped.num_inputs(), circuit.num_inputs());
+        for (layer, swapped_layer) in circuit.layers().iter().zip(swapped.layers()) {
+            for (gate, swapped_gate) in layer.gates().iter().zip(swapped_layer.gates()) {
+                assert_eq!(swapped_gate.inputs(), gate.inputs());
+                assert_eq!(swapped_gate.ttype(), GateType::Mul);
+            }
+        }
+        assert_eq!(
+            swapped.evaluate(&[1, 2, 3, 4, 5, 6, 7, 8]).layers[0],
+            vec![24, 1680]
+        );
+        assert_eq!(swapped.map_gate_types(swap), circuit);
+
+        let input: Vec<_> = (1..=8).map(Fp389::from).collect();
+        assert!(run_protocol(&swapped, &input));
+    }
+
+    /// Run the whole protocol between an honest [`Prover`] and
+    /// a [`Verifier`] returning the verdict on the input.
+    pub(crate) fn run_protocol<F: Field>(circuit: &Circuit, input: &[F]) -> bool {
+        let (verifier, _) = run_rounds(circuit, Prover::new(circuit.clone(), input));
+        verifier.check_input(input)
+    }
+
+    /// Run all the rounds of the protocol with `prover` returning the
+    /// verifier about to check the input and the final point $r_d$.
+    fn run_rounds<F: Field>(circuit: &Circuit, mut prover: Prover<F>) -> (Verifier<F>, Vec<F>) {
+        run_rounds_with(Verifier::new(circuit.clone()), &mut prover)
+    }
+
+    /// [`run_rounds`] with a `verifier` of its own, keeping `prover`.
+    fn run_rounds_with<F: Field>(
+        mut verifier: Verifier<F>,
+        prover: &mut Prover<F>,
+    ) -> (Verifier<F>, Vec<F>) {
+        let rng = &mut test_rng();
+        let circuit = verifier.circuit.clone();
+
+        let verifier_message = verifier
+            .receive_prover_msg(prover.start_protocol(), rng)
+            .unwrap();
+
+        let (mut r_i, mut coefficients) = match verifier_message {
+            VerifierMessage::R { r } => (r, None),
+            VerifierMessage::Coefficients { coefficients } => (vec![], Some(coefficients)),
+            _ => panic!(),
+        };
+
+        for i in 0..circuit.num_layers() {
+            let prover_msg = match coefficients.take() {
+                Some(coefficients) => prover.start_claims_round(&coefficients),
+                None => prover.start_round(i, &r_i),
+            };
+            verifier.receive_prover_msg(prover_msg, rng).unwrap();
+
+            let num_vars = 2 * circuit.num_vars_at(i + 1).unwrap();
+
+            for j in 0..(num_vars - 1) {
+                let prover_msg = prover.round_msg(j);
+                let verifier_msg = verifier.receive_prover_msg(prover_msg, rng).unwrap();
+                prover.receive_verifier_msg(verifier_msg);
+            }
+
+            let last_rand = verifier.final_random_point(rng).unwrap();
+            prover.receive_verifier_msg(last_rand);
+
+            let prover_msg = prover.round_msg(num_vars - 1);
+            match verifier.receive_prover_msg(prover_msg, rng).unwrap() {
+                VerifierMessage::R { r } => r_i = r,
+                msg => panic!("{:?}", msg),
+            }
+        }
+
+        (verifier, r_i)
+    }
+
+    #[test]
+    fn final_round_checks() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let circuit = circuit_from_book();
+        let input: Vec<_> = [3u64, 2, 3, 1].into_iter().map(Fp389::from).collect();
+        let num_vars = 2 * circuit.num_vars_at(1).unwrap();
+
+        // The verifier about to receive the final round message of the
+        // first layer, and the message.
+        let final_round = || {
+            let rng = &mut test_rng();
+            let mut prover = Prover::new(circuit.clone(), &input);
+            let mut verifier = Verifier::new(circuit.clone());
+            let r = match verifier.receive_prover_msg(prover.start_protocol(), rng) {
+                Ok(VerifierMessage::R { r }) => r,
+                msg => panic!("{:?}", msg),
+            };
+            verifier
+                .receive_prover_msg(prover.start_round(0, &r), rng)
+                .unwrap();
+            for j in 0..(num_vars - 1) {
+                let verifier_msg = verifier
+                    .receive_prover_msg(prover.round_msg(j), rng)
+                    .unwrap();
+                prover.receive_verifier_msg(verifier_msg);
+            }
+            prover.receive_verifier_msg(verifier.final_random_point(rng).unwrap());
+
+            match prover.round_msg(num_vars - 1) {
+                ProverMessage::FinalRoundMessage { p, q } => (verifier, p, q),
+                msg => panic!("{:?}", msg),
+            }
+        };
+        let monomial = |degree: usize, coefficient: i64| {
+            univariate::SparsePolynomial::from_coefficients_vec(vec![(
+                degree,
+                Fp389::from(coefficient),
+            )])
+        };
+
+        let (mut verifier, p, q) = final_round();
+        assert!(matches!(
+            verifier.receive_prover_msg(ProverMessage::FinalRoundMessage { p, q }, &mut test_rng()),
+            Ok(VerifierMessage::R { .. })
+        ));
+
+        let (mut verifier, p, q) = final_round();
+        let p = &p + &monomial(0, 1);
+        assert!(matches!(
+            verifier.receive_prover_msg(ProverMessage::FinalRoundMessage { p, q }, &mut test_rng()),
+            Err(Error::SumMismatch { layer: 0, round }) if round == num_vars - 1
+        ));
+
+        // Adding $x^2 - x$ keeps the sum but not the value at the point.
+        let (mut verifier, p, q) = final_round();
+        let p = &(&p + &monomial(2, 1)) + &monomial(1, -1);
+        assert!(matches!(
+            verifier.receive_prover_msg(ProverMessage::FinalRoundMessage { p, q }, &mut test_rng()),
+            Err(Error::LayerCheckFailed { layer: 0 })
+        ));
+
+        let (mut verifier, p, q) = final_round();
+        let max = circuit.num_vars_at(1).unwrap();
+        let q = &q + &monomial(max + 1, 1);
+        assert!(matches!(
+            verifier.receive_prover_msg(ProverMessage::FinalRoundMessage { p, q }, &mut test_rng()),
+            Err(Error::LineDegreeTooHigh { layer: 0, degree, max: m }) if (degree, m) == (max + 1, max)
+        ));
+    }
+
+    #[test]
+    fn output_claims() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let circuit = circuit_from_book();
+        let input: Vec<_> = [3u64, 2, 3, 1].into_iter().map(Fp389::from).collect();
+        let claims = |second: u64| vec![(1, Fp389::from(second)), (0, Fp389::from(36u64))];
+
+        let prover = Prover::with_output_claims(circuit.clone(), &input, claims(6));
+        let (verifier, _) = run_rounds(&circuit, prover);
+        assert!(verifier.check_input(&input));
+
+        // The sum of the honest prover is not the claim.
+        let rng = &mut test_rng();
+        let mut prover = Prover::with_output_claims(circuit.clone(), &input, claims(7));
+        let mut verifier = Verifier::new(circuit.clone());
+        let coefficients = match verifier.receive_prover_msg(prover.start_protocol(), rng) {
+            Ok(VerifierMessage::Coefficients { coefficients }) => coefficients,
+            msg => panic!("{:?}", msg),
+        };
+        assert!(matches!(
+            verifier.receive_prover_msg(prover.start_claims_round(&coefficients), rng),
+            Err(Error::ClaimMismatch { layer: 0 })
+        ));
+
+        let prover = Prover::with_output_claims(circuit.clone(), &input, vec![(2, Fp389::zero())]);
+        assert!(matches!(
+            Verifier::new(circuit).receive_prover_msg(prover.start_protocol(), rng),
+            Err(Error::NoSuchOutput { index: 2 })
+        ));
+    }
+
     #[test]
     /// Test restrict poly
     fn test_restrict_poly() {
@@ -547,6 +1240,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cached_round_polynomials() {
+        use ark_std::UniformRand;
+        use sum_check_protocol::SumCheckPolynomial;
+
+        let rng = &mut test_rng();
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let circuit = circuit_from_book();
+        let input: Vec<_> = [3u32, 2, 3, 1].into_iter().map(Fp389::from).collect();
+        let evaluation = circuit.evaluate(&input);
+
+        for i in 0..circuit.layers().len() {
+            let num_vars_next = circuit.num_vars_at(i + 1).unwrap();
+            let r_i: Vec<_> = (0..circuit.num_vars_at(i).unwrap())
+                .map(|_| Fp389::rand(rng))
+                .collect();
+
+            let wiring = circuit.layer_wiring(i);
+            let predicates: Vec<_> = gate_types(&wiring)
+                .into_iter()
+                .map(|ttype| (ttype, wiring.ext(ttype, &r_i)))
+                .collect();
+            let w_b = LayerMleCache::new(num_vars_next, &evaluation.layers[i + 1]);
+            let original = W::new(predicates, w_b.clone(), w_b, circuit.layer_degree(i));
+
+            // Fold in place round after round against fixing all the
+            // points so far on the original polynomial.
+            let mut cached = original.clone();
+            let mut r = vec![];
+            for _ in 0..original.num_vars() {
+                let recomputed = original.fix_variables(&r);
+                assert_eq!(cached.to_univariate(), recomputed.to_univariate());
+                assert_eq!(cached.to_evaluations(), recomputed.to_evaluations());
+
+                let r_j = Fp389::rand(rng);
+                cached.fix_next(r_j);
+                r.push(r_j);
+            }
+            assert_eq!(
+                cached.to_evaluations(),
+                vec![original.evaluate(&r).unwrap()]
+            );
+        }
+    }
+
     #[test]
     fn protocol_test_from_book() {
         let rng = &mut test_rng();
@@ -623,6 +1367,45 @@ mod tests {
         assert!(verifier.check_input(&input));
     }
 
+    #[test]
+    fn verifier_errors() {
+        let rng = &mut test_rng();
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let circuit = circuit_from_book();
+        let input = |values: [u64; 4]| values.map(Fp389::from).to_vec();
+
+        let mut honest = Prover::new(circuit.clone(), &input([3, 2, 3, 1]));
+        let mut cheat = Prover::new(circuit.clone(), &input([3, 2, 3, 2]));
+        let mut verifier = Verifier::new(circuit.clone());
+
+        let err = verifier.final_random_point(rng).unwrap_err();
+        assert!(matches!(err, Error::WrongVerifierState));
+
+        let VerifierMessage::R { r } = verifier
+            .receive_prover_msg(honest.start_protocol(), rng)
+            .unwrap()
+        else {
+            panic!()
+        };
+        verifier
+            .receive_prover_msg(honest.start_round(0, &r), rng)
+            .unwrap();
+
+        // The cheat claims different outputs in the first round.
+        cheat.start_round(0, &r);
+        let err = verifier
+            .receive_prover_msg(cheat.round_msg(0), rng)
+            .unwrap_err();
+        assert!(matches!(err, Error::SumCheck(_)));
+        assert!(err.to_string().starts_with("sum-check round rejected"));
+    }
+
     #[test]
     fn three_layer_protocol_test() {
         let rng = &mut test_rng();
@@ -701,4 +1484,295 @@ mod tests {
 
         assert!(verifier.check_input(&input));
     }
+
+    #[test]
+    fn sub_gates_protocol_test() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let circuit = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Sub, [0, 1]),
+                    Gate::new(GateType::Mul, [2, 3]),
+                ]),
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Sub, [0, 1]),
+                    Gate::new(GateType::Add, [2, 3]),
+                    Gate::new(GateType::Sub, [3, 0]),
+                    Gate::new(GateType::Mul, [1, 2]),
+                ]),
+            ],
+            4,
+        );
+
+        let input: Vec<_> = [3u32, 7, 2, 5]
+            .into_iter()
+            .map(|i| Fp389::from_bigint(i.into()).unwrap())
+            .collect();
+
+        assert!(run_protocol(&circuit, &input));
+    }
+
+    #[test]
+    fn relay_protocol_test() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let circuit = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![Gate::new(GateType::Mul, [0, 1])]),
+                CircuitLayer::new(vec![Gate::new(GateType::Add, [0, 1]), Gate::relay(2)]),
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 1]),
+                    Gate::new(GateType::Sub, [2, 3]),
+                    Gate::relay(3),
+                ]),
+            ],
+            4,
+        );
+
+        let input: Vec<_> = [3u32, 2, 5, 1].into_iter().map(Fp389::from).collect();
+        let evaluation = circuit.evaluate(&input);
+        assert_eq!(
+            evaluation.layers[..3],
+            [
+                vec![Fp389::from(10)],
+                vec![Fp389::from(10), Fp389::from(1)],
+                vec![Fp389::from(6), Fp389::from(4), Fp389::from(1)],
+            ]
+        );
+        assert!(run_protocol(&circuit, &input));
+
+        let proof = proof::prove(&circuit, &input).unwrap();
+        proof::verify(&circuit, &input, &proof).unwrap();
+    }
+
+    #[test]
+    fn scaled_gates_protocol_test() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let scaled_circuit = |coeff| {
+            Circuit::new(
+                vec![
+                    CircuitLayer::new(vec![
+                        Gate::scaled(GateType::Add, [0, 1], [3, 5]),
+                        Gate::scaled(GateType::Mul, [1, 2], [2, 7]),
+                    ]),
+                    CircuitLayer::new(vec![
+                        Gate::scaled(GateType::Add, [0, 1], [coeff, 1]),
+                        Gate::scaled(GateType::Sub, [2, 3], [4, 4]),
+                        Gate::scaled(GateType::Relay, [3, 3], [6, 1]),
+                    ]),
+                ],
+                4,
+            )
+        };
+        let circuit = scaled_circuit(10);
+
+        let input: Vec<_> = [3u32, 7, 2, 5].into_iter().map(Fp389::from).collect();
+        // 10 * 3 + 7, 4 * (2 - 5) and 6 * 5 below.
+        let outputs = [3 * 37 - 5 * 12, 2 * 7 * (-12) * 30]
+            .map(|value: i64| Fp389::from(value.rem_euclid(389) as u64));
+        assert_eq!(circuit.evaluate(&input).layers[0], outputs);
+        assert!(run_protocol(&circuit, &input));
+
+        let proof = proof::prove(&circuit, &input).unwrap();
+        proof::verify(&circuit, &input, &proof).unwrap();
+
+        // The verifier holds a copy of the circuit with one
+        // coefficient tampered with.
+        let tampered = scaled_circuit(11);
+        assert!(proof::verify(&tampered, &input, &proof).is_err());
+    }
+
+    #[test]
+    fn uniform_protocol_test() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let layer = CircuitLayer::new(vec![
+            Gate::new(GateType::Mul, [0, 1]),
+            Gate::new(GateType::Add, [1, 0]),
+        ]);
+        let circuit = Circuit::new(vec![layer.clone(), layer.clone(), layer], 2);
+        assert!(circuit.is_uniform());
+
+        let input = [Fp389::from(3u32), Fp389::from(5u32)];
+
+        assert!(run_protocol(&circuit, &input));
+    }
+
+    #[test]
+    fn homogeneous_layers_protocol_test() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        // Squares of pairwise sums of squares.
+        let circuit = Circuit::new(
+            vec![
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Mul, [0, 0]),
+                    Gate::new(GateType::Mul, [1, 1]),
+                ]),
+                CircuitLayer::new(vec![
+                    Gate::new(GateType::Add, [0, 1]),
+                    Gate::new(GateType::Add, [2, 3]),
+                ]),
+                CircuitLayer::new((0..4).map(|i| Gate::new(GateType::Mul, [i, i])).collect()),
+            ],
+            4,
+        );
+        assert!(circuit
+            .layers()
+            .iter()
+            .all(|layer| layer.gate_type_uniform().is_some()));
+
+        let input: Vec<_> = [3u32, 5, 1, 2].into_iter().map(Fp389::from).collect();
+        assert!(run_protocol(&circuit, &input));
+
+        let proof = proof::prove(&circuit, &input).unwrap();
+        assert_eq!(
+            proof.outputs,
+            vec![Fp389::from(34u32 * 34), Fp389::from(5u32 * 5)]
+        );
+        proof::verify(&circuit, &input, &proof).unwrap();
+
+        let mut other = input.clone();
+        other[3] = Fp389::from(4u32);
+        assert!(proof::verify(&circuit, &other, &proof).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn public_input_protocol_test() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        // The witnesses `w` and `v` are checked to multiply to the
+        // public `x` and to add up to the public `y`.
+        let mut builder = CircuitBuilder::new();
+        let w = builder.apply_witness();
+        let x = builder.apply_public_input();
+        let v = builder.apply_witness();
+        let y = builder.apply_public_input();
+        let product = builder.append_mul_gate(w, v).unwrap();
+        let sum = builder.append_add_gate(w, v).unwrap();
+        let (x_1, y_1) = (builder.relay(x).unwrap(), builder.relay(y).unwrap());
+        builder.append_sub_gate(product, x_1).unwrap();
+        builder.append_sub_gate(sum, y_1).unwrap();
+
+        let circuit = builder.build_circuit().unwrap();
+        assert_eq!(circuit.num_public_inputs(), 2);
+        assert_eq!(
+            builder.input_mapping().unwrap().slots,
+            vec![
+                InputSlot::Public(x),
+                InputSlot::Public(y),
+                InputSlot::Witness(w),
+                InputSlot::Witness(v),
+                InputSlot::Const(0),
+            ]
+        );
+
+        let input = builder
+            .input_values(|cell| Fp389::from([3u32, 12, 4, 7][cell]))
+            .unwrap();
+        assert_eq!(circuit.outputs(&input).unwrap(), vec![Fp389::zero(); 2]);
+        let (public, private) = input.split_at(2);
+        let constants = &private[2..];
+
+        let (root, tree) = WitnessCommitment::<Sha256>::commit(&private[..2]);
+        let mut verifier = Verifier::new(circuit.clone());
+        verifier.receive_witness_root(root.clone()).unwrap();
+        let mut prover = Prover::with_public_input(circuit.clone(), public, private);
+        let opening = prover.witness_opening(&tree);
+        let (verifier, r) = run_rounds_with(verifier, &mut prover);
+        assert!(verifier.check_public_input::<Sha256>(public, constants, &opening));
+        assert!(verifier.check_input(&input));
+
+        let other = [Fp389::from(12u32), Fp389::from(8u32)];
+        assert!(!verifier.check_public_input::<Sha256>(&other, constants, &opening));
+        assert!(!verifier.check_public_input::<Sha256>(&public[..1], constants, &opening));
+
+        let mut tampered = opening.clone();
+        tampered.values[0] += Fp389::from(1u32);
+        assert!(!verifier.check_public_input::<Sha256>(public, constants, &tampered));
+
+        // The root comes before the protocol, or not at all.
+        let (mut late, _) = run_rounds(
+            &circuit,
+            Prover::with_public_input(circuit.clone(), public, private),
+        );
+        assert!(matches!(
+            late.receive_witness_root(root.clone()),
+            Err(Error::WrongVerifierState)
+        ));
+        assert!(!late.check_public_input::<Sha256>(public, constants, &opening));
+
+        // The zero outputs are wrong for the public `x = 13`, yet the
+        // prover runs the protocol on its witness for `x = 12` and
+        // forges a witness value to make up for the public part.
+        let wrong = [Fp389::from(13u32), Fp389::from(7u32)];
+        let eq = circuit::eq_evals(&r);
+        let mut forged = opening.clone();
+        forged.values[0] += eq[0] * (public[0] - wrong[0]) / eq[2];
+        let input = [&wrong[..], &forged.values, constants].concat();
+        assert!(verifier.check_input(&input));
+        assert!(!verifier.check_public_input::<Sha256>(&wrong, constants, &forged));
+        assert!(!verifier.check_public_input::<Sha256>(&wrong, constants, &opening));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn prover_stats_test() {
+        #[derive(MontConfig)]
+        #[modulus = "389"]
+        #[generator = "2"]
+        struct FrConfig;
+
+        type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+        let circuit = three_layer_circuit();
+
+        let input: Vec<_> = (0..8u32)
+            .map(|i| Fp389::from_bigint(i.into()).unwrap())
+            .collect();
+
+        let mut prover = Prover::new(circuit.clone(), &input);
+        let (verifier, _) = run_rounds_with(Verifier::new(circuit.clone()), &mut prover);
+        assert!(verifier.check_input(&input));
+
+        let stats = prover.stats();
+        assert_eq!(stats.layer_times.len(), circuit.num_layers());
+        assert!(stats
+            .layer_times
+            .iter()
+            .all(|layer| !layer.sumcheck.is_zero()));
+    }
 }
@@ -0,0 +1,252 @@
+//! The [`circuit!`](crate::circuit!) macro declaring circuits with
+//! symbolic names.
+
+use std::collections::HashMap;
+
+use crate::{
+    circuit::{Circuit, GateType},
+    circuit_builder::{BuildError, CircuitBuilder},
+};
+
+/// An error building a circuit with the [`circuit!`](crate::circuit!) macro.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum CircuitMacroError {
+    /// A name is bound more than once.
+    #[error("`{0}` is bound more than once")]
+    DuplicateName(&'static str),
+
+    /// A binding refers to a name that is not bound before it.
+    #[error("`{binding}` refers to undefined `{name}`")]
+    UndefinedName {
+        /// The offending binding.
+        binding: &'static str,
+        /// The undefined name.
+        name: &'static str,
+    },
+
+    /// The builder rejected the gate of a binding.
+    #[error("`{binding}` is rejected by the builder: {error}")]
+    Build {
+        /// The offending binding.
+        binding: &'static str,
+        /// The error of the builder.
+        error: BuildError,
+    },
+}
+
+/// The names bound so far by the [`circuit!`](crate::circuit!) macro.
+#[doc(hidden)]
+pub struct Bindings {
+    builder: CircuitBuilder,
+    names: HashMap<&'static str, usize>,
+}
+
+impl Bindings {
+    /// Run the bindings of a macro invocation and build the circuit.
+    pub fn build(
+        bind: impl FnOnce(&mut Self) -> Result<(), CircuitMacroError>,
+    ) -> Result<(Circuit, HashMap<&'static str, usize>), CircuitMacroError> {
+        let mut bindings = Self {
+            builder: CircuitBuilder::new(),
+            names: HashMap::new(),
+        };
+
+        bind(&mut bindings)?;
+
+        let circuit = bindings
+            .builder
+            .build_circuit()
+            .expect("bindings only put witnesses into the input layer");
+
+        Ok((circuit, bindings.names))
+    }
+
+    /// Bind `name` to a new witness.
+    pub fn witness(&mut self, name: &'static str) -> Result<(), CircuitMacroError> {
+        if self.names.contains_key(name) {
+            return Err(CircuitMacroError::DuplicateName(name));
+        }
+
+        let cell = self.builder.apply_witness();
+        self.names.insert(name, cell);
+        Ok(())
+    }
+
+    /// Bind `binding` to a gate on top of the cells bound to `left` and `right`.
+    pub fn gate(
+        &mut self,
+        binding: &'static str,
+        ttype: GateType,
+        left: &'static str,
+        right: &'static str,
+    ) -> Result<(), CircuitMacroError> {
+        if self.names.contains_key(binding) {
+            return Err(CircuitMacroError::DuplicateName(binding));
+        }
+
+        let lookup = |name| {
+            self.names
+                .get(name)
+                .copied()
+                .ok_or(CircuitMacroError::UndefinedName { binding, name })
+        };
+        let (l, r) = (lookup(left)?, lookup(right)?);
+
+        let cell = match ttype {
+            GateType::Add => self.builder.append_add_gate(l, r),
+            GateType::Mul => self.builder.append_mul_gate(l, r),
+            GateType::Sub => self.builder.append_sub_gate(l, r),
+            GateType::Relay => self.builder.relay(l),
+        }
+        .map_err(|error| CircuitMacroError::Build { binding, error })?;
+
+        self.names.insert(binding, cell);
+        Ok(())
+    }
+}
+
+/// Declare a circuit with named witnesses and gates.
+///
+/// Every gate binds a name to a binary operation (`+`, `*` or `-`)
+/// on two previously bound names. Evaluates to a
+/// `Result<(Circuit, HashMap<&str, usize>), CircuitMacroError>`
+/// holding the built circuit and the builder cell of every name.
+///
+/// ```
+/// use gkr_protocol::circuit;
+///
+/// let (circuit, names) = circuit! {
+///     inputs: [a, b, c];
+///     v0 = a * a;
+///     v1 = b * c;
+///     out = v0 + v1;
+/// }
+/// .unwrap();
+///
+/// assert_eq!(circuit.evaluate(&[2, 3, 4]).layers[0], vec![16]);
+/// assert_eq!(names["out"], 5);
+/// ```
+#[macro_export]
+macro_rules! circuit {
+    (
+        inputs: [$($input:ident),* $(,)?];
+        $($binding:ident = $left:ident $op:tt $right:ident;)*
+    ) => {
+        $crate::macros::Bindings::build(|bindings| {
+            $(bindings.witness(stringify!($input))?;)*
+            $(
+                bindings.gate(
+                    stringify!($binding),
+                    $crate::circuit!(@op $op),
+                    stringify!($left),
+                    stringify!($right),
+                )?;
+            )*
+            Ok(())
+        })
+    };
+    (@op +) => { $crate::circuit::GateType::Add };
+    (@op *) => { $crate::circuit::GateType::Mul };
+    (@op -) => { $crate::circuit::GateType::Sub };
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::CircuitMacroError;
+    use crate::circuit_builder::{BuildError, CellGateType, CellRef};
+
+    #[test]
+    fn duplicate_names() {
+        let err = circuit! {
+            inputs: [a, b, a];
+        }
+        .unwrap_err();
+        assert_eq!(err, CircuitMacroError::DuplicateName("a"));
+
+        let err = circuit! {
+            inputs: [a, b];
+            v = a * b;
+            v = a + b;
+        }
+        .unwrap_err();
+        assert_eq!(err, CircuitMacroError::DuplicateName("v"));
+    }
+
+    #[test]
+    fn undefined_references() {
+        let err = circuit! {
+            inputs: [a, b];
+            v0 = a * c;
+        }
+        .unwrap_err();
+        assert_eq!(
+            err,
+            CircuitMacroError::UndefinedName {
+                binding: "v0",
+                name: "c"
+            }
+        );
+
+        // Names are only visible to the bindings following them.
+        let err = circuit! {
+            inputs: [a, b];
+            v0 = a * v1;
+            v1 = a + b;
+        }
+        .unwrap_err();
+        assert_eq!(
+            err,
+            CircuitMacroError::UndefinedName {
+                binding: "v0",
+                name: "v1"
+            }
+        );
+    }
+
+    #[test]
+    fn build_errors() {
+        let err = circuit! {
+            inputs: [a, b];
+            v0 = a * b;
+            v1 = v0 - a;
+        }
+        .unwrap_err();
+        assert_eq!(
+            err,
+            CircuitMacroError::Build {
+                binding: "v1",
+                error: BuildError::IllegalGate {
+                    left: CellRef {
+                        index: 2,
+                        label: None
+                    },
+                    right: CellRef {
+                        index: 0,
+                        label: None
+                    },
+                    left_layer: 1,
+                    right_layer: 0,
+                }
+            }
+        );
+
+        let err = circuit! {
+            inputs: [a, b];
+            v0 = a + b;
+            v1 = a + b;
+        }
+        .unwrap_err();
+        assert_eq!(
+            err,
+            CircuitMacroError::Build {
+                binding: "v1",
+                error: BuildError::DuplicateGate {
+                    gate: CellGateType::Add(0, 1),
+                    existing: 2
+                }
+            }
+        );
+    }
+}
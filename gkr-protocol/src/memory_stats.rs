@@ -0,0 +1,193 @@
+//! Memory instrumentation by a counting global allocator.
+//!
+//! Only compiled with the `memory-stats` feature enabled. The library
+//! does not install [`CountingAllocator`] itself, the program measuring
+//! does, as its global allocator:
+//!
+//! ```
+//! use gkr_protocol::memory_stats::{measure, CountingAllocator};
+//!
+//! #[global_allocator]
+//! static GLOBAL: CountingAllocator = CountingAllocator;
+//!
+//! let (_, report) = measure(|| vec![0u8; 100]);
+//! assert_eq!(report.peak_bytes, 100);
+//! ```
+//!
+//! Without it [`measure`] reports nothing.
+//!
+//! The counts are kept per thread: [`measure`] reports what the
+//! current thread allocates, whatever the other threads do. With the
+//! `rayon` feature the prover allocates on the threads of the pool as
+//! well, which are left out, so that the report of [`proof::prove`]
+//! is then below what it takes in all.
+//!
+//! [`proof::prove`]: crate::proof::prove
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+thread_local! {
+    /// The bytes held by the thread, negative once it frees memory
+    /// allocated by another one.
+    static CURRENT: Cell<isize> = const { Cell::new(0) };
+
+    /// The highest value of `CURRENT` since the last reset.
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+
+    /// The number of allocations and reallocations of the thread.
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The [`System`] allocator counting the allocations of every thread,
+/// to be installed as the global allocator, see the
+/// [module documentation](self).
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn record(grown: isize) {
+        // The thread locals are gone while the thread is torn down.
+        let _ = CURRENT.try_with(|current| {
+            let now = current.get() + grown;
+            current.set(now);
+            if grown > 0 {
+                let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+            }
+        });
+    }
+
+    fn count() {
+        let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::record(layout.size() as isize);
+            Self::count();
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::record(layout.size() as isize);
+            Self::count();
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::record(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::record(new_size as isize - layout.size() as isize);
+            Self::count();
+        }
+        new_ptr
+    }
+}
+
+/// The memory taken by a computation on the current thread, see
+/// [`measure`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// The most bytes held at once on top of those held before.
+    pub peak_bytes: usize,
+
+    /// The number of allocations and reallocations.
+    pub allocations: u64,
+}
+
+/// Run `f` and report the memory it takes on the current thread, such
+/// as [`proof::prove`], [`CircuitBuilder::build_circuit`] or
+/// [`Circuit::evaluate`].
+///
+/// Measurements nest, the peak of an outer one includes the inner ones.
+///
+/// [`proof::prove`]: crate::proof::prove
+/// [`CircuitBuilder::build_circuit`]: crate::circuit_builder::CircuitBuilder::build_circuit
+/// [`Circuit::evaluate`]: crate::circuit::Circuit::evaluate
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, MemoryReport) {
+    let before = CURRENT.with(Cell::get);
+    let outer_peak = PEAK.with(|peak| peak.replace(before));
+    let allocations = ALLOCATIONS.with(Cell::get);
+
+    let res = f();
+
+    let peak = PEAK.with(|peak| peak.replace(peak.get().max(outer_peak)));
+    let report = MemoryReport {
+        peak_bytes: (peak - before).max(0) as usize,
+        allocations: ALLOCATIONS.with(Cell::get) - allocations,
+    };
+
+    (res, report)
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use ark_ff::{Fp64, MontBackend, MontConfig};
+
+    use super::{measure, CountingAllocator};
+    use crate::{harness, proof};
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    #[test]
+    fn measure_allocations() {
+        let (v, report) = measure(|| vec![0u64; 1 << 10]);
+        assert_eq!(report.peak_bytes, 8 << 10);
+        assert_eq!(report.allocations, 1);
+        drop(v);
+
+        let ((_, inner), outer) = measure(|| {
+            let v = vec![0u8; 100];
+            let inner = measure(|| vec![0u8; 1000]);
+            drop(v);
+            inner
+        });
+        assert_eq!(inner.peak_bytes, 1000);
+        assert_eq!(outer.peak_bytes, 1100);
+        assert_eq!(outer.allocations, 2);
+
+        assert_eq!(measure(|| 1 + 1).1.peak_bytes, 0);
+    }
+
+    #[test]
+    fn prover_peak() {
+        let small = harness::circuit(4, 16);
+        let large = harness::circuit(4, 64);
+
+        let (circuit, build) = measure(|| harness::builder(4, 64).build_circuit().unwrap());
+        assert_eq!(circuit, large);
+        assert!(build.allocations > 0);
+
+        let input = harness::input::<Fp389>(64);
+        let (evaluation, evaluate) = measure(|| large.evaluate(&input));
+        let values: usize = evaluation.layers.iter().map(Vec::len).sum();
+        assert!(evaluate.peak_bytes >= values * core::mem::size_of::<Fp389>());
+
+        let (_, prove_small) = measure(|| proof::prove(&small, &harness::input::<Fp389>(16)));
+        let (_, prove_large) = measure(|| proof::prove(&large, &input));
+        assert!(prove_small.peak_bytes < prove_large.peak_bytes);
+        assert!(prove_large.peak_bytes >= evaluate.peak_bytes);
+    }
+}
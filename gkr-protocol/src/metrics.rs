@@ -0,0 +1,37 @@
+//! Timing instrumentation of the [`Prover`](crate::Prover).
+//!
+//! Only compiled with the `metrics` feature enabled, so that the
+//! prover carries no bookkeeping otherwise.
+
+use std::time::Duration;
+
+/// Time spent by the [`Prover`](crate::Prover) on a single layer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LayerTimes {
+    /// Building the wiring predicates $\widetilde{add}_i$ and
+    /// $\widetilde{mul}_i$ at $r_i$.
+    pub predicates: Duration,
+
+    /// Running the Sum-Check protocol, including the restriction
+    /// of $\tilde{W}_{i+1}$ to a line in the last round.
+    pub sumcheck: Duration,
+}
+
+/// Statistics collected by the [`Prover`](crate::Prover) through a run
+/// of the protocol, returned along with the proof by
+/// [`prove_with_stats`](crate::proof::prove_with_stats).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProverStats {
+    /// Timings on per-layer basis, output layer first.
+    pub layer_times: Vec<LayerTimes>,
+}
+
+impl ProverStats {
+    pub(crate) fn layer_mut(&mut self, layer: usize) -> &mut LayerTimes {
+        if self.layer_times.len() <= layer {
+            self.layer_times.resize(layer + 1, LayerTimes::default());
+        }
+
+        &mut self.layer_times[layer]
+    }
+}
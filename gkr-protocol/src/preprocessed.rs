@@ -0,0 +1,294 @@
+//! Preprocessing of a public circuit verified over and over.
+//!
+//! The verifier only ever reads the wiring predicates of a circuit,
+//! the numbers of variables labeling its layers and the degrees of
+//! the Sum-Check protocols. A [`PreprocessedCircuit`] keeps these,
+//! computed once, so that many parties may verify proofs with
+//! [`Verifier::verify_preprocessed`](crate::Verifier::verify_preprocessed)
+//! without the circuit, provided they trust its fingerprint.
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use std::io;
+
+use crate::circuit::{Circuit, PaddingInfo, Wiring};
+
+/// An error loading a [`PreprocessedCircuit`] saved by
+/// [`PreprocessedCircuit::save`].
+#[cfg(feature = "serde")]
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    /// The bytes do not decode.
+    #[error("malformed preprocessing: {0}")]
+    Decode(#[from] bincode::Error),
+
+    /// The wiring tables do not make up the circuit of the
+    /// fingerprint, or disagree with their commitments.
+    #[error("inconsistent preprocessing")]
+    Inconsistent,
+
+    /// The preprocessing is of a circuit other than the expected one.
+    #[error("the preprocessing is of another circuit")]
+    FingerprintMismatch,
+}
+
+/// Everything the verifier reads of a circuit, see the
+/// [module documentation](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PreprocessedCircuit {
+    fingerprint: [u8; 32],
+    num_inputs: usize,
+    num_public_inputs: usize,
+
+    /// The number of variables labeling every layer, inputs last.
+    num_vars: Vec<usize>,
+
+    /// The largest degree of the round polynomials of every layer.
+    degrees: Vec<usize>,
+
+    /// The wiring predicates of every layer.
+    wirings: Vec<Wiring>,
+
+    padding: PaddingInfo,
+
+    /// The commitments to the wiring predicates of every layer, see
+    /// [`PreprocessedCircuit::with_predicate_commitments`].
+    predicate_commitments: Option<Vec<[u8; 32]>>,
+}
+
+impl PreprocessedCircuit {
+    /// Preprocess `circuit`.
+    pub fn new(circuit: &Circuit) -> Self {
+        let num_layers = circuit.num_layers();
+
+        Self {
+            fingerprint: circuit.fingerprint(),
+            num_inputs: circuit.num_inputs(),
+            num_public_inputs: circuit.num_public_inputs(),
+            num_vars: (0..=num_layers)
+                .map(|i| circuit.num_vars_at(i).unwrap())
+                .collect(),
+            degrees: (0..num_layers).map(|i| circuit.layer_degree(i)).collect(),
+            wirings: (0..num_layers).map(|i| circuit.layer_wiring(i)).collect(),
+            padding: circuit.pad_to_power_of_two().1,
+            predicate_commitments: None,
+        }
+    }
+
+    /// Also keep a commitment to the sparse predicates of every
+    /// layer, see [`Wiring::commitment`].
+    pub fn with_predicate_commitments(mut self) -> Self {
+        self.predicate_commitments = Some(self.wirings.iter().map(Wiring::commitment).collect());
+        self
+    }
+
+    /// The fingerprint of the circuit, see [`Circuit::fingerprint`].
+    pub fn fingerprint(&self) -> [u8; 32] {
+        self.fingerprint
+    }
+
+    /// The commitments to the predicates of every layer, if kept.
+    pub fn predicate_commitments(&self) -> Option<&[[u8; 32]]> {
+        self.predicate_commitments.as_deref()
+    }
+
+    /// The real widths of the circuit padded to powers of two.
+    pub fn padding(&self) -> &PaddingInfo {
+        &self.padding
+    }
+
+    /// The number of gate layers.
+    pub fn num_layers(&self) -> usize {
+        self.wirings.len()
+    }
+
+    /// The number of inputs.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    /// The number of outputs.
+    pub fn num_outputs(&self) -> usize {
+        self.padding
+            .widths
+            .first()
+            .copied()
+            .unwrap_or(self.num_inputs)
+    }
+
+    /// The number of variables labeling layer `layer`, the input
+    /// layer being layer [`PreprocessedCircuit::num_layers`].
+    pub fn num_vars_at(&self, layer: usize) -> usize {
+        self.num_vars[layer]
+    }
+
+    /// See [`Circuit::layer_degree`].
+    pub fn layer_degree(&self, layer: usize) -> usize {
+        self.degrees[layer]
+    }
+
+    /// The wiring predicates of every layer.
+    pub fn wirings(&self) -> &[Wiring] {
+        &self.wirings
+    }
+
+    /// Serialize the preprocessing into bytes.
+    #[cfg(feature = "serde")]
+    pub fn save(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("preprocessings always serialize")
+    }
+
+    /// Deserialize a preprocessing saved by [`PreprocessedCircuit::save`],
+    /// failing unless it is of the circuit of fingerprint `expected`.
+    ///
+    /// The fingerprint is computed anew from the wiring tables, so that
+    /// they cannot be swapped for those of another circuit.
+    #[cfg(feature = "serde")]
+    pub fn load(bytes: &[u8], expected: &[u8; 32]) -> Result<Self, LoadError> {
+        bincode::deserialize::<Self>(bytes)?.validate(expected)
+    }
+
+    /// Write the preprocessing to `writer`, the bytes of
+    /// [`PreprocessedCircuit::save`].
+    #[cfg(feature = "serde")]
+    pub fn save_to(&self, mut writer: impl io::Write) -> io::Result<()> {
+        writer.write_all(&self.save())
+    }
+
+    /// Read a preprocessing written by [`PreprocessedCircuit::save_to`]
+    /// from `reader`, see [`PreprocessedCircuit::load`].
+    #[cfg(feature = "serde")]
+    pub fn load_from(reader: impl io::Read, expected: &[u8; 32]) -> Result<Self, LoadError> {
+        bincode::deserialize_from::<_, Self>(reader)?.validate(expected)
+    }
+
+    #[cfg(feature = "serde")]
+    fn validate(self, expected: &[u8; 32]) -> Result<Self, LoadError> {
+        if self.fingerprint != *expected {
+            return Err(LoadError::FingerprintMismatch);
+        }
+
+        if self.num_public_inputs > self.num_inputs {
+            return Err(LoadError::Inconsistent);
+        }
+        let layers = self
+            .wirings
+            .iter()
+            .map(Wiring::to_layer)
+            .collect::<Option<_>>()
+            .ok_or(LoadError::Inconsistent)?;
+        let circuit =
+            Circuit::new(layers, self.num_inputs).with_public_inputs(self.num_public_inputs);
+
+        // All the rest follows from the gates, the fingerprint included.
+        let mut rebuilt = PreprocessedCircuit::new(&circuit);
+        if self.predicate_commitments.is_some() {
+            rebuilt = rebuilt.with_predicate_commitments();
+        }
+        if rebuilt != self {
+            return Err(LoadError::Inconsistent);
+        }
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use ark_ff::{Fp64, MontBackend, MontConfig};
+
+    use super::PreprocessedCircuit;
+    use crate::{
+        circuit::{circuit_from_book, ripple_carry_adder},
+        proof, Verifier,
+    };
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    #[test]
+    fn verify_preprocessed() {
+        let circuit = circuit_from_book().with_public_inputs(1);
+        let preprocessed = PreprocessedCircuit::new(&circuit);
+        assert_eq!(preprocessed.fingerprint(), circuit.fingerprint());
+        assert_eq!(preprocessed.num_outputs(), 2);
+        assert_eq!(preprocessed.padding(), &circuit.pad_to_power_of_two().1);
+
+        let input: Vec<Fp389> = [3u64, 2, 3, 1].into_iter().map(Fp389::from).collect();
+        let proof = proof::prove(&circuit, &input).unwrap();
+        Verifier::verify_preprocessed(&preprocessed, &input, &proof).unwrap();
+
+        let mut other = input.clone();
+        other[3] = Fp389::from(2u64);
+        assert_eq!(
+            Verifier::verify_preprocessed(&preprocessed, &other, &proof),
+            proof::verify(&circuit, &other, &proof)
+        );
+        assert!(Verifier::verify_preprocessed(&preprocessed, &other, &proof).is_err());
+
+        let adder = ripple_carry_adder(2);
+        assert!(
+            Verifier::verify_preprocessed(&PreprocessedCircuit::new(&adder), &input, &proof)
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn save_and_load() {
+        use super::LoadError;
+
+        let circuit = circuit_from_book();
+        let preprocessed = PreprocessedCircuit::new(&circuit).with_predicate_commitments();
+        assert_eq!(
+            preprocessed.predicate_commitments().map(<[_]>::len),
+            Some(2)
+        );
+
+        let mut bytes = vec![];
+        preprocessed.save_to(&mut bytes).unwrap();
+        assert_eq!(bytes, preprocessed.save());
+        assert_eq!(
+            PreprocessedCircuit::load_from(&bytes[..], &circuit.fingerprint()).unwrap(),
+            preprocessed
+        );
+
+        // Preprocessed from another circuit.
+        let adder = ripple_carry_adder(2);
+        let other = PreprocessedCircuit::new(&adder).save();
+        assert!(matches!(
+            PreprocessedCircuit::load(&other, &circuit.fingerprint()),
+            Err(LoadError::FingerprintMismatch)
+        ));
+        assert!(PreprocessedCircuit::load(&other, &adder.fingerprint()).is_ok());
+
+        // Passing for the circuit by its fingerprint alone.
+        let mut forged = PreprocessedCircuit::new(&adder);
+        forged.fingerprint = circuit.fingerprint();
+        assert!(matches!(
+            PreprocessedCircuit::load(&forged.save(), &circuit.fingerprint()),
+            Err(LoadError::Inconsistent)
+        ));
+
+        let mut forged = preprocessed.clone();
+        forged.predicate_commitments.as_mut().unwrap()[1] = [0; 32];
+        assert!(matches!(
+            PreprocessedCircuit::load(&forged.save(), &circuit.fingerprint()),
+            Err(LoadError::Inconsistent)
+        ));
+
+        assert!(matches!(
+            PreprocessedCircuit::load(&bytes[1..], &circuit.fingerprint()),
+            Err(LoadError::Decode(_))
+        ));
+    }
+}
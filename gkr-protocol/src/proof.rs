@@ -0,0 +1,2327 @@
+//! Non-interactive GKR proofs.
+//!
+//! The [`Prover`] and the [`Verifier`] are run against each other with
+//! the challenges of the verifier drawn from a [`Transcript`] of the
+//! prover messages, so that the messages alone make up the proof.
+//!
+//! [`verify`] replays the checks of the verifier on a proof and tells
+//! which one fails as a [`VerifyError`].
+//!
+//! The round polynomials of a proof are kept by their coefficients,
+//! and may be written by their values instead, see [`MessageForm`].
+
+use alloc::{borrow::Cow, vec, vec::Vec};
+use core::iter;
+
+use ark_ff::Field;
+use ark_poly::{univariate, MultilinearExtension, Polynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use sha2::Digest;
+
+#[cfg(feature = "std")]
+use crate::circuit_builder::{BuildError, CircuitSource};
+#[cfg(feature = "metrics")]
+use crate::metrics::ProverStats;
+use crate::{
+    circuit::{Circuit, GateType, Wiring},
+    commitment::{Commitment, HashCommitment, MerkleTree, WitnessCommitment},
+    extension::ExtensionOf,
+    gate_types, line, output_weights, padded_mle,
+    preprocessed::PreprocessedCircuit,
+    transcript::Transcript,
+    uni_poly::UniPoly,
+    Prover, ProverMessage, Verifier, VerifierMessage,
+};
+
+/// The domain separator of GKR transcripts.
+const TRANSCRIPT_LABEL: &[u8] = b"gkr-protocol";
+
+/// The domain separator of every circuit of a [`MultiProof`].
+const CIRCUIT_LABEL: &[u8] = b"gkr-protocol circuit";
+
+/// The messages of the prover for a single layer of the circuit.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct LayerProof<F: Field> {
+    /// The claimed sum $c_1$ of the Sum-Check protocol.
+    pub c_1: F,
+
+    /// The round polynomials of the Sum-Check protocol.
+    pub round_polys: Vec<univariate::SparsePolynomial<F>>,
+
+    /// The restriction $q$ of $\tilde{W}_{i+1}$ to the line through $b$ and $c$.
+    pub q: univariate::SparsePolynomial<F>,
+}
+
+/// A non-interactive proof of the evaluation of a [`Circuit`].
+///
+/// Proofs are serialized with [`CanonicalSerialize`].
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct GkrProof<F: Field> {
+    /// The claimed outputs of the circuit, none in proofs of claims
+    /// about single outputs, see [`prove_claims`].
+    pub outputs: Vec<F>,
+
+    /// The proofs of the layers, output layer first.
+    pub layers: Vec<LayerProof<F>>,
+
+    /// The commitments to the values of the layers below the outputs,
+    /// the input layer last, empty unless made by [`prove_committed`].
+    pub commitments: Vec<Vec<u8>>,
+}
+
+impl<F: Field> GkrProof<F> {
+    /// The number of field elements in the proof.
+    ///
+    /// Polynomials are counted by their non-zero coefficients.
+    pub fn num_field_elements(&self) -> usize {
+        self.outputs.len()
+            + self
+                .layers
+                .iter()
+                .map(|layer| {
+                    1 + layer.round_polys.iter().map(|p| p.len()).sum::<usize>() + layer.q.len()
+                })
+                .sum::<usize>()
+    }
+
+    /// The round polynomials of the proof by layer and round, to be
+    /// tampered with.
+    pub fn round_polys_mut(
+        &mut self,
+    ) -> impl Iterator<Item = ((usize, usize), &mut univariate::SparsePolynomial<F>)> {
+        self.layers.iter_mut().enumerate().flat_map(|(i, layer)| {
+            layer
+                .round_polys
+                .iter_mut()
+                .enumerate()
+                .map(move |(j, p)| ((i, j), p))
+        })
+    }
+
+    /// The size of the field elements of the proof in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.num_field_elements() * F::zero().uncompressed_size()
+    }
+
+    /// An upper bound on the probability that the verifier accepts
+    /// this proof of a false claim over a field of `field_bits` bits,
+    /// by the Schwartz-Zippel lemma.
+    ///
+    /// The random point $r_0$ errs with probability $k_0 / |\mathbb{F}|$.
+    /// Every Sum-Check round errs with probability $2 / |\mathbb{F}|$,
+    /// the degree of $f^{(i)}_{r_i}$ in each variable. The restriction
+    /// to a line errs with probability $k_{i+1} / |\mathbb{F}|$. A
+    /// union bound adds these up.
+    pub fn soundness_error(&self, field_bits: usize) -> f64 {
+        let k_0 = self.outputs.len().next_power_of_two().trailing_zeros() as usize;
+        let numerator: usize = k_0
+            + self
+                .layers
+                .iter()
+                .map(|layer| {
+                    let rounds = layer.round_polys.len();
+                    2 * rounds + rounds / 2
+                })
+                .sum::<usize>();
+
+        let mut error = numerator as f64;
+        for _ in 0..field_bits {
+            error /= 2.0;
+        }
+
+        error.min(1.0)
+    }
+}
+
+/// How the round polynomials of the Sum-Check protocol are written in
+/// a proof, see [`GkrProof::serialize_in_form`].
+///
+/// The transcript always absorbs the coefficients, so that the
+/// challenges, and the proof in memory, do not depend on the form.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MessageForm {
+    /// The coefficients, as with [`CanonicalSerialize`].
+    #[default]
+    Coefficients,
+
+    /// The values at $0, 1, \ldots, d$ for a `degree` $d$ at least
+    /// the degree of the round polynomials.
+    Evaluations {
+        /// The degree $d$.
+        degree: usize,
+    },
+}
+
+/// The values of `p` at $0, 1, \ldots,$ `degree`.
+pub fn evaluate_at<F: Field>(p: &univariate::SparsePolynomial<F>, degree: usize) -> Vec<F> {
+    (0..=degree as u64)
+        .map(|t| p.evaluate(&F::from(t)))
+        .collect()
+}
+
+/// The polynomial taking the values `evals` at $0, 1, \ldots$, the
+/// inverse of [`evaluate_at`].
+pub fn to_coefficients<F: Field>(evals: &[F]) -> univariate::SparsePolynomial<F> {
+    UniPoly::from_evaluations(evals).into()
+}
+
+/// [`LayerProof`] with the round polynomials by their values.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct LayerEvaluations<F: Field> {
+    c_1: F,
+    round_evals: Vec<Vec<F>>,
+    q: univariate::SparsePolynomial<F>,
+}
+
+/// [`GkrProof`] with the round polynomials by their values.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct ProofEvaluations<F: Field> {
+    outputs: Vec<F>,
+    layers: Vec<LayerEvaluations<F>>,
+    commitments: Vec<Vec<u8>>,
+}
+
+impl<F: Field> GkrProof<F> {
+    /// Write the proof compressed, the round polynomials in `form`.
+    ///
+    /// Fails with [`SerializationError::InvalidData`] if a round
+    /// polynomial is of a degree higher than that of `form`.
+    pub fn serialize_in_form(
+        &self,
+        form: MessageForm,
+        writer: impl Write,
+    ) -> Result<(), SerializationError> {
+        let degree = match form {
+            MessageForm::Coefficients => return self.serialize_compressed(writer),
+            MessageForm::Evaluations { degree } => degree,
+        };
+
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let round_evals = layer
+                    .round_polys
+                    .iter()
+                    .map(|p| match p.degree() <= degree {
+                        true => Ok(evaluate_at(p, degree)),
+                        false => Err(SerializationError::InvalidData),
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                Ok(LayerEvaluations {
+                    c_1: layer.c_1,
+                    round_evals,
+                    q: layer.q.clone(),
+                })
+            })
+            .collect::<Result<_, SerializationError>>()?;
+
+        ProofEvaluations {
+            outputs: self.outputs.clone(),
+            layers,
+            commitments: self.commitments.clone(),
+        }
+        .serialize_compressed(writer)
+    }
+
+    /// Read a proof written by [`GkrProof::serialize_in_form`] in
+    /// `form`.
+    ///
+    /// Fails with [`SerializationError::InvalidData`] if a round
+    /// polynomial does not have as many values as `form` requires.
+    pub fn deserialize_in_form(
+        form: MessageForm,
+        reader: impl Read,
+    ) -> Result<Self, SerializationError> {
+        let degree = match form {
+            MessageForm::Coefficients => return Self::deserialize_compressed(reader),
+            MessageForm::Evaluations { degree } => degree,
+        };
+
+        let proof = ProofEvaluations::<F>::deserialize_compressed(reader)?;
+        let layers = proof
+            .layers
+            .into_iter()
+            .map(|layer| {
+                let round_polys = layer
+                    .round_evals
+                    .iter()
+                    .map(|evals| match evals.len() == degree + 1 {
+                        true => Ok(to_coefficients(evals)),
+                        false => Err(SerializationError::InvalidData),
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                Ok(LayerProof {
+                    c_1: layer.c_1,
+                    round_polys,
+                    q: layer.q,
+                })
+            })
+            .collect::<Result<_, SerializationError>>()?;
+
+        Ok(Self {
+            outputs: proof.outputs,
+            layers,
+            commitments: proof.commitments,
+        })
+    }
+}
+
+/// Prove the evaluation of `circuit` on `input`.
+pub fn prove<F: Field>(circuit: &Circuit, input: &[F]) -> crate::Result<GkrProof<F>> {
+    prove_in(&mut Transcript::new(TRANSCRIPT_LABEL), circuit, input)
+}
+
+/// Prove the evaluation on `input` of the circuit built by `source`,
+/// returned along with the proof.
+#[cfg(feature = "std")]
+pub fn prove_source<F: Field>(
+    source: &impl CircuitSource,
+    input: &[F],
+) -> crate::Result<(Circuit, GkrProof<F>)> {
+    let circuit = source.build().map_err(Box::new)?;
+    let proof = prove(&circuit, input)?;
+    Ok((circuit, proof))
+}
+
+/// Prove the evaluation of `circuit` on `input` in `transcript`,
+/// separated from other proofs by its label.
+///
+/// [`prove`] runs in a transcript of the default label. The proof is
+/// checked by [`verify_in`] in a transcript of the same label.
+pub fn prove_in<F: Field>(
+    transcript: &mut Transcript,
+    circuit: &Circuit,
+    input: &[F],
+) -> crate::Result<GkrProof<F>> {
+    let mut prover = Prover::new(circuit.clone(), input);
+    prove_with(transcript, circuit, &mut prover, vec![])
+}
+
+/// [`prove`] returning along with the proof the timings of the
+/// prover, see [`ProverStats`].
+#[cfg(feature = "metrics")]
+pub fn prove_with_stats<F: Field>(
+    circuit: &Circuit,
+    input: &[F],
+) -> crate::Result<(GkrProof<F>, ProverStats)> {
+    let mut prover = Prover::new(circuit.clone(), input);
+    let proof = prove_with(
+        &mut Transcript::new(TRANSCRIPT_LABEL),
+        circuit,
+        &mut prover,
+        vec![],
+    )?;
+
+    Ok((proof, prover.stats().clone()))
+}
+
+/// Prove `claims` about single outputs of `circuit` on `input`, as
+/// `(output index, value)` pairs, see [`Prover::with_output_claims`].
+///
+/// The claims are absorbed into the transcript before the coefficients
+/// combining them are drawn. The proof keeps no outputs and is checked
+/// by [`verify_claims`] against the same claims.
+pub fn prove_claims<F: Field>(
+    circuit: &Circuit,
+    input: &[F],
+    claims: Vec<(usize, F)>,
+) -> crate::Result<GkrProof<F>> {
+    let mut prover = Prover::with_output_claims(circuit.clone(), input, claims);
+    prove_with(
+        &mut Transcript::new(TRANSCRIPT_LABEL),
+        circuit,
+        &mut prover,
+        vec![],
+    )
+}
+
+/// Prove the evaluation of `circuit` on `input`, committing to the
+/// values of every layer in `scheme` before the first challenge.
+///
+/// The proof is checked by [`verify_committed`].
+pub fn prove_committed<F: Field, C: Commitment<F>>(
+    circuit: &Circuit,
+    input: &[F],
+    scheme: &C,
+) -> crate::Result<GkrProof<F>> {
+    let mut prover = Prover::new(circuit.clone(), input);
+    let commitments = prover
+        .commit_layers(scheme)
+        .iter()
+        .map(|c| c.as_ref().to_vec())
+        .collect();
+    prove_with(
+        &mut Transcript::new(TRANSCRIPT_LABEL),
+        circuit,
+        &mut prover,
+        commitments,
+    )
+}
+
+/// Prove the evaluation of `circuit` on `input` over the base field
+/// `B`, with the challenges drawn from its extension `E`.
+///
+/// The values of the layers are computed over `B`, the messages of
+/// the proof are elements of `E`. The proof is checked by
+/// [`verify_over_extension`].
+pub fn prove_over_extension<B: Field, E: ExtensionOf<B>>(
+    circuit: &Circuit,
+    input: &[B],
+) -> crate::Result<GkrProof<E>> {
+    let mut prover = Prover::from_base_input(circuit.clone(), input);
+    prove_with(
+        &mut Transcript::new(TRANSCRIPT_LABEL),
+        circuit,
+        &mut prover,
+        vec![],
+    )
+}
+
+/// A single proof of the evaluations of several circuits in one
+/// transcript, see [`prove_many`].
+///
+/// The inputs are committed to before any challenge, each distinct
+/// input once, so that circuits sharing their input share the
+/// commitment too.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MultiProof<F: Field> {
+    /// The distinct commitments to the inputs of the circuits.
+    pub input_commitments: Vec<Vec<u8>>,
+
+    /// The position of the commitment to the input of every circuit
+    /// among `input_commitments`.
+    pub input_indices: Vec<usize>,
+
+    /// The proof of every circuit, without commitments of its own.
+    pub proofs: Vec<GkrProof<F>>,
+}
+
+/// Prove the evaluations of the circuits of `instances` on their
+/// inputs in a single `transcript`, so that the challenges about
+/// every circuit depend on all the inputs.
+///
+/// The inputs are committed to in [`HashCommitment`] first, then
+/// every circuit is proven in turn, separated from the others by its
+/// index. The proof is checked by [`verify_many`].
+pub fn prove_many<F: Field>(
+    instances: &[(Circuit, Vec<F>)],
+    transcript: &mut Transcript,
+) -> crate::Result<MultiProof<F>> {
+    let mut input_commitments: Vec<Vec<u8>> = vec![];
+    let input_indices = instances
+        .iter()
+        .map(|(_, input)| {
+            let commitment = HashCommitment.commit(input).to_vec();
+            match input_commitments.iter().position(|c| *c == commitment) {
+                Some(index) => index,
+                None => {
+                    input_commitments.push(commitment);
+                    input_commitments.len() - 1
+                }
+            }
+        })
+        .collect();
+    transcript.append(&input_commitments);
+    transcript.append(&input_indices);
+
+    let proofs = instances
+        .iter()
+        .enumerate()
+        .map(|(index, (circuit, input))| {
+            transcript.append(&(CIRCUIT_LABEL.to_vec(), index as u64));
+            let mut prover = Prover::new(circuit.clone(), input);
+            prove_with(transcript, circuit, &mut prover, vec![])
+        })
+        .collect::<crate::Result<_>>()?;
+
+    Ok(MultiProof {
+        input_commitments,
+        input_indices,
+        proofs,
+    })
+}
+
+/// The opening of a witness committed to by a [`WitnessCommitment`]:
+/// every value along with its authentication path, see
+/// [`prove_committed_witness`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessOpening<F: Field> {
+    /// The values of the witness.
+    pub values: Vec<F>,
+
+    /// The authentication path of every value.
+    pub paths: Vec<Vec<Vec<u8>>>,
+}
+
+/// Prove the evaluation of `circuit` on `input`, the witness of which
+/// is committed to by `tree`, and open the witness at the point the
+/// claim about the input layer ends up at.
+///
+/// The root is absorbed into the transcript before any challenge, as
+/// the only commitment of the proof. The proof and the opening are
+/// checked by [`verify_committed_witness`].
+///
+/// The opening is not succinct: $\tilde{W}_d(r)$ combines every value
+/// by $\widetilde{eq}(r, x)$, which is nonzero on the whole hypercube,
+/// so the opening takes every value of the witness along with a path
+/// of $\log n$ hashes, $O(n \log n)$ in all. Nor does it hide the
+/// witness. It stands in for a polynomial commitment, which would
+/// open $\tilde{W}_d(r)$ alone.
+///
+/// # Panics
+///
+/// Panics if the number of inputs does not match the circuit.
+pub fn prove_committed_witness<F: Field, D: Digest>(
+    circuit: &Circuit,
+    input: &[F],
+    tree: &MerkleTree<D>,
+) -> crate::Result<(GkrProof<F>, WitnessOpening<F>)> {
+    let mut prover = Prover::new(circuit.clone(), input);
+    let opening = prover.witness_opening(tree);
+    let proof = prove_with(
+        &mut Transcript::new(TRANSCRIPT_LABEL),
+        circuit,
+        &mut prover,
+        vec![tree.root().to_vec()],
+    )?;
+
+    Ok((proof, opening))
+}
+
+/// Prove the evaluation of the boolean `circuit` on `bits` over
+/// GF(2), by proving its arithmetization [`Circuit::lift_gf2`] over
+/// `F` on the bits lifted to zeroes and ones.
+///
+/// The proof is checked by [`verify_gf2`].
+pub fn prove_gf2<F: Field>(circuit: &Circuit, bits: &[bool]) -> crate::Result<GkrProof<F>> {
+    prove(&circuit.lift_gf2(), &lift_bits(bits))
+}
+
+fn lift_bits<F: Field>(bits: &[bool]) -> Vec<F> {
+    bits.iter().map(|&bit| F::from(bit)).collect()
+}
+
+fn prove_with<F: Field>(
+    transcript: &mut Transcript,
+    circuit: &Circuit,
+    prover: &mut Prover<F>,
+    commitments: Vec<Vec<u8>>,
+) -> crate::Result<GkrProof<F>> {
+    let mut verifier = Verifier::new(circuit.clone());
+
+    // A proof of claims about single outputs keeps no outputs, the
+    // verifier has the claims.
+    let begin = prover.start_protocol();
+    let outputs = match &begin {
+        ProverMessage::Begin { circuit_outputs } => {
+            transcript.append(circuit_outputs);
+            circuit_outputs.clone()
+        }
+        ProverMessage::BeginClaims { claims } => {
+            transcript.append(claims);
+            vec![]
+        }
+        _ => unreachable!(),
+    };
+
+    // Proofs without commitments keep their challenges.
+    if !commitments.is_empty() {
+        transcript.append(&commitments);
+    }
+    let (mut r_i, mut coefficients) = match verifier.receive_prover_msg(begin, transcript)? {
+        VerifierMessage::R { r } => (r, None),
+        VerifierMessage::Coefficients { coefficients } => (vec![], Some(coefficients)),
+        _ => unreachable!(),
+    };
+
+    let mut layers = vec![];
+    for i in 0..circuit.num_layers() {
+        #[cfg(feature = "tracing")]
+        let _layer =
+            tracing::info_span!("gkr.layer", layer = i, width = circuit.layer(i).len()).entered();
+
+        let msg = match coefficients.take() {
+            Some(coefficients) => prover.start_claims_round(&coefficients),
+            None => prover.start_round(i, &r_i),
+        };
+        let c_1 = match msg {
+            msg @ ProverMessage::StartSumCheck { c_1, .. } => {
+                transcript.append(&c_1);
+                verifier.receive_prover_msg(msg, transcript)?;
+                c_1
+            }
+            _ => unreachable!(),
+        };
+
+        let num_vars = 2 * circuit.num_vars_at(i + 1).unwrap();
+        let mut round_polys = vec![];
+
+        for j in 0..(num_vars - 1) {
+            #[cfg(feature = "tracing")]
+            let _round = tracing::debug_span!("gkr.round", round = j).entered();
+
+            let msg = prover.round_msg(j);
+            if let ProverMessage::SumCheckProverMessage { p } = &msg {
+                transcript.append(p);
+                round_polys.push(p.clone());
+            }
+            prover.receive_verifier_msg(verifier.receive_prover_msg(msg, transcript)?);
+        }
+
+        #[cfg(feature = "tracing")]
+        let _round = tracing::debug_span!("gkr.round", round = num_vars - 1).entered();
+
+        prover.receive_verifier_msg(verifier.final_random_point(transcript)?);
+
+        let msg = prover.round_msg(num_vars - 1);
+        let q = match &msg {
+            ProverMessage::FinalRoundMessage { p, q } => {
+                transcript.append(p);
+                transcript.append(q);
+                round_polys.push(p.clone());
+                q.clone()
+            }
+            _ => unreachable!(),
+        };
+        r_i = expect_r(verifier.receive_prover_msg(msg, transcript)?);
+
+        layers.push(LayerProof {
+            c_1,
+            round_polys,
+            q,
+        });
+    }
+
+    Ok(GkrProof {
+        outputs,
+        layers,
+        commitments,
+    })
+}
+
+/// A reason to reject a proof, see [`verify`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum VerifyError<F: Field> {
+    /// The proof claims a number of outputs other than the circuit's.
+    #[error("the circuit has {expected} outputs, the proof claims {got}")]
+    OutputCount {
+        /// The number of outputs of the circuit.
+        expected: usize,
+        /// The number of outputs in the proof.
+        got: usize,
+    },
+
+    /// A claim is about an output the circuit does not have.
+    #[error("the circuit has no output {index}")]
+    NoSuchOutput {
+        /// The index of the output.
+        index: usize,
+    },
+
+    /// The proof has a number of layers other than the circuit's.
+    #[error("the circuit has {expected} layers, the proof has {got}")]
+    LayerCount {
+        /// The number of layers of the circuit.
+        expected: usize,
+        /// The number of layers in the proof.
+        got: usize,
+    },
+
+    /// The Sum-Check protocol about a layer has a number of rounds
+    /// other than twice the number of variables of the layer below.
+    #[error("layer {layer} takes {expected} rounds, the proof has {got}")]
+    RoundCount {
+        /// The layer.
+        layer: usize,
+        /// The number of rounds the layer takes.
+        expected: usize,
+        /// The number of rounds in the proof.
+        got: usize,
+    },
+
+    /// The claimed sum $c_1$ about a layer is not the value the
+    /// previous layer reduced the claim to.
+    #[error("layer {layer}: the claim reduces to {expected}, the sum is {got}")]
+    ClaimMismatch {
+        /// The layer.
+        layer: usize,
+        /// The value of the claim about the layer.
+        expected: F,
+        /// The claimed sum $c_1$.
+        got: F,
+    },
+
+    /// $g_j(0) + g_j(1)$ is not the value of the previous round.
+    #[error("layer {layer}, round {round}: expected the sum {expected}, got {got}")]
+    SumMismatch {
+        /// The layer.
+        layer: usize,
+        /// The round of the Sum-Check protocol.
+        round: usize,
+        /// $c_1$ in the first round, $g_{j-1}(r_{j-1})$ in the others.
+        expected: F,
+        /// $g_j(0) + g_j(1)$.
+        got: F,
+    },
+
+    /// A round polynomial is of a degree higher than
+    /// [`Circuit::layer_degree`].
+    #[error("layer {layer}, round {round}: degree {degree} over {max}")]
+    DegreeTooHigh {
+        /// The layer.
+        layer: usize,
+        /// The round of the Sum-Check protocol.
+        round: usize,
+        /// The degree of the round polynomial.
+        degree: usize,
+        /// The largest degree allowed.
+        max: usize,
+    },
+
+    /// The restriction $q$ of $\tilde{W}_{i+1}$ to a line is of a
+    /// degree higher than the number of variables of $\tilde{W}_{i+1}$.
+    #[error("layer {layer}: the restriction to a line has degree {degree} over {max}")]
+    LineDegreeTooHigh {
+        /// The layer.
+        layer: usize,
+        /// The degree of $q$.
+        degree: usize,
+        /// The largest degree allowed.
+        max: usize,
+    },
+
+    /// The last round polynomial disagrees with the value of the
+    /// wiring predicates on $q(0)$ and $q(1)$.
+    #[error("layer {layer}: the wiring gives {expected}, the last round {got}")]
+    FinalClaimMismatch {
+        /// The layer.
+        layer: usize,
+        /// The value of the wiring predicates on $q(0)$ and $q(1)$.
+        expected: F,
+        /// The value of the last round polynomial at the last point.
+        got: F,
+    },
+
+    /// The input is of the wrong arity.
+    #[error("the circuit takes {expected} inputs, got {got}")]
+    InputCount {
+        /// The number of inputs of the circuit.
+        expected: usize,
+        /// The number of inputs given.
+        got: usize,
+    },
+
+    /// The claim about the input layer does not hold.
+    #[error("the claim about the input is {expected}, the input gives {got}")]
+    InputEvaluationMismatch {
+        /// The claimed value of $\tilde{W}_d(r_d)$.
+        expected: F,
+        /// The value of $\tilde{W}_d(r_d)$ on the input.
+        got: F,
+    },
+
+    /// The proof commits to a number of layers other than the
+    /// circuit's, see [`verify_committed`].
+    #[error("the circuit has {expected} layers to commit to, the proof has {got}")]
+    CommitmentCount {
+        /// The number of layers of the circuit.
+        expected: usize,
+        /// The number of commitments in the proof.
+        got: usize,
+    },
+
+    /// The commitment to the input layer does not open to the input.
+    #[error("the commitment to the input layer does not open to the input")]
+    OpeningMismatch,
+
+    /// The proof does not commit to the root of the witness alone, see
+    /// [`verify_committed_witness`].
+    #[error("the proof does not commit to the root of the witness")]
+    RootMismatch,
+
+    /// The path of a value of the witness does not lead to the root.
+    #[error("witness {index} does not open against the root")]
+    WitnessPathMismatch {
+        /// The index of the value in the witness.
+        index: usize,
+    },
+
+    /// The circuit to verify against does not build, see
+    /// [`verify_source`].
+    #[cfg(feature = "std")]
+    #[error("the circuit does not build: {0}")]
+    Build(#[from] Box<BuildError>),
+}
+
+/// A reason to reject a [`MultiProof`], see [`verify_many`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum MultiVerifyError<F: Field> {
+    /// The proof is about a number of circuits other than the
+    /// statement's.
+    #[error("the statement has {expected} circuits, the proof has {got}")]
+    CircuitCount {
+        /// The number of circuits of the statement.
+        expected: usize,
+        /// The number of proofs.
+        got: usize,
+    },
+
+    /// The proof of a circuit fails, or the commitment to its input
+    /// does not open to it.
+    #[error("circuit {index}: {error}")]
+    Circuit {
+        /// The index of the circuit.
+        index: usize,
+        /// The first check of the circuit that fails.
+        error: VerifyError<F>,
+    },
+}
+
+/// The values of the wiring predicates of a layer at a point, by
+/// gate type, see [`Wiring::ext`].
+///
+/// [`Wiring::ext`]: crate::circuit::Wiring::ext
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PredicateEvals<F> {
+    /// $\widetilde{add}_i$.
+    pub add: F,
+
+    /// $\widetilde{mul}_i$.
+    pub mul: F,
+
+    /// $\widetilde{sub}_i$.
+    pub sub: F,
+
+    /// $\widetilde{relay}_i$.
+    pub relay: F,
+}
+
+impl<F: Field> PredicateEvals<F> {
+    /// The sum of the predicates times their gates on the values
+    /// `left` and `right`.
+    fn combine(&self, left: F, right: F) -> F {
+        [
+            (GateType::Add, self.add),
+            (GateType::Mul, self.mul),
+            (GateType::Sub, self.sub),
+            (GateType::Relay, self.relay),
+        ]
+        .into_iter()
+        .map(|(ttype, predicate)| predicate * ttype.evaluate(left, right))
+        .sum()
+    }
+}
+
+/// The wiring predicates of layer $i$ at the point $(r_i, b, c)$ in
+/// closed form, given $i$ and the point.
+pub type PredicateOracle<'a, F> = &'a dyn Fn(usize, &[F]) -> PredicateEvals<F>;
+
+/// Verify a `proof` of the evaluation of `circuit` on `input`,
+/// failing at the first check that does not hold.
+pub fn verify<F: Field>(
+    circuit: &Circuit,
+    input: &[F],
+    proof: &GkrProof<F>,
+) -> Result<(), VerifyError<F>> {
+    verify_with_oracle(circuit, input, proof, None)
+}
+
+/// Verify a `proof` of the evaluation on `input` of the circuit
+/// built by `source`, see [`prove_source`].
+#[cfg(feature = "std")]
+pub fn verify_source<F: Field>(
+    source: &impl CircuitSource,
+    input: &[F],
+    proof: &GkrProof<F>,
+) -> Result<(), VerifyError<F>> {
+    verify(&source.build().map_err(Box::new)?, input, proof)
+}
+
+/// [`verify`] with the wiring predicates given by `oracle`, if any,
+/// rather than computed from the gates of `circuit`.
+///
+/// Circuits of a regular structure often have predicates in closed
+/// form, much cheaper to evaluate than going over the gates.
+pub fn verify_with_oracle<F: Field>(
+    circuit: &Circuit,
+    input: &[F],
+    proof: &GkrProof<F>,
+    oracle: Option<PredicateOracle<'_, F>>,
+) -> Result<(), VerifyError<F>> {
+    let mut failures = Failures {
+        errors: vec![],
+        all: false,
+    };
+    let transcript = &mut Transcript::new(TRANSCRIPT_LABEL);
+    let _ = check(
+        transcript,
+        circuit,
+        input,
+        proof,
+        oracle,
+        None,
+        &mut failures,
+    );
+
+    match failures.errors.pop() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// [`verify`] a proof made by [`prove_in`], the challenges drawn
+/// from `transcript`.
+pub fn verify_in<F: Field>(
+    transcript: &mut Transcript,
+    circuit: &Circuit,
+    input: &[F],
+    proof: &GkrProof<F>,
+) -> Result<(), VerifyError<F>> {
+    let mut failures = Failures {
+        errors: vec![],
+        all: false,
+    };
+    let _ = check(transcript, circuit, input, proof, None, None, &mut failures);
+
+    match failures.errors.pop() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Verify a `proof` of `claims` about single outputs of `circuit` on
+/// `input`, made by [`prove_claims`].
+pub fn verify_claims<F: Field>(
+    circuit: &Circuit,
+    input: &[F],
+    claims: &[(usize, F)],
+    proof: &GkrProof<F>,
+) -> Result<(), VerifyError<F>> {
+    let mut failures = Failures {
+        errors: vec![],
+        all: false,
+    };
+    let transcript = &mut Transcript::new(TRANSCRIPT_LABEL);
+    let _ = check(
+        transcript,
+        circuit,
+        input,
+        proof,
+        None,
+        Some(claims),
+        &mut failures,
+    );
+
+    match failures.errors.pop() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// [`verify`] reading only the `preprocessed` circuit, see
+/// [`PreprocessedCircuit`].
+pub fn verify_preprocessed<F: Field>(
+    preprocessed: &PreprocessedCircuit,
+    input: &[F],
+    proof: &GkrProof<F>,
+) -> Result<(), VerifyError<F>> {
+    let mut failures = Failures {
+        errors: vec![],
+        all: false,
+    };
+    let transcript = &mut Transcript::new(TRANSCRIPT_LABEL);
+    let _ = check(
+        transcript,
+        preprocessed,
+        input,
+        proof,
+        None,
+        None,
+        &mut failures,
+    );
+
+    match failures.errors.pop() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Verify a `proof` made by [`prove_committed`] with `scheme`.
+///
+/// The commitments bind the challenges, so that tampering with any
+/// of them fails the checks of [`verify`], and the commitment to the
+/// input layer is opened against `input`.
+pub fn verify_committed<F: Field, C: Commitment<F>>(
+    circuit: &Circuit,
+    input: &[F],
+    proof: &GkrProof<F>,
+    scheme: &C,
+) -> Result<(), VerifyError<F>> {
+    if proof.commitments.len() != circuit.num_layers() {
+        return Err(VerifyError::CommitmentCount {
+            expected: circuit.num_layers(),
+            got: proof.commitments.len(),
+        });
+    }
+    if proof.commitments.last().map(Vec::as_slice) != Some(scheme.commit(input).as_ref()) {
+        return Err(VerifyError::OpeningMismatch);
+    }
+
+    verify(circuit, input, proof)
+}
+
+/// Verify a `proof` made by [`prove_committed_witness`] of the
+/// evaluation of `circuit` on its `public_inputs`, the witness
+/// committed to by `root` and its `constants`.
+///
+/// Every value of the `opening` is authenticated against `root`, then
+/// $\tilde{W}_d(r)$ is evaluated on the input they make up with the
+/// public inputs and the constants.
+pub fn verify_committed_witness<F: Field, D: Digest>(
+    circuit: &Circuit,
+    public_inputs: &[F],
+    constants: &[F],
+    root: &[u8],
+    proof: &GkrProof<F>,
+    opening: &WitnessOpening<F>,
+) -> Result<(), VerifyError<F>> {
+    if proof.commitments != [root] {
+        return Err(VerifyError::RootMismatch);
+    }
+
+    let got = public_inputs.len() + opening.values.len() + constants.len();
+    if public_inputs.len() != circuit.num_public_inputs()
+        || opening.values.len() != circuit.num_witnesses()
+        || constants.len() != circuit.num_constants()
+    {
+        return Err(VerifyError::InputCount {
+            expected: circuit.num_inputs(),
+            got,
+        });
+    }
+
+    for (index, value) in opening.values.iter().enumerate() {
+        let authentic = opening
+            .paths
+            .get(index)
+            .is_some_and(|path| WitnessCommitment::<D>::verify_path(root, index, value, path));
+        if !authentic {
+            return Err(VerifyError::WitnessPathMismatch { index });
+        }
+    }
+
+    let input = [public_inputs, &opening.values, constants].concat();
+    verify(circuit, &input, proof)
+}
+
+/// Verify a `proof` made by [`prove_many`] of the evaluations of the
+/// circuits of `instances` on their inputs, replaying `transcript`.
+///
+/// The whole proof fails with the index of the first circuit whose
+/// proof fails.
+pub fn verify_many<F: Field>(
+    instances: &[(Circuit, Vec<F>)],
+    proof: &MultiProof<F>,
+    transcript: &mut Transcript,
+) -> Result<(), MultiVerifyError<F>> {
+    for got in [proof.input_indices.len(), proof.proofs.len()] {
+        if got != instances.len() {
+            return Err(MultiVerifyError::CircuitCount {
+                expected: instances.len(),
+                got,
+            });
+        }
+    }
+
+    for (index, (_, input)) in instances.iter().enumerate() {
+        let commitment = proof.input_commitments.get(proof.input_indices[index]);
+        if commitment.map(Vec::as_slice) != Some(&HashCommitment.commit(input)[..]) {
+            return Err(MultiVerifyError::Circuit {
+                index,
+                error: VerifyError::OpeningMismatch,
+            });
+        }
+    }
+    transcript.append(&proof.input_commitments);
+    transcript.append(&proof.input_indices);
+
+    for (index, ((circuit, input), proof)) in instances.iter().zip(&proof.proofs).enumerate() {
+        transcript.append(&(CIRCUIT_LABEL.to_vec(), index as u64));
+
+        let mut failures = Failures {
+            errors: vec![],
+            all: false,
+        };
+        let _ = check(transcript, circuit, input, proof, None, None, &mut failures);
+        if let Some(error) = failures.errors.pop() {
+            return Err(MultiVerifyError::Circuit { index, error });
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify a `proof` made by [`prove_over_extension`] of the
+/// evaluation of `circuit` on `input` over the base field `B`.
+pub fn verify_over_extension<B: Field, E: ExtensionOf<B>>(
+    circuit: &Circuit,
+    input: &[B],
+    proof: &GkrProof<E>,
+) -> Result<(), VerifyError<E>> {
+    let input: Vec<E> = input.iter().map(|&value| E::from_base(value)).collect();
+    verify(circuit, &input, proof)
+}
+
+/// Verify a `proof` made by [`prove_gf2`] of the evaluation of the
+/// boolean `circuit` on `bits`.
+pub fn verify_gf2<F: Field>(
+    circuit: &Circuit,
+    bits: &[bool],
+    proof: &GkrProof<F>,
+) -> Result<(), VerifyError<F>> {
+    verify(&circuit.lift_gf2(), &lift_bits(bits), proof)
+}
+
+/// Verify a `proof` of the evaluation of `circuit` on `input`,
+/// collecting the failures of all the checks.
+///
+/// Checks go on past a failure with the values of the proof, so
+/// one corrupted value may fail later checks too. Only a proof of
+/// the wrong shape stops the checks. The proof is accepted if no
+/// failure is returned.
+pub fn verify_verbose<F: Field>(
+    circuit: &Circuit,
+    input: &[F],
+    proof: &GkrProof<F>,
+) -> Vec<VerifyError<F>> {
+    let mut failures = Failures {
+        errors: vec![],
+        all: true,
+    };
+    let transcript = &mut Transcript::new(TRANSCRIPT_LABEL);
+    let _ = check(transcript, circuit, input, proof, None, None, &mut failures);
+
+    failures.errors
+}
+
+/// The failures found by [`check`] so far.
+struct Failures<F: Field> {
+    errors: Vec<VerifyError<F>>,
+
+    /// Whether to go on after a failure.
+    all: bool,
+}
+
+/// The checks of a proof are stopped.
+struct Stopped;
+
+impl<F: Field> Failures<F> {
+    fn report(&mut self, error: VerifyError<F>) -> Result<(), Stopped> {
+        self.errors.push(error);
+        match self.all {
+            true => Ok(()),
+            false => Err(Stopped),
+        }
+    }
+
+    fn stop(&mut self, error: VerifyError<F>) -> Result<(), Stopped> {
+        self.errors.push(error);
+        Err(Stopped)
+    }
+}
+
+/// What the checks read of a circuit, from its gates or from its
+/// preprocessing.
+trait Structure {
+    fn num_outputs(&self) -> usize;
+
+    fn num_inputs(&self) -> usize;
+
+    fn num_layers(&self) -> usize;
+
+    fn num_vars_at(&self, layer: usize) -> usize;
+
+    fn layer_degree(&self, layer: usize) -> usize;
+
+    /// The wiring predicates of every layer, a single one if all the
+    /// layers share it.
+    fn wirings(&self) -> Cow<'_, [Wiring]>;
+}
+
+impl Structure for Circuit {
+    fn num_outputs(&self) -> usize {
+        Circuit::num_outputs(self)
+    }
+
+    fn num_inputs(&self) -> usize {
+        Circuit::num_inputs(self)
+    }
+
+    fn num_layers(&self) -> usize {
+        Circuit::num_layers(self)
+    }
+
+    fn num_vars_at(&self, layer: usize) -> usize {
+        Circuit::num_vars_at(self, layer).unwrap()
+    }
+
+    fn layer_degree(&self, layer: usize) -> usize {
+        Circuit::layer_degree(self, layer)
+    }
+
+    fn wirings(&self) -> Cow<'_, [Wiring]> {
+        match self.is_uniform() {
+            true => vec![self.layer_wiring(0)].into(),
+            false => (0..self.num_layers())
+                .map(|i| self.layer_wiring(i))
+                .collect(),
+        }
+    }
+}
+
+impl Structure for PreprocessedCircuit {
+    fn num_outputs(&self) -> usize {
+        PreprocessedCircuit::num_outputs(self)
+    }
+
+    fn num_inputs(&self) -> usize {
+        PreprocessedCircuit::num_inputs(self)
+    }
+
+    fn num_layers(&self) -> usize {
+        PreprocessedCircuit::num_layers(self)
+    }
+
+    fn num_vars_at(&self, layer: usize) -> usize {
+        PreprocessedCircuit::num_vars_at(self, layer)
+    }
+
+    fn layer_degree(&self, layer: usize) -> usize {
+        PreprocessedCircuit::layer_degree(self, layer)
+    }
+
+    fn wirings(&self) -> Cow<'_, [Wiring]> {
+        PreprocessedCircuit::wirings(self).into()
+    }
+}
+
+/// Run the checks of the [`Verifier`] on `proof`, the challenges
+/// drawn in the same order.
+fn check<F: Field>(
+    transcript: &mut Transcript,
+    circuit: &impl Structure,
+    input: &[F],
+    proof: &GkrProof<F>,
+    oracle: Option<PredicateOracle<'_, F>>,
+    claims: Option<&[(usize, F)]>,
+    failures: &mut Failures<F>,
+) -> Result<(), Stopped> {
+    let num_outputs = match claims {
+        Some(_) => 0,
+        None => circuit.num_outputs(),
+    };
+    if proof.outputs.len() != num_outputs {
+        failures.stop(VerifyError::OutputCount {
+            expected: num_outputs,
+            got: proof.outputs.len(),
+        })?;
+    }
+    if let Some(&(index, _)) = claims
+        .into_iter()
+        .flatten()
+        .find(|(index, _)| *index >= circuit.num_outputs())
+    {
+        failures.stop(VerifyError::NoSuchOutput { index })?;
+    }
+    if proof.layers.len() != circuit.num_layers() {
+        failures.stop(VerifyError::LayerCount {
+            expected: circuit.num_layers(),
+            got: proof.layers.len(),
+        })?;
+    }
+
+    // The first layer of a proof of claims is checked against the
+    // wiring predicates weighted by the claims, not by the oracle.
+    let wirings = match oracle {
+        Some(_) if claims.is_none() => Cow::Borrowed(&[][..]),
+        _ => circuit.wirings(),
+    };
+
+    match claims {
+        Some(claims) => transcript.append(&claims),
+        None => transcript.append(&proof.outputs),
+    }
+    if !proof.commitments.is_empty() {
+        transcript.append(&proof.commitments);
+    }
+    let num_output_vars = circuit.num_vars_at(0);
+    let (mut r_i, mut m_i, mut weights) = match claims {
+        Some(claims) => {
+            let coefficients: Vec<F> = claims.iter().map(|_| F::rand(transcript)).collect();
+            let m_0 = iter::zip(claims, &coefficients)
+                .map(|((_, value), coefficient)| *coefficient * value)
+                .sum();
+            let weights = output_weights(num_output_vars, claims, &coefficients);
+
+            (vec![], m_0, Some(weights))
+        }
+        None => {
+            let r_0: Vec<F> = (0..num_output_vars).map(|_| F::rand(transcript)).collect();
+            let m_0 = padded_mle(num_output_vars, &proof.outputs)
+                .evaluate(&r_0)
+                .unwrap();
+
+            (r_0, m_0, None)
+        }
+    };
+
+    for (i, layer) in proof.layers.iter().enumerate() {
+        let num_vars_next = circuit.num_vars_at(i + 1);
+        let num_rounds = 2 * num_vars_next;
+        if layer.round_polys.len() != num_rounds {
+            failures.stop(VerifyError::RoundCount {
+                layer: i,
+                expected: num_rounds,
+                got: layer.round_polys.len(),
+            })?;
+        }
+
+        if layer.c_1 != m_i {
+            failures.report(VerifyError::ClaimMismatch {
+                layer: i,
+                expected: m_i,
+                got: layer.c_1,
+            })?;
+        }
+        transcript.append(&layer.c_1);
+
+        // The point of the last round is drawn before its polynomial,
+        // which goes along with $q$.
+        let mut expected = layer.c_1;
+        let mut bc = vec![];
+        let max_degree = circuit.layer_degree(i);
+        for (j, p) in layer.round_polys.iter().enumerate() {
+            if j + 1 < num_rounds {
+                transcript.append(p);
+            }
+            let r_j = F::rand(transcript);
+
+            if p.degree() > max_degree {
+                failures.report(VerifyError::DegreeTooHigh {
+                    layer: i,
+                    round: j,
+                    degree: p.degree(),
+                    max: max_degree,
+                })?;
+            }
+
+            let got = p.evaluate(&F::zero()) + p.evaluate(&F::one());
+            if got != expected {
+                failures.report(VerifyError::SumMismatch {
+                    layer: i,
+                    round: j,
+                    expected,
+                    got,
+                })?;
+            }
+
+            expected = p.evaluate(&r_j);
+            bc.push(r_j);
+        }
+        transcript.append(layer.round_polys.last().unwrap());
+        transcript.append(&layer.q);
+
+        if layer.q.degree() > num_vars_next {
+            failures.report(VerifyError::LineDegreeTooHigh {
+                layer: i,
+                degree: layer.q.degree(),
+                max: num_vars_next,
+            })?;
+        }
+
+        let (q_0, q_1) = (layer.q.evaluate(&F::zero()), layer.q.evaluate(&F::one()));
+        let wired: F = match (oracle, weights.take()) {
+            (Some(oracle), None) => oracle(i, &[&r_i[..], &bc].concat()).combine(q_0, q_1),
+            (_, weights) => {
+                let wiring = &wirings[i.min(wirings.len() - 1)];
+                gate_types(wiring)
+                    .into_iter()
+                    .map(|ttype| {
+                        let predicate = match &weights {
+                            Some(weights) => wiring.ext_weighted(ttype, weights),
+                            None => wiring.ext(ttype, &r_i),
+                        };
+                        predicate.evaluate(&bc).unwrap() * ttype.evaluate(q_0, q_1)
+                    })
+                    .sum()
+            }
+        };
+        if wired != expected {
+            failures.report(VerifyError::FinalClaimMismatch {
+                layer: i,
+                expected: wired,
+                got: expected,
+            })?;
+        }
+
+        let r = F::rand(transcript);
+        let (b, c) = bc.split_at(bc.len() / 2);
+        r_i = line(b, c).into_iter().map(|l| l.evaluate(&r)).collect();
+        m_i = layer.q.evaluate(&r);
+    }
+
+    if input.len() != circuit.num_inputs() {
+        failures.stop(VerifyError::InputCount {
+            expected: circuit.num_inputs(),
+            got: input.len(),
+        })?;
+    }
+    let got = padded_mle(circuit.num_vars_at(circuit.num_layers()), input)
+        .evaluate(&r_i)
+        .unwrap();
+    if got != m_i {
+        failures.report(VerifyError::InputEvaluationMismatch { expected: m_i, got })?;
+    }
+
+    Ok(())
+}
+
+fn expect_r<F: Field>(msg: VerifierMessage<F>) -> Vec<F> {
+    match msg {
+        VerifierMessage::R { r } => r,
+        msg => unreachable!("{msg:?}"),
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use ark_ff::{Field, Fp64, MontBackend, MontConfig, One, Zero};
+    use ark_poly::{univariate::SparsePolynomial, Polynomial};
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use pretty_assertions::assert_eq;
+
+    use ark_std::UniformRand;
+
+    use super::{
+        evaluate_at, prove, prove_claims, prove_committed, prove_committed_witness, prove_gf2,
+        prove_in, to_coefficients, verify, verify_claims, verify_committed,
+        verify_committed_witness, verify_gf2, verify_in, verify_verbose, verify_with_oracle,
+        GkrProof, MessageForm, PredicateEvals, VerifyError, TRANSCRIPT_LABEL,
+    };
+    // Only used by the tests of the `std` feature.
+    #[cfg(feature = "std")]
+    use super::{
+        prove_many, prove_over_extension, prove_with, verify_many, verify_over_extension,
+        MultiVerifyError,
+    };
+    use crate::circuit::{
+        circuit_from_book, ripple_carry_adder, Circuit, CircuitLayer, Gate, GateType,
+    };
+    #[cfg(feature = "std")]
+    use crate::circuit_builder::{CircuitBuilder, InputSlot};
+    use crate::commitment::{Commitment, HashCommitment, WitnessCommitment};
+    use crate::transcript::Transcript;
+    #[cfg(feature = "std")]
+    use crate::{Prover, Verifier};
+    use sha2::{Sha256, Sha512};
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    /// The proof of the evaluation of the circuit of the book on
+    /// $(3, 2, 3, 1)$, compressed.
+    const BOOK_PROOF: &str = concat!(
+        "0200000000000000240006000200000000000000ce0004000000000000000300",
+        "0000000000000000000000000000ce0001000000000000004e00020000000000",
+        "0000690003000000000000000000000000000000700001000000000000001b01",
+        "0200000000000000880002000000000000000100000000000000060002000000",
+        "00000000280103000000000000000000000000000000cc000100000000000000",
+        "320102000000000000006901020000000000000000000000000000001a010100",
+        "0000000000009600820004000000000000000300000000000000000000000000",
+        "0000080001000000000000001200020000000000000060000300000000000000",
+        "0000000000000000b4000100000000000000da00020000000000000062010300",
+        "00000000000000000000000000006e0001000000000000003f00020000000000",
+        "00000d01030000000000000000000000000000002d010100000000000000c000",
+        "0200000000000000750003000000000000000000000000000000170001000000",
+        "0000000099000200000000000000d4000000000000000000",
+    );
+
+    fn book_input() -> Vec<Fp389> {
+        [3u64, 2, 3, 1].into_iter().map(Fp389::from).collect()
+    }
+
+    #[test]
+    fn prove_and_verify() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+
+        let proof = prove(&circuit, &input).unwrap();
+        assert_eq!(proof.outputs, vec![Fp389::from(36), Fp389::from(6)]);
+        verify(&circuit, &input, &proof).unwrap();
+
+        let mut other = input.clone();
+        other[0] = Fp389::from(4);
+        assert!(matches!(
+            verify(&circuit, &other, &proof),
+            Err(VerifyError::InputEvaluationMismatch { .. })
+        ));
+
+        let mut bytes = vec![];
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let loaded = GkrProof::<Fp389>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(loaded, proof);
+    }
+
+    #[test]
+    fn proof_size() {
+        let circuit = circuit_from_book();
+        let proof = prove(&circuit, &book_input()).unwrap();
+
+        let round_polys: usize = proof
+            .layers
+            .iter()
+            .flat_map(|layer| &layer.round_polys)
+            .map(|p| p.len())
+            .sum();
+        let qs: usize = proof.layers.iter().map(|layer| layer.q.len()).sum();
+
+        assert_eq!(
+            proof.num_field_elements(),
+            proof.outputs.len() + proof.layers.len() + round_polys + qs
+        );
+        // Elements of the 9-bit field take two bytes.
+        assert_eq!(proof.size_bytes(), 2 * proof.num_field_elements());
+    }
+
+    #[test]
+    fn soundness_error() {
+        let circuit = circuit_from_book();
+        let proof = prove(&circuit, &book_input()).unwrap();
+
+        // One output variable and two layers of four rounds, each
+        // with a line through two-variable points.
+        assert_eq!(proof.soundness_error(64), 21.0 / 2f64.powi(64));
+        assert_eq!(proof.soundness_error(0), 1.0);
+
+        let errors: Vec<_> = [9, 16, 32, 64, 128, 256]
+            .into_iter()
+            .map(|bits| proof.soundness_error(bits))
+            .collect();
+        assert!(errors.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn canned_proof() {
+        let circuit = circuit_from_book();
+        let bytes: Vec<_> = (0..BOOK_PROOF.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&BOOK_PROOF[i..i + 2], 16).unwrap())
+            .collect();
+        let proof = GkrProof::<Fp389>::deserialize_compressed(&bytes[..]).unwrap();
+
+        verify(&circuit, &book_input(), &proof).unwrap();
+
+        let mut fresh = vec![];
+        prove(&circuit, &book_input())
+            .unwrap()
+            .serialize_compressed(&mut fresh)
+            .unwrap();
+        assert_eq!(fresh, bytes);
+    }
+
+    #[test]
+    fn message_forms() {
+        let circuit = circuit_from_book();
+        let proof = prove(&circuit, &book_input()).unwrap();
+
+        for p in proof.layers.iter().flat_map(|layer| &layer.round_polys) {
+            assert_eq!(&to_coefficients(&evaluate_at(p, 2)), p);
+            assert_eq!(&to_coefficients(&evaluate_at(p, 3)), p);
+        }
+
+        let mut coefficients = vec![];
+        proof
+            .serialize_in_form(MessageForm::Coefficients, &mut coefficients)
+            .unwrap();
+        let mut compressed = vec![];
+        proof.serialize_compressed(&mut compressed).unwrap();
+        assert_eq!(coefficients, compressed);
+
+        // Values at $0, 1, 2, 3$, read back to the same proof.
+        let form = MessageForm::Evaluations { degree: 3 };
+        let mut evaluations = vec![];
+        proof.serialize_in_form(form, &mut evaluations).unwrap();
+        let loaded = GkrProof::<Fp389>::deserialize_in_form(form, &evaluations[..]).unwrap();
+        assert_eq!(loaded, proof);
+        verify(&circuit, &book_input(), &loaded).unwrap();
+
+        // Converted to coefficients, the proof reads in the other form.
+        let mut converted = vec![];
+        loaded
+            .serialize_in_form(MessageForm::Coefficients, &mut converted)
+            .unwrap();
+        let loaded = GkrProof::<Fp389>::deserialize_compressed(&converted[..]).unwrap();
+        verify(&circuit, &book_input(), &loaded).unwrap();
+
+        assert!(GkrProof::<Fp389>::deserialize_in_form(
+            MessageForm::Evaluations { degree: 2 },
+            &evaluations[..]
+        )
+        .is_err());
+        assert!(proof
+            .serialize_in_form(MessageForm::Evaluations { degree: 1 }, &mut vec![])
+            .is_err());
+    }
+
+    /// The sum $g(0) + g(1)$ of a round polynomial.
+    fn round_sum(p: &SparsePolynomial<Fp389>) -> Fp389 {
+        p.evaluate(&Fp389::zero()) + p.evaluate(&Fp389::one())
+    }
+
+    /// `p` with `offset` added to its coefficient of `degree`.
+    fn perturbed(
+        p: &SparsePolynomial<Fp389>,
+        degree: usize,
+        offset: u64,
+    ) -> SparsePolynomial<Fp389> {
+        p + &SparsePolynomial::from_coefficients_vec(vec![(degree, Fp389::from(offset))])
+    }
+
+    #[test]
+    fn verify_errors() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+        let proof = prove(&circuit, &input).unwrap();
+        assert_eq!(verify_verbose(&circuit, &input, &proof), vec![]);
+
+        // One more in the constant coefficient is two more in the sum.
+        let mut corrupt = proof.clone();
+        let p = &mut corrupt.layers[0].round_polys[1];
+        let expected = round_sum(p);
+        *p = perturbed(p, 0, 1);
+        assert_eq!(
+            verify(&circuit, &input, &corrupt),
+            Err(VerifyError::SumMismatch {
+                layer: 0,
+                round: 1,
+                expected,
+                got: expected + Fp389::from(2u64),
+            })
+        );
+
+        let mut corrupt = proof.clone();
+        let p = &mut corrupt.layers[1].round_polys[0];
+        *p = perturbed(p, 3, 1);
+        assert_eq!(
+            verify(&circuit, &input, &corrupt),
+            Err(VerifyError::DegreeTooHigh {
+                layer: 1,
+                round: 0,
+                degree: 3,
+                max: 2,
+            })
+        );
+
+        let mut corrupt = proof.clone();
+        corrupt.layers[1].c_1 += Fp389::one();
+        assert_eq!(
+            verify(&circuit, &input, &corrupt),
+            Err(VerifyError::ClaimMismatch {
+                layer: 1,
+                expected: proof.layers[1].c_1,
+                got: proof.layers[1].c_1 + Fp389::one(),
+            })
+        );
+
+        let mut corrupt = proof.clone();
+        corrupt.layers[1].q = perturbed(&corrupt.layers[1].q, 0, 1);
+        assert!(matches!(
+            verify(&circuit, &input, &corrupt),
+            Err(VerifyError::FinalClaimMismatch { layer: 1, .. })
+        ));
+
+        let mut corrupt = proof.clone();
+        corrupt.layers[0].q = perturbed(&corrupt.layers[0].q, 3, 1);
+        assert_eq!(
+            verify(&circuit, &input, &corrupt),
+            Err(VerifyError::LineDegreeTooHigh {
+                layer: 0,
+                degree: 3,
+                max: 2,
+            })
+        );
+
+        let mut other = input.clone();
+        other[3] = other[3].double();
+        assert!(matches!(
+            verify(&circuit, &other, &proof),
+            Err(VerifyError::InputEvaluationMismatch { .. })
+        ));
+        assert_eq!(
+            verify(&circuit, &input[1..], &proof),
+            Err(VerifyError::InputCount {
+                expected: 4,
+                got: 3
+            })
+        );
+
+        let mut corrupt = proof.clone();
+        corrupt.layers[1].round_polys.pop();
+        assert_eq!(
+            verify(&circuit, &input, &corrupt),
+            Err(VerifyError::RoundCount {
+                layer: 1,
+                expected: 4,
+                got: 3
+            })
+        );
+    }
+
+    #[test]
+    fn verify_verbose_collects_failures() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+        let mut corrupt = prove(&circuit, &input).unwrap();
+        for (layer, round) in [(0, 1), (1, 2)] {
+            let p = &mut corrupt.layers[layer].round_polys[round];
+            *p = perturbed(p, 0, 1);
+        }
+
+        let failures = verify_verbose(&circuit, &input, &corrupt);
+        assert_eq!(Err(failures[0].clone()), verify(&circuit, &input, &corrupt));
+        for (layer, round) in [(0, 1), (1, 2)] {
+            assert!(failures.iter().any(|failure| matches!(
+                failure,
+                VerifyError::SumMismatch { layer: l, round: r, .. } if (*l, *r) == (layer, round)
+            )));
+        }
+    }
+
+    #[test]
+    fn padded_round_polynomial() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+        let mut proof = prove(&circuit, &input).unwrap();
+        assert_eq!(circuit.layer_degree(0), 2);
+        assert_eq!(circuit.layer_degree(1), 2);
+
+        // Replay the transcript up to the point of the last round of
+        // the last layer, which is drawn before its polynomial.
+        let transcript = &mut Transcript::new(TRANSCRIPT_LABEL);
+        transcript.append(&proof.outputs);
+        for _ in 0..circuit.num_vars_at(0).unwrap() {
+            Fp389::rand(transcript);
+        }
+        let mut r = Fp389::zero();
+        for (i, layer) in proof.layers.iter().enumerate() {
+            transcript.append(&layer.c_1);
+            let (last, rounds) = layer.round_polys.split_last().unwrap();
+            for p in rounds {
+                transcript.append(p);
+                Fp389::rand(transcript);
+            }
+            r = Fp389::rand(transcript);
+            if i + 1 < proof.layers.len() {
+                transcript.append(last);
+                transcript.append(&layer.q);
+                Fp389::rand(transcript);
+            }
+        }
+
+        // X (X - 1) (X - r) changes neither the sum nor the value at r.
+        let pad = SparsePolynomial::from_coefficients_vec(vec![
+            (1, r),
+            (2, -(r + Fp389::one())),
+            (3, Fp389::one()),
+        ]);
+        let last = proof.layers[1].round_polys.last_mut().unwrap();
+        *last = &*last + &pad;
+
+        assert_eq!(
+            verify_verbose(&circuit, &input, &proof),
+            vec![VerifyError::DegreeTooHigh {
+                layer: 1,
+                round: 3,
+                degree: 3,
+                max: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn predicate_oracle() {
+        // Gate $a$ of the layer reads the inputs $b = 0$ and $c = 1$.
+        let circuit = Circuit::new(
+            vec![CircuitLayer::new(vec![
+                Gate::new(GateType::Add, [0, 1]),
+                Gate::new(GateType::Mul, [0, 1]),
+            ])],
+            2,
+        );
+        let input = vec![Fp389::from(3u64), Fp389::from(5u64)];
+        let proof = prove(&circuit, &input).unwrap();
+
+        let oracle = |layer: usize, point: &[Fp389]| {
+            assert_eq!(layer, 0);
+            let [a, b, c] = [point[0], point[1], point[2]];
+            let one = Fp389::one();
+            PredicateEvals {
+                add: (one - a) * (one - b) * c,
+                mul: a * (one - b) * c,
+                ..Default::default()
+            }
+        };
+        verify(&circuit, &input, &proof).unwrap();
+        verify_with_oracle(&circuit, &input, &proof, Some(&oracle)).unwrap();
+
+        let swapped = |layer: usize, point: &[Fp389]| {
+            let evals = oracle(layer, point);
+            PredicateEvals {
+                add: evals.mul,
+                mul: evals.add,
+                ..evals
+            }
+        };
+        assert!(matches!(
+            verify_with_oracle(&circuit, &input, &proof, Some(&swapped)),
+            Err(VerifyError::FinalClaimMismatch { layer: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn prove_in_transcript() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+        let proof = prove(&circuit, &input).unwrap();
+        assert_eq!(
+            prove_in(&mut Transcript::new(TRANSCRIPT_LABEL), &circuit, &input).unwrap(),
+            proof
+        );
+
+        let mut prover = Transcript::new(b"other");
+        prover.record();
+        let other = prove_in(&mut prover, &circuit, &input).unwrap();
+        assert_ne!(other, proof);
+
+        // The verifier draws the very same challenges.
+        let mut verifier = Transcript::new(b"other");
+        verifier.record();
+        verify_in(&mut verifier, &circuit, &input, &other).unwrap();
+        assert_eq!(verifier.squeezes(), prover.squeezes());
+        assert!(!prover.squeezes().is_empty());
+
+        assert!(verify(&circuit, &input, &other).is_err());
+        assert!(verify_in(&mut Transcript::new(b"other"), &circuit, &input, &proof).is_err());
+    }
+
+    /// Circuits of depths two, one and two sharing their input.
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn prove_with_stats() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+
+        let (proof, stats) = super::prove_with_stats(&circuit, &input).unwrap();
+        assert_eq!(proof, prove(&circuit, &input).unwrap());
+        assert_eq!(stats.layer_times.len(), circuit.num_layers());
+    }
+
+    #[test]
+    fn output_claims() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+        let claims = |second: u64| vec![(1, Fp389::from(6u64)), (0, Fp389::from(second))];
+
+        let proof = prove_claims(&circuit, &input, claims(36)).unwrap();
+        assert!(proof.outputs.is_empty());
+        verify_claims(&circuit, &input, &claims(36), &proof).unwrap();
+
+        // The coefficients are drawn after the claims are absorbed.
+        assert!(verify_claims(&circuit, &input, &claims(37), &proof).is_err());
+        assert!(matches!(
+            verify_claims(&circuit, &input, &[(2, Fp389::zero())], &proof),
+            Err(VerifyError::NoSuchOutput { index: 2 })
+        ));
+        assert!(matches!(
+            verify(&circuit, &input, &proof),
+            Err(VerifyError::OutputCount { .. })
+        ));
+
+        // The sum of the honest prover is not a false claim.
+        assert!(matches!(
+            prove_claims(&circuit, &input, claims(37)),
+            Err(crate::Error::ClaimMismatch { layer: 0 })
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    fn instances() -> Vec<(Circuit, Vec<Fp389>)> {
+        let book = circuit_from_book();
+        [
+            book.clone(),
+            book.sub_circuit(1),
+            crate::circuits::inner_product(2),
+        ]
+        .map(|circuit| (circuit, book_input()))
+        .to_vec()
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn multi_proof() {
+        let instances = instances();
+        let proof = Prover::prove_many(&instances, &mut Transcript::new(b"multi")).unwrap();
+        assert_eq!(proof.input_commitments.len(), 1);
+        assert_eq!(proof.input_indices, [0, 0, 0]);
+        assert_eq!(
+            proof.proofs[1].outputs,
+            instances[1].0.outputs(&book_input()).unwrap()
+        );
+        Verifier::verify_many(&instances, &proof, &mut Transcript::new(b"multi")).unwrap();
+
+        // The challenges depend on the other circuits and the labels.
+        assert_ne!(
+            proof.proofs[0],
+            prove(&instances[0].0, &book_input()).unwrap()
+        );
+        assert!(verify_many(&instances, &proof, &mut Transcript::new(b"other")).is_err());
+
+        // Smaller than independent proofs each bound to its input.
+        let independent: usize = instances
+            .iter()
+            .map(|(circuit, input)| {
+                let mut prover = Prover::new(circuit.clone(), input);
+                let commitment = HashCommitment.commit(input).to_vec();
+                prove_with(
+                    &mut Transcript::new(TRANSCRIPT_LABEL),
+                    circuit,
+                    &mut prover,
+                    vec![commitment],
+                )
+                .unwrap()
+                .compressed_size()
+            })
+            .sum();
+        assert!(proof.compressed_size() < independent);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn multi_proof_failures() {
+        let mut instances = instances();
+        let proof = prove_many(&instances, &mut Transcript::new(b"multi")).unwrap();
+
+        let mut tampered = proof.clone();
+        tampered.proofs[1].layers[0].c_1 += Fp389::one();
+        assert!(matches!(
+            verify_many(&instances, &tampered, &mut Transcript::new(b"multi")),
+            Err(MultiVerifyError::Circuit { index: 1, .. })
+        ));
+
+        assert_eq!(
+            verify_many(&instances[..2], &proof, &mut Transcript::new(b"multi")),
+            Err(MultiVerifyError::CircuitCount {
+                expected: 2,
+                got: 3
+            })
+        );
+
+        instances[2].1[0] += Fp389::one();
+        assert_eq!(
+            verify_many(&instances, &proof, &mut Transcript::new(b"multi")),
+            Err(MultiVerifyError::Circuit {
+                index: 2,
+                error: VerifyError::OpeningMismatch
+            })
+        );
+    }
+
+    #[test]
+    fn committed_witness() {
+        let circuit = circuit_from_book().with_public_inputs(1).with_constants(1);
+        let input = book_input();
+        let (public, witness, constants) = (&input[..1], &input[1..3], &input[3..]);
+
+        let (root, tree) = WitnessCommitment::<Sha256>::commit(witness);
+        assert_eq!(tree.num_leaves(), 2);
+        for (index, value) in witness.iter().enumerate() {
+            let path = tree.path(index);
+            assert!(WitnessCommitment::<Sha256>::verify_path(
+                &root, index, value, &path
+            ));
+            assert!(!WitnessCommitment::<Sha256>::verify_path(
+                &root,
+                1 - index,
+                value,
+                &path
+            ));
+        }
+
+        let (proof, opening) = prove_committed_witness(&circuit, &input, &tree).unwrap();
+        assert_eq!(proof.commitments, vec![root.clone()]);
+        assert_eq!(opening.values, witness);
+        verify_committed_witness::<_, Sha256>(&circuit, public, constants, &root, &proof, &opening)
+            .unwrap();
+
+        // The root binds the challenges.
+        assert_eq!(
+            verify_committed_witness::<_, Sha256>(
+                &circuit,
+                public,
+                constants,
+                &WitnessCommitment::<Sha256>::commit(&input[..2]).0,
+                &proof,
+                &opening,
+            ),
+            Err(VerifyError::RootMismatch)
+        );
+        let uncommitted = prove(&circuit, &input).unwrap();
+        assert_eq!(
+            verify_committed_witness::<_, Sha256>(
+                &circuit,
+                public,
+                constants,
+                &root,
+                &uncommitted,
+                &opening,
+            ),
+            Err(VerifyError::RootMismatch)
+        );
+    }
+
+    #[test]
+    fn committed_witness_swapped() {
+        let circuit = circuit_from_book().with_public_inputs(1).with_constants(1);
+        let input = book_input();
+        let mut other = input.clone();
+        other[2] += Fp389::one();
+
+        // A prover committing to one witness and proving about another
+        // opens the witness it proves about, which the paths reject...
+        let (root, tree) = WitnessCommitment::<Sha512>::commit(&input[1..3]);
+        let (proof, opening) = prove_committed_witness(&circuit, &other, &tree).unwrap();
+        assert_eq!(
+            verify_committed_witness::<_, Sha512>(
+                &circuit,
+                &input[..1],
+                &input[3..],
+                &root,
+                &proof,
+                &opening,
+            ),
+            Err(VerifyError::WitnessPathMismatch { index: 1 })
+        );
+
+        // ...or the committed one, which the claim about the input
+        // layer rejects.
+        let mut opening = opening;
+        opening.values = input[1..3].to_vec();
+        assert!(matches!(
+            verify_committed_witness::<_, Sha512>(
+                &circuit,
+                &input[..1],
+                &input[3..],
+                &root,
+                &proof,
+                &opening,
+            ),
+            Err(VerifyError::InputEvaluationMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn committed_witness_from_builder() {
+        // A constant declared between two witnesses still comes after
+        // them in the input layer.
+        let mut builder = CircuitBuilder::new();
+        let x = builder.apply_witness();
+        let five = builder.constant(5);
+        let y = builder.apply_witness();
+        builder.append_mul_gate(x, five).unwrap();
+        builder.append_add_gate(five, y).unwrap();
+
+        let circuit = builder.build_circuit().unwrap();
+        assert_eq!(
+            builder.input_mapping().unwrap().slots,
+            [
+                InputSlot::Witness(x),
+                InputSlot::Witness(y),
+                InputSlot::Const(5)
+            ]
+        );
+        assert_eq!(circuit.num_witnesses(), 2);
+        assert_eq!(circuit.num_constants(), 1);
+
+        let values = [(x, 3u64), (y, 7)];
+        let input: Vec<Fp389> = builder
+            .input_values(|cell| Fp389::from(values.iter().find(|v| v.0 == cell).unwrap().1))
+            .unwrap();
+        assert_eq!(input, [3, 7, 5].map(Fp389::from));
+
+        let (root, tree) = WitnessCommitment::<Sha256>::commit(&input[..2]);
+        let (proof, opening) = prove_committed_witness(&circuit, &input, &tree).unwrap();
+        assert_eq!(opening.values, input[..2]);
+        verify_committed_witness::<_, Sha256>(&circuit, &[], &input[2..], &root, &proof, &opening)
+            .unwrap();
+    }
+
+    #[test]
+    fn committed_proof() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+        let proof = prove_committed(&circuit, &input, &HashCommitment).unwrap();
+
+        assert_eq!(proof.commitments.len(), circuit.num_layers());
+        assert_eq!(
+            proof.commitments.last().unwrap().as_slice(),
+            HashCommitment.commit(&input)
+        );
+        verify_committed(&circuit, &input, &proof, &HashCommitment).unwrap();
+        verify(&circuit, &input, &proof).unwrap();
+
+        // The commitments bind the challenges.
+        let uncommitted = prove(&circuit, &input).unwrap();
+        assert!(uncommitted.commitments.is_empty());
+        assert_ne!(uncommitted.layers, proof.layers);
+        assert_eq!(
+            verify_committed(&circuit, &input, &uncommitted, &HashCommitment),
+            Err(VerifyError::CommitmentCount {
+                expected: 2,
+                got: 0
+            })
+        );
+
+        let mut altered = proof.clone();
+        altered.commitments[0][0] ^= 1;
+        assert!(verify_committed(&circuit, &input, &altered, &HashCommitment).is_err());
+
+        let mut altered = proof.clone();
+        altered.commitments[1] = HashCommitment
+            .commit(&[Fp389::from(4u64), Fp389::from(2u64)])
+            .to_vec();
+        assert_eq!(
+            verify_committed(&circuit, &input, &altered, &HashCommitment),
+            Err(VerifyError::OpeningMismatch)
+        );
+    }
+
+    #[test]
+    fn gf2_proof() {
+        let adder = ripple_carry_adder(8);
+        let bits = |value: u16, n: usize| (0..n).map(move |i| value >> i & 1 == 1);
+        let input: Vec<bool> = bits(200, 8).chain(bits(99, 8)).chain([true]).collect();
+
+        let proof = prove_gf2::<Fp389>(&adder, &input).unwrap();
+        let sum: Vec<Fp389> = bits(300, 9).map(Fp389::from).collect();
+        assert_eq!(proof.outputs, sum);
+        verify_gf2(&adder, &input, &proof).unwrap();
+
+        let mut other = input.clone();
+        other[0] = !other[0];
+        assert!(verify_gf2(&adder, &other, &proof).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extension_challenges() {
+        use crate::goldilocks::{GoldilocksExt2, GoldilocksFp};
+
+        assert!(GoldilocksFp::from(7u64).legendre().is_qnr());
+
+        let circuit = circuit_from_book();
+        let input = [3u64, 2, 3, 1].map(GoldilocksFp::from);
+        let proof = prove_over_extension::<_, GoldilocksExt2>(&circuit, &input).unwrap();
+        assert_eq!(proof.outputs, [36u64, 6].map(GoldilocksExt2::from));
+        // The claims are about points off the base field.
+        assert!(proof.layers.iter().all(|layer| !layer.c_1.c1.is_zero()));
+        verify_over_extension(&circuit, &input, &proof).unwrap();
+
+        let other = [3u64, 2, 3, 2].map(GoldilocksFp::from);
+        assert!(matches!(
+            verify_over_extension(&circuit, &other, &proof),
+            Err(VerifyError::InputEvaluationMismatch { .. })
+        ));
+
+        let circuit = crate::harness::circuit(3, 16);
+        let input = crate::harness::input::<GoldilocksFp>(16);
+        let proof = prove_over_extension::<_, GoldilocksExt2>(&circuit, &input).unwrap();
+        verify_over_extension(&circuit, &input, &proof).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[allow(unexpected_cfgs)]
+    fn extension_of_itself() {
+        use ark_ff::Fp256;
+
+        // `MontConfig` of four limbs checks for the `asm` feature of `ark-ff`.
+        #[derive(MontConfig)]
+        #[modulus = "21888242871839275222246405745257275088548364400416034343698204186575808495617"]
+        #[generator = "5"]
+        struct Bn254Config;
+
+        type Bn254 = Fp256<MontBackend<Bn254Config, 4>>;
+
+        let circuit = crate::harness::circuit(3, 16);
+        let input = crate::harness::input::<Bn254>(16);
+        let proof = prove_over_extension::<Bn254, Bn254>(&circuit, &input).unwrap();
+        assert_eq!(proof, prove(&circuit, &input).unwrap());
+        verify_over_extension(&circuit, &input, &proof).unwrap();
+
+        let circuit = circuit_from_book();
+        assert_eq!(
+            prove_over_extension::<Fp389, Fp389>(&circuit, &book_input()).unwrap(),
+            prove(&circuit, &book_input()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_spans() {
+        use std::sync::{Arc, Mutex};
+        use tracing::{
+            field::{Field, Visit},
+            span, Event, Metadata, Subscriber,
+        };
+
+        /// A span by its name, its parent and its integer fields.
+        #[derive(Debug, Default)]
+        struct Recorded {
+            name: &'static str,
+            parent: Option<usize>,
+            fields: Vec<(&'static str, u64)>,
+        }
+
+        impl Visit for Recorded {
+            fn record_u64(&mut self, field: &Field, value: u64) {
+                self.fields.push((field.name(), value));
+            }
+
+            fn record_debug(&mut self, _: &Field, _: &dyn core::fmt::Debug) {}
+        }
+
+        /// Spans and progress events by the index of their spans.
+        #[derive(Default)]
+        struct Log {
+            spans: Vec<Recorded>,
+            progress: Vec<Recorded>,
+            entered: Vec<usize>,
+        }
+
+        struct Recorder(Arc<Mutex<Log>>);
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+                let mut log = self.0.lock().unwrap();
+                let mut span = Recorded {
+                    name: attrs.metadata().name(),
+                    parent: log.entered.last().copied(),
+                    ..Default::default()
+                };
+                attrs.record(&mut span);
+                log.spans.push(span);
+                span::Id::from_u64(log.spans.len() as u64)
+            }
+
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut log = self.0.lock().unwrap();
+                if event.metadata().target() == "gkr.progress" {
+                    let mut progress = Recorded {
+                        parent: log.entered.last().copied(),
+                        ..Default::default()
+                    };
+                    event.record(&mut progress);
+                    log.progress.push(progress);
+                }
+            }
+
+            fn enter(&self, id: &span::Id) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .entered
+                    .push(id.into_u64() as usize - 1);
+            }
+
+            fn exit(&self, _: &span::Id) {
+                self.0.lock().unwrap().entered.pop();
+            }
+        }
+
+        let circuit = crate::harness::circuit(3, 8);
+        let input = crate::harness::input::<Fp389>(8);
+        let log = Arc::new(Mutex::new(Log::default()));
+        let proof = tracing::subscriber::with_default(Recorder(log.clone()), || {
+            prove(&circuit, &input).unwrap()
+        });
+        verify(&circuit, &input, &proof).unwrap();
+
+        let log = log.lock().unwrap();
+        let field = |span: &Recorded, name| {
+            span.fields
+                .iter()
+                .find_map(|(field, value)| (*field == name).then_some(*value))
+        };
+        let children = |parent, name| {
+            log.spans
+                .iter()
+                .filter(move |span| span.parent == Some(parent) && span.name == name)
+        };
+
+        let layers: Vec<_> = (0..log.spans.len())
+            .filter(|i| log.spans[*i].name == "gkr.layer")
+            .collect();
+        assert_eq!(layers.len(), 3);
+        for (i, layer) in layers.iter().enumerate() {
+            let span = &log.spans[*layer];
+            assert_eq!(span.parent, None);
+            assert_eq!(field(span, "layer"), Some(i as u64));
+            assert_eq!(field(span, "width"), Some(8));
+
+            assert_eq!(children(*layer, "gkr.wiring").count(), 1);
+            let rounds: Vec<_> = children(*layer, "gkr.round")
+                .map(|round| field(round, "round").unwrap())
+                .collect();
+            assert_eq!(rounds, (0..6).collect::<Vec<_>>());
+            assert!(children(*layer, "gkr.transcript").count() > 0);
+        }
+        assert!(log
+            .spans
+            .iter()
+            .enumerate()
+            .filter(|(_, span)| span.name == "gkr.round")
+            .all(|(round, _)| children(round, "gkr.transcript").count() > 0));
+
+        // Progress within each round, all of the hypercube by the last.
+        assert_eq!(log.progress.len(), 3 * 6);
+        for progress in log.progress.chunks(6) {
+            let percents: Vec<_> = progress
+                .iter()
+                .map(|event| field(event, "percent").unwrap())
+                .collect();
+            assert_eq!(percents, [50, 76, 88, 95, 98, 100]);
+            assert!(progress
+                .iter()
+                .all(|event| log.spans[event.parent.unwrap()].name == "gkr.round"));
+        }
+    }
+}
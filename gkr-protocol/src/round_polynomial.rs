@@ -1,15 +1,89 @@
 //! The polynomial $f^{(i)}_{r_i}$ used to run Sum-Check
 //! at the $i$-th step of the GKR protocol.
 
-use std::cmp;
+use alloc::{vec, vec::Vec};
+use core::cmp;
 
-use ark_ff::{FftField, Field};
-use ark_poly::{
-    univariate, DenseMultilinearExtension, EvaluationDomain, Evaluations, GeneralEvaluationDomain,
-    MultilinearExtension,
-};
+use ark_ff::Field;
+use ark_poly::{univariate, DenseMultilinearExtension, MultilinearExtension};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use sum_check_protocol::SumCheckPolynomial;
 
+use crate::{circuit::GateType, uni_poly::IntegerDomain};
+
+/// The evaluation table of $\tilde{W}_{i+1}$ kept between the rounds
+/// of a Sum-Check run and folded in place as the variables get fixed,
+/// the lowest one first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayerMleCache<F: Field> {
+    evaluations: Vec<F>,
+    num_vars: usize,
+}
+
+impl<F: Field> LayerMleCache<F> {
+    /// Cache the values of a layer padded with zeroes up to
+    /// `1 << num_vars` evaluations.
+    pub fn new(num_vars: usize, values: &[F]) -> Self {
+        let mut evaluations = values.to_vec();
+        evaluations.resize(1 << num_vars, F::zero());
+
+        Self {
+            evaluations,
+            num_vars,
+        }
+    }
+
+    /// Fix the lowest unfixed variable at `r`, halving the table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all the variables are already fixed.
+    pub fn fix_next(&mut self, r: F) {
+        assert!(self.num_vars > 0, "all the variables are fixed");
+
+        let half = self.evaluations.len() / 2;
+        for k in 0..half {
+            let (lo, hi) = (self.evaluations[2 * k], self.evaluations[2 * k + 1]);
+            self.evaluations[k] = lo + r * (hi - lo);
+        }
+        self.evaluations.truncate(half);
+        self.num_vars -= 1;
+    }
+
+    /// A copy of the cache with the lowest `partial_point.len()`
+    /// variables fixed.
+    pub fn fix_variables(&self, partial_point: &[F]) -> Self {
+        let mut res = self.clone();
+        for r in partial_point {
+            res.fix_next(*r);
+        }
+
+        res
+    }
+
+    /// Evaluate at `point`, `None` if its dimension does not match.
+    pub fn evaluate(&self, point: &[F]) -> Option<F> {
+        (point.len() == self.num_vars).then(|| self.fix_variables(point).evaluations[0])
+    }
+
+    /// The number of variables left unfixed.
+    pub fn num_vars(&self) -> usize {
+        self.num_vars
+    }
+
+    /// The evaluations over the boolean hypercube of the variables
+    /// left unfixed.
+    pub fn evaluations(&self) -> &[F] {
+        &self.evaluations
+    }
+
+    /// The multilinear extension of the evaluations.
+    pub fn to_mle(&self) -> DenseMultilinearExtension<F> {
+        DenseMultilinearExtension::from_evaluations_slice(self.num_vars, &self.evaluations)
+    }
+}
+
 /// A $2k_{i+1}$ variate polynomial used for each step of GKR protocol.
 ///
 /// $$
@@ -17,46 +91,95 @@ use sum_check_protocol::SumCheckPolynomial;
 /// \widetilde{add}_i(r_i, b, c)(\tilde{W}\_{i+1}(b) +
 /// \tilde{W}\_{i+1}(c)) +
 /// \widetilde{mul}_i(r_i, b, c)(\tilde{W}\_{i+1}(b) \cdot
+/// \tilde{W}\_{i+1}(c)) +
+/// \widetilde{sub}_i(r_i, b, c)(\tilde{W}\_{i+1}(b) -
 /// \tilde{W}\_{i+1}(c))
 /// $$
+///
+/// Only the predicates of the gate types present in the layer are
+/// kept, the others vanish identically.
 #[derive(Clone)]
 pub struct W<F: Field> {
-    add_i: DenseMultilinearExtension<F>,
-    mul_i: DenseMultilinearExtension<F>,
-    w_b: DenseMultilinearExtension<F>,
-    w_c: DenseMultilinearExtension<F>,
+    predicates: Vec<(GateType, DenseMultilinearExtension<F>)>,
+    w_b: LayerMleCache<F>,
+    w_c: LayerMleCache<F>,
+
+    /// The points $0, 1, \ldots, d$ for the degree $d$ of the
+    /// polynomial in each variable.
+    domain: IntegerDomain<F>,
 }
 
 impl<F: Field> W<F> {
-    /// Create a new `W` polynomial.
+    /// Create a new `W` polynomial from the wiring predicates of
+    /// the gate types present in the layer and its `degree` in each
+    /// variable, see [`Circuit::layer_degree`].
+    ///
+    /// [`Circuit::layer_degree`]: crate::circuit::Circuit::layer_degree
     pub fn new(
-        add_i: DenseMultilinearExtension<F>,
-        mul_i: DenseMultilinearExtension<F>,
-        w_b: DenseMultilinearExtension<F>,
-        w_c: DenseMultilinearExtension<F>,
+        predicates: Vec<(GateType, DenseMultilinearExtension<F>)>,
+        w_b: LayerMleCache<F>,
+        w_c: LayerMleCache<F>,
+        degree: usize,
     ) -> Self {
         Self {
-            add_i,
-            mul_i,
+            predicates,
             w_b,
             w_c,
+            domain: IntegerDomain::new(degree),
         }
     }
+
+    /// The sum of the evaluations over the hypercube.
+    ///
+    /// The contributions of every value of $c$ are independent, with
+    /// the `rayon` feature they are accumulated in parallel. They are
+    /// summed in the order of $c$ either way, so that the result does
+    /// not depend on the scheduling.
+    fn sum_evaluations(&self) -> F {
+        let w_b_evals = self.w_b.evaluations();
+        let w_c_evals = self.w_c.evaluations();
+        let predicate_evals: Vec<_> = self
+            .predicates
+            .iter()
+            .map(|(ttype, predicate)| (*ttype, predicate.to_evaluations()))
+            .collect();
+
+        let column = |c_idx: usize| -> F {
+            w_b_evals
+                .iter()
+                .enumerate()
+                .map(|(b_idx, w_b_item)| {
+                    let bc_idx = idx(c_idx, b_idx, self.w_b.num_vars());
+                    predicate_evals
+                        .iter()
+                        .map(|(ttype, evals)| {
+                            evals[bc_idx] * ttype.evaluate(*w_b_item, w_c_evals[c_idx])
+                        })
+                        .sum::<F>()
+                })
+                .sum()
+        };
+
+        #[cfg(feature = "rayon")]
+        let columns: Vec<F> = (0..w_c_evals.len()).into_par_iter().map(column).collect();
+        #[cfg(not(feature = "rayon"))]
+        let columns: Vec<F> = (0..w_c_evals.len()).map(column).collect();
+
+        columns.into_iter().sum()
+    }
 }
 
-impl<F: FftField> SumCheckPolynomial<F> for W<F> {
+impl<F: Field> SumCheckPolynomial<F> for W<F> {
     fn evaluate(&self, point: &[F]) -> Option<F> {
-        let (b, c) = point.split_at({
-            let this = &self.w_b;
-            this.num_vars
-        });
-        let add_e = self.add_i.evaluate(point)?;
-        let mul_e = self.mul_i.evaluate(point)?;
+        let (b, c) = point.split_at(self.w_b.num_vars());
 
         let w_b = self.w_b.evaluate(b)?;
         let w_c = self.w_c.evaluate(c)?;
 
-        Some(add_e * (w_b + w_c) + mul_e * (w_b * w_c))
+        self.predicates
+            .iter()
+            .map(|(ttype, predicate)| Some(predicate.evaluate(point)? * ttype.evaluate(w_b, w_c)))
+            .sum()
     }
 
     fn fix_variables(&self, partial_point: &[F]) -> Self {
@@ -65,45 +188,58 @@ impl<F: FftField> SumCheckPolynomial<F> for W<F> {
             .unwrap_or(&[]);
         let c_partial = partial_point.get(self.w_b.num_vars()..).unwrap_or(&[]);
 
-        let add_i = self.add_i.fix_variables(partial_point);
-        let mul_i = self.mul_i.fix_variables(partial_point);
+        let predicates = self
+            .predicates
+            .iter()
+            .map(|(ttype, predicate)| (*ttype, predicate.fix_variables(partial_point)))
+            .collect();
         let w_b = self.w_b.fix_variables(b_partial);
         let w_c = self.w_c.fix_variables(c_partial);
 
         Self {
-            add_i,
-            mul_i,
+            predicates,
             w_b,
             w_c,
+            domain: self.domain.clone(),
         }
     }
 
-    fn to_univariate(&self) -> univariate::SparsePolynomial<F> {
-        let domain = GeneralEvaluationDomain::new(3).unwrap();
+    fn fix_next(&mut self, r: F) {
+        for (_, predicate) in &mut self.predicates {
+            *predicate = predicate.fix_variables(&[r]);
+        }
 
-        let evals = domain
-            .elements()
-            .map(|e| self.fix_variables(&[e]).to_evaluations().into_iter().sum())
-            .collect();
+        if self.w_b.num_vars() > 0 {
+            self.w_b.fix_next(r);
+        } else {
+            self.w_c.fix_next(r);
+        }
+    }
 
-        let evaluations = Evaluations::from_vec_and_domain(evals, domain);
-        let p = evaluations.interpolate();
+    fn to_univariate(&self) -> univariate::SparsePolynomial<F> {
+        // Just as many points as the degree takes.
+        let evals: Vec<F> = (0..=self.domain.degree() as u64)
+            .map(|t| self.fix_variables(&[F::from(t)]).sum_evaluations())
+            .collect();
 
-        p.into()
+        self.domain.interpolate(&evals).into()
     }
 
     fn num_vars(&self) -> usize {
-        self.add_i.num_vars()
+        self.w_b.num_vars() + self.w_c.num_vars()
     }
 
     fn to_evaluations(&self) -> Vec<F> {
         // combine the evaluations of separate multilinear
         // extensions into a vector of evaluations of the
         // whole polynomial
-        let w_b_evals = self.w_b.to_evaluations();
-        let w_c_evals = self.w_c.to_evaluations();
-        let add_i_evals = self.add_i.to_evaluations();
-        let mul_i_evals = self.mul_i.to_evaluations();
+        let w_b_evals = self.w_b.evaluations();
+        let w_c_evals = self.w_c.evaluations();
+        let predicate_evals: Vec<_> = self
+            .predicates
+            .iter()
+            .map(|(ttype, predicate)| (*ttype, predicate.to_evaluations()))
+            .collect();
 
         let mut res = vec![];
         for (b_idx, w_b_item) in w_b_evals.iter().enumerate() {
@@ -111,8 +247,10 @@ impl<F: FftField> SumCheckPolynomial<F> for W<F> {
                 let bc_idx = idx(c_idx, b_idx, self.w_b.num_vars());
 
                 res.push(
-                    add_i_evals[bc_idx] * (*w_b_item + w_c_item)
-                        + mul_i_evals[bc_idx] * (*w_b_item * w_c_item),
+                    predicate_evals
+                        .iter()
+                        .map(|(ttype, evals)| evals[bc_idx] * ttype.evaluate(*w_b_item, *w_c_item))
+                        .sum(),
                 );
             }
         }
@@ -126,3 +264,56 @@ impl<F: FftField> SumCheckPolynomial<F> for W<F> {
 fn idx(i: usize, j: usize, num_vars: usize) -> usize {
     (i << num_vars) | j
 }
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use ark_ff::{Fp64, MontBackend, MontConfig, Zero};
+    use ark_poly::{DenseMultilinearExtension, MultilinearExtension, Polynomial};
+    use ark_std::{test_rng, UniformRand};
+    use sum_check_protocol::SumCheckPolynomial;
+
+    use super::{LayerMleCache, W};
+    use crate::circuit::GateType;
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    /// The round polynomials are those of the sequential sum of the
+    /// evaluations, with the `rayon` feature or without.
+    #[test]
+    fn round_polynomials() {
+        let rng = &mut test_rng();
+        let num_vars = 3;
+        let values: Vec<Fp389> = (0..1 << num_vars).map(|_| Fp389::rand(rng)).collect();
+        let predicates = [GateType::Add, GateType::Mul, GateType::Sub]
+            .map(|ttype| (ttype, DenseMultilinearExtension::rand(2 * num_vars, rng)))
+            .to_vec();
+        let mut w = W::new(
+            predicates,
+            LayerMleCache::new(num_vars, &values),
+            LayerMleCache::new(num_vars, &values[..5]),
+            2,
+        );
+
+        for _ in 0..2 * num_vars {
+            let p = w.to_univariate();
+            for t in 0..=2u64 {
+                let t = Fp389::from(t);
+                let sum = w
+                    .fix_variables(&[t])
+                    .to_evaluations()
+                    .into_iter()
+                    .fold(Fp389::zero(), |sum, eval| sum + eval);
+                assert_eq!(p.evaluate(&t), sum);
+            }
+            w.fix_next(Fp389::rand(rng));
+        }
+    }
+}
@@ -0,0 +1,520 @@
+//! Running the protocol interactively.
+//!
+//! Instead of squeezing the challenges out of a [`Transcript`], the
+//! prover and the verifier exchange typed [`Message`]s over an
+//! in-memory [`Channel`] driven by [`simulate`]. An observer may be
+//! handed to the driver to watch every message as it is delivered.
+//!
+//! Both sides are traits, so a prover may be wrapped to tamper with
+//! some of its messages and see where the verifier rejects them.
+//!
+//! [`Transcript`]: crate::transcript::Transcript
+
+use alloc::{collections::VecDeque, vec, vec::Vec};
+
+use ark_ff::Field;
+use ark_poly::univariate;
+use ark_std::rand::Rng;
+use sum_check_protocol::VerifierRoundResult as SumCheckVerifierRoundResult;
+
+use crate::{Error, Prover, ProverMessage, Verifier, VerifierMessage, VerifierState};
+
+/// A claim of the prover opening a part of the protocol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LayerClaim<F> {
+    /// The outputs of the circuit, opening the protocol.
+    Outputs(Vec<F>),
+
+    /// The values of single outputs by their indices, opening the
+    /// protocol instead, see [`Prover::with_output_claims`].
+    OutputClaims(Vec<(usize, F)>),
+
+    /// The sum $c_1$ opening the Sum-Check protocol about a layer.
+    Sum {
+        /// The layer of the claim.
+        layer: usize,
+
+        /// The claimed sum.
+        c_1: F,
+
+        /// The number of variables of the Sum-Check protocol.
+        num_vars: usize,
+    },
+}
+
+/// A polynomial of a round of the Sum-Check protocol about a layer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SumcheckPoly<F: Field> {
+    /// The layer of the Sum-Check protocol.
+    pub layer: usize,
+
+    /// The round of the Sum-Check protocol.
+    pub round: usize,
+
+    /// The polynomial of the round.
+    pub p: univariate::SparsePolynomial<F>,
+
+    /// The restriction $q$ of $\tilde{W}_{i+1}$ to a line, sent
+    /// along with the polynomial of the last round.
+    pub q: Option<univariate::SparsePolynomial<F>>,
+}
+
+/// A random challenge of the verifier.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Challenge<F> {
+    /// The random point of a round of the Sum-Check protocol.
+    Round {
+        /// The layer of the Sum-Check protocol.
+        layer: usize,
+
+        /// The round of the Sum-Check protocol.
+        round: usize,
+
+        /// The random point.
+        r: F,
+    },
+
+    /// The point $r_i$ of the next claim about layer $i$, the
+    /// inputs being the layer after the last one.
+    Point {
+        /// The layer of the claim.
+        layer: usize,
+
+        /// $r_i$.
+        r: Vec<F>,
+    },
+
+    /// The coefficients of the claims about single outputs in the
+    /// claim about layer $0$.
+    Coefficients(Vec<F>),
+}
+
+/// A message exchanged in an interactive run of the protocol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message<F: Field> {
+    /// A claim of the prover.
+    LayerClaim(LayerClaim<F>),
+
+    /// A Sum-Check polynomial of the prover.
+    SumcheckPoly(SumcheckPoly<F>),
+
+    /// A challenge of the verifier.
+    Challenge(Challenge<F>),
+}
+
+/// The prover side of an interactive run.
+pub trait ProverRound<F: Field> {
+    /// The claim about the outputs opening the protocol.
+    fn begin(&mut self) -> LayerClaim<F>;
+
+    /// Answer a challenge of the verifier, possibly by nothing.
+    fn respond(&mut self, challenge: &Challenge<F>) -> Vec<Message<F>>;
+}
+
+/// The verifier side of an interactive run.
+pub trait VerifierRound<F: Field> {
+    /// Check a message of the prover and answer it, possibly by
+    /// nothing.
+    ///
+    /// Fails if the message is rejected.
+    fn receive<R: Rng>(
+        &mut self,
+        msg: &Message<F>,
+        rng: &mut R,
+    ) -> Result<Vec<Challenge<F>>, Error>;
+}
+
+/// An in-memory channel delivering messages in the order they are
+/// sent.
+#[derive(Clone, Debug)]
+pub struct Channel<F: Field> {
+    queue: VecDeque<Message<F>>,
+    delivered: usize,
+}
+
+impl<F: Field> Default for Channel<F> {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            delivered: 0,
+        }
+    }
+}
+
+impl<F: Field> Channel<F> {
+    /// Create a new empty `Channel`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send a message.
+    pub fn send(&mut self, msg: Message<F>) {
+        self.queue.push_back(msg);
+    }
+
+    /// Receive the oldest message not yet delivered.
+    pub fn recv(&mut self) -> Option<Message<F>> {
+        let msg = self.queue.pop_front()?;
+        self.delivered += 1;
+        Some(msg)
+    }
+
+    /// The number of messages delivered so far.
+    pub fn delivered(&self) -> usize {
+        self.delivered
+    }
+}
+
+/// A callback watching the messages of [`simulate`].
+pub type Observer<'a, F> = &'a mut dyn FnMut(&Message<F>);
+
+/// A message rejected by the verifier in [`simulate`].
+#[derive(Debug, thiserror::Error)]
+#[error("message {index} rejected: {error}")]
+pub struct Rejection<F: Field> {
+    /// The position of the message among the delivered ones.
+    pub index: usize,
+
+    /// The rejected message.
+    pub message: Message<F>,
+
+    /// The reason of the rejection.
+    pub error: Error,
+}
+
+/// Run the protocol between `prover` and `verifier`, the challenges
+/// drawn from `rng`.
+///
+/// Every message is handed to `observer`, if any, before it is
+/// delivered. Stops at the first rejected message, otherwise once
+/// neither side has anything left to say, after which the verifier
+/// is left to check the inputs.
+pub fn simulate<F, P, V, R>(
+    prover: &mut P,
+    verifier: &mut V,
+    rng: &mut R,
+    mut observer: Option<Observer<'_, F>>,
+) -> Result<(), Rejection<F>>
+where
+    F: Field,
+    P: ProverRound<F>,
+    V: VerifierRound<F>,
+    R: Rng,
+{
+    let mut channel = Channel::new();
+    channel.send(Message::LayerClaim(prover.begin()));
+
+    while let Some(msg) = channel.recv() {
+        if let Some(observer) = observer.as_mut() {
+            observer(&msg);
+        }
+
+        match &msg {
+            Message::Challenge(challenge) => {
+                for answer in prover.respond(challenge) {
+                    channel.send(answer);
+                }
+            }
+            _ => match verifier.receive(&msg, rng) {
+                Ok(challenges) => {
+                    for challenge in challenges {
+                        channel.send(Message::Challenge(challenge));
+                    }
+                }
+                Err(error) => {
+                    return Err(Rejection {
+                        index: channel.delivered() - 1,
+                        message: msg,
+                        error,
+                    })
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+impl<F: Field> Prover<F> {
+    /// The message of `round` of the Sum-Check protocol about the
+    /// current layer.
+    fn sumcheck_poly(&mut self, round: usize) -> Message<F> {
+        let (p, q) = match self.round_msg(round) {
+            ProverMessage::SumCheckProverMessage { p } => (p, None),
+            ProverMessage::FinalRoundMessage { p, q } => (p, Some(q)),
+            _ => unreachable!(),
+        };
+
+        Message::SumcheckPoly(SumcheckPoly {
+            layer: self.i,
+            round,
+            p,
+            q,
+        })
+    }
+}
+
+impl<F: Field> Prover<F> {
+    /// The claim of the Sum-Check protocol `start` about a layer
+    /// along with its first polynomial.
+    fn open_layer(&mut self, start: ProverMessage<F>) -> Vec<Message<F>> {
+        let claim = match start {
+            ProverMessage::StartSumCheck {
+                c_1,
+                round,
+                num_vars,
+            } => LayerClaim::Sum {
+                layer: round,
+                c_1,
+                num_vars,
+            },
+            _ => unreachable!(),
+        };
+
+        vec![Message::LayerClaim(claim), self.sumcheck_poly(0)]
+    }
+}
+
+impl<F: Field> ProverRound<F> for Prover<F> {
+    fn begin(&mut self) -> LayerClaim<F> {
+        match self.start_protocol() {
+            ProverMessage::Begin { circuit_outputs } => LayerClaim::Outputs(circuit_outputs),
+            ProverMessage::BeginClaims { claims } => LayerClaim::OutputClaims(claims),
+            _ => unreachable!(),
+        }
+    }
+
+    fn respond(&mut self, challenge: &Challenge<F>) -> Vec<Message<F>> {
+        match challenge {
+            Challenge::Point { layer, .. } if *layer == self.circuit.num_layers() => vec![],
+            Challenge::Point { layer, r } => {
+                let start = self.start_round(*layer, r);
+                self.open_layer(start)
+            }
+            Challenge::Coefficients(coefficients) => {
+                let start = self.start_claims_round(coefficients);
+                self.open_layer(start)
+            }
+            Challenge::Round { round, r, .. } => {
+                self.receive_verifier_msg(VerifierMessage::SumCheckRoundResult {
+                    res: SumCheckVerifierRoundResult::JthRound(*r),
+                });
+
+                // The last polynomial waits for the point of the
+                // last round to restrict $\tilde{W}_{i+1}$ to a line.
+                let num_rounds = 2 * self.circuit.num_vars_at(self.i + 1).unwrap();
+                match round + 1 {
+                    next if next < num_rounds - 1 => vec![self.sumcheck_poly(next)],
+                    next if next == num_rounds => vec![self.sumcheck_poly(next - 1)],
+                    _ => vec![],
+                }
+            }
+        }
+    }
+}
+
+impl<F: Field> VerifierRound<F> for Verifier<F> {
+    fn receive<R: Rng>(
+        &mut self,
+        msg: &Message<F>,
+        rng: &mut R,
+    ) -> Result<Vec<Challenge<F>>, Error> {
+        let msg = match msg {
+            Message::LayerClaim(LayerClaim::Outputs(outputs)) => ProverMessage::Begin {
+                circuit_outputs: outputs.clone(),
+            },
+            Message::LayerClaim(LayerClaim::OutputClaims(claims)) => ProverMessage::BeginClaims {
+                claims: claims.clone(),
+            },
+            Message::LayerClaim(LayerClaim::Sum {
+                layer,
+                c_1,
+                num_vars,
+            }) => ProverMessage::StartSumCheck {
+                c_1: *c_1,
+                round: *layer,
+                num_vars: *num_vars,
+            },
+            Message::SumcheckPoly(SumcheckPoly { p, q: None, .. }) => {
+                ProverMessage::SumCheckProverMessage { p: p.clone() }
+            }
+            Message::SumcheckPoly(SumcheckPoly { p, q: Some(q), .. }) => {
+                ProverMessage::FinalRoundMessage {
+                    p: p.clone(),
+                    q: q.clone(),
+                }
+            }
+            Message::Challenge(_) => return Err(Error::WrongVerifierState),
+        };
+
+        // The layer and the round are the verifier's own, whatever
+        // the prover labels its messages with.
+        let (layer, round) = match &self.state {
+            VerifierState::RunningSumCheck { bc, .. } => (self.r.len() - 1, bc.len()),
+            VerifierState::Empty => (0, 0),
+        };
+
+        match self.receive_prover_msg(msg, rng)? {
+            VerifierMessage::R { r } => Ok(vec![Challenge::Point {
+                layer: self.r.len() - 1,
+                r,
+            }]),
+            VerifierMessage::Coefficients { coefficients } => {
+                Ok(vec![Challenge::Coefficients(coefficients)])
+            }
+            VerifierMessage::SumCheckRoundResult {
+                res: SumCheckVerifierRoundResult::JthRound(r),
+            } => {
+                let mut challenges = vec![Challenge::Round { layer, round, r }];
+
+                let num_rounds = 2 * self.circuit.num_vars_at(layer + 1).unwrap();
+                if round + 2 == num_rounds {
+                    if let VerifierMessage::SumCheckRoundResult {
+                        res: SumCheckVerifierRoundResult::JthRound(r),
+                    } = self.final_random_point(rng)?
+                    {
+                        challenges.push(Challenge::Round {
+                            layer,
+                            round: round + 1,
+                            r,
+                        });
+                    }
+                }
+
+                Ok(challenges)
+            }
+            _ => Ok(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use ark_ff::{Fp64, MontBackend, MontConfig, One};
+    use ark_poly::univariate::SparsePolynomial;
+    use ark_std::test_rng;
+    use pretty_assertions::assert_eq;
+
+    use super::{simulate, Challenge, LayerClaim, Message, ProverRound, SumcheckPoly};
+    use crate::{circuit::circuit_from_book, Error, Prover, Verifier};
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    /// A prover adding one to the polynomial of a single round.
+    struct Perturbed {
+        honest: Prover<Fp389>,
+        layer: usize,
+        round: usize,
+    }
+
+    impl ProverRound<Fp389> for Perturbed {
+        fn begin(&mut self) -> LayerClaim<Fp389> {
+            self.honest.begin()
+        }
+
+        fn respond(&mut self, challenge: &Challenge<Fp389>) -> Vec<Message<Fp389>> {
+            let mut messages = self.honest.respond(challenge);
+            for msg in &mut messages {
+                if let Message::SumcheckPoly(SumcheckPoly {
+                    layer, round, p, ..
+                }) = msg
+                {
+                    if (*layer, *round) == (self.layer, self.round) {
+                        *p =
+                            &*p + &SparsePolynomial::from_coefficients_vec(vec![(0, Fp389::one())]);
+                    }
+                }
+            }
+            messages
+        }
+    }
+
+    fn book_input() -> Vec<Fp389> {
+        [3u64, 2, 3, 1].into_iter().map(Fp389::from).collect()
+    }
+
+    #[test]
+    fn honest_round_trip() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+        let mut prover = Prover::new(circuit.clone(), &input);
+        let mut verifier = Verifier::new(circuit.clone());
+
+        let mut messages = vec![];
+        let mut observer = |msg: &Message<Fp389>| messages.push(msg.clone());
+        simulate(
+            &mut prover,
+            &mut verifier,
+            &mut test_rng(),
+            Some(&mut observer),
+        )
+        .unwrap();
+        assert!(verifier.check_input(&input));
+
+        assert_eq!(
+            messages[0],
+            Message::LayerClaim(LayerClaim::Outputs(vec![36u64.into(), 6u64.into()]))
+        );
+        assert!(matches!(
+            messages.last(),
+            Some(Message::Challenge(Challenge::Point { layer: 2, .. }))
+        ));
+
+        for layer in 0..circuit.num_layers() {
+            let rounds: Vec<_> = messages
+                .iter()
+                .filter_map(|msg| match msg {
+                    Message::SumcheckPoly(poly) if poly.layer == layer => Some(poly.round),
+                    _ => None,
+                })
+                .collect();
+            let num_rounds = 2 * circuit.num_vars_at(layer + 1).unwrap();
+            assert_eq!(rounds, (0..num_rounds).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn perturbed_round_is_rejected() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+        let mut prover = Perturbed {
+            honest: Prover::new(circuit.clone(), &input),
+            layer: 1,
+            round: 3,
+        };
+        let mut verifier = Verifier::new(circuit);
+
+        let mut messages = vec![];
+        let mut observer = |msg: &Message<Fp389>| messages.push(msg.clone());
+        let rejection = simulate(
+            &mut prover,
+            &mut verifier,
+            &mut test_rng(),
+            Some(&mut observer),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            rejection.message,
+            Message::SumcheckPoly(SumcheckPoly {
+                layer: 1,
+                round: 3,
+                ..
+            })
+        ));
+        assert!(matches!(
+            rejection.error,
+            Error::SumMismatch { layer: 1, round: 3 }
+        ));
+        assert_eq!(rejection.index, messages.len() - 1);
+        assert_eq!(messages.last(), Some(&rejection.message));
+    }
+}
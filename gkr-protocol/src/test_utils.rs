@@ -0,0 +1,182 @@
+//! Fault injection for soundness tests.
+
+use alloc::{vec, vec::Vec};
+
+use ark_ff::Field;
+use ark_poly::univariate::SparsePolynomial;
+
+use crate::{
+    simulation::{Challenge, LayerClaim, Message, ProverRound, SumcheckPoly},
+    Prover,
+};
+
+/// A single coefficient of a single round polynomial to perturb.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct FaultSpec<F> {
+    /// The layer of the Sum-Check protocol.
+    pub layer: usize,
+
+    /// The round of the Sum-Check protocol.
+    pub round: usize,
+
+    /// The degree of the perturbed coefficient.
+    pub coefficient: usize,
+
+    /// The offset added to the coefficient.
+    pub offset: F,
+}
+
+impl<F: Field> FaultSpec<F> {
+    /// Whether the polynomial of `round` of `layer` is perturbed.
+    pub fn targets(&self, layer: usize, round: usize) -> bool {
+        (self.layer, self.round) == (layer, round)
+    }
+
+    /// Add the offset to the coefficient of `p`.
+    pub fn apply(&self, p: &mut SparsePolynomial<F>) {
+        let fault = SparsePolynomial::from_coefficients_vec(vec![(self.coefficient, self.offset)]);
+        *p = &*p + &fault;
+    }
+}
+
+/// The honest [`Prover`] but for a single faulty round polynomial.
+pub(crate) struct FaultyProver<F: Field> {
+    honest: Prover<F>,
+    fault: FaultSpec<F>,
+}
+
+impl<F: Field> FaultyProver<F> {
+    /// Wrap the `honest` prover.
+    pub fn new(honest: Prover<F>, fault: FaultSpec<F>) -> Self {
+        Self { honest, fault }
+    }
+}
+
+impl<F: Field> ProverRound<F> for FaultyProver<F> {
+    fn begin(&mut self) -> LayerClaim<F> {
+        self.honest.begin()
+    }
+
+    fn respond(&mut self, challenge: &Challenge<F>) -> Vec<Message<F>> {
+        let mut messages = self.honest.respond(challenge);
+        for msg in &mut messages {
+            if let Message::SumcheckPoly(SumcheckPoly {
+                layer, round, p, ..
+            }) = msg
+            {
+                if self.fault.targets(*layer, *round) {
+                    self.fault.apply(p);
+                }
+            }
+        }
+
+        messages
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use alloc::{format, vec, vec::Vec};
+
+    use ark_ff::{Fp64, MontBackend, MontConfig, Zero};
+    use ark_std::test_rng;
+
+    use super::{FaultSpec, FaultyProver};
+    use crate::{
+        circuit::{circuit_from_book, Circuit},
+        proof::{prove, verify},
+        simulation::{simulate, Message, SumcheckPoly},
+        Prover, Verifier,
+    };
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    /// Every single coefficient fault of the round polynomials of
+    /// the proof on the book circuit, the polynomials being of
+    /// degree at most two.
+    fn faults(circuit: &Circuit, offset: Fp389) -> Vec<FaultSpec<Fp389>> {
+        let mut faults = vec![];
+        for layer in 0..circuit.num_layers() {
+            for round in 0..2 * circuit.num_vars_at(layer + 1).unwrap() {
+                for coefficient in 0..3 {
+                    faults.push(FaultSpec {
+                        layer,
+                        round,
+                        coefficient,
+                        offset,
+                    });
+                }
+            }
+        }
+
+        faults
+    }
+
+    fn book_input() -> Vec<Fp389> {
+        [3u64, 2, 3, 1].into_iter().map(Fp389::from).collect()
+    }
+
+    #[test]
+    fn faulty_rounds_are_rejected() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+
+        for fault in faults(&circuit, Fp389::from(5u64)) {
+            let mut prover = FaultyProver::new(Prover::new(circuit.clone(), &input), fault);
+            let mut verifier = Verifier::new(circuit.clone());
+
+            let rejection = simulate(&mut prover, &mut verifier, &mut test_rng(), None)
+                .expect_err(&format!("{fault:?} is accepted"));
+            assert!(
+                matches!(
+                    rejection.message,
+                    Message::SumcheckPoly(SumcheckPoly { layer, round, .. })
+                        if fault.targets(layer, round)
+                ),
+                "{fault:?} is rejected at {:?}",
+                rejection.message
+            );
+        }
+    }
+
+    #[test]
+    fn zero_offsets_are_accepted() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+
+        for fault in faults(&circuit, Fp389::zero()) {
+            let mut prover = FaultyProver::new(Prover::new(circuit.clone(), &input), fault);
+            let mut verifier = Verifier::new(circuit.clone());
+
+            simulate(&mut prover, &mut verifier, &mut test_rng(), None).unwrap();
+            assert!(verifier.check_input(&input), "{fault:?} is rejected");
+        }
+    }
+
+    #[test]
+    fn faulty_proofs_are_rejected() {
+        let circuit = circuit_from_book();
+        let input = book_input();
+        let proof = prove(&circuit, &input).unwrap();
+
+        for offset in [Fp389::zero(), Fp389::from(5u64)] {
+            for fault in faults(&circuit, offset) {
+                let mut faulty = proof.clone();
+                for ((layer, round), p) in faulty.round_polys_mut() {
+                    if fault.targets(layer, round) {
+                        fault.apply(p);
+                    }
+                }
+
+                let accepted = verify(&circuit, &input, &faulty).is_ok();
+                assert_eq!(accepted, offset.is_zero(), "{fault:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Test vectors pinning down the proofs of the protocol byte for byte.
+//!
+//! A [`TestVector`] proves a fixed circuit on a fixed witness over
+//! [`GoldilocksFp`] in a transcript of a fixed label, and keeps all
+//! another implementation needs to check its proofs against: the
+//! circuit, the witness, the bytes of every challenge squeezed out of
+//! the transcript, the round polynomials and the bytes of the proof.
+//! Field elements and bytes are written as `0x`-prefixed big-endian
+//! hex strings, as in the `wasm` bindings.
+//!
+//! The vectors of [`generate`] are checked into `test-vectors/` as
+//! JSON, and the tests regenerate them and compare, so that any
+//! change to the proofs shows up there. A change on purpose takes
+//! regenerating them with
+//!
+//! ```text
+//! cargo run -p gkr-protocol --example test_vectors --features test-vectors
+//! ```
+
+use std::{fs, io, path::Path};
+
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_poly::univariate::DensePolynomial;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circuit::Circuit,
+    circuits,
+    goldilocks::GoldilocksFp,
+    proof::{self, GkrProof, VerifyError},
+    transcript::Transcript,
+};
+
+/// The label of the transcripts of [`proof::prove`].
+const DEFAULT_LABEL: &str = "gkr-protocol";
+
+/// The label of the other vectors, so that labels are covered too.
+const VECTORS_LABEL: &str = "gkr-protocol test vectors";
+
+/// An error checking a [`TestVector`].
+#[derive(Debug, thiserror::Error)]
+pub enum VectorError {
+    /// A field element or the proof is not a hex string.
+    #[error("`{0}` is not a hex string")]
+    BadHex(String),
+
+    /// The bytes of the proof do not decode.
+    #[error("malformed proof: {0}")]
+    Proof(#[from] SerializationError),
+
+    /// The proof does not verify.
+    #[error("the proof does not verify: {0}")]
+    Verify(#[from] VerifyError<GoldilocksFp>),
+
+    /// A part of the vector disagrees with the proof.
+    #[error("the {0} disagree with the proof")]
+    Mismatch(&'static str),
+}
+
+/// A proof along with all that goes into it, see the
+/// [module documentation](self).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// The name of the vector, that of its file.
+    pub name: String,
+
+    /// The label of the transcript.
+    pub label: String,
+
+    /// The circuit proven.
+    pub circuit: Circuit,
+
+    /// The input of the circuit.
+    pub witness: Vec<String>,
+
+    /// The outputs of the circuit on the witness.
+    pub outputs: Vec<String>,
+
+    /// The bytes of every squeeze of the transcript in order, see
+    /// [`Transcript::squeezes`].
+    pub challenges: Vec<String>,
+
+    /// The coefficients of the round polynomials by layer and round,
+    /// lowest degree first.
+    pub round_polys: Vec<Vec<Vec<String>>>,
+
+    /// The compressed [`CanonicalSerialize`] bytes of the proof.
+    pub proof: String,
+}
+
+impl TestVector {
+    /// Prove `circuit` on `witness` in a transcript of `label`,
+    /// recording the challenges.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of inputs does not match the circuit.
+    pub fn new(name: &str, label: &str, circuit: Circuit, witness: &[GoldilocksFp]) -> Self {
+        let mut transcript = Transcript::new(label.as_bytes());
+        transcript.record();
+        let proof = proof::prove_in(&mut transcript, &circuit, witness)
+            .expect("the witness is an input of the circuit");
+
+        let mut bytes = vec![];
+        proof
+            .serialize_compressed(&mut bytes)
+            .expect("serializing into a vector does not fail");
+
+        Self {
+            name: name.to_owned(),
+            label: label.to_owned(),
+            circuit,
+            witness: witness.iter().map(to_hex).collect(),
+            outputs: proof.outputs.iter().map(to_hex).collect(),
+            challenges: transcript.squeezes().iter().map(|s| hex(s)).collect(),
+            round_polys: round_polys(&proof),
+            proof: hex(&bytes),
+        }
+    }
+
+    /// Verify the proof of the vector as it is written, and check that
+    /// the rest of the vector agrees with it.
+    pub fn verify(&self) -> Result<(), VectorError> {
+        let witness = self
+            .witness
+            .iter()
+            .map(|value| Ok(GoldilocksFp::from_be_bytes_mod_order(&from_hex(value)?)))
+            .collect::<Result<Vec<_>, VectorError>>()?;
+        let proof = GkrProof::deserialize_compressed(&from_hex(&self.proof)?[..])?;
+
+        let mut transcript = Transcript::new(self.label.as_bytes());
+        transcript.record();
+        proof::verify_in(&mut transcript, &self.circuit, &witness, &proof)?;
+
+        let challenges: Vec<_> = transcript.squeezes().iter().map(|s| hex(s)).collect();
+        if challenges != self.challenges {
+            return Err(VectorError::Mismatch("challenges"));
+        }
+        if proof
+            .outputs
+            .iter()
+            .map(to_hex)
+            .ne(self.outputs.iter().cloned())
+        {
+            return Err(VectorError::Mismatch("outputs"));
+        }
+        if round_polys(&proof) != self.round_polys {
+            return Err(VectorError::Mismatch("round polynomials"));
+        }
+
+        Ok(())
+    }
+
+    /// The name of the file of the vector.
+    pub fn file_name(&self) -> String {
+        format!("{}.json", self.name)
+    }
+
+    /// The vector as pretty-printed JSON, as in its file.
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).expect("vectors always serialize");
+        json.push('\n');
+        json
+    }
+}
+
+/// The vectors checked into `test-vectors/`.
+pub fn generate() -> Vec<TestVector> {
+    let field = |values: &[u64]| -> Vec<GoldilocksFp> {
+        values.iter().copied().map(GoldilocksFp::from).collect()
+    };
+
+    let mut vectors = vec![];
+    for n in [4, 3] {
+        let circuit = circuits::inner_product(n);
+        let mut witness: Vec<_> = (1..=2 * n as u64).map(GoldilocksFp::from).collect();
+        witness.resize(circuit.num_inputs(), GoldilocksFp::zero());
+
+        let name = format!("inner_product_{n}");
+        vectors.push(TestVector::new(&name, DEFAULT_LABEL, circuit, &witness));
+    }
+
+    let (circuit, layout) = circuits::matmul(2);
+    let witness = layout.input(
+        &[field(&[1, 2]), field(&[3, 4])],
+        &[field(&[5, 6]), field(&[7, 8])],
+    );
+    vectors.push(TestVector::new(
+        "matmul_2",
+        VECTORS_LABEL,
+        circuit,
+        &witness,
+    ));
+
+    let witness = circuits::mimc_input(2, GoldilocksFp::from(3u64), GoldilocksFp::from(5u64));
+    vectors.push(TestVector::new(
+        "mimc_2",
+        VECTORS_LABEL,
+        circuits::mimc(2),
+        &witness,
+    ));
+
+    vectors
+}
+
+/// Write every vector of [`generate`] to its file in `dir`.
+pub fn write_all(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for vector in generate() {
+        fs::write(dir.join(vector.file_name()), vector.to_json())?;
+    }
+
+    Ok(())
+}
+
+fn round_polys(proof: &GkrProof<GoldilocksFp>) -> Vec<Vec<Vec<String>>> {
+    proof
+        .layers
+        .iter()
+        .map(|layer| {
+            layer
+                .round_polys
+                .iter()
+                .map(|p| {
+                    DensePolynomial::from(p.clone())
+                        .coeffs
+                        .iter()
+                        .map(to_hex)
+                        .collect()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn to_hex(value: &GoldilocksFp) -> String {
+    hex(&value.into_bigint().to_bytes_be())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let digits: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+
+    format!("0x{digits}")
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, VectorError> {
+    let bad = || VectorError::BadHex(hex.to_owned());
+    let digits = hex.strip_prefix("0x").ok_or_else(bad)?;
+    if digits.len() % 2 != 0 {
+        return Err(bad());
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2).ok_or_else(bad)?, 16).map_err(|_| bad()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use ark_serialize::CanonicalDeserialize;
+    use pretty_assertions::assert_eq;
+
+    use super::{from_hex, generate, TestVector, VectorError};
+    use crate::{
+        goldilocks::GoldilocksFp,
+        proof::{self, GkrProof},
+    };
+
+    fn vectors_dir() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors"))
+    }
+
+    #[test]
+    fn checked_in_vectors() {
+        let vectors = generate();
+        let mut files: Vec<_> = fs::read_dir(vectors_dir())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        let mut names: Vec<_> = vectors.iter().map(TestVector::file_name).collect();
+        names.sort();
+        assert_eq!(files, names);
+
+        for vector in vectors {
+            let json = fs::read_to_string(vectors_dir().join(vector.file_name())).unwrap();
+            assert_eq!(
+                json,
+                vector.to_json(),
+                "{} changed, see the module documentation to regenerate it",
+                vector.name
+            );
+
+            let loaded: TestVector = serde_json::from_str(&json).unwrap();
+            assert_eq!(loaded, vector);
+            loaded.verify().unwrap();
+        }
+    }
+
+    #[test]
+    fn verify() {
+        let vectors = generate();
+        let vector = &vectors[0];
+        assert_eq!(vector.label, "gkr-protocol");
+        assert_eq!(vector.outputs, ["0x0000000000000046"]);
+
+        // The proof of the default label is that of `prove`.
+        let witness = [1u64, 2, 3, 4, 5, 6, 7, 8].map(GoldilocksFp::from);
+        let bytes = from_hex(&vector.proof).unwrap();
+        assert_eq!(
+            GkrProof::deserialize_compressed(&bytes[..]).unwrap(),
+            proof::prove(&vector.circuit, &witness).unwrap()
+        );
+
+        let mut relabeled = vector.clone();
+        relabeled.label = "other".into();
+        assert!(matches!(relabeled.verify(), Err(VectorError::Verify(_))));
+
+        let mut tampered = vector.clone();
+        tampered.challenges.pop();
+        assert!(matches!(
+            tampered.verify(),
+            Err(VectorError::Mismatch("challenges"))
+        ));
+
+        let mut tampered = vector.clone();
+        tampered.round_polys[0][0][0] = "0x01".into();
+        assert!(matches!(
+            tampered.verify(),
+            Err(VectorError::Mismatch("round polynomials"))
+        ));
+
+        let mut tampered = vector.clone();
+        tampered.proof.push('0');
+        assert!(matches!(tampered.verify(), Err(VectorError::BadHex(_))));
+    }
+}
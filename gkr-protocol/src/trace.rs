@@ -0,0 +1,392 @@
+//! Inspecting the values of builder wires during an evaluation.
+//!
+//! An [`EvaluationTrace`] looks the wires of a [`CircuitBuilder`] up
+//! in the built [`Circuit`] through the [`WireLabels`] of
+//! [`CircuitBuilder::build_circuit_with_labels`].
+//!
+//! Traces of circuits without a builder come from
+//! [`Circuit::evaluate_trace`]. With the `serde` feature traces
+//! serialize, the values as their canonical compressed bytes, to be
+//! compared with the traces of other implementations.
+//!
+//! [`CircuitBuilder`]: crate::circuit_builder::CircuitBuilder
+//! [`CircuitBuilder::build_circuit_with_labels`]: crate::circuit_builder::CircuitBuilder::build_circuit_with_labels
+
+use std::{
+    collections::BTreeMap,
+    ops::{Add, Mul, Sub},
+};
+
+use crate::{
+    circuit::{Circuit, CircuitEvaluation, EvalError, GateLabels},
+    circuit_builder::{Wire, WireLabels},
+};
+
+/// A gate at which two traces differ, see [`EvaluationTrace::diff`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The layer of the gate, layer $0$ being the output layer.
+    pub layer: usize,
+
+    /// The label of the gate in its layer.
+    pub label: usize,
+}
+
+/// The values recorded by an [`EvaluationTrace`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        into = "canonical::Values",
+        try_from = "canonical::Values",
+        bound(
+            serialize = "F: ark_serialize::CanonicalSerialize + Clone",
+            deserialize = "F: ark_serialize::CanonicalDeserialize"
+        )
+    )
+)]
+enum Values<F> {
+    /// Every value of the circuit.
+    All(CircuitEvaluation<F>),
+
+    /// The values of the watched gates by layer and label.
+    Watched(BTreeMap<(usize, usize), F>),
+}
+
+/// The values taken by the wires of a builder on an input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "F: ark_serialize::CanonicalSerialize + Clone",
+        deserialize = "F: ark_serialize::CanonicalDeserialize"
+    ))
+)]
+pub struct EvaluationTrace<F> {
+    labels: WireLabels,
+    gate_labels: Option<GateLabels>,
+    values: Values<F>,
+}
+
+impl<F> EvaluationTrace<F>
+where
+    F: Add<Output = F> + Mul<Output = F> + Sub<Output = F> + Copy + PartialEq,
+{
+    /// Evaluate `circuit` on `input` recording every value.
+    pub fn new(circuit: &Circuit, labels: WireLabels, input: &[F]) -> Result<Self, EvalError> {
+        Ok(Self {
+            labels,
+            gate_labels: circuit.gate_labels().cloned(),
+            values: Values::All(circuit.try_evaluate(input)?),
+        })
+    }
+
+    /// Evaluate `circuit` on `input` recording only the values of
+    /// the `watched` wires.
+    ///
+    /// Only a single layer of values is held at a time, so large
+    /// circuits can be traced in little memory.
+    pub fn watch(
+        circuit: &Circuit,
+        labels: WireLabels,
+        input: &[F],
+        watched: &[Wire],
+    ) -> Result<Self, EvalError> {
+        if input.len() != circuit.num_inputs() {
+            return Err(EvalError::InputArityMismatch {
+                expected: circuit.num_inputs(),
+                actual: input.len(),
+            });
+        }
+
+        let mut positions: Vec<_> = watched
+            .iter()
+            .filter_map(|&wire| labels.get(wire))
+            .collect();
+        positions.sort();
+
+        let mut values = BTreeMap::new();
+        let mut record = |layer: usize, layer_values: &[F]| {
+            for &(l, label) in positions.iter().filter(|(l, _)| *l == layer) {
+                values.insert((l, label), layer_values[label]);
+            }
+        };
+
+        let num_layers = circuit.num_layers();
+        record(num_layers, input);
+
+        let mut current = input.to_vec();
+        for (layer, gates) in circuit.layers().iter().enumerate().rev() {
+            current = gates.evaluate(&current);
+            record(layer, &current);
+        }
+
+        Ok(Self {
+            labels,
+            gate_labels: circuit.gate_labels().cloned(),
+            values: Values::Watched(values),
+        })
+    }
+
+    /// The value of `wire`, `None` if it was dropped from the circuit
+    /// or is not watched.
+    pub fn value_of(&self, wire: Wire) -> Option<F> {
+        let (layer, label) = self.labels.get(wire)?;
+        self.value_at(layer, label)
+    }
+
+    /// The value of the gate labeled `name`, see
+    /// [`CircuitBuilder::set_label`](crate::circuit_builder::CircuitBuilder::set_label).
+    pub fn value_of_named(&self, name: &str) -> Option<F> {
+        let (layer, label) = self.gate_labels.as_ref()?.position(name)?;
+        self.value_at(layer, label)
+    }
+
+    /// The value of gate `label` at `layer` if it is recorded.
+    pub fn value_at(&self, layer: usize, label: usize) -> Option<F> {
+        match &self.values {
+            Values::All(evaluation) => evaluation.layers.get(layer)?.get(label).copied(),
+            Values::Watched(values) => values.get(&(layer, label)).copied(),
+        }
+    }
+
+    /// The gates at which the values of `self` and `other` differ
+    /// in evaluation order, inputs first.
+    ///
+    /// Only the gates recorded by both traces are compared.
+    pub fn diff(&self, other: &Self) -> Vec<Divergence> {
+        let mut positions: Vec<_> = match &self.values {
+            Values::All(evaluation) => evaluation
+                .layers
+                .iter()
+                .enumerate()
+                .flat_map(|(layer, values)| (0..values.len()).map(move |label| (layer, label)))
+                .collect(),
+            Values::Watched(values) => values.keys().copied().collect(),
+        };
+        positions.sort_by_key(|&(layer, label)| (usize::MAX - layer, label));
+
+        positions
+            .into_iter()
+            .filter(|&(layer, label)| {
+                matches!(
+                    (self.value_at(layer, label), other.value_at(layer, label)),
+                    (Some(a), Some(b)) if a != b
+                )
+            })
+            .map(|(layer, label)| Divergence { layer, label })
+            .collect()
+    }
+}
+
+/// Values serialized as their canonical compressed bytes.
+#[cfg(feature = "serde")]
+mod canonical {
+    use std::collections::BTreeMap;
+
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+
+    use crate::circuit::CircuitEvaluation;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    pub enum Values {
+        All(Vec<Vec<Vec<u8>>>),
+        Watched(Vec<((usize, usize), Vec<u8>)>),
+    }
+
+    fn to_bytes<F: CanonicalSerialize>(value: &F) -> Vec<u8> {
+        let mut bytes = vec![];
+        value
+            .serialize_compressed(&mut bytes)
+            .expect("serializing into a vector does not fail");
+        bytes
+    }
+
+    impl<F: CanonicalSerialize> From<super::Values<F>> for Values {
+        fn from(values: super::Values<F>) -> Self {
+            match values {
+                super::Values::All(evaluation) => Values::All(
+                    evaluation
+                        .layers
+                        .iter()
+                        .map(|layer| layer.iter().map(to_bytes).collect())
+                        .collect(),
+                ),
+                super::Values::Watched(values) => Values::Watched(
+                    values
+                        .iter()
+                        .map(|(&position, value)| (position, to_bytes(value)))
+                        .collect(),
+                ),
+            }
+        }
+    }
+
+    impl<F: CanonicalDeserialize> TryFrom<Values> for super::Values<F> {
+        type Error = SerializationError;
+
+        fn try_from(values: Values) -> Result<Self, Self::Error> {
+            Ok(match values {
+                Values::All(layers) => super::Values::All(CircuitEvaluation {
+                    layers: layers
+                        .iter()
+                        .map(|layer| {
+                            layer
+                                .iter()
+                                .map(|bytes| F::deserialize_compressed(&bytes[..]))
+                                .collect()
+                        })
+                        .collect::<Result<_, _>>()?,
+                }),
+                Values::Watched(values) => super::Values::Watched(
+                    values
+                        .into_iter()
+                        .map(|(position, bytes)| {
+                            Ok((position, F::deserialize_compressed(&bytes[..])?))
+                        })
+                        .collect::<Result<BTreeMap<_, _>, SerializationError>>()?,
+                ),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use ark_ff::{Fp64, MontBackend, MontConfig};
+    use pretty_assertions::assert_eq;
+
+    use super::{Divergence, EvaluationTrace};
+    use crate::{
+        circuit::{circuit_from_book, Circuit, CircuitLayer, Gate, GateType},
+        circuit_builder::CircuitBuilder,
+    };
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    /// The circuit of the book squaring its third input instead of
+    /// multiplying it by the second one.
+    fn corrupted() -> Circuit {
+        let circuit = circuit_from_book();
+        let mut layers = circuit.layers().to_vec();
+        let mut gates = layers[1].gates().to_vec();
+        gates[2] = Gate::new(GateType::Mul, [2, 2]);
+        layers[1] = CircuitLayer::new(gates);
+
+        Circuit::new(layers, circuit.num_inputs())
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut builder = CircuitBuilder::new();
+        let a = builder.apply_witness();
+        let b = builder.apply_witness();
+        let c = builder.apply_witness();
+        let unused = builder.apply_witness();
+        let v0 = builder.append_mul_gate(a, a).unwrap();
+        let v1 = builder.append_mul_gate_named(b, c, "bc").unwrap();
+        let out = builder.append_add_gate(v0, v1).unwrap();
+
+        let (circuit, labels) = builder.build_circuit_with_labels().unwrap();
+        let input = [2i64, 3, 4];
+
+        let trace = EvaluationTrace::watch(&circuit, labels.clone(), &input, &[v0, v1]).unwrap();
+        assert_eq!(trace.value_of(v0), Some(4));
+        assert_eq!(trace.value_of(v1), Some(12));
+        assert_eq!(trace.value_of(out), None);
+        assert_eq!(trace.value_of(unused), None);
+        assert_eq!(trace.value_of_named("bc"), Some(12));
+
+        let full = EvaluationTrace::new(&circuit, labels, &input).unwrap();
+        assert_eq!(full.value_of(out), Some(16));
+        assert_eq!(full.value_of(b), Some(3));
+        assert_eq!(full.value_of_named("bc"), Some(12));
+        assert_eq!(full.value_of_named("out"), None);
+        assert_eq!(full.diff(&trace), vec![]);
+    }
+
+    #[test]
+    fn diff() {
+        let mut builder = CircuitBuilder::new();
+        let w: Vec<_> = (0..4).map(|_| builder.apply_witness()).collect();
+        let v0 = builder.append_mul_gate(w[0], w[1]).unwrap();
+        let v1 = builder.append_sub_gate(w[2], w[3]).unwrap();
+        builder.append_add_gate(v0, v1).unwrap();
+
+        let (circuit, labels) = builder.build_circuit_with_labels().unwrap();
+        let trace = |input: &[i64]| EvaluationTrace::new(&circuit, labels.clone(), input).unwrap();
+
+        let (layer, _) = labels.get(w[2]).unwrap();
+        assert_eq!(
+            trace(&[1, 2, 3, 4]).diff(&trace(&[1, 2, 5, 4])),
+            vec![
+                Divergence { layer, label: 2 },
+                Divergence { layer: 1, label: 1 },
+                Divergence { layer: 0, label: 0 },
+            ]
+        );
+        assert_eq!(trace(&[1, 2, 3, 4]).diff(&trace(&[1, 2, 3, 4])), vec![]);
+
+        // Watched traces only compare the watched gates.
+        let watched =
+            EvaluationTrace::watch(&circuit, labels.clone(), &[1, 2, 5, 4], &[v0]).unwrap();
+        assert_eq!(trace(&[1, 2, 3, 4]).diff(&watched), vec![]);
+        let watched =
+            EvaluationTrace::watch(&circuit, labels.clone(), &[1, 2, 5, 4], &[v1]).unwrap();
+        assert_eq!(
+            trace(&[1, 2, 3, 4]).diff(&watched),
+            vec![Divergence { layer: 1, label: 1 }]
+        );
+    }
+
+    #[test]
+    fn corrupted_trace() {
+        let input = [3, 2, 3, 1].map(Fp389::from);
+        let trace = circuit_from_book().evaluate_trace(&input).unwrap();
+        let other = corrupted().evaluate_trace(&input).unwrap();
+
+        assert_eq!(trace.value_at(0, 1), Some(Fp389::from(6)));
+        assert_eq!(other.value_at(0, 1), Some(Fp389::from(9)));
+        assert_eq!(
+            trace.diff(&other),
+            vec![
+                Divergence { layer: 1, label: 2 },
+                Divergence { layer: 0, label: 1 }
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        let input = [3, 2, 3, 388].map(Fp389::from);
+        let trace = circuit_from_book().evaluate_trace(&input).unwrap();
+
+        let json = serde_json::to_string(&trace).unwrap();
+        let loaded: EvaluationTrace<Fp389> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, trace);
+
+        let mut builder = CircuitBuilder::new();
+        let a = builder.apply_witness_named("a");
+        let b = builder.apply_witness();
+        let ab = builder.append_mul_gate(a, b).unwrap();
+        builder.append_add_gate(a, b).unwrap();
+        let (circuit, labels) = builder.build_circuit_with_labels().unwrap();
+        let input = [4, 5].map(Fp389::from);
+        let watched = EvaluationTrace::watch(&circuit, labels, &input, &[ab]).unwrap();
+
+        let json = serde_json::to_string(&watched).unwrap();
+        let loaded: EvaluationTrace<Fp389> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, watched);
+        assert_eq!(loaded.value_of(ab), Some(Fp389::from(20)));
+        assert_eq!(loaded.value_of_named("a"), None);
+    }
+}
@@ -0,0 +1,250 @@
+//! A hash based transcript turning the interactive protocol
+//! into a non-interactive one by the Fiat-Shamir transformation.
+//!
+//! The hash defaults to SHA-256, any other [`Digest`] may be
+//! plugged in.
+
+use alloc::{vec, vec::Vec};
+
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::{Error as RandError, RngCore};
+use core::marker::PhantomData;
+
+use sha2::{digest::Output, Digest, Sha256};
+
+/// A transcript of the messages of the [`Prover`](crate::Prover).
+///
+/// Challenges are squeezed out of the hash of all the messages
+/// appended so far. The transcript is an [`RngCore`], so it can be
+/// handed to the [`Verifier`](crate::Verifier) in place of a source
+/// of randomness.
+#[derive(Clone)]
+pub struct Transcript<D: Digest = Sha256> {
+    state: Output<D>,
+    counter: u64,
+    digest: PhantomData<D>,
+
+    /// The bytes of every squeeze, if recorded.
+    squeezes: Option<Vec<Vec<u8>>>,
+}
+
+impl Transcript {
+    /// Create a new SHA-256 `Transcript` separated from others by a `label`.
+    pub fn new(label: &[u8]) -> Self {
+        Self::with_digest(label)
+    }
+}
+
+impl<D: Digest> Transcript<D> {
+    /// Create a new `Transcript` hashing with `D` separated from
+    /// others by a `label`.
+    pub fn with_digest(label: &[u8]) -> Self {
+        Self {
+            state: D::digest(label),
+            counter: 0,
+            digest: PhantomData,
+            squeezes: None,
+        }
+    }
+
+    /// Record the bytes of every squeeze from now on, see
+    /// [`squeezes`](Self::squeezes).
+    pub fn record(&mut self) {
+        self.squeezes.get_or_insert_with(Vec::new);
+    }
+
+    /// The bytes of every squeeze since [`record`](Self::record), in
+    /// the order they were drawn.
+    ///
+    /// A challenge drawn by [`challenge`](Self::challenge) is a single
+    /// squeeze, one drawn by `F::rand` through [`RngCore`] as many as
+    /// it takes words.
+    pub fn squeezes(&self) -> &[Vec<u8>] {
+        self.squeezes.as_deref().unwrap_or_default()
+    }
+
+    /// Append a prover message to the transcript.
+    pub fn append<T: CanonicalSerialize>(&mut self, message: &T) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("gkr.transcript", op = "append").entered();
+
+        let mut bytes = vec![];
+        message
+            .serialize_uncompressed(&mut bytes)
+            .expect("serializing into a vector does not fail");
+
+        self.state = D::new()
+            .chain_update(&self.state)
+            .chain_update(&bytes)
+            .finalize();
+        self.counter = 0;
+    }
+
+    /// Squeeze a challenge out of the transcript.
+    ///
+    /// The challenge is reduced from 128 more bits than the modulus
+    /// has, so it is close to uniform.
+    pub fn challenge<F: PrimeField>(&mut self) -> F {
+        let mut bytes = vec![0; challenge_len::<F>()];
+        self.fill_bytes(&mut bytes);
+        F::from_le_bytes_mod_order(&bytes)
+    }
+
+    /// Squeeze `n` challenges out of the transcript at once.
+    ///
+    /// The bytes of all the challenges come from a single expansion
+    /// of the hash in counter mode, so these are not the challenges
+    /// of `n` calls of [`challenge`](Self::challenge) unless the
+    /// bytes of a challenge happen to fill whole digests.
+    pub fn challenge_vec<F: PrimeField>(&mut self, n: usize) -> Vec<F> {
+        let len = challenge_len::<F>();
+        let mut bytes = vec![0; n * len];
+        self.fill_bytes(&mut bytes);
+
+        bytes.chunks(len).map(F::from_le_bytes_mod_order).collect()
+    }
+}
+
+/// The number of bytes a challenge in `F` is reduced from.
+fn challenge_len<F: PrimeField>() -> usize {
+    (F::MODULUS_BIT_SIZE as usize).div_ceil(8) + 16
+}
+
+impl<D: Digest> RngCore for Transcript<D> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("gkr.transcript", op = "squeeze").entered();
+
+        for chunk in dest.chunks_mut(<D as Digest>::output_size()) {
+            let block = D::new()
+                .chain_update(&self.state)
+                .chain_update(self.counter.to_le_bytes())
+                .finalize();
+            self.counter += 1;
+
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+
+        if let Some(squeezes) = &mut self.squeezes {
+            squeezes.push(dest.to_vec());
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use alloc::{collections::BTreeSet, vec::Vec};
+
+    use ark_ff::{Fp64, MontBackend, MontConfig, PrimeField};
+    use ark_std::rand::RngCore;
+    use sha2::Sha512;
+
+    use super::Transcript;
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    #[test]
+    fn challenges_depend_on_messages() {
+        let mut a = Transcript::new(b"test");
+        let mut b = Transcript::new(b"test");
+        assert_eq!(a.next_u64(), b.next_u64());
+
+        a.append(&1u64);
+        b.append(&2u64);
+        assert_ne!(a.next_u64(), b.next_u64());
+
+        // Squeezing twice gives different challenges.
+        let mut c = Transcript::new(b"test");
+        assert_ne!(c.next_u64(), c.next_u64());
+    }
+
+    #[test]
+    fn pluggable_digest() {
+        let mut sha256 = Transcript::new(b"test");
+        let mut sha512 = Transcript::<Sha512>::with_digest(b"test");
+        sha256.append(&1u64);
+        sha512.append(&1u64);
+
+        let (mut a, mut b) = ([0; 100], [0; 100]);
+        sha256.fill_bytes(&mut a);
+        sha512.fill_bytes(&mut b);
+        assert_ne!(a, b);
+
+        let mut again = Transcript::<Sha512>::with_digest(b"test");
+        again.append(&1u64);
+        let mut c = [0; 100];
+        again.fill_bytes(&mut c);
+        assert_eq!(b, c);
+    }
+
+    #[test]
+    fn challenge_vec() {
+        // The prover and the verifier append the same messages.
+        let mut prover = Transcript::new(b"test");
+        let mut verifier = Transcript::new(b"test");
+        prover.append(&1u64);
+        verifier.append(&1u64);
+
+        let challenges: Vec<Fp389> = prover.challenge_vec(100);
+        assert_eq!(challenges, verifier.challenge_vec::<Fp389>(100));
+        assert_eq!(prover.challenge::<Fp389>(), verifier.challenge());
+
+        // The challenges are spread over the field.
+        let distinct: BTreeSet<_> = challenges.iter().collect();
+        assert!(distinct.len() > 50);
+
+        // Deterministic from the messages alone.
+        let mut again = Transcript::new(b"test");
+        again.append(&1u64);
+        assert_eq!(again.challenge_vec::<Fp389>(100), challenges);
+
+        let mut other = Transcript::new(b"test");
+        other.append(&2u64);
+        assert_ne!(other.challenge_vec::<Fp389>(100), challenges);
+    }
+
+    #[test]
+    fn record() {
+        let mut transcript = Transcript::new(b"test");
+        let mut recorded = transcript.clone();
+        recorded.record();
+
+        let _: Fp389 = transcript.challenge();
+        assert!(transcript.squeezes().is_empty());
+
+        let first: Fp389 = recorded.challenge();
+        recorded.append(&1u64);
+        let word = recorded.next_u64();
+        assert_eq!(recorded.squeezes().len(), 2);
+        assert_eq!(recorded.squeezes()[0].len(), 18);
+        assert_eq!(
+            Fp389::from_le_bytes_mod_order(&recorded.squeezes()[0]),
+            first
+        );
+        assert_eq!(recorded.squeezes()[1], word.to_le_bytes());
+    }
+}
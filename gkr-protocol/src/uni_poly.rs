@@ -0,0 +1,343 @@
+//! Univariate polynomials of low degree.
+//!
+//! The round polynomials of the Sum-Check protocol and the
+//! restrictions of $\tilde{W}_{i+1}$ to lines are built from a few
+//! coefficients or values. [`UniPoly`] keeps them densely and converts
+//! to the sparse polynomials of the messages at the boundary.
+
+use alloc::{vec, vec::Vec};
+use core::ops::{Add, AddAssign, Mul};
+
+use ark_ff::Field;
+use ark_poly::univariate;
+
+/// A univariate polynomial by its coefficients, the lowest degree
+/// first and no trailing zeroes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UniPoly<F> {
+    coeffs: Vec<F>,
+}
+
+impl<F: Field> UniPoly<F> {
+    /// The polynomial of the coefficients `coeffs`, the lowest degree
+    /// first.
+    pub fn from_coefficients(mut coeffs: Vec<F>) -> Self {
+        while coeffs.last().is_some_and(|c| c.is_zero()) {
+            coeffs.pop();
+        }
+
+        Self { coeffs }
+    }
+
+    /// The zero polynomial.
+    pub fn zero() -> Self {
+        Self { coeffs: vec![] }
+    }
+
+    /// Whether this is the zero polynomial.
+    pub fn is_zero(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    /// The coefficients, the lowest degree first.
+    pub fn coeffs(&self) -> &[F] {
+        &self.coeffs
+    }
+
+    /// The degree, zero for the zero polynomial.
+    pub fn degree(&self) -> usize {
+        self.coeffs.len().saturating_sub(1)
+    }
+
+    /// The value at `x` by Horner's rule.
+    pub fn evaluate(&self, x: &F) -> F {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(F::zero(), |acc, c| acc * x + c)
+    }
+
+    /// The polynomial times the constant `by`.
+    pub fn scale(&self, by: F) -> Self {
+        Self::from_coefficients(self.coeffs.iter().map(|c| *c * by).collect())
+    }
+
+    /// The polynomial of degree less than `points.len()` through the
+    /// `(x, y)` `points` by Lagrange interpolation, `None` if two of
+    /// them share an $x$.
+    pub fn interpolate(points: &[(F, F)]) -> Option<Self> {
+        let mut res = Self::zero();
+
+        for (i, (x_i, y_i)) in points.iter().enumerate() {
+            // \prod_{j \ne i} (X - x_j) / (x_i - x_j)
+            let mut basis = Self::from_coefficients(vec![F::one()]);
+            let mut denominator = F::one();
+            for (x_j, _) in points.iter().take(i).chain(points.iter().skip(i + 1)) {
+                basis = &basis * &Self::from_coefficients(vec![-*x_j, F::one()]);
+                denominator *= *x_i - x_j;
+            }
+
+            res += &basis.scale(*y_i * denominator.inverse()?);
+        }
+
+        Some(res)
+    }
+
+    /// The polynomial of degree less than `evals.len()` taking the
+    /// value `evals[t]` at every $t = 0, 1, \ldots$, see
+    /// [`IntegerDomain`].
+    pub fn from_evaluations(evals: &[F]) -> Self {
+        match evals.len() {
+            0 => Self::zero(),
+            n => IntegerDomain::new(n - 1).interpolate(evals),
+        }
+    }
+}
+
+impl<F: Field> Add<&UniPoly<F>> for &UniPoly<F> {
+    type Output = UniPoly<F>;
+
+    fn add(self, other: &UniPoly<F>) -> UniPoly<F> {
+        let mut res = self.clone();
+        res += other;
+        res
+    }
+}
+
+impl<F: Field> AddAssign<&UniPoly<F>> for UniPoly<F> {
+    fn add_assign(&mut self, other: &UniPoly<F>) {
+        if self.coeffs.len() < other.coeffs.len() {
+            self.coeffs.resize(other.coeffs.len(), F::zero());
+        }
+        for (c, o) in self.coeffs.iter_mut().zip(&other.coeffs) {
+            *c += o;
+        }
+        *self = Self::from_coefficients(core::mem::take(&mut self.coeffs));
+    }
+}
+
+impl<F: Field> Mul<&UniPoly<F>> for &UniPoly<F> {
+    type Output = UniPoly<F>;
+
+    fn mul(self, other: &UniPoly<F>) -> UniPoly<F> {
+        if self.is_zero() || other.is_zero() {
+            return UniPoly::zero();
+        }
+
+        let mut coeffs = vec![F::zero(); self.coeffs.len() + other.coeffs.len() - 1];
+        for (i, a) in self.coeffs.iter().enumerate() {
+            for (j, b) in other.coeffs.iter().enumerate() {
+                coeffs[i + j] += *a * b;
+            }
+        }
+
+        UniPoly::from_coefficients(coeffs)
+    }
+}
+
+impl<F: Field> From<UniPoly<F>> for univariate::SparsePolynomial<F> {
+    /// Only the non-zero coefficients are kept.
+    fn from(p: UniPoly<F>) -> Self {
+        univariate::SparsePolynomial::from_coefficients_vec(
+            p.coeffs
+                .into_iter()
+                .enumerate()
+                .filter(|(_, c)| !c.is_zero())
+                .collect(),
+        )
+    }
+}
+
+impl<F: Field> From<&univariate::SparsePolynomial<F>> for UniPoly<F> {
+    fn from(p: &univariate::SparsePolynomial<F>) -> Self {
+        let mut coeffs = vec![];
+        for (degree, c) in p.iter() {
+            if coeffs.len() <= *degree {
+                coeffs.resize(degree + 1, F::zero());
+            }
+            coeffs[*degree] += c;
+        }
+
+        Self::from_coefficients(coeffs)
+    }
+}
+
+/// The points $0, 1, \ldots, d$ along with what interpolating over
+/// them takes, to be computed once for every polynomial of degree $d$.
+///
+/// The barycentric weights are
+/// $w_i = 1 / \prod_{j \ne i} (i - j) = (-1)^{d - i} / (i! (d - i)!)$,
+/// so interpolating takes $O(d^2)$ rather than building every Lagrange
+/// basis polynomial anew.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntegerDomain<F> {
+    /// $\prod_j (X - j)$, the lowest degree first.
+    vanishing: Vec<F>,
+
+    /// $w_i$.
+    weights: Vec<F>,
+}
+
+impl<F: Field> IntegerDomain<F> {
+    /// The domain of the `degree + 1` points $0, 1, \ldots,$ `degree`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the points are not distinct in `F`.
+    pub fn new(degree: usize) -> Self {
+        let mut factorials = vec![F::one()];
+        for i in 1..=degree {
+            factorials.push(factorials[i - 1] * F::from(i as u64));
+        }
+        let weights = (0..=degree)
+            .map(|i| {
+                let w = (factorials[i] * factorials[degree - i])
+                    .inverse()
+                    .expect("interpolation points are distinct");
+                match (degree - i) % 2 {
+                    0 => w,
+                    _ => -w,
+                }
+            })
+            .collect();
+
+        let mut vanishing = vec![F::one()];
+        for j in 0..=degree {
+            let j = F::from(j as u64);
+            vanishing.insert(0, F::zero());
+            for k in 0..vanishing.len() - 1 {
+                let next = vanishing[k + 1];
+                vanishing[k] -= j * next;
+            }
+        }
+
+        Self { vanishing, weights }
+    }
+
+    /// The degree $d$ of the polynomials over the domain.
+    pub fn degree(&self) -> usize {
+        self.weights.len() - 1
+    }
+
+    /// The polynomial of degree at most $d$ taking the value
+    /// `evals[t]` at every point $t$.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not $d + 1$ values.
+    pub fn interpolate(&self, evals: &[F]) -> UniPoly<F> {
+        assert_eq!(evals.len(), self.weights.len(), "one value per point");
+
+        let n = self.weights.len();
+        let mut coeffs = vec![F::zero(); n];
+        let mut quotient = vec![F::zero(); n];
+        for (i, (y, w)) in evals.iter().zip(&self.weights).enumerate() {
+            if y.is_zero() {
+                continue;
+            }
+
+            // \prod_{j \ne i} (X - j), dividing out X - i.
+            let i = F::from(i as u64);
+            quotient[n - 1] = self.vanishing[n];
+            for k in (1..n).rev() {
+                quotient[k - 1] = self.vanishing[k] + i * quotient[k];
+            }
+
+            let scale = *y * w;
+            for (c, q) in coeffs.iter_mut().zip(&quotient) {
+                *c += scale * q;
+            }
+        }
+
+        UniPoly::from_coefficients(coeffs)
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use ark_ff::{Fp64, MontBackend, MontConfig};
+    use ark_poly::{univariate::SparsePolynomial, Polynomial};
+    use ark_std::{test_rng, UniformRand};
+    use pretty_assertions::assert_eq;
+
+    use super::{IntegerDomain, UniPoly};
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    #[test]
+    fn arithmetic() {
+        let p = UniPoly::from_coefficients([1u64, 2, 0, 0].map(Fp389::from).to_vec());
+        let q = UniPoly::from_coefficients([0u64, 1].map(Fp389::from).to_vec());
+        assert_eq!(p.degree(), 1);
+        assert_eq!(p.evaluate(&Fp389::from(3u64)), Fp389::from(7u64));
+
+        // (1 + 2X)X + 1 + 2X
+        let r = &(&p * &q) + &p;
+        assert_eq!(r.coeffs(), [1u64, 3, 2].map(Fp389::from));
+        assert!((&r + &r.scale(-Fp389::from(1u64))).is_zero());
+
+        let sparse: SparsePolynomial<Fp389> = r.clone().into();
+        assert_eq!(UniPoly::from(&sparse), r);
+        assert_eq!(
+            sparse.evaluate(&Fp389::from(5u64)),
+            r.evaluate(&Fp389::from(5u64))
+        );
+    }
+
+    #[test]
+    fn interpolate_then_evaluate() {
+        let rng = &mut test_rng();
+
+        for n in 1..8 {
+            let points: Vec<_> = (0..n)
+                .map(|i| (Fp389::from(3 * i as u64 + 1), Fp389::rand(rng)))
+                .collect();
+            let p = UniPoly::interpolate(&points).unwrap();
+            assert!(p.degree() < n);
+            for (x, y) in &points {
+                assert_eq!(p.evaluate(x), *y);
+            }
+
+            let evals: Vec<_> = (0..n).map(|_| Fp389::rand(rng)).collect();
+            let p = UniPoly::from_evaluations(&evals);
+            for (t, y) in evals.iter().enumerate() {
+                assert_eq!(p.evaluate(&Fp389::from(t as u64)), *y);
+            }
+        }
+
+        let repeated = [(Fp389::from(1u64), Fp389::from(2u64)); 2];
+        assert_eq!(UniPoly::interpolate(&repeated), None);
+    }
+
+    #[test]
+    fn barycentric_matches_lagrange() {
+        let rng = &mut test_rng();
+
+        for degree in 0..8 {
+            let domain = IntegerDomain::new(degree);
+            assert_eq!(domain.degree(), degree);
+
+            for _ in 0..4 {
+                let evals: Vec<_> = (0..=degree).map(|_| Fp389::rand(rng)).collect();
+                let points: Vec<_> = evals
+                    .iter()
+                    .enumerate()
+                    .map(|(t, y)| (Fp389::from(t as u64), *y))
+                    .collect();
+
+                assert_eq!(
+                    domain.interpolate(&evals),
+                    UniPoly::interpolate(&points).unwrap()
+                );
+            }
+        }
+    }
+}
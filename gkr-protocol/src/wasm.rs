@@ -0,0 +1,329 @@
+//! [`wasm_bindgen`](mod@wasm_bindgen) bindings to build and evaluate circuits and to
+//! verify proofs in the browser.
+//!
+//! Field elements are elements of $\mathbb{F}_{2^{64} - 2^{32} + 1}$
+//! passed as big-endian hex strings. Circuits are passed as the
+//! [`bincode`] bytes of [`Circuit`] and proofs as the compressed
+//! [`CanonicalSerialize`] bytes of [`GkrProof`], the formats of the
+//! `gkr-cli` binary.
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    circuit::{Circuit, EvalError},
+    circuit_builder::{BuildError, CircuitBuilder},
+    goldilocks::GoldilocksFp,
+    proof::{verify, GkrProof},
+};
+
+/// The field of the bindings.
+pub type Fq = GoldilocksFp;
+
+/// An error of the bindings.
+#[derive(Debug, thiserror::Error)]
+pub enum WasmError {
+    /// A field element is not a hex string.
+    #[error("`{0}` is not a hex string")]
+    BadHex(String),
+
+    /// The circuit bytes do not decode.
+    #[error("malformed circuit: {0}")]
+    Circuit(#[from] bincode::Error),
+
+    /// The proof bytes do not decode.
+    #[error("malformed proof: {0}")]
+    Proof(#[from] ark_serialize::SerializationError),
+
+    /// The builder rejected a gate.
+    #[error(transparent)]
+    Build(#[from] BuildError),
+
+    /// The input does not fit the circuit.
+    #[error(transparent)]
+    Eval(#[from] EvalError),
+}
+
+/// Parse a big-endian hex string into a field element, reducing it
+/// modulo the field order.
+pub fn from_hex(hex: &str) -> Result<Fq, WasmError> {
+    let digits = hex.strip_prefix("0x").unwrap_or(hex);
+    if digits.is_empty() || !digits.is_ascii() {
+        return Err(WasmError::BadHex(hex.to_owned()));
+    }
+
+    let padded = format!("{}{digits}", "0".repeat(digits.len() % 2));
+    let bytes = (0..padded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&padded[i..i + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| WasmError::BadHex(hex.to_owned()))?;
+
+    Ok(Fq::from_be_bytes_mod_order(&bytes))
+}
+
+/// Format a field element as a `0x`-prefixed big-endian hex string.
+pub fn to_hex(value: Fq) -> String {
+    let digits: String = value
+        .into_bigint()
+        .to_bytes_be()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    format!("0x{digits}")
+}
+
+fn parse_all(values: &[String]) -> Result<Vec<Fq>, WasmError> {
+    values.iter().map(|value| from_hex(value)).collect()
+}
+
+/// A [`CircuitBuilder`] exported to JavaScript.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmBuilder {
+    builder: CircuitBuilder,
+}
+
+#[wasm_bindgen]
+impl WasmBuilder {
+    /// Create an empty builder.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a witness returning its wire.
+    pub fn witness(&mut self) -> usize {
+        self.builder.apply_witness()
+    }
+
+    /// Add a gate computing `left + right`.
+    pub fn add(&mut self, left: usize, right: usize) -> Result<usize, JsError> {
+        Ok(self.builder.append_add_gate(left, right)?)
+    }
+
+    /// Add a gate computing `left * right`.
+    pub fn mul(&mut self, left: usize, right: usize) -> Result<usize, JsError> {
+        Ok(self.builder.append_mul_gate(left, right)?)
+    }
+
+    /// Add a gate computing `left - right`.
+    pub fn sub(&mut self, left: usize, right: usize) -> Result<usize, JsError> {
+        Ok(self.builder.append_sub_gate(left, right)?)
+    }
+
+    /// Build the circuit.
+    pub fn build(&self) -> Result<WasmCircuit, JsError> {
+        Ok(WasmCircuit {
+            circuit: self.builder.build_circuit()?,
+        })
+    }
+}
+
+/// A [`Circuit`] exported to JavaScript.
+#[wasm_bindgen]
+pub struct WasmCircuit {
+    circuit: Circuit,
+}
+
+impl WasmCircuit {
+    /// See [`WasmCircuit::from_bytes`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, WasmError> {
+        Ok(Self {
+            circuit: bincode::deserialize(bytes)?,
+        })
+    }
+
+    /// See [`WasmCircuit::evaluate`].
+    pub fn outputs(&self, input: &[String]) -> Result<Vec<String>, WasmError> {
+        let outputs = self.circuit.outputs(&parse_all(input)?)?;
+
+        Ok(outputs.into_iter().map(to_hex).collect())
+    }
+
+    /// See [`WasmCircuit::verify`].
+    pub fn check(
+        &self,
+        input: &[String],
+        outputs: &[String],
+        proof: &[u8],
+    ) -> Result<bool, WasmError> {
+        let input = parse_all(input)?;
+        let outputs = parse_all(outputs)?;
+        let proof = GkrProof::<Fq>::deserialize_compressed(proof)?;
+
+        if input.len() != self.circuit.num_inputs() || proof.outputs != outputs {
+            return Ok(false);
+        }
+
+        Ok(verify(&self.circuit, &input, &proof).is_ok())
+    }
+}
+
+#[wasm_bindgen]
+impl WasmCircuit {
+    /// Decode a circuit from its [`bincode`] bytes.
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WasmCircuit, JsError> {
+        Ok(Self::decode(bytes)?)
+    }
+
+    /// The [`bincode`] bytes of the circuit.
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(&self.circuit).expect("circuits serialize")
+    }
+
+    /// The outputs of the circuit on `input`.
+    pub fn evaluate(&self, input: Vec<String>) -> Result<Vec<String>, JsError> {
+        Ok(self.outputs(&input)?)
+    }
+
+    /// Verify that `proof` proves the evaluation of the circuit on
+    /// `input` to `outputs`.
+    pub fn verify(
+        &self,
+        input: Vec<String>,
+        outputs: Vec<String>,
+        proof: &[u8],
+    ) -> Result<bool, JsError> {
+        Ok(self.check(&input, &outputs, proof)?)
+    }
+}
+
+/// The compressed bytes of `proof`, as taken by [`WasmCircuit::verify`].
+pub fn proof_bytes(proof: &GkrProof<Fq>) -> Vec<u8> {
+    let mut bytes = vec![];
+    proof
+        .serialize_compressed(&mut bytes)
+        .expect("serializing into a vector does not fail");
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{from_hex, proof_bytes, to_hex, Fq, WasmCircuit, WasmError};
+    use crate::{circuit::circuit_from_book, proof::prove};
+
+    /// The proof of the evaluation of the circuit of the book on
+    /// $(3, 2, 3, 1)$ as produced natively.
+    pub(super) const BOOK_PROOF: &str = concat!(
+        "0200000000000000240000000000000006000000000000000200000000000000",
+        "1d1be01575f40354040000000000000003000000000000000000000000000000",
+        "1d1be01575f40354010000000000000053d72fdf4f11fa010200000000000000",
+        "910df00a3afa01aa03000000000000000000000000000000cd8619962269864a",
+        "0100000000000000ecc194c9bd39ae4a02000000000000000557b10c0f950fe8",
+        "020000000000000001000000000000003c0eacb3affe4b3a0200000000000000",
+        "3ede8fb353583aad03000000000000000000000000000000a55014e246cbb246",
+        "0100000000000000e28a24bf60bf866d0200000000000000890207e685f3bf78",
+        "02000000000000000000000000000000e5618b30d0fef4490100000000000000",
+        "98d50c0c699722fde99d9a648d5a7ccf04000000000000000300000000000000",
+        "0000000000000000ec56077a05710e0e0100000000000000ba3fc958c18f9bc0",
+        "020000000000000058b0c217c0e8c3f203000000000000000000000000000000",
+        "f74f3236a5dc87730100000000000000782fd1b5623fc2b40200000000000000",
+        "969bb6d72c52032703000000000000000000000000000000894343f8664b818f",
+        "010000000000000011936bc1caad9eae02000000000000004fb3e64da55e8bd9",
+        "030000000000000000000000000000006925db5f0194a0420100000000000000",
+        "3f0b17d335da2a2702000000000000008dcb1edf4c95e7840300000000000000",
+        "0000000000000000790f984b53b582040100000000000000d6f3158452834220",
+        "0200000000000000a7f1e8886e7fa0b40000000000000000",
+    );
+
+    pub(super) fn book_input() -> Vec<String> {
+        ["3", "0x2", "03", "0x0000000000000001"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    pub(super) fn book_circuit() -> Vec<u8> {
+        bincode::serialize(&circuit_from_book()).unwrap()
+    }
+
+    pub(super) fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(from_hex("0x1f").unwrap(), Fq::from(31));
+        assert_eq!(from_hex("abc").unwrap(), Fq::from(0xabc));
+        assert_eq!(to_hex(Fq::from(31)), "0x000000000000001f");
+        assert_eq!(from_hex(&to_hex(-Fq::from(1))).unwrap(), -Fq::from(1));
+        assert!(matches!(from_hex("0xzz"), Err(WasmError::BadHex(_))));
+        assert!(matches!(from_hex(""), Err(WasmError::BadHex(_))));
+    }
+
+    #[test]
+    fn native_proof() {
+        let circuit = circuit_from_book();
+        let input: Vec<_> = [3u64, 2, 3, 1].into_iter().map(Fq::from).collect();
+        let proof = prove(&circuit, &input).unwrap();
+
+        let hex: String = proof_bytes(&proof)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        assert_eq!(hex, BOOK_PROOF);
+
+        let circuit = WasmCircuit::decode(&book_circuit()).unwrap();
+        let outputs = circuit.outputs(&book_input()).unwrap();
+        assert_eq!(outputs, ["0x0000000000000024", "0x0000000000000006"]);
+
+        let proof = decode_hex(BOOK_PROOF);
+        assert!(circuit.check(&book_input(), &outputs, &proof).unwrap());
+        assert!(!circuit
+            .check(
+                &book_input(),
+                &[outputs[1].clone(), outputs[0].clone()],
+                &proof
+            )
+            .unwrap());
+        assert!(matches!(
+            circuit.check(&book_input(), &outputs, &proof[1..]),
+            Err(WasmError::Proof(_))
+        ));
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::{
+        tests::{book_circuit, book_input, decode_hex, BOOK_PROOF},
+        WasmBuilder, WasmCircuit,
+    };
+
+    #[wasm_bindgen_test]
+    fn verify_native_proof() {
+        let circuit = WasmCircuit::from_bytes(&book_circuit()).unwrap();
+        let outputs = circuit.evaluate(book_input()).unwrap();
+
+        assert!(circuit
+            .verify(book_input(), outputs, &decode_hex(BOOK_PROOF))
+            .unwrap());
+    }
+
+    #[wasm_bindgen_test]
+    fn build_in_wasm() {
+        let mut builder = WasmBuilder::new();
+        let w: Vec<_> = (0..4).map(|_| builder.witness()).collect();
+        let v0 = builder.mul(w[0], w[1]).unwrap();
+        let v1 = builder.mul(w[2], w[3]).unwrap();
+        builder.add(v0, v1).unwrap();
+
+        let circuit = builder.build().unwrap();
+        assert_eq!(
+            circuit.evaluate(book_input()).unwrap(),
+            ["0x0000000000000009"]
+        );
+    }
+}
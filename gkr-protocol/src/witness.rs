@@ -0,0 +1,214 @@
+//! Assignments of the witnesses of a [`CircuitBuilder`] turned into
+//! the input of the built [`Circuit`].
+//!
+//! [`CircuitBuilder`]: crate::circuit_builder::CircuitBuilder
+//! [`Circuit`]: crate::circuit::Circuit
+
+use std::collections::BTreeMap;
+
+use crate::circuit_builder::{InputMapping, InputSlot, Wire};
+
+/// An error assigning a [`Witness`].
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum WitnessError {
+    /// The wire is not a witness of the builder.
+    #[error("wire {0} is not a witness")]
+    NotAWitness(Wire),
+
+    /// The witness feeding an input of the circuit is not assigned.
+    #[error("witness {0} is not assigned")]
+    MissingAssignment(Wire),
+}
+
+/// The values of the witnesses of a builder by wire, public inputs
+/// included.
+///
+/// Constants and padding are filled in by [`Witness::to_input`],
+/// and the witnesses pruned from the circuit may be assigned but
+/// are not required.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "F: ark_serialize::CanonicalSerialize",
+        deserialize = "F: ark_serialize::CanonicalDeserialize"
+    ))
+)]
+pub struct Witness<F> {
+    mapping: InputMapping,
+    #[cfg_attr(feature = "serde", serde(with = "canonical"))]
+    values: BTreeMap<Wire, F>,
+}
+
+impl<F> Witness<F> {
+    /// Create a `Witness` with no assignment for the inputs of `mapping`.
+    pub fn new(mapping: InputMapping) -> Self {
+        Self {
+            mapping,
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Assign `value` to the witness `wire`, replacing any earlier value.
+    pub fn assign(&mut self, wire: Wire, value: F) -> Result<(), WitnessError> {
+        let is_witness = self.mapping.pruned.contains(&wire)
+            || self.mapping.slots.contains(&InputSlot::Witness(wire))
+            || self.mapping.slots.contains(&InputSlot::Public(wire));
+        if !is_witness {
+            return Err(WitnessError::NotAWitness(wire));
+        }
+
+        self.values.insert(wire, value);
+        Ok(())
+    }
+
+    /// The value assigned to the witness `wire`, if any.
+    pub fn get(&self, wire: Wire) -> Option<&F> {
+        self.values.get(&wire)
+    }
+
+    /// The input of the circuit as taken by [`Circuit::evaluate`]
+    /// and the [`Prover`](crate::Prover).
+    ///
+    /// [`Circuit::evaluate`]: crate::circuit::Circuit::evaluate
+    pub fn to_input(&self) -> Result<Vec<F>, WitnessError>
+    where
+        F: Copy + From<u64>,
+    {
+        self.mapping
+            .slots
+            .iter()
+            .map(|slot| match *slot {
+                InputSlot::Witness(wire) | InputSlot::Public(wire) => self
+                    .values
+                    .get(&wire)
+                    .copied()
+                    .ok_or(WitnessError::MissingAssignment(wire)),
+                InputSlot::Const(value) => Ok(F::from(value)),
+                InputSlot::Padding => Ok(F::from(0)),
+            })
+            .collect()
+    }
+}
+
+/// Field elements serialized as their canonical compressed bytes.
+#[cfg(feature = "serde")]
+mod canonical {
+    use std::collections::BTreeMap;
+
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::circuit_builder::Wire;
+
+    pub fn serialize<F: CanonicalSerialize, S: Serializer>(
+        values: &BTreeMap<Wire, F>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        values
+            .iter()
+            .map(|(&wire, value)| {
+                let mut bytes = vec![];
+                value
+                    .serialize_compressed(&mut bytes)
+                    .expect("serializing into a vector does not fail");
+                (wire, bytes)
+            })
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, F: CanonicalDeserialize, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Wire, F>, D::Error> {
+        Vec::<(Wire, Vec<u8>)>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(wire, bytes)| {
+                F::deserialize_compressed(&bytes[..])
+                    .map(|value| (wire, value))
+                    .map_err(D::Error::custom)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(non_local_definitions)]
+mod tests {
+    use ark_ff::{Fp64, MontBackend, MontConfig};
+    use pretty_assertions::assert_eq;
+
+    use super::{Witness, WitnessError};
+    use crate::circuit_builder::{BuildOptions, CircuitBuilder};
+
+    #[derive(MontConfig)]
+    #[modulus = "389"]
+    #[generator = "2"]
+    struct FrConfig;
+
+    type Fp389 = Fp64<MontBackend<FrConfig, 1>>;
+
+    /// $x \cdot y + 3$ with an unused witness $z$.
+    fn builder() -> (CircuitBuilder, [usize; 3]) {
+        let mut builder = CircuitBuilder::new();
+        let x = builder.apply_witness();
+        let z = builder.apply_witness();
+        let y = builder.apply_witness();
+        let three = builder.constant(3);
+        let one = builder.constant(1);
+        let xy = builder.append_mul_gate(x, y).unwrap();
+        let three = builder.append_mul_gate(three, one).unwrap();
+        builder.append_add_gate(xy, three).unwrap();
+        (builder, [x, y, z])
+    }
+
+    #[test]
+    fn assignments() {
+        let (mut builder, [x, y, z]) = builder();
+        builder.set_options(BuildOptions {
+            pad_to_power_of_two: true,
+            ..Default::default()
+        });
+        let circuit = builder.build_circuit().unwrap();
+
+        let mapping = builder.input_mapping().unwrap();
+        assert_eq!(mapping.pruned, vec![z]);
+
+        let mut witness = Witness::new(mapping);
+        witness.assign(x, Fp389::from(4)).unwrap();
+        assert_eq!(
+            witness.to_input().unwrap_err(),
+            WitnessError::MissingAssignment(y)
+        );
+        // Cell 3 is the constant 3.
+        assert_eq!(
+            witness.assign(3, Fp389::from(1)),
+            Err(WitnessError::NotAWitness(3))
+        );
+
+        witness.assign(y, Fp389::from(5)).unwrap();
+        witness.assign(z, Fp389::from(6)).unwrap();
+        let input = witness.to_input().unwrap();
+        assert_eq!(
+            input,
+            builder.input_values(|w| Fp389::from([4, 6, 5][w])).unwrap()
+        );
+        assert_eq!(circuit.outputs(&input).unwrap(), vec![Fp389::from(23)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        let (builder, [x, y, _]) = builder();
+
+        let mut witness = Witness::new(builder.input_mapping().unwrap());
+        witness.assign(x, Fp389::from(4)).unwrap();
+        witness.assign(y, Fp389::from(388)).unwrap();
+
+        let json = serde_json::to_string(&witness).unwrap();
+        let loaded: Witness<Fp389> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, witness);
+        assert_eq!(loaded.to_input().unwrap(), witness.to_input().unwrap());
+    }
+}
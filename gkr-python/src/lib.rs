@@ -0,0 +1,306 @@
+//! [`pyo3`] bindings to build circuits, evaluate them and prove and
+//! verify their evaluations from Python.
+//!
+//! Field elements are Python ints reduced modulo the order of
+//! $\mathbb{F}_{2^{64} - 2^{32} + 1}$, the field of `gkr-cli`.
+//! Circuits are passed as the [`bincode`] bytes of [`Circuit`] and
+//! proofs as the compressed [`CanonicalSerialize`] bytes of
+//! [`GkrProof`], so proofs produced by either side are accepted by
+//! the other.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use gkr_protocol::{
+    circuit::Circuit,
+    circuit_builder::{self, CircuitBuilder},
+    goldilocks::{GoldilocksFp, MODULUS},
+    proof::{self, GkrProof},
+    witness::Witness,
+};
+use pyo3::{create_exception, exceptions::PyException, exceptions::PyValueError, prelude::*};
+
+/// The field of all the values.
+type Fq = GoldilocksFp;
+
+create_exception!(gkr, BuildError, PyException, "The builder rejected a gate.");
+create_exception!(
+    gkr,
+    EvalError,
+    PyException,
+    "The input does not fit the circuit."
+);
+create_exception!(gkr, VerifyError, PyException, "The proof is rejected.");
+
+/// Reduce a Python int modulo the field order.
+fn to_field(value: &Bound<'_, PyAny>) -> PyResult<Fq> {
+    Ok(Fq::from(value.rem(MODULUS)?.extract::<u64>()?))
+}
+
+fn to_fields(values: &[Bound<'_, PyAny>]) -> PyResult<Vec<Fq>> {
+    values.iter().map(to_field).collect()
+}
+
+fn to_int(value: Fq) -> u64 {
+    value.into_bigint().0[0]
+}
+
+/// The ids of the builders, telling their wires apart.
+static NEXT_BUILDER: AtomicU64 = AtomicU64::new(0);
+
+/// A wire of a [`PyBuilder`].
+///
+/// Wires are only handed out by their builder and cannot be created
+/// from Python nor passed to another builder.
+#[pyclass(name = "Wire", module = "gkr", frozen, eq, hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PyWire {
+    builder: u64,
+    index: usize,
+}
+
+#[pymethods]
+impl PyWire {
+    fn __repr__(&self) -> String {
+        format!("Wire({})", self.index)
+    }
+}
+
+/// A [`CircuitBuilder`] exported to Python.
+#[pyclass(name = "CircuitBuilder", module = "gkr")]
+struct PyBuilder {
+    id: u64,
+    builder: CircuitBuilder,
+}
+
+impl PyBuilder {
+    fn wire(&self, index: usize) -> PyWire {
+        PyWire {
+            builder: self.id,
+            index,
+        }
+    }
+
+    /// The index of `wire`, which must be a wire of this builder.
+    fn index(&self, wire: PyWire) -> PyResult<usize> {
+        if wire.builder == self.id {
+            Ok(wire.index)
+        } else {
+            Err(BuildError::new_err(format!(
+                "wire {} belongs to another builder",
+                wire.index
+            )))
+        }
+    }
+
+    fn gate(
+        &mut self,
+        left: PyWire,
+        right: PyWire,
+        append: fn(&mut CircuitBuilder, usize, usize) -> Result<usize, circuit_builder::BuildError>,
+    ) -> PyResult<PyWire> {
+        let (left, right) = (self.index(left)?, self.index(right)?);
+        let index = append(&mut self.builder, left, right)
+            .map_err(|error| BuildError::new_err(error.to_string()))?;
+
+        Ok(self.wire(index))
+    }
+}
+
+#[pymethods]
+impl PyBuilder {
+    /// Create an empty builder.
+    #[new]
+    fn new() -> Self {
+        Self {
+            id: NEXT_BUILDER.fetch_add(1, Ordering::Relaxed),
+            builder: CircuitBuilder::new(),
+        }
+    }
+
+    /// Add a witness returning its wire.
+    fn witness(&mut self) -> PyWire {
+        let index = self.builder.apply_witness();
+        self.wire(index)
+    }
+
+    /// Add a public input returning its wire.
+    fn public_input(&mut self) -> PyWire {
+        let index = self.builder.apply_public_input();
+        self.wire(index)
+    }
+
+    /// Add a constant, reduced modulo the field order.
+    fn constant(&mut self, value: &Bound<'_, PyAny>) -> PyResult<PyWire> {
+        let value = to_int(to_field(value)?);
+        let index = self.builder.constant(value);
+
+        Ok(self.wire(index))
+    }
+
+    /// Add a gate computing `left + right`.
+    fn add(&mut self, left: PyWire, right: PyWire) -> PyResult<PyWire> {
+        self.gate(left, right, CircuitBuilder::append_add_gate)
+    }
+
+    /// Add a gate computing `left * right`.
+    fn mul(&mut self, left: PyWire, right: PyWire) -> PyResult<PyWire> {
+        self.gate(left, right, CircuitBuilder::append_mul_gate)
+    }
+
+    /// Add a gate computing `left - right`.
+    fn sub(&mut self, left: PyWire, right: PyWire) -> PyResult<PyWire> {
+        self.gate(left, right, CircuitBuilder::append_sub_gate)
+    }
+
+    /// Make `wire` an output of the built circuit.
+    fn mark_output(&mut self, wire: PyWire) -> PyResult<()> {
+        let index = self.index(wire)?;
+        self.builder.mark_output(index);
+
+        Ok(())
+    }
+
+    /// Build the circuit.
+    fn build(&self) -> PyResult<PyCircuit> {
+        let circuit = self
+            .builder
+            .build_circuit()
+            .map_err(|error| BuildError::new_err(error.to_string()))?;
+
+        Ok(PyCircuit { circuit })
+    }
+
+    /// The input of the built circuit given the values of the
+    /// witnesses as a dict by wire, constants filled in.
+    fn input(&self, values: HashMap<PyWire, Bound<'_, PyAny>>) -> PyResult<Vec<u64>> {
+        let mapping = self
+            .builder
+            .input_mapping()
+            .map_err(|error| BuildError::new_err(error.to_string()))?;
+
+        let mut witness = Witness::new(mapping);
+        for (wire, value) in values {
+            witness
+                .assign(self.index(wire)?, to_field(&value)?)
+                .map_err(|error| PyValueError::new_err(error.to_string()))?;
+        }
+
+        let input = witness
+            .to_input()
+            .map_err(|error| PyValueError::new_err(error.to_string()))?;
+
+        Ok(input.into_iter().map(to_int).collect())
+    }
+}
+
+/// A [`Circuit`] exported to Python.
+#[pyclass(name = "Circuit", module = "gkr", frozen)]
+struct PyCircuit {
+    circuit: Circuit,
+}
+
+#[pymethods]
+impl PyCircuit {
+    /// Decode a circuit from its [`bincode`] bytes.
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        let circuit = bincode::deserialize(bytes)
+            .map_err(|error| PyValueError::new_err(error.to_string()))?;
+
+        Ok(Self { circuit })
+    }
+
+    /// The [`bincode`] bytes of the circuit.
+    fn to_bytes(&self) -> Cow<'static, [u8]> {
+        Cow::Owned(bincode::serialize(&self.circuit).expect("circuits serialize"))
+    }
+
+    /// The number of inputs of the circuit.
+    fn num_inputs(&self) -> usize {
+        self.circuit.num_inputs()
+    }
+
+    /// The outputs of the circuit on `input`.
+    fn evaluate(&self, input: Vec<Bound<'_, PyAny>>) -> PyResult<Vec<u64>> {
+        let outputs = self
+            .circuit
+            .outputs(&to_fields(&input)?)
+            .map_err(|error| EvalError::new_err(error.to_string()))?;
+
+        Ok(outputs.into_iter().map(to_int).collect())
+    }
+}
+
+/// Prove the evaluation of `circuit` on `input`.
+#[pyfunction]
+fn prove(circuit: &PyCircuit, input: Vec<Bound<'_, PyAny>>) -> PyResult<Cow<'static, [u8]>> {
+    let input = to_fields(&input)?;
+    if input.len() != circuit.circuit.num_inputs() {
+        return Err(EvalError::new_err(format!(
+            "the circuit takes {} inputs, got {}",
+            circuit.circuit.num_inputs(),
+            input.len()
+        )));
+    }
+
+    let proof = proof::prove(&circuit.circuit, &input)
+        .map_err(|error| VerifyError::new_err(error.to_string()))?;
+
+    let mut bytes = vec![];
+    proof
+        .serialize_compressed(&mut bytes)
+        .expect("serializing into a vector does not fail");
+
+    Ok(Cow::Owned(bytes))
+}
+
+/// Verify that `proof` proves the evaluation of `circuit` on `input`
+/// to `outputs`, raising [`VerifyError`] if it does not.
+#[pyfunction]
+fn verify(
+    circuit: &PyCircuit,
+    input: Vec<Bound<'_, PyAny>>,
+    outputs: Vec<Bound<'_, PyAny>>,
+    proof: &[u8],
+) -> PyResult<()> {
+    let input = to_fields(&input)?;
+    let outputs = to_fields(&outputs)?;
+    let proof = GkrProof::<Fq>::deserialize_compressed(proof)
+        .map_err(|error| VerifyError::new_err(format!("malformed proof: {error}")))?;
+
+    if input.len() != circuit.circuit.num_inputs() {
+        return Err(VerifyError::new_err(format!(
+            "the circuit takes {} inputs, got {}",
+            circuit.circuit.num_inputs(),
+            input.len()
+        )));
+    }
+    if proof.outputs != outputs {
+        return Err(VerifyError::new_err("the proof is about other outputs"));
+    }
+
+    proof::verify(&circuit.circuit, &input, &proof)
+        .map_err(|error| VerifyError::new_err(error.to_string()))
+}
+
+/// The `gkr` Python module.
+#[pymodule]
+fn gkr(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("MODULUS", MODULUS)?;
+    m.add("BuildError", m.py().get_type::<BuildError>())?;
+    m.add("EvalError", m.py().get_type::<EvalError>())?;
+    m.add("VerifyError", m.py().get_type::<VerifyError>())?;
+    m.add_class::<PyWire>()?;
+    m.add_class::<PyBuilder>()?;
+    m.add_class::<PyCircuit>()?;
+    m.add_function(wrap_pyfunction!(prove, m)?)?;
+    m.add_function(wrap_pyfunction!(verify, m)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+import pytest
+
+import gkr
+
+# The proof of the evaluation of the circuit of figure 4.12 of the
+# book on (3, 2, 3, 1), as produced by the `native_proof` test of
+# `gkr-protocol`.
+BOOK_PROOF = bytes.fromhex(
+    "0200000000000000240000000000000006000000000000000200000000000000"
+    "1d1be01575f40354040000000000000003000000000000000000000000000000"
+    "1d1be01575f40354010000000000000053d72fdf4f11fa010200000000000000"
+    "910df00a3afa01aa03000000000000000000000000000000cd8619962269864a"
+    "0100000000000000ecc194c9bd39ae4a02000000000000000557b10c0f950fe8"
+    "020000000000000001000000000000003c0eacb3affe4b3a0200000000000000"
+    "3ede8fb353583aad03000000000000000000000000000000a55014e246cbb246"
+    "0100000000000000e28a24bf60bf866d0200000000000000890207e685f3bf78"
+    "02000000000000000000000000000000e5618b30d0fef4490100000000000000"
+    "98d50c0c699722fde99d9a648d5a7ccf04000000000000000300000000000000"
+    "0000000000000000ec56077a05710e0e0100000000000000ba3fc958c18f9bc0"
+    "020000000000000058b0c217c0e8c3f203000000000000000000000000000000"
+    "f74f3236a5dc87730100000000000000782fd1b5623fc2b40200000000000000"
+    "969bb6d72c52032703000000000000000000000000000000894343f8664b818f"
+    "010000000000000011936bc1caad9eae02000000000000004fb3e64da55e8bd9"
+    "030000000000000000000000000000006925db5f0194a0420100000000000000"
+    "3f0b17d335da2a2702000000000000008dcb1edf4c95e7840300000000000000"
+    "0000000000000000790f984b53b582040100000000000000d6f3158452834220"
+    "0200000000000000a7f1e8886e7fa0b40000000000000000"
+)
+
+
+def book_circuit():
+    """The circuit of figure 4.12 of the book and its witnesses."""
+    builder = gkr.CircuitBuilder()
+    w = [builder.witness() for _ in range(4)]
+    v = [
+        builder.mul(w[0], w[0]),
+        builder.mul(w[1], w[1]),
+        builder.mul(w[1], w[2]),
+        builder.mul(w[3], w[3]),
+    ]
+    builder.mul(v[0], v[1])
+    builder.mul(v[2], v[3])
+    return builder, w
+
+
+def test_evaluate():
+    builder, w = book_circuit()
+    circuit = builder.build()
+
+    input = builder.input(dict(zip(w, [3, 2, 3, 1])))
+    assert input == [3, 2, 3, 1]
+    assert circuit.num_inputs() == 4
+    assert circuit.evaluate(input) == [36, 6]
+    # Values are reduced modulo the field order.
+    assert circuit.evaluate([3 + gkr.MODULUS, 2, 3 - gkr.MODULUS, 1]) == [36, 6]
+    assert circuit.evaluate([-1, 1, 1, 1]) == [1, 1]
+
+    with pytest.raises(gkr.EvalError):
+        circuit.evaluate([3, 2, 3])
+
+
+def test_verify_rust_proof():
+    builder, _ = book_circuit()
+    circuit = builder.build()
+
+    gkr.verify(circuit, [3, 2, 3, 1], [36, 6], BOOK_PROOF)
+    assert gkr.prove(circuit, [3, 2, 3, 1]) == BOOK_PROOF
+    assert gkr.Circuit.from_bytes(circuit.to_bytes()).evaluate([3, 2, 3, 1]) == [36, 6]
+
+    with pytest.raises(gkr.VerifyError, match="other outputs"):
+        gkr.verify(circuit, [3, 2, 3, 1], [6, 36], BOOK_PROOF)
+    with pytest.raises(gkr.VerifyError, match="malformed proof"):
+        gkr.verify(circuit, [3, 2, 3, 1], [36, 6], BOOK_PROOF[1:])
+    with pytest.raises(gkr.VerifyError):
+        gkr.verify(circuit, [4, 2, 3, 1], [36, 6], BOOK_PROOF)
+
+
+def test_build_errors():
+    builder, w = book_circuit()
+    other = gkr.CircuitBuilder()
+    x = other.witness()
+
+    with pytest.raises(gkr.BuildError, match="already exists"):
+        builder.mul(w[0], w[0])
+    with pytest.raises(gkr.BuildError, match="cannot combine"):
+        builder.add(w[0], builder.mul(w[0], w[1]))
+    with pytest.raises(gkr.BuildError, match="another builder"):
+        builder.add(w[0], x)
+    with pytest.raises(TypeError):
+        gkr.Wire()
+
+
+def test_constants():
+    builder = gkr.CircuitBuilder()
+    x = builder.witness()
+    three = builder.constant(3)
+    builder.mark_output(builder.mul(x, three))
+    circuit = builder.build()
+
+    input = builder.input({x: 5})
+    assert circuit.evaluate(input) == [15]
+    proof = gkr.prove(circuit, input)
+    gkr.verify(circuit, input, [15], proof)
@@ -1,3 +1,5 @@
+#![allow(non_local_definitions)]
+
 use std::ops::Mul;
 
 use ark_ff::{Field, Fp64, MontBackend, MontConfig, One};
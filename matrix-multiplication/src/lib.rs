@@ -145,6 +145,7 @@ impl<F: FftField> SumCheckPolynomial<F> for G<F> {
 }
 
 #[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
     use std::ops::Mul;
 
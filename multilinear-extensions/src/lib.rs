@@ -60,6 +60,7 @@ fn lagrange_basis_poly_at<F: Field>(x: &[F], w: &[F]) -> Option<F> {
 }
 
 #[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
     use ark_ff::{Fp64, MontBackend, MontConfig, PrimeField};
     use pretty_assertions::assert_eq;
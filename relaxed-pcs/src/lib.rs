@@ -13,7 +13,7 @@ use ark_ff::Field;
 use ark_poly::{univariate, MultilinearExtension, Polynomial};
 use ark_std::rand::Rng;
 
-use gkr_protocol::{line, restrict_poly};
+use gkr_protocol::{line, restrict_poly, uni_poly::UniPoly};
 
 mod permutations;
 
@@ -66,7 +66,7 @@ pub struct Verifier<F: Field, P: Config<Leaf = F>> {
     x: F,
     degree: usize,
     challenge_point: Vec<F>,
-    line: Vec<univariate::SparsePolynomial<F>>,
+    line: Vec<UniPoly<F>>,
     num_vars: usize,
     prover_univariate: Option<univariate::SparsePolynomial<F>>,
     merkle_root: P::InnerDigest,
@@ -216,6 +216,7 @@ impl<F: IF, M: MultilinearExtension<F>, P: Config<Leaf = F>> Prover<F, M, P> {
 }
 
 #[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
     use std::{borrow::Borrow, marker::PhantomData};
 
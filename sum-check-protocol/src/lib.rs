@@ -1,10 +1,15 @@
-use std::marker::PhantomData;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::marker::PhantomData;
 
 use ark_ff::{Field, Zero};
 use ark_poly::{
     multivariate::{self, SparseTerm, Term},
     polynomial::DenseMVPolynomial,
-    univariate, Polynomial,
+    univariate, DenseMultilinearExtension, MultilinearExtension, Polynomial,
 };
 use ark_std::rand::Rng;
 use bitvec::slice::BitSlice;
@@ -107,7 +112,7 @@ impl<F: Field, P: SumCheckPolynomial<F>> Prover<F, P> {
     pub fn round(&mut self, r_prev: F, j: usize) -> univariate::SparsePolynomial<F> {
         if j != 0 {
             self.r.push(r_prev);
-            self.g = self.g.fix_variables(&[r_prev]);
+            self.g.fix_next(r_prev);
         }
 
         self.g.to_univariate()
@@ -131,6 +136,19 @@ pub trait SumCheckPolynomial<F: Field> {
     /// `partial_point.len()` variables at `partial_point`.
     fn fix_variables(&self, partial_point: &[F]) -> Self;
 
+    /// Fix the first variable of `self` at `r` in place.
+    ///
+    /// Defaults to [`fix_variables`], polynomials backed by tables
+    /// may fold them without allocating new ones.
+    ///
+    /// [`fix_variables`]: SumCheckPolynomial::fix_variables
+    fn fix_next(&mut self, r: F)
+    where
+        Self: Sized,
+    {
+        *self = self.fix_variables(&[r]);
+    }
+
     /// Compute the $j$-th round of polynomial for sumcheck over
     /// first variable.
     ///
@@ -159,7 +177,7 @@ pub trait SumCheckPolynomial<F: Field> {
 
 impl<F: Field> SumCheckPolynomial<F> for multivariate::SparsePolynomial<F, SparseTerm> {
     fn evaluate(&self, point: &[F]) -> Option<F> {
-        Some(Polynomial::evaluate(self, &point.to_owned()))
+        Some(Polynomial::evaluate(self, &point.to_vec()))
     }
 
     fn fix_variables(&self, partial_point: &[F]) -> Self {
@@ -225,6 +243,120 @@ impl<F: Field> SumCheckPolynomial<F> for multivariate::SparsePolynomial<F, Spars
     }
 }
 
+/// A product $g = f_1 \cdot f_2 \cdots f_k$ of multilinear polynomials
+/// over the same variables.
+///
+/// $g$ has degree $k$ in every variable, so the round polynomials
+/// are found by interpolating through $k + 1$ points. The field has
+/// to have more than $k$ elements.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultilinearProduct<F: Field> {
+    factors: Vec<DenseMultilinearExtension<F>>,
+}
+
+impl<F: Field> MultilinearProduct<F> {
+    /// Create the product of `factors`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no factors or they differ in the number
+    /// of variables.
+    pub fn new(factors: Vec<DenseMultilinearExtension<F>>) -> Self {
+        let num_vars = factors.first().expect("at least one factor").num_vars;
+        assert!(factors.iter().all(|f| f.num_vars == num_vars));
+
+        Self { factors }
+    }
+
+    /// The factors of the product.
+    pub fn factors(&self) -> &[DenseMultilinearExtension<F>] {
+        &self.factors
+    }
+}
+
+impl<F: Field> SumCheckPolynomial<F> for MultilinearProduct<F> {
+    fn evaluate(&self, point: &[F]) -> Option<F> {
+        self.factors.iter().map(|f| f.evaluate(point)).product()
+    }
+
+    fn fix_variables(&self, partial_point: &[F]) -> Self {
+        Self {
+            factors: self
+                .factors
+                .iter()
+                .map(|f| f.fix_variables(partial_point))
+                .collect(),
+        }
+    }
+
+    fn to_univariate(&self) -> univariate::SparsePolynomial<F> {
+        let evals: Vec<F> = (0..=self.factors.len() as u64)
+            .map(|t| {
+                self.fix_variables(&[F::from(t)])
+                    .to_evaluations()
+                    .into_iter()
+                    .sum()
+            })
+            .collect();
+
+        interpolate(&evals)
+    }
+
+    fn num_vars(&self) -> usize {
+        self.factors[0].num_vars
+    }
+
+    fn to_evaluations(&self) -> Vec<F> {
+        let mut evals = vec![F::one(); 1 << self.num_vars()];
+        for f in &self.factors {
+            for (e, f_e) in evals.iter_mut().zip(&f.evaluations) {
+                *e *= f_e;
+            }
+        }
+
+        evals
+    }
+}
+
+/// The polynomial of degree less than `evals.len()` taking the value
+/// `evals[t]` at every $t = 0, 1, \ldots$ by Lagrange interpolation.
+pub fn interpolate<F: Field>(evals: &[F]) -> univariate::SparsePolynomial<F> {
+    let n = evals.len();
+    let mut coeffs = vec![F::zero(); n];
+
+    for (i, y) in evals.iter().enumerate() {
+        // \prod_{j \ne i} (X - j) / (i - j)
+        let mut basis = vec![F::one()];
+        let mut denominator = F::one();
+        for j in (0..n).filter(|&j| j != i) {
+            let j_f = F::from(j as u64);
+            let mut next = vec![F::zero(); basis.len() + 1];
+            for (k, c) in basis.iter().enumerate() {
+                next[k + 1] += c;
+                next[k] -= *c * j_f;
+            }
+            basis = next;
+            denominator *= F::from(i as u64) - j_f;
+        }
+
+        let scale = *y
+            * denominator
+                .inverse()
+                .expect("interpolation points are distinct");
+        for (c, b) in coeffs.iter_mut().zip(basis) {
+            *c += scale * b;
+        }
+    }
+
+    univariate::SparsePolynomial::from_coefficients_vec(
+        coeffs
+            .into_iter()
+            .enumerate()
+            .filter(|(_, c)| !c.is_zero())
+            .collect(),
+    )
+}
+
 /// The state of the Verifier.
 pub struct Verifier<F: Field, P: SumCheckPolynomial<F>> {
     /// Number of variables in the original polynomial.
@@ -333,7 +465,10 @@ impl<F: Field, P: SumCheckPolynomial<F>> Verifier<F, P> {
 }
 
 #[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
+    use alloc::{vec, vec::Vec};
+
     use ark_ff::{
         fields::Fp64,
         fields::{MontBackend, MontConfig},
@@ -341,12 +476,12 @@ mod tests {
     };
     use ark_poly::{
         multivariate::{self, SparseTerm, Term},
-        DenseMVPolynomial,
+        DenseMVPolynomial, DenseMultilinearExtension, MultilinearExtension, Polynomial,
     };
     use ark_std::{rand::Rng, test_rng};
     use pretty_assertions::assert_eq;
 
-    use crate::{Prover, SumCheckPolynomial, Verifier, VerifierRoundResult};
+    use crate::{MultilinearProduct, Prover, SumCheckPolynomial, Verifier, VerifierRoundResult};
 
     #[derive(MontConfig)]
     #[modulus = "5"]
@@ -521,4 +656,41 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn multilinear_product_test() {
+        let rng = &mut test_rng();
+
+        for n in 1..7 {
+            let factors: Vec<_> = (0..3)
+                .map(|_| DenseMultilinearExtension::<Fp5>::rand(n, rng))
+                .collect();
+            let g = MultilinearProduct::new(factors.clone());
+
+            let mut prover = Prover::new(g.clone());
+            let c_1 = prover.c_1();
+            let sum: Fp5 = (0..1 << n)
+                .map(|x| factors.iter().map(|f| f.evaluations[x]).product::<Fp5>())
+                .sum();
+            assert_eq!(c_1, sum);
+
+            let mut r_j = Fp5::one();
+            let mut verifier = Verifier::new(n, Some(g));
+            verifier.set_c_1(c_1);
+
+            for j in 0..n {
+                let g_j = prover.round(r_j, j);
+                assert!(g_j.degree() <= 3);
+                match verifier.round(g_j, rng).unwrap() {
+                    VerifierRoundResult::JthRound(r) => {
+                        r_j = r;
+                    }
+                    VerifierRoundResult::FinalRound(res) => {
+                        assert!(res);
+                        break;
+                    }
+                }
+            }
+        }
+    }
 }
@@ -172,6 +172,7 @@ fn idx(i: usize, j: usize, num_vars: usize) -> usize {
 }
 
 #[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
     use ark_ff::{Fp64, MontBackend, MontConfig, One, PrimeField};
     use ark_std::{rand::Rng, test_rng};
@@ -183,6 +184,7 @@ mod tests {
     struct AdjMatrix(Vec<Vec<bool>>);
 
     impl AdjMatrix {
+        #[allow(clippy::needless_range_loop)]
         fn new<R: Rng>(n: usize, rng: &mut R) -> Self {
             let mut res = Vec::with_capacity(n);
 